@@ -0,0 +1,62 @@
+//! Time building and laying out a synthetic scene of about 50k nodes.
+//!
+//! `cargo run --release --example layout_bench -- [cells]`: a wrapping grid of `cells` spaces
+//! holding four table legs each, 10000 cells by default. With `--features parallel` the
+//! layout is timed serially and in parallel.
+
+use rsmlc::package::Package;
+use rsmlc::diagnostics::Diagnostics;
+use rsmlc::render_tree::{LayoutOptions, RenderTree};
+use rsmlc::xml_parser::parse_xml_str;
+use std::time::Instant;
+
+const RUNS: u32 = 5;
+
+fn scene(cells: usize) -> String {
+    let mut xml = String::from(
+        r#"<rsml><head></head><body style="size:1000m 1000m 10m">
+        <space id="grid" style="size:1000m 1000m 1m;flex-direction:x;flex-wrap:wrap">"#,
+    );
+    for _ in 0..cells {
+        xml.push_str(r#"<space class="cell" style="flex-direction:x">"#);
+        xml.push_str(&"<object>table_leg</object>".repeat(4));
+        xml.push_str("</space>");
+    }
+    xml.push_str("</space></body></rsml>");
+    xml
+}
+
+fn main() -> anyhow::Result<()> {
+    let cells = match std::env::args().nth(1) {
+        Some(cells) => cells.parse()?,
+        None => 10_000,
+    };
+    let package = Package::from_file("package.toml")?;
+    let dom = parse_xml_str(&scene(cells))?;
+
+    // 启用parallel feature时同时测量串行和并行
+    let modes: &[bool] = if cfg!(feature = "parallel") { &[false, true] } else { &[false] };
+    for &parallel in modes {
+        let options = LayoutOptions {
+            seed: Some(0),
+            parallel,
+            ..LayoutOptions::default()
+        };
+        let (mut build, mut layout) = (0.0, 0.0);
+        let mut nodes = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let mut render_tree = RenderTree::with_options(&dom, &package, Diagnostics::default(), &options)?;
+            let built = Instant::now();
+            render_tree.calculate()?;
+            layout += built.elapsed().as_secs_f64();
+            build += (built - start).as_secs_f64();
+            nodes = render_tree.nodes_count();
+        }
+        println!("{}:", if parallel { "parallel" } else { "serial" });
+        println!("  nodes: {}", nodes);
+        println!("  build: {:.1}ms", build * 1000.0 / RUNS as f64);
+        println!("  layout: {:.1}ms", layout * 1000.0 / RUNS as f64);
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Build a scene from Rust, lay it out and write both MJCF and the equivalent RSML.
+
+use rsmlc::package::Package;
+use rsmlc::render_tree::RenderTree;
+use rsmlc::scene::{Builder, to_rsml_xml};
+use rsmlc::target::MjcfGenerator;
+
+fn main() -> anyhow::Result<()> {
+    let package = Package::from_file("package.toml")?;
+
+    let dom = Builder::body()
+        .space(|s| {
+            s.id("room")
+                .style("size:5m 4m 3m;flex-direction:x;justify-content:space-between")
+                .object("table_plane")
+                .object_with("table_leg", |o| o.id("leg"))
+                .group("bottles", "random")
+        })
+        .build(&package)?;
+
+    let mut render_tree = RenderTree::new(&dom, &package)?;
+    render_tree.calculate()?;
+
+    let mjcf = quick_xml::se::to_string(&MjcfGenerator::generate(&render_tree)?)?;
+    println!("{}", mjcf);
+    println!();
+    println!("{}", to_rsml_xml(&dom));
+    Ok(())
+}
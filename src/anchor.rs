@@ -0,0 +1,309 @@
+//! Anchoring elements to the surface of another element.
+//!
+//! `anchor="top-of:#table"` on an object, group or space puts the element on the top
+//! surface of the element with id `table`. "Top" follows the `<world>` declaration: the
+//! side of the target facing away from the floor. Only the position on the up axis is
+//! changed; the other axes keep what flex or `pos` resolved, and an anchored space moves
+//! its whole subtree.
+//!
+//! Anchors are resolved after the position pass, in dependency order: an anchor waits for
+//! the anchors of its ancestors and for those of its target and the target's ancestors,
+//! since any of them can still move the target. Anchors that depend on each other are an
+//! error.
+
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{NodeId, RenderNode, RenderTree};
+use crate::style::SpacePosition;
+use crate::world::{Floor, UpAxis};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Value of the `anchor` attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    /// `top-of:#id`: rest on the top surface of the element with this id
+    TopOf(String),
+}
+
+impl FromStr for Anchor {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || RsmlError::InvalidAttribute {
+            attribute: "anchor".to_string(),
+            value: s.to_string(),
+        };
+        let (kind, target) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let target = target.trim().strip_prefix('#').filter(|id| !id.is_empty()).ok_or_else(invalid)?;
+        match kind.trim() {
+            "top-of" => Ok(Anchor::TopOf(target.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anchor::TopOf(target) => write!(f, "top-of:#{}", target),
+        }
+    }
+}
+
+impl Anchor {
+    /// Id of the element the anchor refers to
+    pub fn target(&self) -> &str {
+        match self {
+            Anchor::TopOf(target) => target,
+        }
+    }
+}
+
+impl RenderTree<'_> {
+    /// Move every anchored node under `root` onto its target, in dependency order.
+    /// Must run after all positions are computed.
+    pub(crate) fn resolve_anchors(&mut self, root: NodeId) -> Result<()> {
+        let mut targets = HashMap::new();
+        for id in self.descendants(root) {
+            let node_ref = self.node(id);
+            let Some(anchor) = &node_ref.anchor else {
+                continue;
+            };
+            let target = self.find_by_id(anchor.target()).ok_or_else(|| {
+                RsmlError::StyleComputation {
+                    message: format!(
+                        "anchor of {} refers to unknown id '#{}'",
+                        node_ref.label(),
+                        anchor.target()
+                    ),
+                }
+                .at(node_ref.span.as_ref())
+            })?;
+            targets.insert(id, target);
+        }
+
+        // 先序遍历的顺序，结果和HashMap的顺序无关
+        let anchored: Vec<NodeId> = self.descendants(root).filter(|id| targets.contains_key(id)).collect();
+        let mut done = HashSet::new();
+        let mut order = Vec::with_capacity(anchored.len());
+        for id in anchored {
+            self.order_anchor(id, &targets, &mut done, &mut Vec::new(), &mut order)?;
+        }
+        for id in order {
+            self.move_onto(id, targets[&id])?;
+        }
+        Ok(())
+    }
+
+    /// Anchored nodes whose resolution can move `id`: `id` and its ancestors
+    fn anchored_ancestors(&self, id: NodeId, targets: &HashMap<NodeId, NodeId>) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if targets.contains_key(&node) {
+                result.push(node);
+            }
+            current = self.node(node).parent;
+        }
+        result
+    }
+
+    /// Depth-first topological ordering; `path` holds the anchors being ordered, in the
+    /// order they wait for each other
+    fn order_anchor(
+        &self,
+        id: NodeId,
+        targets: &HashMap<NodeId, NodeId>,
+        done: &mut HashSet<NodeId>,
+        path: &mut Vec<NodeId>,
+        order: &mut Vec<NodeId>,
+    ) -> Result<()> {
+        if done.contains(&id) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&node| node == id) {
+            let cycle: Vec<String> = path[start..]
+                .iter()
+                .chain([&id])
+                .map(|&node| self.node(node).label())
+                .collect();
+            return Err(RsmlError::StyleComputation {
+                message: format!("anchor cycle: {}", cycle.join(" -> ")),
+            }
+            .at(self.node(id).span.as_ref()));
+        }
+        path.push(id);
+
+        // 目标和它的祖先、以及自己的祖先上的anchor都可能移动目标或自己
+        let mut dependencies = self.anchored_ancestors(targets[&id], targets);
+        if let Some(parent) = self.node(id).parent {
+            dependencies.extend(self.anchored_ancestors(parent, targets));
+        }
+        for dependency in dependencies {
+            self.order_anchor(dependency, targets, done, path, order)?;
+        }
+
+        path.pop();
+        done.insert(id);
+        order.push(id);
+        Ok(())
+    }
+
+    /// Move `id` and its subtree along the up axis so it rests on `target`
+    fn move_onto(&mut self, id: NodeId, target: NodeId) -> Result<()> {
+        let node_ref = self.node(id);
+        let target_ref = self.node(target);
+        let bounds = |other: &RenderNode| {
+            let computed = &other.computed_style;
+            computed
+                .position
+                .get_length()
+                .zip(computed.size.get_length())
+                .ok_or_else(|| RsmlError::StyleComputation {
+                    message: format!("anchor of {}: {} has no position", node_ref.label(), other.label()),
+                })
+        };
+        let (position, size) = bounds(node_ref)?;
+        let (target_position, target_size) = bounds(target_ref)?;
+
+        let up = self.world.up;
+        let axis = |value: Dim3<Length>| up_component(value, up).mm() as i64;
+        // 地面在max一侧时，顶面是目标的min一侧
+        let resting = match self.world.floor {
+            Floor::Min => axis(target_position) + axis(target_size),
+            Floor::Max => axis(target_position) - axis(size),
+        };
+        let delta = resting - axis(position);
+        let out_of_world = || {
+            RsmlError::StyleComputation {
+                message: format!(
+                    "anchor moves {} out of the world on top of {}",
+                    node_ref.label(),
+                    target_ref.label()
+                ),
+            }
+            .at(node_ref.span.as_ref())
+        };
+
+        let mut moved = Vec::new();
+        for node in self.descendants(id) {
+            let Some(mut position) = self.node(node).computed_style.position.get_length() else {
+                continue;
+            };
+            let value = up_component_mut(&mut position, up);
+            *value = u32::try_from(value.mm() as i64 + delta)
+                .map(Length::from_mm)
+                .map_err(|_| out_of_world())?;
+            moved.push((node, position));
+        }
+        for (node, position) in moved {
+            self.node_mut(node).computed_style.position = SpacePosition::from_dim3(position);
+        }
+        Ok(())
+    }
+}
+
+fn up_component(value: Dim3<Length>, up: UpAxis) -> Length {
+    match up {
+        UpAxis::X => value.x,
+        UpAxis::Y => value.y,
+        UpAxis::Z => value.z,
+    }
+}
+
+fn up_component_mut(value: &mut Dim3<Length>, up: UpAxis) -> &mut Length {
+    match up {
+        UpAxis::X => &mut value.x,
+        UpAxis::Y => &mut value.y,
+        UpAxis::Z => &mut value.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+    use crate::xml_parser::Element;
+
+    /// 2m的cube空间，桌面在`table_pos`，其余的物体按(id, pos, anchor)放入
+    fn kitchen(table_pos: &str, objects: &[(&str, &str, &str)]) -> Element {
+        let package = Package::from_file("package.toml").unwrap();
+        let room = objects.iter().fold(
+            Builder::new_space().id("room").style("size:2m 2m 2m;display:cube"),
+            |room, (id, pos, anchor)| {
+                let bottles = Builder::new_group("bottles", "box_bottle")
+                    .id(id)
+                    .style(&format!("pos:{}", pos))
+                    .attribute("anchor", anchor);
+                room.append_child(bottles)
+            },
+        );
+        let room = room.object_with("table_plane", |table| {
+            table.id("table").style(&format!("pos:{}", table_pos))
+        });
+        Builder::body().append_child(room).build(&package).unwrap()
+    }
+
+    /// 每个id相对于room的位置
+    fn relative_positions(scene: &Element, ids: &[&str]) -> Result<Vec<Dim3<Length>>> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(scene, &package)?;
+        render_tree.calculate()?;
+        let position = |id: &str| {
+            let node = render_tree.find_by_id(id).unwrap();
+            render_tree.node(node).computed_style.position.get_length().unwrap()
+        };
+        let room = position("room");
+        Ok(ids.iter().map(|id| position(id) - room).collect())
+    }
+
+    fn cm(x: u32, y: u32, z: u32) -> Dim3<Length> {
+        Dim3::new(Length::from_cm(x), Length::from_cm(y), Length::from_cm(z))
+    }
+
+    #[test]
+    fn test_parse_anchor() {
+        assert_eq!("top-of:#table".parse::<Anchor>().unwrap(), Anchor::TopOf("table".to_string()));
+        assert_eq!(" top-of: #table ".parse::<Anchor>().unwrap().to_string(), "top-of:#table");
+        for invalid in ["top-of:table", "top-of:#", "bottom-of:#table", "#table"] {
+            assert!(invalid.parse::<Anchor>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_bottle_on_table() {
+        // 桌面10cm厚，瓶子放在桌面上，x和y仍由pos决定；瓶子在桌子之前声明
+        for (table_pos, top) in [("0 0 0", 10), ("0 0 50cm", 60), ("1m 1m 1m", 110)] {
+            let scene = kitchen(table_pos, &[("bottle", "20cm 30cm max", "top-of:#table")]);
+            let positions = relative_positions(&scene, &["bottle"]).unwrap();
+            assert_eq!(positions, vec![cm(20, 30, top)], "{}", table_pos);
+        }
+
+        // 第二个瓶子放在第一个瓶子上（组的尺寸是最大的成员，19cm高），按依赖的顺序计算
+        let scene = kitchen(
+            "0 0 50cm",
+            &[("upper", "0 0 0", "top-of:#lower"), ("lower", "0 0 0", "top-of:#table")],
+        );
+        let positions = relative_positions(&scene, &["lower", "upper"]).unwrap();
+        assert_eq!(positions, vec![cm(0, 0, 60), cm(0, 0, 79)]);
+    }
+
+    #[test]
+    fn test_anchor_errors() {
+        let scene = kitchen("0 0 0", &[("a", "0 0 0", "top-of:#b"), ("b", "0 0 0", "top-of:#a")]);
+        let err = relative_positions(&scene, &[]).unwrap_err();
+        assert!(err.to_string().contains("anchor cycle: group#a -> group#b -> group#a"), "{}", err);
+
+        let scene = kitchen("0 0 0", &[("a", "0 0 0", "top-of:#a")]);
+        let err = relative_positions(&scene, &[]).unwrap_err();
+        assert!(err.to_string().contains("anchor cycle: group#a -> group#a"), "{}", err);
+
+        let scene = kitchen("0 0 0", &[("a", "0 0 0", "top-of:#shelf")]);
+        let err = relative_positions(&scene, &[]).unwrap_err();
+        assert!(err.to_string().contains("anchor of group#a refers to unknown id '#shelf'"), "{}", err);
+    }
+}
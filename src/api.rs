@@ -1,9 +1,12 @@
 use crate::error::{Result, RsmlError};
 use serde::Deserialize;
 use serde_json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
-use std::io::copy;
+use std::io::{Read, Write};
 use std::path::Path;
+use std::time::Duration;
 
 /// Represents a single 3D asset from the API
 #[derive(Deserialize, Debug, Clone)]
@@ -45,53 +48,275 @@ pub struct ApiResponse<T> {
     pub message: String,
 }
 
-/// Generic API call function for robot-3d-assets endpoints
-fn api_call<T: for<'de> Deserialize<'de>>(path: &str, method: &str) -> Result<T> {
-    let base_url = "https://transairobot.com";
-    let url = format!("{}/api/{}", base_url, path);
-    
-    let client = reqwest::blocking::Client::new();
-    let response = match method.to_lowercase().as_str() {
-        "get" => client.get(&url).send(),
-        "post" => client.post(&url).send(),
-        _ => return Err(RsmlError::ParseError {
-            field: "HTTP Method".to_string(),
-            message: format!("Unsupported method: {}", method),
-        }),
-    }
-    .map_err(|e| RsmlError::NetworkError(e.to_string()))?;
-
-    if response.status().is_success() {
-        let text = response.text().map_err(|e| RsmlError::NetworkError(e.to_string()))?;
-        
-        let api_response: ApiResponse<T> = serde_json::from_str(&text).map_err(|e| RsmlError::ParseError {
-            field: "API Response".to_string(),
-            message: e.to_string(),
-        })?;
-        
-        if api_response.code == 0 || api_response.code == 200 {
-            api_response.data.ok_or_else(|| RsmlError::ApiError {
-                status: api_response.code,
-                message: "API returned success code but no data".to_string(),
-            })
-        } else {
-            Err(RsmlError::ApiError {
-                status: api_response.code,
-                message: api_response.message,
-            })
+/// Base URL of the public asset server
+pub const DEFAULT_BASE_URL: &str = "https://transairobot.com";
+/// Timeout of every request, including reading the response body
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client of the robot-3d-assets endpoints of one server, reusing its connections.
+///
+/// `ApiClient::default()` reads the server from `RSML_API_URL` and a bearer token from
+/// `RSML_API_TOKEN`, falling back to the public server without authentication.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    base_url: String,
+    token: Option<String>,
+    timeout: Duration,
+    client: reqwest::blocking::Client,
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        let (base_url, token) = env_settings();
+        let client = ApiClient::new(base_url);
+        match token {
+            Some(token) => client.with_token(token),
+            None => client,
+        }
+    }
+}
+
+/// Base URL and token from `RSML_API_URL` and `RSML_API_TOKEN`
+pub(crate) fn env_settings() -> (String, Option<String>) {
+    let base_url = std::env::var("RSML_API_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+    let token = std::env::var("RSML_API_TOKEN").ok().filter(|token| !token.is_empty());
+    (base_url, token)
+}
+
+impl ApiClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        ApiClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            timeout: DEFAULT_TIMEOUT,
+            client: build_client(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request to the server
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.client = build_client(timeout);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Fetches dependency information from the asset server.
+    pub fn fetch_dependency(&self, name: &str) -> Result<Robot3DAssetCategoryRespItem> {
+        let path = format!("robot-3d-assets/categories/name?name={}", name);
+        self.call(&path, "get")
+    }
+
+    /// Fetches a paginated list of 3D assets within a specific category.
+    pub fn fetch_assets_in_category(
+        &self,
+        category_id: &str,
+        page: u32,
+        limit: u32,
+    ) -> Result<PaginationListResp<Robot3DAsset>> {
+        let path = format!("robot-3d-assets/assets?category_id={}&page={}&limit={}", category_id, page, limit);
+        self.call(&path, "get")
+    }
+
+    /// Every asset of a category, requesting the pages one after another as the iterator
+    /// advances. Assets are deduplicated by id; iteration stops at `total_pages` or at an
+    /// empty page, and an error ends it after being yielded.
+    pub fn iter_assets(&self, category_id: &str) -> AssetIter<'_> {
+        AssetIter {
+            client: self,
+            category_id: category_id.to_string(),
+            next_page: Some(1),
+            pending: Vec::new().into_iter(),
+            seen: HashSet::new(),
         }
+    }
+
+    /// Collect `iter_assets`
+    pub fn fetch_all_assets(&self, category_id: &str) -> Result<Vec<Robot3DAsset>> {
+        self.iter_assets(category_id).collect()
+    }
+
+    /// Downloads a file from a given URL and saves it to a specified path.
+    pub fn download_file(&self, url: &str, path: &Path) -> Result<()> {
+        self.download_file_verified(url, path, &DownloadOptions::default()).map(|_| ())
+    }
+
+    /// Downloads a file like `download_file`, hashing it while it is written, and returns its
+    /// hex SHA-256. A size overflow or hash mismatch deletes the partial file; network errors
+    /// are retried with backoff.
+    pub fn download_file_verified(&self, url: &str, path: &Path, options: &DownloadOptions) -> Result<String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.download_once(url, path, options) {
+                Err(RsmlError::NetworkError(_)) if attempt < options.retries => {
+                    std::thread::sleep(options.backoff * 2u32.pow(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// The token is only sent to this client's server, not to other hosts of resource URLs
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::blocking::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.token {
+            Some(token) if url.starts_with(&self.base_url) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+
+    /// Generic API call function for robot-3d-assets endpoints
+    fn call<T: for<'de> Deserialize<'de>>(&self, path: &str, method: &str) -> Result<T> {
+        let url = format!("{}/api/{}", self.base_url, path);
+        let method = match method.to_lowercase().as_str() {
+            "get" => reqwest::Method::GET,
+            "post" => reqwest::Method::POST,
+            _ => return Err(RsmlError::ParseError {
+                field: "HTTP Method".to_string(),
+                message: format!("Unsupported method: {}", method),
+            }),
+        };
+        let response = self
+            .request(method, &url)
+            .send()
+            .map_err(|e| RsmlError::NetworkError(e.to_string()))?;
+
+        let status = response.status();
+        let text = response.text().map_err(|e| RsmlError::NetworkError(e.to_string()));
+        parse_api_response(status.as_u16(), text)
+    }
+
+    fn download_once(&self, url: &str, path: &Path, options: &DownloadOptions) -> Result<String> {
+        let mut response = self
+            .request(reqwest::Method::GET, url)
+            .send()
+            .map_err(|e| RsmlError::NetworkError(e.to_string()))?;
+        let status = response.status().as_u16() as i32;
+        if !response.status().is_success() {
+            return Err(RsmlError::ApiError {
+                status,
+                message: format!("Failed to download file from {}", url),
+            });
+        }
+
+        let mut dest = fs::File::create(path)?;
+        let result = copy_verified(&mut response, &mut dest, options)
+            .map_err(|e| download_error(e, status, url));
+        if result.is_err() {
+            drop(dest);
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+}
+
+/// Verification errors of a download carry its HTTP status and URL
+pub(crate) fn download_error(error: RsmlError, status: i32, url: &str) -> RsmlError {
+    match error {
+        RsmlError::ApiError { message, .. } => RsmlError::ApiError {
+            status,
+            message: format!("{}: {}", url, message),
+        },
+        other => other,
+    }
+}
+
+/// Iterator of `ApiClient::iter_assets`
+pub struct AssetIter<'c> {
+    client: &'c ApiClient,
+    category_id: String,
+    /// None once the last page was requested or a request failed
+    next_page: Option<u32>,
+    pending: std::vec::IntoIter<Robot3DAsset>,
+    seen: HashSet<String>,
+}
+
+impl AssetIter<'_> {
+    /// Assets requested per page
+    pub const PAGE_SIZE: u32 = 100;
+}
+
+impl Iterator for AssetIter<'_> {
+    type Item = Result<Robot3DAsset>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for asset in self.pending.by_ref() {
+                if self.seen.insert(asset.id.clone()) {
+                    return Some(Ok(asset));
+                }
+            }
+            let page = self.next_page?;
+            match self.client.fetch_assets_in_category(&self.category_id, page, Self::PAGE_SIZE) {
+                Ok(response) => {
+                    // 空页说明服务器的total_pages不可信，提前结束
+                    let last = response.items.is_empty() || i64::from(page) >= response.total_pages;
+                    self.next_page = if last { None } else { Some(page + 1) };
+                    self.pending = response.items.into_iter();
+                }
+                Err(error) => {
+                    self.next_page = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+fn build_client(timeout: Duration) -> reqwest::blocking::Client {
+    reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("failed to initialize the HTTP client")
+}
+
+/// Unwrap the `ApiResponse` envelope of a response body
+pub(crate) fn parse_api_response<T: for<'de> Deserialize<'de>>(status: u16, text: Result<String>) -> Result<T> {
+    if !(200..300).contains(&status) {
+        return Err(RsmlError::ApiError {
+            status: status as i32,
+            message: text.unwrap_or_else(|_| "Unknown error".to_string()),
+        });
+    }
+    let text = text?;
+    let api_response: ApiResponse<T> = serde_json::from_str(&text).map_err(|e| RsmlError::ParseError {
+        field: "API Response".to_string(),
+        message: e.to_string(),
+    })?;
+
+    if api_response.code == 0 || api_response.code == 200 {
+        api_response.data.ok_or_else(|| RsmlError::ApiError {
+            status: api_response.code,
+            message: "API returned success code but no data".to_string(),
+        })
     } else {
         Err(RsmlError::ApiError {
-            status: response.status().as_u16() as i32,
-            message: response.text().unwrap_or_else(|_| "Unknown error".to_string()),
+            status: api_response.code,
+            message: api_response.message,
         })
     }
 }
 
 /// Fetches dependency information from the remote asset server.
 pub fn fetch_dependency(name: &str) -> Result<Robot3DAssetCategoryRespItem> {
-    let path = format!("robot-3d-assets/categories/name?name={}", name);
-    api_call(&path, "get")
+    ApiClient::default().fetch_dependency(name)
 }
 
 /// Fetches a paginated list of 3D assets within a specific category.
@@ -100,27 +325,307 @@ pub fn fetch_assets_in_category(
     page: u32,
     limit: u32,
 ) -> Result<PaginationListResp<Robot3DAsset>> {
-    let path = format!("robot-3d-assets/assets?category_id={}&page={}&limit={}", category_id, page, limit);
-    api_call(&path, "get")
+    ApiClient::default().fetch_assets_in_category(&category_id, page, limit)
+}
+
+/// Checks applied while downloading a file
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Expected hex SHA-256 of the content
+    pub sha256: Option<String>,
+    /// Largest accepted content, in bytes
+    pub max_bytes: Option<u64>,
+    /// How many times a network error is retried
+    pub retries: u32,
+    /// Wait before the first retry; doubled for every further retry
+    pub backoff: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            sha256: None,
+            max_bytes: None,
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
 }
 
 /// Downloads a file from a given URL and saves it to a specified path.
 pub fn download_file(url: &str, path: &Path) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+    ApiClient::default().download_file(url, path)
+}
+
+/// Same as `ApiClient::download_file_verified` with the default client
+pub fn download_file_verified(url: &str, path: &Path, options: &DownloadOptions) -> Result<String> {
+    ApiClient::default().download_file_verified(url, path, options)
+}
+
+/// Copy `reader` into `dest` while hashing it and return the hex SHA-256.
+///
+/// Going over `max_bytes` or a different hash is an `ApiError`; read errors are network
+/// errors, since the reader is a response body.
+pub fn copy_verified(reader: &mut impl Read, dest: &mut impl Write, options: &DownloadOptions) -> Result<String> {
+    let mut verifier = Verifier::new(options);
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(RsmlError::NetworkError(e.to_string())),
+        };
+        verifier.update(&buffer[..read])?;
+        dest.write_all(&buffer[..read])?;
     }
+    verifier.finish()
+}
 
-    let mut response =
-        reqwest::blocking::get(url).map_err(|e| RsmlError::NetworkError(e.to_string()))?;
-    let mut dest = fs::File::create(path)?;
+/// Size limit and hash of a download, checked chunk by chunk
+pub(crate) struct Verifier<'o> {
+    options: &'o DownloadOptions,
+    hasher: Sha256,
+    total: u64,
+}
 
-    if response.status().is_success() {
-        copy(&mut response, &mut dest)?;
+impl<'o> Verifier<'o> {
+    pub(crate) fn new(options: &'o DownloadOptions) -> Self {
+        Verifier {
+            options,
+            hasher: Sha256::new(),
+            total: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, chunk: &[u8]) -> Result<()> {
+        self.total += chunk.len() as u64;
+        if let Some(max_bytes) = self.options.max_bytes.filter(|max_bytes| self.total > *max_bytes) {
+            return Err(RsmlError::ApiError {
+                status: 0,
+                message: format!("download exceeds the limit of {} bytes", max_bytes),
+            });
+        }
+        self.hasher.update(chunk);
         Ok(())
-    } else {
-        Err(RsmlError::ApiError {
-            status: response.status().as_u16() as i32,
-            message: format!("Failed to download file from {}", url),
-        })
+    }
+
+    /// The hex SHA-256, or an error when it is not the expected one
+    pub(crate) fn finish(self) -> Result<String> {
+        let actual = hex(&self.hasher.finalize());
+        match &self.options.sha256 {
+            Some(expected) if !expected.eq_ignore_ascii_case(&actual) => Err(RsmlError::ApiError {
+                status: 0,
+                message: format!("sha256 mismatch: expected {}, got {}", expected, actual),
+            }),
+            _ => Ok(actual),
+        }
+    }
+}
+
+/// Lowercase hex of a digest
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    /// Returns its data, then fails like a dropped connection
+    struct Truncated<'a>(&'a [u8]);
+
+    impl Read for Truncated<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset"));
+            }
+            let read = self.0.read(buf)?;
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_copy_verified() {
+        let mut dest = Vec::new();
+        let options = DownloadOptions {
+            sha256: Some(TEST_SHA256.to_uppercase()),
+            ..DownloadOptions::default()
+        };
+        let sha256 = copy_verified(&mut "test".as_bytes(), &mut dest, &options).unwrap();
+        assert_eq!(sha256, TEST_SHA256);
+        assert_eq!(dest, b"test");
+
+        let error = copy_verified(&mut "tesT".as_bytes(), &mut Vec::new(), &options).unwrap_err();
+        assert!(error.to_string().contains("sha256 mismatch: expected"), "{}", error);
+
+        let limited = DownloadOptions {
+            max_bytes: Some(3),
+            ..DownloadOptions::default()
+        };
+        let error = copy_verified(&mut "test".as_bytes(), &mut Vec::new(), &limited).unwrap_err();
+        assert!(error.to_string().contains("exceeds the limit of 3 bytes"), "{}", error);
+
+        let error = copy_verified(&mut Truncated(b"te"), &mut Vec::new(), &options).unwrap_err();
+        assert!(matches!(error, RsmlError::NetworkError(_)), "{}", error);
+    }
+
+    /// Answers every request with `body` and sends the request line and headers back
+    fn serve(body: &'static str) -> (String, std::sync::mpsc::Receiver<Vec<String>>) {
+        serve_with(move |_| body.to_string())
+    }
+
+    /// Answers every request with the body `respond` returns for its target
+    fn serve_with(
+        respond: impl Fn(&str) -> String + Send + 'static,
+    ) -> (String, std::sync::mpsc::Receiver<Vec<String>>) {
+        use std::io::{BufRead, BufReader};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = Vec::new();
+                let mut reader = BufReader::new(&stream);
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() <= 2 {
+                        break;
+                    }
+                    lines.push(line.trim_end().to_string());
+                }
+                let target = lines[0].split_whitespace().nth(1).unwrap_or("").to_string();
+                let body = respond(&target);
+                let _ = sender.send(lines);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (base_url, receiver)
+    }
+
+    #[test]
+    fn test_client_base_url_and_token() {
+        let (base_url, requests) = serve(
+            r#"{"code":0,"message":"ok","data":{"id":"c7","name":"cutlery","description":"","utime":0,"ctime":0,"asset_count":2}}"#,
+        );
+        let client = ApiClient::new(format!("{}/staging/", base_url))
+            .with_token("secret")
+            .with_timeout(Duration::from_secs(5));
+        assert_eq!(client.base_url(), format!("{}/staging", base_url));
+        assert_eq!(client.fetch_dependency("cutlery").unwrap().id, "c7");
+        let request = requests.recv().unwrap();
+        assert_eq!(request[0], "GET /staging/api/robot-3d-assets/categories/name?name=cutlery HTTP/1.1");
+        assert!(
+            request.iter().any(|line| line.eq_ignore_ascii_case("authorization: Bearer secret")),
+            "{:?}",
+            request
+        );
+
+        // 其他主机上的资源文件不带token
+        let other = ApiClient::new("http://localhost:1").with_token("secret");
+        let path = std::env::temp_dir().join(format!("rsmlc_token_{}", std::process::id()));
+        other.download_file(&format!("{}/files/a1.obj", base_url), &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let request = requests.recv().unwrap();
+        assert!(!request.iter().any(|line| line.to_lowercase().starts_with("authorization")));
+    }
+
+    fn asset_page(ids: &[&str], page: u32, total_pages: u32) -> String {
+        let items: Vec<String> = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{"id":"{0}","name":"{0}","resource_url":"","x_len":1.0,"y_len":1.0,"z_len":1.0}}"#,
+                    id
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"code":0,"message":"ok","data":{{"items":[{}],"total":0,"total_pages":{},"page":{}}}}}"#,
+            items.join(","),
+            total_pages,
+            page
+        )
+    }
+
+    fn ids(assets: impl IntoIterator<Item = Result<Robot3DAsset>>) -> Vec<String> {
+        assets.into_iter().map(|asset| asset.unwrap().id).collect()
+    }
+
+    #[test]
+    fn test_iter_assets_pages() {
+        let (base_url, requests) = serve_with(|target| {
+            let page = target.split("page=").nth(1).and_then(|rest| rest.split('&').next());
+            match page {
+                Some("1") => asset_page(&["a1", "a2"], 1, 3),
+                // 第2页重复了a2
+                Some("2") => asset_page(&["a2", "a3"], 2, 3),
+                Some("3") => asset_page(&["a4"], 3, 3),
+                _ => asset_page(&["a5"], 4, 3),
+            }
+        });
+        let client = ApiClient::new(base_url);
+        assert_eq!(ids(client.iter_assets("c7")), ["a1", "a2", "a3", "a4"]);
+        let pages: Vec<String> = requests.try_iter().map(|request| request[0].clone()).collect();
+        assert_eq!(pages.len(), 3);
+        assert!(pages[2].contains("category_id=c7&page=3&limit=100"), "{}", pages[2]);
+
+        // 服务器声称有5页，但第2页是空的
+        let (base_url, requests) = serve_with(|target| match target.contains("page=1&") {
+            true => asset_page(&["a1"], 1, 5),
+            false => asset_page(&[], 2, 5),
+        });
+        let assets = ApiClient::new(base_url).fetch_all_assets("c7").unwrap();
+        assert_eq!(assets.len(), 1);
+        assert_eq!(requests.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_iter_assets_error_is_yielded() {
+        let (base_url, _requests) = serve_with(|target| match target.contains("page=1&") {
+            true => asset_page(&["a1", "a2"], 1, 3),
+            false => r#"{"code":500,"message":"database unavailable","data":null}"#.to_string(),
+        });
+        let client = ApiClient::new(base_url);
+        let mut assets = client.iter_assets("c7");
+        assert_eq!(assets.next().unwrap().unwrap().id, "a1");
+        assert_eq!(assets.next().unwrap().unwrap().id, "a2");
+        let error = assets.next().unwrap().unwrap_err();
+        assert!(error.to_string().contains("database unavailable"), "{}", error);
+        assert!(assets.next().is_none());
+        assert!(client.fetch_all_assets("c7").is_err());
+    }
+
+    #[test]
+    fn test_download_removes_corrupt_file() {
+        let (base_url, _requests) = serve("test");
+        let url = format!("{}/files/a1.obj", base_url);
+        let client = ApiClient::new(base_url);
+        let path = std::env::temp_dir().join(format!("rsmlc_download_{}", std::process::id()));
+
+        let options = DownloadOptions {
+            sha256: Some(TEST_SHA256.to_string()),
+            ..DownloadOptions::default()
+        };
+        assert_eq!(client.download_file_verified(&url, &path, &options).unwrap(), TEST_SHA256);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "test");
+
+        let options = DownloadOptions {
+            sha256: Some("00".repeat(32)),
+            ..DownloadOptions::default()
+        };
+        let error = client.download_file_verified(&url, &path, &options).unwrap_err();
+        assert!(matches!(error, RsmlError::ApiError { status: 200, .. }), "{}", error);
+        assert!(!path.exists());
     }
 }
@@ -0,0 +1,228 @@
+//! 资源API的异步版本，需要开启`async` feature
+//!
+//! `AsyncApiClient`与`api::ApiClient`对应，方法返回future；`download_all`并发下载多个文件，
+//! 对有数百个资源的依赖很有用。默认仍使用阻塞的`ApiClient`
+
+use crate::api::{
+    download_error, env_settings, parse_api_response, DownloadOptions, PaginationListResp,
+    Robot3DAsset, Robot3DAssetCategoryRespItem, Verifier, DEFAULT_TIMEOUT,
+};
+use crate::error::{Result, RsmlError};
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// 一个服务器的robot-3d-assets接口的异步客户端；参见`api::ApiClient`
+#[derive(Debug, Clone)]
+pub struct AsyncApiClient {
+    base_url: String,
+    token: Option<String>,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+/// `AsyncApiClient::download_all`要下载的一个文件
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub url: String,
+    pub path: PathBuf,
+    pub options: DownloadOptions,
+}
+
+impl Download {
+    /// 资源的文件，未校验
+    pub fn asset(asset: &Robot3DAsset, path: PathBuf) -> Self {
+        Download {
+            url: asset.resource_url.clone(),
+            path,
+            options: DownloadOptions::default(),
+        }
+    }
+}
+
+/// 每完成一个下载后报告
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'d> {
+    /// 目前已完成的下载数，包括这一个
+    pub completed: usize,
+    pub total: usize,
+    pub download: &'d Download,
+}
+
+impl Default for AsyncApiClient {
+    fn default() -> Self {
+        let (base_url, token) = env_settings();
+        let client = AsyncApiClient::new(base_url);
+        match token {
+            Some(token) => client.with_token(token),
+            None => client,
+        }
+    }
+}
+
+impl AsyncApiClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        AsyncApiClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            timeout: DEFAULT_TIMEOUT,
+            client: build_client(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// 每次请求服务器时都发送`Authorization: Bearer <token>`
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.client = build_client(timeout);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// 从资源服务器获取依赖信息
+    pub async fn fetch_dependency(&self, name: &str) -> Result<Robot3DAssetCategoryRespItem> {
+        let path = format!("robot-3d-assets/categories/name?name={}", name);
+        self.call(&path).await
+    }
+
+    /// 分页获取某个分类下的3D资源列表
+    pub async fn fetch_assets_in_category(
+        &self,
+        category_id: &str,
+        page: u32,
+        limit: u32,
+    ) -> Result<PaginationListResp<Robot3DAsset>> {
+        let path = format!("robot-3d-assets/assets?category_id={}&page={}&limit={}", category_id, page, limit);
+        self.call(&path).await
+    }
+
+    /// 与`ApiClient::download_file_verified`相同
+    pub async fn download_file_verified(&self, url: &str, path: &Path, options: &DownloadOptions) -> Result<String> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.download_once(url, path, options).await {
+                Err(RsmlError::NetworkError(_)) if attempt < options.retries => {
+                    tokio::time::sleep(options.backoff * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 下载所有文件，同时最多进行`concurrency`个下载，每完成一个调用一次`progress`，
+    /// 按`downloads`的顺序返回它们的十六进制SHA-256
+    ///
+    /// 返回第一个失败；在它之前完成的文件保留在磁盘上
+    pub async fn download_all(
+        &self,
+        downloads: &[Download],
+        concurrency: usize,
+        mut progress: impl FnMut(Progress<'_>),
+    ) -> Result<Vec<String>> {
+        let mut hashes = vec![String::new(); downloads.len()];
+        let mut finished = stream::iter(downloads.iter().enumerate())
+            .map(|(index, download)| async move {
+                let result = self
+                    .download_file_verified(&download.url, &download.path, &download.options)
+                    .await;
+                (index, result)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        let mut completed = 0;
+        while let Some((index, result)) = finished.next().await {
+            hashes[index] = result?;
+            completed += 1;
+            progress(Progress {
+                completed,
+                total: downloads.len(),
+                download: &downloads[index],
+            });
+        }
+        Ok(hashes)
+    }
+
+    /// token只发送给本客户端的服务器，不会发送给资源URL中的其他主机
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.token {
+            Some(token) if url.starts_with(&self.base_url) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+
+    async fn call<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        let url = format!("{}/api/{}", self.base_url, path);
+        let response = self
+            .request(reqwest::Method::GET, &url)
+            .send()
+            .await
+            .map_err(|e| RsmlError::NetworkError(e.to_string()))?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|e| RsmlError::NetworkError(e.to_string()));
+        parse_api_response(status.as_u16(), text)
+    }
+
+    async fn download_once(&self, url: &str, path: &Path, options: &DownloadOptions) -> Result<String> {
+        let mut response = self
+            .request(reqwest::Method::GET, url)
+            .send()
+            .await
+            .map_err(|e| RsmlError::NetworkError(e.to_string()))?;
+        let status = response.status().as_u16() as i32;
+        if !response.status().is_success() {
+            return Err(RsmlError::ApiError {
+                status,
+                message: format!("Failed to download file from {}", url),
+            });
+        }
+
+        let mut dest = tokio::fs::File::create(path).await?;
+        let mut verifier = Verifier::new(options);
+        let result = async {
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| RsmlError::NetworkError(e.to_string()))?
+            {
+                verifier.update(&chunk)?;
+                dest.write_all(&chunk).await?;
+            }
+            dest.flush().await?;
+            verifier.finish()
+        }
+        .await
+        .map_err(|e| download_error(e, status, url));
+        if result.is_err() {
+            drop(dest);
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        result
+    }
+}
+
+fn build_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("failed to initialize the HTTP client")
+}
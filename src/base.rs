@@ -79,33 +79,154 @@ impl<T> Default for Auto<T> {
     }
 }
 
-/// A length structure with millimeters as the base unit, stored as u32
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
-pub struct Length(u32);
+/// A length structure with millimeters as the base unit, stored as u32.
+///
+/// Lengths parsed from a string remember the number and unit they were written with,
+/// so Display round-trips "500cm" instead of printing "5m", and "1500um" instead of
+/// the rounded "2000um". The authored form does not take part in comparisons, and
+/// lengths created by constructors or arithmetic have none.
+#[derive(Debug, Clone, Copy)]
+pub struct Length(u32, Option<Authored>);
+
+/// The number and unit a length was written with
+#[derive(Debug, Clone, Copy)]
+struct Authored {
+    value: f64,
+    unit: LengthUnit,
+}
+
+/// Units accepted by Length::from_str
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    /// Micrometers, stored rounded to the nearest millimeter
+    Um,
+    Mm,
+    Cm,
+    Dm,
+    M,
+    Km,
+}
+
+impl LengthUnit {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "um" | "µm" | "μm" => Some(LengthUnit::Um),
+            "mm" => Some(LengthUnit::Mm),
+            "cm" => Some(LengthUnit::Cm),
+            "dm" => Some(LengthUnit::Dm),
+            "m" => Some(LengthUnit::M),
+            "km" => Some(LengthUnit::Km),
+            _ => None,
+        }
+    }
+
+    /// Millimeters per unit
+    const fn mm_per_unit(&self) -> f64 {
+        match self {
+            LengthUnit::Um => 0.001,
+            LengthUnit::Mm => 1.0,
+            LengthUnit::Cm => 10.0,
+            LengthUnit::Dm => 100.0,
+            LengthUnit::M => 1000.0,
+            LengthUnit::Km => 1_000_000.0,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LengthUnit::Um => "um",
+            LengthUnit::Mm => "mm",
+            LengthUnit::Cm => "cm",
+            LengthUnit::Dm => "dm",
+            LengthUnit::M => "m",
+            LengthUnit::Km => "km",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl PartialEq for Length {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Length {}
+
+impl PartialOrd for Length {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Length {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Serialize for Length {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
 
-/// A percentage structure stored as u32 (0-100%)
+/// A percentage stored in basis points (hundredths of a percent), so 12.5% is 1250
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct Percentage(u32);
 
 impl Length {
     /// Create a new Length from millimeters
     pub const fn from_mm(mm: u32) -> Self {
-        Length(mm)
+        Length(mm, None)
     }
 
     /// Create a new Length from centimeters
     pub const fn from_cm(cm: u32) -> Self {
-        Length(cm * 10)
+        Length(cm * 10, None)
     }
 
     /// Create a new Length from decimeters
     pub const fn from_dm(dm: u32) -> Self {
-        Length(dm * 100)
+        Length(dm * 100, None)
+    }
+
+    /// Create a new Length from fractional centimeters, rounded to the nearest millimeter
+    pub const fn from_cm_f64(cm: f64) -> Self {
+        Length(round_mm(cm * 10.0), None)
     }
 
-    /// Create a new Length from meters
+    /// Create a new Length from (fractional) meters, rounded to the nearest millimeter
     pub const fn from_m(m: f64) -> Self {
-        Length((m * 1000.0) as u32)
+        Length(round_mm(m * 1000.0), None)
+    }
+
+    /// Create a new Length from micrometers, rounded to the nearest millimeter
+    pub const fn from_um(um: f64) -> Self {
+        Length(round_mm(um / 1000.0), None)
+    }
+
+    /// Create a new Length from (fractional) kilometers, rounded to the nearest millimeter.
+    /// Values beyond u32::MAX millimeters (about 4294km) saturate.
+    pub const fn from_km(km: f64) -> Self {
+        Length(round_mm(km * 1_000_000.0), None)
+    }
+
+    /// The unit the length was written in, if it was parsed from a string
+    pub const fn unit(&self) -> Option<LengthUnit> {
+        match self.1 {
+            Some(authored) => Some(authored.unit),
+            None => None,
+        }
+    }
+
+    /// Whether the authored value was rounded to store it in whole millimeters, e.g. "1500um"
+    pub fn is_rounded(&self) -> bool {
+        self.1.is_some_and(|authored| {
+            (authored.value * authored.unit.mm_per_unit() - self.0 as f64).abs() > 1e-6
+        })
     }
 
     /// Get the length in millimeters
@@ -127,15 +248,47 @@ impl Length {
     pub const fn m(&self) -> u32 {
         self.0 / 1000
     }
+
+    /// Multiply by a factor, rounded to the nearest millimeter.
+    /// Negative and NaN results become 0, results beyond u32::MAX millimeters saturate.
+    pub const fn scale(self, factor: f64) -> Self {
+        Length(round_mm(self.0 as f64 * factor), None)
+    }
+
+    /// Add two lengths, returning None instead of saturating when the sum exceeds u32::MAX millimeters
+    pub const fn checked_add(self, other: Length) -> Option<Self> {
+        match self.0.checked_add(other.0) {
+            Some(mm) => Some(Length(mm, None)),
+            None => None,
+        }
+    }
+
+    /// Subtract a length, returning None instead of saturating at 0 when other is longer
+    pub const fn checked_sub(self, other: Length) -> Option<Self> {
+        match self.0.checked_sub(other.0) {
+            Some(mm) => Some(Length(mm, None)),
+            None => None,
+        }
+    }
+}
+
+/// Round a millimeter value to the nearest whole millimeter, halves away from zero.
+/// Negative values and NaN become 0, values beyond u32::MAX saturate.
+const fn round_mm(mm: f64) -> u32 {
+    (mm + 0.5) as u32
 }
 
 impl fmt::Display for Length {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.0 % 1000 == 0 {
+        if let Some(authored) = self.1 {
+            // f64的Display是能还原数值的最短表示，例如1.5
+            return write!(f, "{}{}", authored.value, authored.unit);
+        }
+        if self.0.is_multiple_of(1000) {
             write!(f, "{}m", self.0 / 1000)
-        } else if self.0 % 100 == 0 {
+        } else if self.0.is_multiple_of(100) {
             write!(f, "{}dm", self.0 / 100)
-        } else if self.0 % 10 == 0 {
+        } else if self.0.is_multiple_of(10) {
             write!(f, "{}cm", self.0 / 10)
         } else {
             write!(f, "{}mm", self.0)
@@ -145,7 +298,7 @@ impl fmt::Display for Length {
 
 impl Default for Length {
     fn default() -> Self {
-        Self(Default::default())
+        Length(0, None)
     }
 }
 
@@ -176,22 +329,17 @@ impl FromStr for Length {
             message: format!("Invalid number: {}", number_str),
         })?;
 
+        // 没有单位时按毫米处理，但不记录单位
         let unit_str = &s[split_pos..].trim_start().to_lowercase();
-        let multiplier = match unit_str.as_str() {
-            "mm" => 1.0,
-            "cm" => 10.0,
-            "dm" => 100.0,
-            "m" => 1000.0,
-            "" => 1.0,
-            _ => {
-                return Err(RsmlError::ParseError {
-                    field: "Length".to_string(),
-                    message: format!("Unknown unit: {}", unit_str),
-                });
-            }
+        let unit = match unit_str.as_str() {
+            "" => None,
+            unit => Some(LengthUnit::parse(unit).ok_or_else(|| RsmlError::ParseError {
+                field: "Length".to_string(),
+                message: format!("Unknown unit: {}", unit_str),
+            })?),
         };
 
-        let mm_value = number * multiplier;
+        let mm_value = number * unit.map_or(1.0, |unit| unit.mm_per_unit());
 
         if mm_value < 0.0 {
             return Err(RsmlError::ParseError {
@@ -207,7 +355,16 @@ impl FromStr for Length {
             });
         }
 
-        Ok(Length(mm_value as u32))
+        // 长度精确到毫米，非零的微米值不能舍入为0
+        if mm_value > 0.0 && round_mm(mm_value) == 0 {
+            return Err(RsmlError::ParseError {
+                field: "Length".to_string(),
+                message: format!("Length {} is below the 1mm resolution", s),
+            });
+        }
+
+        let authored = unit.map(|unit| Authored { value: number, unit });
+        Ok(Length(round_mm(mm_value), authored))
     }
 }
 
@@ -225,7 +382,7 @@ impl std::ops::Add for Length {
     type Output = Length;
 
     fn add(self, other: Length) -> Length {
-        Length(self.0.saturating_add(other.0))
+        Length(self.0.saturating_add(other.0), None)
     }
 }
 
@@ -233,7 +390,7 @@ impl std::ops::Sub for Length {
     type Output = Length;
 
     fn sub(self, other: Length) -> Length {
-        Length(self.0.saturating_sub(other.0))
+        Length(self.0.saturating_sub(other.0), None)
     }
 }
 
@@ -241,7 +398,7 @@ impl std::ops::Mul<u32> for Length {
     type Output = Length;
 
     fn mul(self, scalar: u32) -> Length {
-        Length(self.0.saturating_mul(scalar))
+        Length(self.0.saturating_mul(scalar), None)
     }
 }
 
@@ -250,9 +407,29 @@ impl std::ops::Div<u32> for Length {
 
     fn div(self, scalar: u32) -> Length {
         if scalar == 0 {
-            Length(0)
+            Length(0, None)
         } else {
-            Length(self.0 / scalar)
+            Length(self.0 / scalar, None)
+        }
+    }
+}
+
+impl std::ops::Mul<f64> for Length {
+    type Output = Length;
+
+    fn mul(self, factor: f64) -> Length {
+        self.scale(factor)
+    }
+}
+
+impl std::ops::Div<f64> for Length {
+    type Output = Length;
+
+    fn div(self, divisor: f64) -> Length {
+        if divisor == 0.0 {
+            Length(0, None)
+        } else {
+            self.scale(1.0 / divisor)
         }
     }
 }
@@ -265,27 +442,55 @@ impl std::ops::AddAssign for Length {
 
 impl std::iter::Sum for Length {
     fn sum<I: Iterator<Item = Length>>(iter: I) -> Length {
-        iter.fold(Length(0), |acc, x| acc + x)
+        iter.fold(Length(0, None), |acc, x| acc + x)
     }
 }
 
 impl Percentage {
+    /// Create a new Percentage from whole percent
     pub const fn new(value: u32) -> Self {
-        Percentage(value)
+        Percentage(value * 100)
+    }
+
+    /// Create a new Percentage from basis points
+    pub const fn from_basis_points(basis_points: u32) -> Self {
+        Percentage(basis_points)
     }
 
+    /// Get the percentage in whole percent (truncated)
     pub const fn value(&self) -> u32 {
+        self.0 / 100
+    }
+
+    /// Get the percentage in basis points
+    pub const fn basis_points(&self) -> u32 {
         self.0
     }
 
     pub fn float(&self) -> f32 {
-        self.0 as f32 / 100.0
+        self.0 as f32 / 10000.0
+    }
+
+    /// Whether the percentage is larger than 100%
+    pub const fn exceeds_full(&self) -> bool {
+        self.0 > 10000
+    }
+
+    /// The percentage of a length, rounded to the nearest millimeter
+    pub const fn of(&self, length: Length) -> Length {
+        Length(((length.0 as u64 * self.0 as u64 + 5000) / 10000) as u32, None)
     }
 }
 
 impl fmt::Display for Percentage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}%", self.0)
+        if self.0.is_multiple_of(100) {
+            write!(f, "{}%", self.0 / 100)
+        } else if self.0.is_multiple_of(10) {
+            write!(f, "{}.{}%", self.0 / 100, self.0 % 100 / 10)
+        } else {
+            write!(f, "{}.{:02}%", self.0 / 100, self.0 % 100)
+        }
     }
 }
 
@@ -315,20 +520,18 @@ impl FromStr for Percentage {
             });
         }
 
+        // 允许小数，精确到0.01%；大于100%的值在这里不报错，由使用方决定是否允许
         let number_str = &s[..s.len() - 1];
-        let number: u32 = number_str.parse().map_err(|_| RsmlError::ParseError {
-            field: "Percentage".to_string(),
-            message: format!("Invalid number: {}", number_str),
-        })?;
-
-        if number > 100 {
-            return Err(RsmlError::ParseError {
+        let number: f64 = number_str
+            .parse()
+            .ok()
+            .filter(|number: &f64| number.is_finite() && *number >= 0.0 && !number_str.starts_with('+'))
+            .ok_or_else(|| RsmlError::ParseError {
                 field: "Percentage".to_string(),
-                message: "Percentage cannot be greater than 100%".to_string(),
-            });
-        }
+                message: format!("Invalid number: {}", number_str),
+            })?;
 
-        Ok(Percentage(number))
+        Ok(Percentage((number * 100.0).round() as u32))
     }
 }
 
@@ -367,6 +570,65 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_percentage_from_str() {
+        let percentage: Percentage = "12.5%".parse().unwrap();
+        assert_eq!(percentage.basis_points(), 1250);
+        assert_eq!(percentage.value(), 12);
+        assert_eq!(percentage.to_string(), "12.5%");
+        assert_eq!("33.33%".parse::<Percentage>().unwrap().to_string(), "33.33%");
+        assert_eq!("50%".parse::<Percentage>().unwrap(), Percentage::new(50));
+
+        // 大于100%的值可以解析，由使用方决定是否允许
+        assert!("150%".parse::<Percentage>().unwrap().exceeds_full());
+        assert!(!"100%".parse::<Percentage>().unwrap().exceeds_full());
+
+        assert!("-5%".parse::<Percentage>().is_err());
+        assert!("abc%".parse::<Percentage>().is_err());
+        assert!("12.5".parse::<Percentage>().is_err());
+    }
+
+    #[test]
+    fn test_percentage_of_length() {
+        let third: Percentage = "33.33%".parse().unwrap();
+        assert_eq!(third.of(Length::from_m(3.0)).mm(), 1000);
+        assert_eq!(third.of(Length::from_m(1.0)).mm(), 333);
+        assert_eq!(Percentage::new(150).of(Length::from_cm(10)).mm(), 150);
+    }
+
+    #[test]
+    fn test_length_scale_rounds_to_nearest() {
+        let meter = Length::from_m(1.0);
+        assert_eq!(meter.scale(0.3333).mm(), 333);
+        assert_eq!(meter.scale(0.33).mm(), 330);
+        assert_eq!(Length::from_mm(3).scale(0.5).mm(), 2);
+        assert_eq!(Length::from_mm(5).scale(0.1).mm(), 1);
+        assert_eq!(Length::from_mm(4).scale(0.1).mm(), 0);
+        assert_eq!((meter * 1.5).mm(), 1500);
+        assert_eq!((meter / 3.0).mm(), 333);
+        assert_eq!((Length::from_mm(2) / 3.0).mm(), 1);
+        // 负数和NaN为0，超出范围时饱和，除以0和Div<u32>一样为0
+        assert_eq!((meter * -1.0).mm(), 0);
+        assert_eq!((meter * f64::NAN).mm(), 0);
+        assert_eq!((Length::from_mm(u32::MAX) * 2.0).mm(), u32::MAX);
+        assert_eq!((meter / 0.0).mm(), 0);
+        // 结果不保留单位
+        assert_eq!(("1.5m".parse::<Length>().unwrap() * 2.0).to_string(), "3m");
+    }
+
+    #[test]
+    fn test_length_checked_arithmetic() {
+        let max = Length::from_mm(u32::MAX);
+        assert_eq!(Length::from_cm(1).checked_add(Length::from_mm(5)), Some(Length::from_mm(15)));
+        assert_eq!(max.checked_add(Length::from_mm(0)), Some(max));
+        assert_eq!(max.checked_add(Length::from_mm(1)), None);
+        assert_eq!(Length::from_cm(1).checked_sub(Length::from_mm(4)), Some(Length::from_mm(6)));
+        assert_eq!(Length::from_mm(4).checked_sub(Length::from_cm(1)), None);
+        // 普通运算仍然饱和
+        assert_eq!(max + Length::from_mm(1), max);
+        assert_eq!(Length::from_mm(4) - Length::from_cm(1), Length::from_mm(0));
+    }
+
     #[test]
     fn test_length_from_str() {
         let len: Length = "3mm".parse().unwrap();
@@ -385,6 +647,40 @@ mod tests {
         assert_eq!(len.mm(), 10);
     }
 
+    #[test]
+    fn test_length_um_and_km() {
+        let len: Length = "2km".parse().unwrap();
+        assert_eq!(len.mm(), 2_000_000);
+        assert_eq!(len, Length::from_km(2.0));
+        let len: Length = "1500um".parse().unwrap();
+        assert_eq!(len.mm(), 2);
+        assert_eq!("1500µm".parse::<Length>().unwrap(), len);
+        // 显示书写的值，但标记为已舍入
+        assert_eq!(len.to_string(), "1500um");
+        assert!(len.is_rounded());
+        assert!(!"2000um".parse::<Length>().unwrap().is_rounded());
+        assert!(!"0.3m".parse::<Length>().unwrap().is_rounded());
+        assert_eq!(Length::from_um(1499.0).mm(), 1);
+
+        assert_eq!(Length::from_km(4294.0).mm(), 4_294_000_000);
+        // 构造函数超出范围时饱和
+        assert_eq!(Length::from_km(5000.0).mm(), u32::MAX);
+    }
+
+    #[test]
+    fn test_length_display_preserves_unit() {
+        for authored in ["500cm", "1.5m", "2km", "0.25km", "12mm", "3dm", "1.55cm"] {
+            let len: Length = authored.parse().unwrap();
+            assert_eq!(len.to_string(), authored);
+        }
+        let len: Length = "500cm".parse().unwrap();
+        assert_eq!(len, Length::from_m(5.0));
+        assert_eq!(len.unit(), Some(LengthUnit::Cm));
+        // 计算结果没有单位，使用能精确表示的最大单位
+        assert_eq!((len + Length::from_cm(50)).to_string(), "55dm");
+        assert_eq!("10".parse::<Length>().unwrap().to_string(), "1cm");
+    }
+
     #[test]
     fn test_length_from_dm() {
         let len = Length::from_dm(5);
@@ -392,6 +688,30 @@ mod tests {
         assert_eq!(len.dm(), 5);
     }
 
+    #[test]
+    fn test_length_fractional_constructors() {
+        assert_eq!(Length::from_m(2.5).mm(), 2500);
+        assert_eq!(Length::from_m(1.001).mm(), 1001);
+        assert_eq!(Length::from_m(0.0005).mm(), 1);
+        assert_eq!(Length::from_m(0.0004).mm(), 0);
+        assert_eq!(Length::from_m(-1.0).mm(), 0);
+        assert_eq!(Length::from_cm_f64(1.5).mm(), 15);
+        assert_eq!(Length::from_cm_f64(0.04).mm(), 0);
+        assert_eq!(Length::from_cm_f64(12.0), Length::from_cm(12));
+
+        // 解析同样四舍五入到毫米
+        let len: Length = "1.001m".parse().unwrap();
+        assert_eq!(len.mm(), 1001);
+        let len: Length = "1.5cm".parse().unwrap();
+        assert_eq!(len, Length::from_cm_f64(1.5));
+
+        // Display使用能精确表示的最大单位
+        assert_eq!(Length::from_m(2.5).to_string(), "25dm");
+        assert_eq!(Length::from_m(1.001).to_string(), "1001mm");
+        assert_eq!(Length::from_cm_f64(1.5).to_string(), "15mm");
+        assert_eq!(Length::from_m(3.0).to_string(), "3m");
+    }
+
     #[test]
     fn test_length_display() {
         let len = Length::from_dm(5);
@@ -406,7 +726,14 @@ mod tests {
         let result: Result<Length, _> = "abcmm".parse();
         assert!(result.is_err());
 
-        let result: Result<Length, _> = "5km".parse();
+        let result: Result<Length, _> = "5ft".parse();
+        assert!(result.is_err());
+
+        // 超出u32毫米范围的公里数报错
+        let result: Result<Length, _> = "5000km".parse();
+        assert!(result.is_err());
+
+        let result: Result<Length, _> = "100um".parse();
         assert!(result.is_err());
 
         let result: Result<Length, _> = "-5mm".parse();
@@ -0,0 +1,211 @@
+use crate::compile::{self, CompileOptions, RsmlSource};
+use crate::error::{Result, RsmlError};
+use crate::target::TargetFormat;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 批量编译的选项
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub global_seed: u64,
+    /// 工作线程数，至少为1
+    pub jobs: usize,
+    /// 推导seed之前，场景路径先转换为相对这个目录的路径
+    pub root: PathBuf,
+    pub out_dir: PathBuf,
+    /// 所有场景共用的编译选项，包括package；`rsml`、`seed`和`target`按场景设置
+    pub compile: CompileOptions,
+}
+
+/// 写在每个编译结果旁边的元数据文件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SceneMetadata {
+    /// 场景规范化的相对路径
+    pub scene: String,
+    pub global_seed: u64,
+    /// 该场景布局使用的seed
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneResult {
+    pub metadata: SceneMetadata,
+    pub output: PathBuf,
+}
+
+/// 由全局seed和场景规范化的相对路径推导该场景的seed，增删场景不会改变其他场景的随机结果
+pub fn derive_seed(global_seed: u64, relative_path: &str) -> u64 {
+    // FNV-1a，再用splitmix64打散，保证跨平台、跨版本稳定
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in global_seed.to_le_bytes().iter().chain(relative_path.as_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// 以'/'分隔、不以"./"开头的相对路径
+fn canonical_relative_path(root: &Path, scene: &Path) -> String {
+    let relative = scene.strip_prefix(root).unwrap_or(scene);
+    relative
+        .components()
+        .filter(|component| !matches!(component, std::path::Component::CurDir))
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 把所有场景编译为MJCF写入`out_dir`，同时编译`jobs`个场景。
+/// 结果按输入顺序返回，与`jobs`无关
+pub fn compile_batch(scenes: &[PathBuf], options: &BatchOptions) -> Result<Vec<SceneResult>> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<SceneResult>>>> =
+        Mutex::new((0..scenes.len()).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.max(1) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(scene) = scenes.get(index) else {
+                    break;
+                };
+                let result = compile_scene(scene, options);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every scene is compiled"))
+        .collect()
+}
+
+fn compile_scene(scene: &Path, options: &BatchOptions) -> Result<SceneResult> {
+    let relative = canonical_relative_path(&options.root, scene);
+    let seed = derive_seed(options.global_seed, &relative);
+
+    let compiled = compile::compile(CompileOptions {
+        rsml: RsmlSource::Path(scene.to_path_buf()),
+        seed: Some(seed),
+        target: TargetFormat::Mjcf,
+        ..options.compile.clone()
+    })?;
+    let output = options.out_dir.join(format!("{}.mjcf.xml", relative));
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&output, compiled.output)?;
+
+    let metadata = SceneMetadata {
+        scene: relative.clone(),
+        global_seed: options.global_seed,
+        seed,
+    };
+    let sidecar = serde_json::to_string_pretty(&metadata).map_err(|e| RsmlError::ParseError {
+        field: "metadata".to_string(),
+        message: e.to_string(),
+    })?;
+    std::fs::write(options.out_dir.join(format!("{}.meta.json", relative)), sidecar)?;
+
+    Ok(SceneResult { metadata, output })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::PackageSource;
+    use crate::package::Package;
+
+    const SCENE: &str = r#"<rsml><head></head><body>
+        <space style="size:2m 2m 2m;flex-direction:x">
+            <object>table_leg</object>
+            <group select="random">bottles</group>
+            <group select="random">bottles</group>
+        </space>
+    </body></rsml>"#;
+
+    fn scenes(root: &Path, names: &[&str]) -> Vec<PathBuf> {
+        std::fs::create_dir_all(root).unwrap();
+        names
+            .iter()
+            .map(|name| {
+                let path = root.join(name);
+                std::fs::write(&path, SCENE).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn run(root: &Path, scenes: &[PathBuf], jobs: usize, out: &str) -> Vec<(SceneResult, String)> {
+        let package = Package::from_file("package.toml").unwrap();
+        let options = BatchOptions {
+            global_seed: 1473,
+            jobs,
+            root: root.to_path_buf(),
+            out_dir: root.join(out),
+            compile: CompileOptions::new(RsmlSource::Text(String::new()), PackageSource::Package(package)),
+        };
+        compile_batch(scenes, &options)
+            .unwrap()
+            .into_iter()
+            .map(|result| {
+                let content = std::fs::read_to_string(&result.output).unwrap();
+                (result, content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_is_independent_of_jobs() {
+        let root = std::env::temp_dir().join("rsmlc_batch_jobs");
+        let scenes = scenes(&root, &["a.rsml", "b.rsml", "c.rsml"]);
+
+        let serial = run(&root, &scenes, 1, "out1");
+        let parallel = run(&root, &scenes, 3, "out3");
+        assert_eq!(serial.len(), 3);
+        for ((a, a_content), (b, b_content)) in serial.iter().zip(&parallel) {
+            assert_eq!(a.metadata, b.metadata);
+            assert_eq!(a_content, b_content);
+        }
+        assert_eq!(serial[0].0.metadata.scene, "a.rsml");
+        assert_ne!(serial[0].0.metadata.seed, serial[1].0.metadata.seed);
+        assert!(root.join("out3/b.rsml.meta.json").exists());
+
+        // 增加场景不影响已有场景的seed
+        let more = self::scenes(&root, &["a.rsml", "b.rsml", "c.rsml", "d.rsml"]);
+        let extended = run(&root, &more, 2, "out4");
+        for (a, b) in serial.iter().zip(&extended) {
+            assert_eq!(a.0.metadata.seed, b.0.metadata.seed);
+            assert_eq!(a.1, b.1);
+        }
+    }
+
+    #[test]
+    fn test_batch_resolves_includes() {
+        // 和compile相同的流程，场景中的<include>会被展开
+        let scenes = vec![PathBuf::from("tests/fixtures/includes/warehouse.xml")];
+        let out = std::env::temp_dir().join("rsmlc_batch_includes");
+        let results = run(Path::new("tests/fixtures"), &scenes, 1, &out.to_string_lossy());
+        assert_eq!(results[0].0.metadata.scene, "includes/warehouse.xml");
+        assert!(results[0].1.contains("aisle_a"));
+    }
+
+    #[test]
+    fn test_derive_seed() {
+        assert_eq!(derive_seed(1, "a.rsml"), derive_seed(1, "a.rsml"));
+        assert_ne!(derive_seed(1, "a.rsml"), derive_seed(2, "a.rsml"));
+        assert_ne!(derive_seed(1, "a.rsml"), derive_seed(1, "b.rsml"));
+        assert_eq!(
+            canonical_relative_path(Path::new("/scenes"), Path::new("/scenes/./lab/a.rsml")),
+            "lab/a.rsml"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! 不生成输出的检查：把场景及其包的所有问题汇总成一份报告
+//!
+//! `check`执行`compile`直到布局的各个步骤，收集发现的问题而不是在第一个失败处停止，
+//! 这样CI无需生成目标文件就能检查场景。只有文件无法读取时检查本身才会失败
+
+use crate::compile::{self, CompileOptions, PackageSource, RsmlSource};
+use crate::diagnostics::{self, Diagnostic, Diagnostics, Severity};
+use crate::error::{Result, RsmlError};
+use crate::render_tree::RenderTree;
+use crate::xml_parser::Span;
+use serde::Serialize;
+use std::fmt;
+
+/// 问题所属的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueKind {
+    /// 文档结构、嵌套和属性
+    Structure,
+    Style,
+    /// 文档和包中的名字
+    Reference,
+    /// 尺寸和布局
+    Sizing,
+    Package,
+    Configuration,
+}
+
+impl IssueKind {
+    /// 诊断码对应的类别
+    pub fn of(code: &str) -> Self {
+        match code {
+            diagnostics::UNKNOWN_STYLE_PROPERTY | diagnostics::INVALID_STYLE => IssueKind::Style,
+            diagnostics::DUPLICATE_DEFINITION
+            | diagnostics::SHADOWED_DEFINITION
+            | diagnostics::UNKNOWN_ITEM
+            | diagnostics::EMPTY_ITEM => IssueKind::Reference,
+            diagnostics::LAYOUT_FAILED | diagnostics::ADJUSTED_OVERFLOW | diagnostics::OVERSIZED_ASSET => {
+                IssueKind::Sizing
+            }
+            diagnostics::INVALID_PACKAGE => IssueKind::Package,
+            diagnostics::UNKNOWN_DIAGNOSTIC_CODE => IssueKind::Configuration,
+            _ => IssueKind::Structure,
+        }
+    }
+}
+
+/// `check`发现的一个问题
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// `tag`或`tag#id`
+    pub element: Option<String>,
+    pub span: Option<Span>,
+}
+
+impl From<&Diagnostic> for Issue {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Issue {
+            kind: IssueKind::of(diagnostic.code),
+            code: diagnostic.code,
+            severity: diagnostic.severity,
+            message: diagnostic.message.clone(),
+            element: diagnostic.element.clone(),
+            span: diagnostic.span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    /// 格式与`Diagnostic`相同
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.code)?;
+        if let Some(span) = &self.span {
+            write!(f, "{}: ", span)?;
+        }
+        if let Some(element) = &self.element {
+            write!(f, "{}: ", element)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// `check`发现的所有问题，按发现的顺序排列
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    /// 场景路径，或`<string>`
+    pub scene: String,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+    #[serde(skip)]
+    diagnostics: Diagnostics,
+}
+
+impl CheckReport {
+    fn new(scene: String, diagnostics: Diagnostics) -> Self {
+        CheckReport {
+            scene,
+            errors: diagnostics.count(Severity::Error),
+            warnings: diagnostics.count(Severity::Warning),
+            issues: diagnostics.iter().map(Issue::from).collect(),
+            diagnostics,
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// 有错误级别的问题时返回`RsmlError::DiagnosticErrors`，与`compile`失败时一致
+    pub fn into_result(self) -> Result<Self> {
+        if self.has_errors() {
+            return Err(RsmlError::DiagnosticErrors(Box::new(self.diagnostics)));
+        }
+        Ok(self)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| RsmlError::ParseError {
+            field: "check report".to_string(),
+            message: e.to_string(),
+        })
+    }
+}
+
+/// 使用默认选项，按包检查场景
+pub fn check(rsml: RsmlSource, package: PackageSource) -> Result<CheckReport> {
+    check_with(CompileOptions::new(rsml, package))
+}
+
+/// 使用编译选项中的严重级别、布局和引用选项检查场景；不使用目标相关的选项
+///
+/// 只有场景或包无法读取时才会以`RsmlError::Io`失败
+pub fn check_with(options: CompileOptions) -> Result<CheckReport> {
+    let scene = match &options.rsml {
+        RsmlSource::Path(path) => path.to_string_lossy().to_string(),
+        RsmlSource::Text(_) => "<string>".to_string(),
+    };
+    let mut diagnostics = Diagnostics::new(options.severity.clone());
+
+    let (package, package_source) = match compile::load_package(options.package) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            record_failure(&mut diagnostics, diagnostics::INVALID_PACKAGE, error)?;
+            return Ok(CheckReport::new(scene, diagnostics));
+        }
+    };
+    let root = match compile::parse_document(&options.rsml, &options.includes) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            record_failure(&mut diagnostics, diagnostics::INVALID_STRUCTURE, error)?;
+            return Ok(CheckReport::new(scene, diagnostics));
+        }
+    };
+    compile::check_document(&root, &package, &package_source, &mut diagnostics);
+
+    let layout = compile::layout_options(options.layout, options.seed);
+    let mut render_tree = match RenderTree::with_options(&root, &package, diagnostics.clone(), &layout) {
+        Ok(render_tree) => render_tree,
+        Err(error) => {
+            record_failure(&mut diagnostics, diagnostics::INVALID_STRUCTURE, error)?;
+            return Ok(CheckReport::new(scene, diagnostics));
+        }
+    };
+    let diagnostics = match render_tree.calculate() {
+        Ok(()) => render_tree.diagnostics,
+        Err(RsmlError::DiagnosticErrors(diagnostics)) => *diagnostics,
+        Err(error) => {
+            let mut diagnostics = render_tree.diagnostics;
+            record_failure(&mut diagnostics, diagnostics::LAYOUT_FAILED, error)?;
+            diagnostics
+        }
+    };
+    Ok(CheckReport::new(scene, diagnostics))
+}
+
+/// 以`code`记录失败的步骤；无法读取的文件则作为错误返回
+fn record_failure(diagnostics: &mut Diagnostics, code: &'static str, error: RsmlError) -> Result<()> {
+    if let RsmlError::Io(_) = error.inner() {
+        return Err(error);
+    }
+    diagnostics.set_element(None, error.span().cloned());
+    diagnostics.record(code, error.inner().to_string());
+    diagnostics.set_element(None, None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_text(rsml: &str) -> CheckReport {
+        check(RsmlSource::Text(rsml.to_string()), PackageSource::Path("package.toml".into())).unwrap()
+    }
+
+    #[test]
+    fn test_valid_scene_has_no_issues() {
+        let report = check_text(
+            r#"<rsml><head></head><body>
+                <space id="room" style="size:1m 1m 1m"><object id="leg">table_leg</object></space>
+            </body></rsml>"#,
+        );
+        assert!(report.issues.is_empty(), "{:#?}", report.issues);
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_layout_failure_is_a_sizing_issue() {
+        // 没有其他error时布局错误原本直接返回，这里也作为issue
+        let report = check_text(
+            r#"<rsml><head></head><body>
+                <space id="room" style="size:30cm 30cm 1m;flex-direction:x">
+                    <object>table_leg</object><object>table_leg</object><object>table_leg</object><object>table_leg</object>
+                </space>
+            </body></rsml>"#,
+        );
+        assert_eq!(report.errors, 1, "{:#?}", report.issues);
+        assert_eq!(report.issues[0].kind, IssueKind::Sizing);
+        assert!(report.issues[0].message.contains("overflow"), "{}", report.issues[0]);
+        assert!(matches!(report.into_result(), Err(RsmlError::DiagnosticErrors(_))));
+    }
+
+    #[test]
+    fn test_unreadable_files_fail_the_check() {
+        let result = check(
+            RsmlSource::Path("no_such_scene.xml".into()),
+            PackageSource::Path("package.toml".into()),
+        );
+        assert!(matches!(result, Err(RsmlError::Io(_))));
+    }
+}
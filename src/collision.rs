@@ -0,0 +1,275 @@
+//! Overlap checks between the laid out items.
+//!
+//! Random positions and explicit `pos` values in cube spaces can place two items inside
+//! each other, which the layout accepts but a physics simulation does not. The check
+//! compares the axis-aligned bounding boxes of all items after `calculate`, including
+//! the fixed items imported from scene plans.
+
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::Result;
+use crate::render_tree::{NodeId, RenderTree};
+use std::fmt;
+
+/// One of the two items in a collision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Collider {
+    /// An item laid out in the render tree
+    Node(NodeId),
+    /// An item imported from a scene plan, by its index in `RenderTree::imported`
+    Imported(usize),
+}
+
+/// Two items whose bounding boxes overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionReport {
+    /// The item that comes first in tree order; imported items come after the tree
+    pub first: Collider,
+    pub second: Collider,
+    /// Labels of the two items, like `object#cup`
+    pub labels: [String; 2],
+    /// How deep the boxes overlap along each axis
+    pub penetration: Dim3<Length>,
+}
+
+impl fmt::Display for CollisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} overlaps {} by {}",
+            self.labels[0], self.labels[1], self.penetration
+        )
+    }
+}
+
+/// How deep two boxes, given by their minimum and maximum corners, overlap along each
+/// axis; None when they are apart or only touch
+pub fn penetration(
+    (min, max): (Dim3<Length>, Dim3<Length>),
+    (other_min, other_max): (Dim3<Length>, Dim3<Length>),
+) -> Option<Dim3<Length>> {
+    let overlap = max
+        .min_components(other_max)
+        .zip_with(min.max_components(other_min), |end, start| {
+            end.checked_sub(start).filter(|depth| depth.mm() > 0)
+        });
+    Some(Dim3::new(overlap.x?, overlap.y?, overlap.z?))
+}
+
+impl RenderTree<'_> {
+    /// Every pair of items whose bounding boxes overlap, in tree order, followed by the
+    /// imported items. Boxes that only touch do not overlap. Must be called after `calculate`.
+    pub fn check_collisions(&self) -> Vec<CollisionReport> {
+        let nodes = self.iter_items().filter_map(|id| {
+            let computed = &self.node(id).computed_style;
+            let min = computed.position.get_length()?;
+            Some((Collider::Node(id), min, min + computed.size.get_length()?))
+        });
+        let imported = self
+            .imported
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (Collider::Imported(index), item.position(), item.position() + item.size()));
+        let mut boxes: Vec<(usize, Collider, Dim3<Length>, Dim3<Length>)> = nodes
+            .chain(imported)
+            .enumerate()
+            .map(|(order, (collider, min, max))| (order, collider, min, max))
+            .collect();
+
+        // 按x轴的起点排序，只比较x轴上重叠的盒子
+        boxes.sort_by_key(|&(order, _, min, _)| (min.x, order));
+        let mut pairs = Vec::new();
+        for (index, &(order, id, min, max)) in boxes.iter().enumerate() {
+            for &(other_order, other, other_min, other_max) in &boxes[index + 1..] {
+                if other_min.x >= max.x {
+                    break;
+                }
+                let Some(depth) = penetration((min, max), (other_min, other_max)) else {
+                    continue;
+                };
+                let ((first_order, first), (_, second)) = match order < other_order {
+                    true => ((order, id), (other_order, other)),
+                    false => ((other_order, other), (order, id)),
+                };
+                pairs.push((first_order, first, second, depth));
+            }
+        }
+        pairs.sort_by_key(|&(first_order, _, second, _)| (first_order, second));
+
+        pairs
+            .into_iter()
+            .map(|(_, first, second, penetration)| CollisionReport {
+                first,
+                second,
+                labels: [self.collider_label(first), self.collider_label(second)],
+                penetration,
+            })
+            .collect()
+    }
+
+    fn collider_label(&self, collider: Collider) -> String {
+        match collider {
+            Collider::Node(id) => self.node(id).label(),
+            Collider::Imported(index) => format!("import#{}", self.imported[index].name),
+        }
+    }
+
+    /// Calculate the layout, and while items overlap calculate it again with the next seed,
+    /// up to `attempts` calculations in total. Retry n uses the original seed plus n, so
+    /// `seed()` afterwards reproduces the accepted layout.
+    ///
+    /// Returns the collisions of the last calculation, empty when a layout without
+    /// overlaps was found.
+    pub fn calculate_avoiding_collisions(&mut self, attempts: usize) -> Result<Vec<CollisionReport>> {
+        let seed = self.seed();
+        let diagnostics = self.diagnostics.clone();
+        let mut collisions = Vec::new();
+        for attempt in 0..attempts.max(1) {
+            if attempt > 0 {
+                // 从头计算，之前一次记录的诊断也不保留
+                for id in self.iter().collect::<Vec<_>>() {
+                    self.node_mut(id).computed_style = Default::default();
+                }
+                self.diagnostics = diagnostics.clone();
+                self.set_seed(seed.wrapping_add(attempt as u64));
+            }
+            self.calculate()?;
+            collisions = self.check_collisions();
+            if collisions.is_empty() {
+                break;
+            }
+        }
+        Ok(collisions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+    use crate::package::{GeomType, Package};
+    use crate::plan::PlanItem;
+    use crate::render_tree::LayoutOptions;
+    use crate::scene::Builder;
+    use crate::xml_parser::Element;
+
+    /// 10cm x 10cm x 50cm的桌腿，按给出的pos放进一个cube空间
+    fn cube_room(package: &Package, size: &str, positions: &[&str]) -> Element {
+        let room = positions.iter().enumerate().fold(
+            Builder::new_space().style(&format!("size:{};display:cube", size)),
+            |room, (index, pos)| {
+                room.object_with("table_leg", |leg| {
+                    leg.id(&format!("leg{}", index)).style(&format!("pos:{}", pos))
+                })
+            },
+        );
+        Builder::body().append_child(room).build(package).unwrap()
+    }
+
+    #[test]
+    fn test_overlapping_boxes_are_reported() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = cube_room(&package, "1m 1m 1m", &["0 0 0", "5cm 8cm 20cm", "50cm 50cm 0"]);
+        let mut render_tree = RenderTree::new(&scene, &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let collisions = render_tree.check_collisions();
+        assert_eq!(collisions.len(), 1);
+        let leg = |id: &str| render_tree.find_by_id(id).unwrap();
+        assert_eq!(
+            (collisions[0].first, collisions[0].second),
+            (Collider::Node(leg("leg0")), Collider::Node(leg("leg1")))
+        );
+        assert_eq!(
+            collisions[0].penetration,
+            Dim3::new(Length::from_cm(5), Length::from_cm(2), Length::from_cm(30))
+        );
+        assert_eq!(collisions[0].to_string(), "object#leg0 overlaps object#leg1 by 5cm 2cm 3dm");
+    }
+
+    #[test]
+    fn test_imported_items_collide() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = cube_room(&package, "1m 1m 1m", &["0 0 0", "50cm 50cm 0"]);
+        let mut render_tree = RenderTree::new(&scene, &package).unwrap();
+        render_tree.calculate().unwrap();
+        // 导入的桌腿与leg1重叠，另一个导入的物体与所有物体都不接触
+        for (name, position) in [("base/leg", [520, 530, 100]), ("base/far", [900, 900, 900])] {
+            render_tree.imported.push(PlanItem {
+                name: name.to_string(),
+                geom_type: GeomType::Box,
+                path: None,
+                position,
+                size: [100, 100, 100],
+                attributes: Vec::new(),
+            });
+        }
+
+        let collisions = render_tree.check_collisions();
+        assert_eq!(collisions.len(), 1);
+        let leg1 = render_tree.find_by_id("leg1").unwrap();
+        assert_eq!((collisions[0].first, collisions[0].second), (Collider::Node(leg1), Collider::Imported(0)));
+        assert_eq!(collisions[0].to_string(), "object#leg1 overlaps import#base/leg by 8cm 7cm 1dm");
+    }
+
+    #[test]
+    fn test_touching_boxes_do_not_collide() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 沿x、y、z轴各有一对只接触的桌腿
+        let scene = cube_room(&package, "1m 1m 1m", &["0 0 0", "10cm 0 0", "0 10cm 0", "0 0 50cm"]);
+        let mut render_tree = RenderTree::new(&scene, &package).unwrap();
+        render_tree.calculate().unwrap();
+        assert_eq!(render_tree.check_collisions(), Vec::new());
+
+        // flex布局中相邻的物体也只是接触
+        let scene = Builder::body()
+            .space(|row| row.style("size:1m 1m 1m;flex-direction:x").object("table_leg").object("table_leg"))
+            .build(&package)
+            .unwrap();
+        let mut render_tree = RenderTree::new(&scene, &package).unwrap();
+        render_tree.calculate().unwrap();
+        assert!(render_tree.check_collisions().is_empty());
+    }
+
+    /// 不在布局时重新采样重叠的随机位置
+    fn overlapping_tree<'a>(scene: &Element, package: &'a Package) -> RenderTree<'a> {
+        let options = LayoutOptions {
+            placement_attempts: 0,
+            ..Default::default()
+        };
+        RenderTree::with_options(scene, package, Diagnostics::default(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_random_placement_is_retried() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 40cm x 40cm的空间里两个随机位置的桌腿，大约三分之一的seed会重叠
+        let scene = cube_room(&package, "40cm 40cm 1m", &["random random 0", "random random 0"]);
+        let colliding = (0..100)
+            .find(|&seed| {
+                let mut render_tree = overlapping_tree(&scene, &package);
+                render_tree.set_seed(seed);
+                render_tree.calculate().unwrap();
+                !render_tree.check_collisions().is_empty()
+            })
+            .unwrap();
+
+        let mut render_tree = overlapping_tree(&scene, &package);
+        render_tree.set_seed(colliding);
+        assert_eq!(render_tree.calculate_avoiding_collisions(1).unwrap().len(), 1);
+
+        let mut render_tree = overlapping_tree(&scene, &package);
+        render_tree.set_seed(colliding);
+        assert!(render_tree.calculate_avoiding_collisions(20).unwrap().is_empty());
+        let accepted = render_tree.seed();
+        assert_ne!(accepted, colliding);
+        assert!(render_tree.check_collisions().is_empty());
+        assert!(render_tree.diagnostics.iter().next().is_none());
+
+        // 使用找到的seed直接计算得到相同的布局
+        let mut replay = overlapping_tree(&scene, &package);
+        replay.set_seed(accepted);
+        replay.calculate().unwrap();
+        assert_eq!(replay.to_plan().unwrap(), render_tree.to_plan().unwrap());
+    }
+}
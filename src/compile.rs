@@ -0,0 +1,158 @@
+//! 一次调用完成编译：输入RSML文档和package，输出目标格式的文档。
+//!
+//! `compile`按命令行相同的顺序执行各个步骤：解析并检查文档、检查符号、构建渲染树、
+//! 计算布局、生成目标格式。布局之前发现的问题会被收集起来，一次运行全部报告。
+
+use crate::collision::CollisionReport;
+use crate::diagnostics::{Diagnostics, SeverityConfig};
+use crate::error::{Result, RsmlError};
+use crate::package::Package;
+use crate::render_tree::{self, RenderTree};
+use crate::schema;
+use crate::symbols::SymbolTable;
+use crate::target::{MjcfOptions, PreviewOptions, SvgOptions, TargetFormat};
+use crate::xml_parser::{parse_xml_file_with_includes, parse_xml_str, Element, IncludeOptions};
+use std::path::PathBuf;
+
+/// RSML文档的来源
+#[derive(Debug, Clone)]
+pub enum RsmlSource {
+    Path(PathBuf),
+    Text(String),
+}
+
+/// package的来源
+#[derive(Debug, Clone)]
+pub enum PackageSource {
+    Path(PathBuf),
+    Package(Package),
+}
+
+/// `compile`需要的所有输入
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    pub rsml: RsmlSource,
+    pub package: PackageSource,
+    pub target: TargetFormat,
+    /// 所有随机选择使用的seed，设置时覆盖`layout.seed`
+    pub seed: Option<u64>,
+    /// 只在`target`为MJCF时使用
+    pub mjcf: MjcfOptions,
+    /// 只在`target`为OBJ预览时使用
+    pub preview: PreviewOptions,
+    /// 只在`target`为SVG时使用
+    pub svg: SvgOptions,
+    pub severity: SeverityConfig,
+    /// 构建渲染树和计算布局的选项
+    pub layout: render_tree::LayoutOptions,
+    /// `<include>`元素的解析方式
+    pub includes: IncludeOptions,
+    /// 布局之后报告包围盒重叠的物体
+    pub check_collisions: bool,
+    /// 开启`check_collisions`时，物体重叠后换下一个seed重新布局的最多次数
+    pub collision_retries: usize,
+}
+
+impl CompileOptions {
+    /// 默认选项输出MJCF，不固定seed
+    pub fn new(rsml: RsmlSource, package: PackageSource) -> Self {
+        CompileOptions {
+            rsml,
+            package,
+            target: TargetFormat::default(),
+            seed: None,
+            mjcf: MjcfOptions::default(),
+            preview: PreviewOptions::default(),
+            svg: SvgOptions::default(),
+            severity: SeverityConfig::default(),
+            layout: render_tree::LayoutOptions::default(),
+            includes: IncludeOptions::default(),
+            check_collisions: false,
+            collision_retries: 0,
+        }
+    }
+}
+
+/// 编译成功的结果
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    /// 序列化后的目标文档
+    pub output: String,
+    /// 计算出的布局，`ScenePlan`格式的JSON文档
+    pub layout_json: String,
+    /// warning和note；出现error时`compile`直接失败
+    pub diagnostics: Diagnostics,
+    /// 重叠的物体，只在开启`CompileOptions::check_collisions`时检查
+    pub collisions: Vec<CollisionReport>,
+}
+
+/// 把RSML文档编译为目标格式
+///
+/// 有error级别的诊断时返回`RsmlError::DiagnosticErrors`，其中包含全部诊断
+pub fn compile(options: CompileOptions) -> Result<CompileOutput> {
+    let (package, package_source) = load_package(options.package)?;
+    let root = parse_document(&options.rsml, &options.includes)?;
+    let mut diagnostics = Diagnostics::new(options.severity);
+    check_document(&root, &package, &package_source, &mut diagnostics);
+
+    let layout = layout_options(options.layout, options.seed);
+    let mut render_tree = RenderTree::with_options(&root, &package, diagnostics, &layout)?;
+    // 布局必须在生成之前计算；之前记录的error在布局之后一起返回
+    let collisions = match options.check_collisions {
+        true => render_tree.calculate_avoiding_collisions(options.collision_retries + 1)?,
+        false => {
+            render_tree.calculate()?;
+            Vec::new()
+        }
+    };
+
+    let output = options
+        .target
+        .to_checked_output(&render_tree, &options.mjcf, &options.preview, &options.svg)?;
+    let layout_json =
+        serde_json::to_string_pretty(&render_tree.to_plan()?).map_err(|e| RsmlError::ParseError {
+            field: "plan".to_string(),
+            message: e.to_string(),
+        })?;
+    Ok(CompileOutput {
+        output,
+        layout_json,
+        diagnostics: render_tree.diagnostics,
+        collisions,
+    })
+}
+
+/// 加载package，同时返回报告其符号时使用的名字
+pub(crate) fn load_package(source: PackageSource) -> Result<(Package, String)> {
+    match source {
+        PackageSource::Path(path) => {
+            let path = path.to_string_lossy().to_string();
+            Ok((Package::from_file(&path)?, path))
+        }
+        PackageSource::Package(package) => Ok((package, "package.toml".to_string())),
+    }
+}
+
+/// 解析文档并展开其中的include
+pub fn parse_document(rsml: &RsmlSource, includes: &IncludeOptions) -> Result<Element> {
+    match rsml {
+        RsmlSource::Path(path) => parse_xml_file_with_includes(&path.to_string_lossy(), includes),
+        RsmlSource::Text(text) => parse_xml_str(text),
+    }
+}
+
+/// 构建渲染树之前的检查：文档结构和package中的符号。
+/// 重复的元素id由渲染树在建立索引时报告
+pub(crate) fn check_document(root: &Element, package: &Package, package_source: &str, diagnostics: &mut Diagnostics) {
+    schema::check_structure(root, diagnostics);
+    let mut symbols = SymbolTable::new();
+    symbols.add_package(package, package_source);
+    symbols.check(diagnostics);
+}
+
+pub(crate) fn layout_options(mut layout: render_tree::LayoutOptions, seed: Option<u64>) -> render_tree::LayoutOptions {
+    if seed.is_some() {
+        layout.seed = seed;
+    }
+    layout
+}
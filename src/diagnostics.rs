@@ -0,0 +1,450 @@
+use crate::error::{Result, RsmlError};
+use crate::xml_parser::Span;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// 应用severity配置之后诊断的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// 不输出，但仍然计入统计
+    Allow,
+    Warning,
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "allow" => Ok(Severity::Allow),
+            "warn" | "warning" => Ok(Severity::Warning),
+            "deny" | "error" => Ok(Severity::Error),
+            _ => Err(RsmlError::ParseError {
+                field: "Severity".to_string(),
+                message: format!("Invalid severity: {}", s),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// 注册的诊断码及其默认的严重程度
+#[derive(Debug, Serialize)]
+pub struct DiagnosticCode {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub summary: &'static str,
+}
+
+/// severity配置中引用了不存在的诊断码
+pub const UNKNOWN_DIAGNOSTIC_CODE: &str = "W0001";
+/// 文档的结构不符合schema的要求
+pub const INVALID_STRUCTURE: &str = "E0100";
+/// space中同时包含item（object、group）和space
+pub const MIXED_CHILDREN: &str = "E0101";
+/// space中包含多于一个item；默认不启用，flex布局会在一个space中放置多个item
+pub const MULTIPLE_ITEMS: &str = "E0102";
+/// object或group元素有子元素
+pub const ITEM_CHILDREN: &str = "E0103";
+/// 样式声明使用了编译器不认识的属性
+pub const UNKNOWN_STYLE_PROPERTY: &str = "W0110";
+/// 无法解析的style属性，已被忽略
+pub const INVALID_STYLE: &str = "W0111";
+/// 元素上有schema中没有定义的属性
+pub const UNKNOWN_ATTRIBUTE: &str = "W0112";
+/// 未知属性匹配透传的前缀，作为元数据保留
+pub const PASSTHROUGH_ATTRIBUTE: &str = "N0113";
+/// 样式中的长度精度高于1mm，已舍入到毫米
+pub const ROUNDED_LENGTH: &str = "W0114";
+/// 同一个命名空间中重复定义的名字
+pub const DUPLICATE_DEFINITION: &str = "E0120";
+/// 嵌套命名空间中的名字遮蔽了根命名空间中的名字
+pub const SHADOWED_DEFINITION: &str = "W0121";
+/// object或group元素引用了package中没有定义的名字
+pub const UNKNOWN_ITEM: &str = "E0130";
+/// object或group元素没有指定放置什么的文本
+pub const EMPTY_ITEM: &str = "E0131";
+/// 无法计算布局
+pub const LAYOUT_FAILED: &str = "E0140";
+/// 布局pass移动后的物体超出了它的父空间
+pub const ADJUSTED_OVERFLOW: &str = "E0141";
+/// 依赖的资源超过了该依赖的尺寸限制
+pub const OVERSIZED_ASSET: &str = "W0150";
+/// 无法解析package文件
+pub const INVALID_PACKAGE: &str = "E0160";
+
+/// 编译器可能产生的所有诊断码
+pub const REGISTRY: &[DiagnosticCode] = &[
+    DiagnosticCode {
+        code: UNKNOWN_DIAGNOSTIC_CODE,
+        severity: Severity::Warning,
+        summary: "unknown diagnostic code in severity configuration",
+    },
+    DiagnosticCode {
+        code: INVALID_STRUCTURE,
+        severity: Severity::Error,
+        summary: "document structure does not match the schema",
+    },
+    DiagnosticCode {
+        code: MIXED_CHILDREN,
+        severity: Severity::Error,
+        summary: "space contains both items and spaces",
+    },
+    DiagnosticCode {
+        code: MULTIPLE_ITEMS,
+        severity: Severity::Allow,
+        summary: "space contains more than one item",
+    },
+    DiagnosticCode {
+        code: ITEM_CHILDREN,
+        severity: Severity::Error,
+        summary: "object or group element with element children",
+    },
+    DiagnosticCode {
+        code: UNKNOWN_STYLE_PROPERTY,
+        severity: Severity::Warning,
+        summary: "unknown style property",
+    },
+    DiagnosticCode {
+        code: INVALID_STYLE,
+        severity: Severity::Warning,
+        summary: "style attribute could not be parsed",
+    },
+    DiagnosticCode {
+        code: UNKNOWN_ATTRIBUTE,
+        severity: Severity::Warning,
+        summary: "unknown attribute, kept as metadata",
+    },
+    DiagnosticCode {
+        code: PASSTHROUGH_ATTRIBUTE,
+        severity: Severity::Allow,
+        summary: "passthrough attribute kept as metadata",
+    },
+    DiagnosticCode {
+        code: ROUNDED_LENGTH,
+        severity: Severity::Warning,
+        summary: "length rounded to the 1mm resolution",
+    },
+    DiagnosticCode {
+        code: DUPLICATE_DEFINITION,
+        severity: Severity::Error,
+        summary: "duplicate definition within a namespace",
+    },
+    DiagnosticCode {
+        code: SHADOWED_DEFINITION,
+        severity: Severity::Warning,
+        summary: "definition shadows a name from another namespace",
+    },
+    DiagnosticCode {
+        code: UNKNOWN_ITEM,
+        severity: Severity::Error,
+        summary: "object or group not found in the package",
+    },
+    DiagnosticCode {
+        code: EMPTY_ITEM,
+        severity: Severity::Error,
+        summary: "object or group element without a name",
+    },
+    DiagnosticCode {
+        code: LAYOUT_FAILED,
+        severity: Severity::Error,
+        summary: "layout could not be calculated",
+    },
+    DiagnosticCode {
+        code: ADJUSTED_OVERFLOW,
+        severity: Severity::Error,
+        summary: "item moved outside its parent space by a layout pass",
+    },
+    DiagnosticCode {
+        code: OVERSIZED_ASSET,
+        severity: Severity::Warning,
+        summary: "dependency asset larger than its size-limit",
+    },
+    DiagnosticCode {
+        code: INVALID_PACKAGE,
+        severity: Severity::Error,
+        summary: "package file could not be parsed",
+    },
+];
+
+/// 查找注册的诊断码
+pub fn lookup(code: &str) -> Option<&'static DiagnosticCode> {
+    REGISTRY.iter().find(|entry| entry.code == code)
+}
+
+/// 编译过程中记录的一条诊断
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 报告诊断的元素，格式为`tag`或`tag#id`
+    pub element: Option<String>,
+    /// 该元素在源文件中的位置，只有从XML解析时才有
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.code)?;
+        if let Some(span) = &self.span {
+            write!(f, "{}: ", span)?;
+        }
+        if let Some(element) = &self.element {
+            write!(f, "{}: ", element)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// 按诊断码覆盖的severity，来自rsmlc.toml的`[diagnostics]`表
+/// 以及命令行的`--deny <code>`、`--allow <code>`和`-D warnings`参数
+#[derive(Debug, Clone, Default)]
+pub struct SeverityConfig {
+    overrides: HashMap<String, Severity>,
+    /// 把所有警告提升为错误（`-D warnings`）
+    pub deny_warnings: bool,
+}
+
+impl SeverityConfig {
+    /// 从rsmlc.toml文件的内容中解析`[diagnostics]`表
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct ConfigFile {
+            #[serde(default)]
+            diagnostics: HashMap<String, String>,
+        }
+
+        let file: ConfigFile = toml::from_str(contents).map_err(|e| RsmlError::ParseError {
+            field: "rsmlc.toml".to_string(),
+            message: e.to_string(),
+        })?;
+
+        let mut config = SeverityConfig::default();
+        for (code, severity) in file.diagnostics {
+            config.set(&code, severity.parse()?);
+        }
+        Ok(config)
+    }
+
+    /// 从rsmlc.toml文件加载severity配置
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_toml_str(&contents)
+    }
+
+    /// 覆盖单个诊断码的severity
+    pub fn set(&mut self, code: &str, severity: Severity) {
+        self.overrides.insert(code.to_string(), severity);
+    }
+
+    /// 应用`--deny <code>`、`--allow <code>`和`-D warnings`参数，
+    /// 不是severity参数的原样返回
+    pub fn apply_args(&mut self, args: &[String]) -> Result<Vec<String>> {
+        let mut rest = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let severity = match arg.as_str() {
+                "--deny" | "-D" => Severity::Error,
+                "--allow" | "-A" => Severity::Allow,
+                _ => {
+                    rest.push(arg.clone());
+                    continue;
+                }
+            };
+            let code = iter.next().ok_or_else(|| RsmlError::ParseError {
+                field: arg.clone(),
+                message: "missing diagnostic code".to_string(),
+            })?;
+            if severity == Severity::Error && code == "warnings" {
+                self.deny_warnings = true;
+            } else {
+                self.set(code, severity);
+            }
+        }
+        Ok(rest)
+    }
+
+    /// 配置中没有注册的诊断码
+    pub fn unknown_codes(&self) -> Vec<&str> {
+        let mut codes: Vec<&str> = self
+            .overrides
+            .keys()
+            .map(String::as_str)
+            .filter(|code| lookup(code).is_none())
+            .collect();
+        codes.sort();
+        codes
+    }
+
+    /// 计算诊断码实际的severity
+    pub fn severity_for(&self, code: &str, default: Severity) -> Severity {
+        let severity = self.overrides.get(code).copied().unwrap_or(default);
+        if self.deny_warnings && severity == Severity::Warning {
+            Severity::Error
+        } else {
+            severity
+        }
+    }
+}
+
+/// 收集诊断，记录时应用severity配置
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    config: SeverityConfig,
+    items: Vec<Diagnostic>,
+    /// 之后记录的诊断所属的元素
+    element: Option<String>,
+    span: Option<Span>,
+}
+
+impl Diagnostics {
+    pub fn new(config: SeverityConfig) -> Self {
+        let mut diagnostics = Diagnostics {
+            config,
+            items: Vec::new(),
+            element: None,
+            span: None,
+        };
+        let unknown: Vec<String> = diagnostics
+            .config
+            .unknown_codes()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        for code in unknown {
+            diagnostics.record(
+                UNKNOWN_DIAGNOSTIC_CODE,
+                format!("unknown diagnostic code '{}' in severity configuration", code),
+            );
+        }
+        diagnostics
+    }
+
+    /// 按注册的诊断码记录一条诊断
+    pub fn record(&mut self, code: &'static str, message: impl Into<String>) {
+        let default = lookup(code).map_or(Severity::Warning, |entry| entry.severity);
+        let severity = self.config.severity_for(code, default);
+        self.items.push(Diagnostic {
+            code,
+            severity,
+            message: message.into(),
+            element: self.element.clone(),
+            span: self.span.clone(),
+        });
+    }
+
+    /// 之后记录的诊断附加给定的元素和它的位置，
+    /// 直到用`None`重置
+    pub fn set_element(&mut self, element: Option<String>, span: Option<Span>) {
+        self.element = element;
+        self.span = span;
+    }
+
+    /// 覆盖之后记录的诊断中某个诊断码的severity
+    pub fn set_severity(&mut self, code: &str, severity: Severity) {
+        self.config.set(code, severity);
+    }
+
+    /// 需要显示给用户的诊断（跳过allow的诊断）
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.items
+            .iter()
+            .filter(|diagnostic| diagnostic.severity != Severity::Allow)
+    }
+
+    /// 给定severity的诊断数量
+    pub fn count(&self, severity: Severity) -> usize {
+        self.items
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_config_from_toml() {
+        let config = SeverityConfig::from_toml_str(
+            r#"
+            [diagnostics]
+            W0110 = "allow"
+            W0111 = "error"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.severity_for("W0110", Severity::Warning), Severity::Allow);
+        assert_eq!(config.severity_for("W0111", Severity::Warning), Severity::Error);
+        assert!(config.unknown_codes().is_empty());
+
+        assert!(SeverityConfig::from_toml_str("[diagnostics]\nW0110 = \"loud\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_code_in_config_is_warning() {
+        let config = SeverityConfig::from_toml_str("[diagnostics]\nE9999 = \"error\"").unwrap();
+        assert_eq!(config.unknown_codes(), vec!["E9999"]);
+
+        let diagnostics = Diagnostics::new(config);
+        let items: Vec<_> = diagnostics.iter().collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, UNKNOWN_DIAGNOSTIC_CODE);
+        assert_eq!(items[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_allowed_diagnostics_are_counted_but_hidden() {
+        let mut config = SeverityConfig::default();
+        config.set(UNKNOWN_STYLE_PROPERTY, Severity::Allow);
+        let mut diagnostics = Diagnostics::new(config);
+        diagnostics.record(UNKNOWN_STYLE_PROPERTY, "unknown style property 'colour'");
+
+        assert_eq!(diagnostics.iter().count(), 0);
+        assert_eq!(diagnostics.count(Severity::Allow), 1);
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn test_apply_args() {
+        let args: Vec<String> = ["--allow", "W0110", "-D", "warnings", "scene.xml"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut config = SeverityConfig::default();
+        let rest = config.apply_args(&args).unwrap();
+        assert_eq!(rest, vec!["scene.xml".to_string()]);
+        assert!(config.deny_warnings);
+
+        let mut diagnostics = Diagnostics::new(config);
+        diagnostics.record(UNKNOWN_STYLE_PROPERTY, "allowed");
+        diagnostics.record(INVALID_STYLE, "promoted");
+        assert_eq!(diagnostics.count(Severity::Allow), 1);
+        assert_eq!(diagnostics.count(Severity::Error), 1);
+
+        assert!(SeverityConfig::default().apply_args(&["--deny".to_string()]).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::ops::{Add, Sub, Mul, Div};
 use std::fmt;
+use std::iter::Sum;
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
@@ -35,6 +36,31 @@ impl<T> Dim3<T> {
     pub fn z(&self) -> T where T: Copy {
         self.z
     }
+
+    /// Apply `f` to each component
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Dim3<U> {
+        Dim3::new(f(self.x), f(self.y), f(self.z))
+    }
+
+    /// Combine the matching components of two Dim3s with `f`
+    pub fn zip_with<U, V>(self, other: Dim3<U>, mut f: impl FnMut(T, U) -> V) -> Dim3<V> {
+        Dim3::new(f(self.x, other.x), f(self.y, other.y), f(self.z, other.z))
+    }
+
+    /// Component-wise maximum
+    pub fn max_components(self, other: Self) -> Self where T: Ord {
+        self.zip_with(other, Ord::max)
+    }
+
+    /// Component-wise minimum
+    pub fn min_components(self, other: Self) -> Self where T: Ord {
+        self.zip_with(other, Ord::min)
+    }
+
+    /// Whether every component is no larger than the matching component of `other`
+    pub fn fits_within(&self, other: &Self) -> bool where T: PartialOrd {
+        self.x <= other.x && self.y <= other.y && self.z <= other.z
+    }
 }
 
 impl<T: Add<Output = T>> Add for Dim3<T> {
@@ -85,8 +111,61 @@ impl<T: Div<Output = T>> Div for Dim3<T> {
     }
 }
 
+impl<T: Add<Output = T> + Default> Sum for Dim3<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, dim| total + dim)
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Dim3<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {} {}", self.x, self.y, self.z)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Length;
+
+    fn mm(x: u32, y: u32, z: u32) -> Dim3<Length> {
+        Dim3::new(x, y, z).map(Length::from_mm)
+    }
+
+    #[test]
+    fn test_map_and_zip_with() {
+        assert_eq!(mm(1, 20, 300).map(|length| length.mm() * 2), Dim3::new(2, 40, 600));
+        let labels = Dim3::new("x", "y", "z").zip_with(Dim3::new(1, 2, 3), |axis, n| format!("{}{}", axis, n));
+        assert_eq!(labels, Dim3::new("x1".to_string(), "y2".to_string(), "z3".to_string()));
+    }
+
+    #[test]
+    fn test_component_wise_max_and_min() {
+        let a = mm(10, 200, 30);
+        let b = mm(100, 20, 30);
+        assert_eq!(a.max_components(b), mm(100, 200, 30));
+        assert_eq!(a.min_components(b), mm(10, 20, 30));
+        assert_eq!(a.max_components(a), a);
+    }
+
+    #[test]
+    fn test_fits_within() {
+        let limit = mm(300, 300, 300);
+        assert!(mm(300, 300, 300).fits_within(&limit));
+        assert!(mm(0, 120, 299).fits_within(&limit));
+        // 任意一个轴超出都不算
+        assert!(!mm(301, 1, 1).fits_within(&limit));
+        assert!(!mm(1, 1, 301).fits_within(&limit));
+        assert!(!Dim3::new(0.0, f64::NAN, 0.0).fits_within(&Dim3::new(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_sum() {
+        let total: Dim3<Length> = [mm(1, 2, 3), mm(10, 20, 30), mm(100, 200, 300)].into_iter().sum();
+        assert_eq!(total, mm(111, 222, 333));
+        let empty: Dim3<Length> = std::iter::empty().sum();
+        assert_eq!(empty, mm(0, 0, 0));
+        let floats: Dim3<f64> = [Dim3::new(0.5, 1.0, 1.5), Dim3::new(0.5, 1.0, 1.5)].into_iter().sum();
+        assert_eq!(floats, Dim3::new(1.0, 2.0, 3.0));
+    }
+}
@@ -1,4 +1,6 @@
 use thiserror::Error;
+use crate::diagnostics::{Diagnostics, Severity};
+use crate::xml_parser::Span;
 
 #[derive(Error, Debug)]
 pub enum RsmlError {
@@ -43,6 +45,47 @@ pub enum RsmlError {
 
     #[error("package config error: {0}")]
     PackageConfigError(String),
+
+    /// 缓存中的文件和lockfile记录的内容不同
+    #[error("hash mismatch for '{path}': expected sha256 {expected}, found {actual}")]
+    HashMismatch { path: String, expected: String, actual: String },
+
+    /// 带有全部诊断信息，调用方可以逐条输出；装箱避免所有Result都变大
+    #[error("compilation failed with {} error diagnostic(s)", .0.count(Severity::Error))]
+    DiagnosticErrors(Box<Diagnostics>),
+
+    /// 发生在源文件中某个元素上的错误，输出为path:line:col: 原来的错误
+    #[error("{span}: {error}")]
+    Located { span: Span, error: Box<RsmlError> },
+}
+
+impl RsmlError {
+    /// 加上源文件位置；没有位置或者已经有更精确的位置时保持不变
+    pub fn at(self, span: Option<&Span>) -> Self {
+        match (self, span) {
+            (error @ RsmlError::Located { .. }, _) | (error, None) => error,
+            (error, Some(span)) => RsmlError::Located {
+                span: span.clone(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// 去掉位置之后的错误，用于按类型匹配
+    pub fn inner(&self) -> &RsmlError {
+        match self {
+            RsmlError::Located { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+
+    /// 错误发生的位置
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            RsmlError::Located { span, .. } => Some(span),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = anyhow::Result<T, RsmlError>;
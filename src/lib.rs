@@ -1,11 +1,36 @@
+pub mod anchor;
 pub mod base;
+pub mod batch;
+pub mod check;
+pub mod collision;
+pub mod compile;
+pub mod diagnostics;
 pub mod dim3;
 pub mod error;
+pub mod occupancy;
 pub mod package;
+pub mod pass;
+pub mod pick;
+pub mod placement;
+pub mod plan;
+pub mod reload;
+pub mod world;
 pub mod render_tree;
+pub mod report;
+pub mod schema;
+pub mod scene;
+pub mod settings;
 pub mod style;
+pub mod symbols;
 pub mod target;
+pub mod template;
+pub mod variance;
+pub mod watch;
 pub mod xml_parser;
 pub mod api;
+#[cfg(feature = "async")]
+pub mod async_api;
 
+pub use check::{check, CheckReport};
+pub use compile::{compile, CompileOptions, CompileOutput, PackageSource, RsmlSource};
 pub use error::{RsmlError, Result};
\ No newline at end of file
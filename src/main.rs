@@ -1,131 +1,560 @@
-pub mod base;
-mod style;
-mod xml_parser;
-mod render_tree;
-mod package;
-mod dim3;
-mod error;
-mod api;
-mod target;
-
-use anyhow::Result;
-use xml_parser::{parse_xml_file, Element};
-use render_tree::RenderTree;
-use package::Package;
-use error::RsmlError;
-use target::MjcfGenerator;
-
-fn main() -> Result<()> {
-    // 解析package.toml文件
-    let package = Package::from_file("package.toml")?;
-    println!("Package configuration loaded successfully!");
-    println!("Objects: {:?}", package.objects.keys().collect::<Vec<_>>());
-    println!("Groups: {:?}", package.groups.iter().map(|g| &g.name).collect::<Vec<_>>());
-    println!();
-    
-    // 解析RSML XML文件
-    let root_element = parse_xml_file("rsml_example.xml")?;
-    
-    // 打印DOM树结构
-    println!("DOM Tree:");
-    print_element(&root_element, 0);
-    
-    // 验证解析结果
-    validate_rsml_structure(&root_element)?;
-    
-    // 构建渲染树
-    println!("\n正在构建渲染树...");
-    let render_tree = RenderTree::new(&root_element, &package)?;
-    
-    // 计算尺寸和布局
-    println!("\n正在计算尺寸和布局...");
-    render_tree.calculate()?;
-    
-    // 生成MJCF文件
-    println!("\n正在生成MJCF文件...");
-    let mjcf_content = MjcfGenerator::generate(&render_tree);
-    
-    // 将MJCF序列化为XML格式
-    let mjcf_xml = quick_xml::se::to_string(&mjcf_content)?;
-    
-    // 将MJCF内容写入文件
-    std::fs::write("output.xml", &mjcf_xml)?;
-    println!("MJCF文件已生成: output.xml");
-    
-    // 打印MJCF内容预览
-    println!("\nMJCF内容预览:");
-    println!("{}", mjcf_xml);
-    
-    Ok(())
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rsmlc::check::check_with;
+use rsmlc::compile::{self, CompileOptions, CompileOutput, PackageSource, RsmlSource};
+use rsmlc::diagnostics::{Diagnostics, SeverityConfig};
+use rsmlc::error::{Result, RsmlError};
+use rsmlc::package::{Package, ResolveOptions};
+use rsmlc::render_tree::{LayoutOptions, RenderTree};
+use rsmlc::symbols::SymbolTable;
+use rsmlc::target::{MjcfOptions, Plane, PreviewOptions, SvgOptions, TargetFormat};
+use rsmlc::variance::VarianceReport;
+use rsmlc::watch::{self, WatchOptions};
+use rsmlc::xml_parser::{parse_xml_file_with_includes, Element, IncludeOptions};
+use rsmlc::{batch, report, schema};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// 退出码：0成功，1编译错误（包括诊断中的error），2读写文件失败
+const EXIT_ERRORS: u8 = 1;
+const EXIT_IO: u8 = 2;
+
+#[derive(Parser)]
+#[command(name = "rsmlc", version, about = "Compile RSML scenes to MJCF, URDF or SDF")]
+struct Cli {
+    /// Print the package, the DOM tree and the document structure
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
 }
 
-fn print_element(element: &Element, depth: usize) {
-    let indent = "  ".repeat(depth);
-    
-    // 打印元素名称
-    print!("{}{}", indent, element.name);
-    
-    // 打印属性
-    if !element.attributes.is_empty() {
-        print!(" [");
-        let mut first = true;
-        for (key, value) in &element.attributes {
-            if !first {
-                print!( ", ");
+#[derive(Subcommand)]
+enum Command {
+    /// Compile a scene to the target format
+    Build(BuildArgs),
+    /// Validate a scene and print its diagnostics without writing any output
+    Check(CheckArgs),
+    /// Manage the dependencies of a package
+    Package {
+        #[command(subcommand)]
+        command: PackageCommand,
+    },
+    /// Print the elements, style properties and diagnostic codes the compiler accepts
+    Schema {
+        #[arg(long, value_enum, default_value_t = SchemaFormat::Json)]
+        format: SchemaFormat,
+    },
+    /// Compile many scenes, each with its own seed derived from the global one
+    Batch {
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+        #[arg(long, default_value = "out")]
+        out: PathBuf,
+        #[arg(long, default_value = "package.toml")]
+        package: PathBuf,
+        #[arg(required = true)]
+        scenes: Vec<PathBuf>,
+    },
+    /// Write an HTML report of a scene that opens directly in a browser
+    Report {
+        scene: PathBuf,
+        #[arg(short, long, default_value = "report.html")]
+        out: PathBuf,
+        #[arg(long)]
+        max_items: Option<usize>,
+        #[arg(long, default_value = "package.toml")]
+        package: PathBuf,
+    },
+    /// Compare the layout of a scene across seeds
+    Variance {
+        scene: PathBuf,
+        #[arg(long, required = true, value_delimiter = ',')]
+        seeds: Vec<u64>,
+        #[arg(long)]
+        json: bool,
+        #[arg(long, default_value = "package.toml")]
+        package: PathBuf,
+    },
+    /// Look up information about a scene
+    Query {
+        scene: PathBuf,
+        /// Print every name the scene and its package define
+        #[arg(long, required = true)]
+        symbols: bool,
+        #[arg(long, default_value = "package.toml")]
+        package: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum PackageCommand {
+    /// Download the assets of the dependencies and write the lockfile
+    Resolve {
+        #[arg(long, default_value = "package.toml")]
+        package: PathBuf,
+        /// Ignore the lockfile and resolve every dependency from the server again
+        #[arg(long)]
+        update: bool,
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+        #[arg(long)]
+        lockfile: Option<PathBuf>,
+    },
+}
+
+/// Inputs and options shared by `build` and `check`
+#[derive(Args)]
+struct SceneArgs {
+    /// The RSML scene
+    scene: PathBuf,
+    #[arg(long, default_value = "package.toml")]
+    package: PathBuf,
+    /// Report unknown style properties and items larger than their dependency's size-limit as errors
+    #[arg(long)]
+    strict: bool,
+    /// Allow percentage sizes above 100%
+    #[arg(long)]
+    allow_oversized_percentages: bool,
+    /// Report spaces mixing items and spaces, and items with child elements, as warnings
+    #[arg(long)]
+    lenient_nesting: bool,
+    /// Unknown attributes with this prefix are only noted
+    #[arg(long)]
+    passthrough_prefix: Option<String>,
+    /// How deep <include> elements may nest
+    #[arg(long)]
+    max_include_depth: Option<usize>,
+    /// How often a random position in a cube space is sampled before overlapping siblings fail the layout; 0 allows overlaps
+    #[arg(long, default_value_t = rsmlc::placement::DEFAULT_PLACEMENT_ATTEMPTS)]
+    placement_attempts: usize,
+    /// Report a diagnostic code as an error; `-D warnings` denies every warning
+    #[arg(short = 'D', long = "deny", value_name = "CODE")]
+    deny: Vec<String>,
+    /// Do not report a diagnostic code
+    #[arg(short = 'A', long = "allow", value_name = "CODE")]
+    allow: Vec<String>,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    #[command(flatten)]
+    scene: SceneArgs,
+    #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+    format: CheckFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaFormat {
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CheckFormat {
+    /// One line per issue on stderr
+    Text,
+    /// The whole report as JSON on stdout
+    Json,
+}
+
+#[derive(Args)]
+struct BuildArgs {
+    #[command(flatten)]
+    scene: SceneArgs,
+    #[arg(long, default_value = "mjcf", value_parser = parse_target)]
+    target: TargetFormat,
+    /// Output path; output.xml, output.urdf, output.sdf, output.obj or output.svg by default
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Seed of every random choice; the same seed gives the same output
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Also write the computed layout as JSON
+    #[arg(long, value_name = "PATH")]
+    emit_layout_json: Option<PathBuf>,
+    /// Comment the size and position after every MJCF geom
+    #[arg(long)]
+    annotate: bool,
+    /// Put every MJCF geom directly in the worldbody
+    #[arg(long)]
+    flat: bool,
+    /// Also draw every space as a wireframe box in the preview
+    #[arg(long)]
+    preview_spaces: bool,
+    /// Plane the SVG floor plan projects onto: xy, xz or yz
+    #[arg(long, default_value = "xy", value_parser = parse_plane)]
+    svg_plane: Plane,
+    /// Pixels of the longer side of the SVG floor plan
+    #[arg(long, value_name = "PIXELS", default_value_t = 800.0)]
+    svg_size: f64,
+    /// Rebuild whenever the scene, one of its includes or the package changes
+    #[arg(long)]
+    watch: bool,
+    /// Clear the terminal before every rebuild in watch mode
+    #[arg(long, requires = "watch")]
+    clear: bool,
+    /// Report objects whose bounding boxes overlap
+    #[arg(long)]
+    check_collisions: bool,
+    /// Lay out again with the next seed up to N times while objects overlap
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "check_collisions")]
+    collision_retries: usize,
+}
+
+fn parse_target(value: &str) -> std::result::Result<TargetFormat, String> {
+    value.parse().map_err(|e: RsmlError| e.to_string())
+}
+
+fn parse_plane(value: &str) -> std::result::Result<Plane, String> {
+    value.parse().map_err(|e: RsmlError| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            match error.inner() {
+                RsmlError::Io(_) => ExitCode::from(EXIT_IO),
+                _ => ExitCode::from(EXIT_ERRORS),
             }
-            print!("{}=\"{}\"", key, value);
-            first = false;
         }
-        print!("]");
     }
-    
-    // 打印文本内容（如果有的话）
-    if !element.text.trim().is_empty() {
-        println!(" \"{}\"", element.text.trim());
-    } else {
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Build(args) => run_build(args, cli.verbose),
+        Command::Check(args) => run_check(args, cli.verbose),
+        Command::Package {
+            command: PackageCommand::Resolve { package, update, cache_dir, lockfile },
+        } => {
+            let mut options = ResolveOptions {
+                update,
+                ..ResolveOptions::default()
+            };
+            if let Some(cache_dir) = cache_dir {
+                options.cache_dir = cache_dir;
+            }
+            if let Some(lockfile) = lockfile {
+                options.lockfile = lockfile;
+            }
+            run_resolve(&package, &options)
+        }
+        Command::Schema { format } => {
+            match format {
+                SchemaFormat::Json => println!("{}", schema::to_json()?),
+            }
+            Ok(())
+        }
+        Command::Batch { seed, jobs, out, package, scenes } => {
+            let package = PackageSource::Package(load_package(&package)?);
+            let options = batch::BatchOptions {
+                global_seed: seed,
+                jobs,
+                root: PathBuf::from("."),
+                out_dir: out,
+                compile: CompileOptions {
+                    severity: load_severity()?,
+                    ..CompileOptions::new(RsmlSource::Text(String::new()), package)
+                },
+            };
+            for result in batch::compile_batch(&scenes, &options)? {
+                println!(
+                    "{} -> {} (seed {})",
+                    result.metadata.scene,
+                    result.output.display(),
+                    result.metadata.seed
+                );
+            }
+            Ok(())
+        }
+        Command::Report { scene, out, max_items, package } => {
+            run_report(&scene, &out, max_items, &package)
+        }
+        Command::Variance { scene, seeds, json, package } => {
+            let package = load_package(&package)?;
+            let root_element = load_scene(&scene)?;
+            let report = VarianceReport::analyze(&root_element, &package, &seeds)?;
+            if json {
+                println!("{}", report.to_json()?);
+            } else {
+                print!("{}", report.to_table());
+            }
+            Ok(())
+        }
+        Command::Query { scene, symbols: _, package } => {
+            let package_path = package.to_string_lossy().to_string();
+            let package = load_package(&package)?;
+            let root_element = load_scene(&scene)?;
+            let mut symbols = SymbolTable::new();
+            symbols.add_package(&package, &package_path);
+            symbols.add_document(&root_element, &scene.to_string_lossy());
+            print!("{}", symbols.dump());
+            Ok(())
+        }
+    }
+}
+
+/// 编译选项：构建渲染树、计算布局和生成目标格式都由compile完成
+fn compile_options(
+    args: &SceneArgs,
+    package: Package,
+    verbose: bool,
+) -> Result<CompileOptions> {
+    if verbose {
+        println!("Objects: {:?}", package.objects.keys().collect::<Vec<_>>());
+        println!("Groups: {:?}", package.groups.iter().map(|g| &g.name).collect::<Vec<_>>());
         println!();
     }
-    
-    // 递归打印子元素
-    for child in &element.children {
-        print_element(child, depth + 1);
-    }
-}
-
-fn validate_rsml_structure(element: &Element) -> Result<()> {
-    // 验证根元素是rsml
-    if element.name != "rsml" {
-        return Err(RsmlError::InvalidStructure { 
-            message: format!("根元素应该是'rsml'，但实际是'{}'", element.name) 
-        }.into());
-    }
-    
-    // 验证必须有head和body子元素
-    let has_head = element.find_child("head").is_some();
-    let has_body = element.find_child("body").is_some();
-    
-    if !has_head {
-        return Err(RsmlError::MissingElement { element: "head".to_string() }.into());
-    }
-    
-    if !has_body {
-        return Err(RsmlError::MissingElement { element: "body".to_string() }.into());
-    }
-    
-    println!("\n结构验证通过:");
-    println!("- 根元素: {}", element.name);
-    println!("- 包含head元素: {}", has_head);
-    println!("- 包含body元素: {}", has_body);
-    
-    // 验证body中的主要元素
-    if let Some(body) = element.find_child("body") {
-        if let Some(main_room) = body.find_child("space") {
-            println!("- 主房间ID: {:?}", main_room.get_attribute("id"));
-            
-            // 验证主房间的子元素
-            println!("- 主房间子元素数量: {}", main_room.children.len());
+    let mut options = CompileOptions::new(
+        RsmlSource::Path(args.scene.clone()),
+        PackageSource::Package(package),
+    );
+    if let Some(depth) = args.max_include_depth {
+        options.includes.max_depth = depth;
+    }
+    if verbose {
+        let root_element =
+            parse_xml_file_with_includes(&args.scene.to_string_lossy(), &options.includes)?;
+        println!("DOM Tree:");
+        print_element(&root_element, 0);
+        print_structure(&root_element);
+    }
+
+    // 命令行参数优先于rsmlc.toml
+    let mut severity = load_severity()?;
+    let flags: Vec<String> = args
+        .deny
+        .iter()
+        .flat_map(|code| ["--deny".to_string(), code.clone()])
+        .chain(args.allow.iter().flat_map(|code| ["--allow".to_string(), code.clone()]))
+        .collect();
+    severity.apply_args(&flags)?;
+    options.severity = severity;
+
+    let mut layout = LayoutOptions::default();
+    if let Some(prefix) = &args.passthrough_prefix {
+        layout.passthrough_prefix = prefix.clone();
+    }
+    layout.strict_styles = args.strict;
+    layout.allow_oversized_percentages = args.allow_oversized_percentages;
+    layout.lenient_nesting = args.lenient_nesting;
+    layout.placement_attempts = args.placement_attempts;
+    options.layout = layout;
+    Ok(options)
+}
+
+/// 输出所有诊断；有error时compile返回DiagnosticErrors
+fn compile_and_report(options: CompileOptions) -> Result<CompileOutput> {
+    match compile::compile(options) {
+        Ok(compiled) => {
+            for diagnostic in compiled.diagnostics.iter() {
+                eprintln!("{}", diagnostic);
+            }
+            Ok(compiled)
+        }
+        Err(RsmlError::DiagnosticErrors(diagnostics)) => {
+            for diagnostic in diagnostics.iter() {
+                eprintln!("{}", diagnostic);
+            }
+            Err(RsmlError::DiagnosticErrors(diagnostics))
         }
+        Err(e) => Err(e),
+    }
+}
+
+fn run_build(args: BuildArgs, verbose: bool) -> Result<()> {
+    if args.watch {
+        return run_watch(&args, verbose);
     }
-    
+    build_once(&args, load_package(&args.scene.package)?, verbose)
+}
+
+/// 每次重建都重新读取包；失败只输出摘要，继续监视
+fn run_watch(args: &BuildArgs, verbose: bool) -> Result<()> {
+    let mut options = WatchOptions::new(&args.scene.scene, &args.scene.package);
+    if let Some(depth) = args.scene.max_include_depth {
+        options.includes.max_depth = depth;
+    }
+    watch::watch(
+        &options,
+        |package| {
+            if args.clear {
+                print!("\x1b[2J\x1b[H");
+            }
+            build_once(args, package.clone(), verbose)
+        },
+        |rebuild| {
+            println!("[{}] {}", args.scene.scene.display(), rebuild.summary());
+            println!("watching {} file(s)", rebuild.files.len());
+        },
+    )
+}
+
+fn build_once(args: &BuildArgs, package: Package, verbose: bool) -> Result<()> {
+    let mut options = compile_options(&args.scene, package, verbose)?;
+    options.target = args.target;
+    options.seed = args.seed;
+    options.mjcf = MjcfOptions {
+        annotate: args.annotate,
+        flat: args.flat,
+    };
+    options.preview = PreviewOptions {
+        spaces: args.preview_spaces,
+    };
+    options.svg = SvgOptions {
+        plane: args.svg_plane,
+        max_size: args.svg_size,
+    };
+    options.check_collisions = args.check_collisions;
+    options.collision_retries = args.collision_retries;
+    let compiled = compile_and_report(options)?;
+    for collision in &compiled.collisions {
+        eprintln!("collision: {}", collision);
+    }
+
+    let output = args
+        .out
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(args.target.default_output()));
+    std::fs::write(&output, &compiled.output)?;
+    println!("{} written to {}", args.target, output.display());
+    if let Some(path) = &args.emit_layout_json {
+        std::fs::write(path, &compiled.layout_json)?;
+        println!("layout written to {}", path.display());
+    }
+    if verbose {
+        println!("\n{}", compiled.output);
+    }
+    Ok(())
+}
+
+/// 只检查不生成输出；有error时退出码为1
+fn run_check(args: CheckArgs, verbose: bool) -> Result<()> {
+    let report = check_with(compile_options(&args.scene, load_package(&args.scene.package)?, verbose)?)?;
+    match args.format {
+        CheckFormat::Json => println!("{}", report.to_json()?),
+        CheckFormat::Text => {
+            for issue in &report.issues {
+                eprintln!("{}", issue);
+            }
+        }
+    }
+    let report = report.into_result()?;
+    if let CheckFormat::Text = args.format {
+        println!("{}: ok, {} diagnostic(s)", args.scene.scene.display(), report.issues.len());
+    }
+    Ok(())
+}
+
+fn run_resolve(package: &Path, options: &ResolveOptions) -> Result<()> {
+    let mut package = load_package(package)?;
+    let lockfile = package.resolve_dependencies(options)?;
+    // 超过依赖size-limit的资源只警告
+    let mut diagnostics = Diagnostics::new(SeverityConfig::default());
+    package.check_size_limits(&lockfile, &mut diagnostics);
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{}", diagnostic);
+    }
+    for (name, dependency) in &lockfile.dependencies {
+        println!("{}: {} asset(s)", name, dependency.assets.len());
+    }
+    println!("Lockfile: {}", options.lockfile.display());
+    Ok(())
+}
+
+fn run_report(scene: &Path, output: &Path, max_items: Option<usize>, package: &Path) -> Result<()> {
+    let mut options = report::ReportOptions::default();
+    if let Some(max_items) = max_items {
+        options.max_items = max_items;
+    }
+    let package = load_package(package)?;
+    let root_element = load_scene(scene)?;
+    // 根元素和必需的head、body子元素由schema检查
+    schema::validate_structure(&root_element)?;
+    let mut render_tree =
+        RenderTree::with_diagnostics(&root_element, &package, Diagnostics::new(load_severity()?))?;
+    // 诊断中的error列在报告里，不影响生成报告
+    match render_tree.calculate() {
+        Ok(()) | Err(RsmlError::DiagnosticErrors(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    options.title = format!("RSML scene report: {}", scene.display());
+    std::fs::write(output, report::render_html(&render_tree, &options)?)?;
+    println!("Report written to {}", output.display());
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 诊断的severity：当前目录下rsmlc.toml中的[diagnostics]表，没有该文件时使用默认值
+fn load_severity() -> Result<SeverityConfig> {
+    if Path::new("rsmlc.toml").exists() {
+        SeverityConfig::from_file("rsmlc.toml")
+    } else {
+        Ok(SeverityConfig::default())
+    }
+}
+
+/// 与compile相同的方式读取场景，解析其中的<include>
+fn load_scene(scene: &Path) -> Result<Element> {
+    compile::parse_document(&RsmlSource::Path(scene.to_path_buf()), &IncludeOptions::default())
+}
+
+/// 读取失败保留为Io错误，退出码为2
+fn load_package(path: &Path) -> Result<Package> {
+    let contents = std::fs::read_to_string(path)?;
+    contents.parse::<Package>().map_err(|e| RsmlError::ParseError {
+        field: "package".to_string(),
+        message: format!("Failed to parse package file '{}': {}", path.display(), e),
+    })
+}
+
+fn print_element(element: &Element, depth: usize) {
+    // 先序遍历，栈顶是下一个打印的元素
+    let mut stack = vec![(element, depth)];
+    while let Some((element, depth)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+
+        // 打印元素名称
+        print!("{}{}", indent, element.name);
+
+        // 打印属性
+        if !element.attributes.is_empty() {
+            print!(" [");
+            let mut first = true;
+            for (key, value) in &element.attributes {
+                if !first {
+                    print!( ", ");
+                }
+                print!("{}=\"{}\"", key, value);
+                first = false;
+            }
+            print!("]");
+        }
+
+        // 打印文本内容（如果有的话）
+        if !element.text.trim().is_empty() {
+            println!(" \"{}\"", element.text.trim());
+        } else {
+            println!();
+        }
+
+        stack.extend(element.children.iter().rev().map(|child| (child, depth + 1)));
+    }
+}
+
+/// 打印结构信息；结构错误由compile收集，和其他诊断一起输出
+fn print_structure(element: &Element) {
+    println!("\nStructure:");
+    println!("- root element: {}", element.name);
+    println!("- has head: {}", element.find_child("head").is_some());
+    println!("- has body: {}", element.find_child("body").is_some());
+    if let Some(main_room) = element.find_child("body").and_then(|body| body.find_child("space")) {
+        println!("- main space id: {:?}", main_room.get_attribute("id"));
+        println!("- main space children: {}", main_room.children.len());
+    }
+    println!();
+}
@@ -0,0 +1,383 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{RenderNode, RenderNodeType, RenderTree};
+use std::io::Write;
+
+/// 一个网格默认最多分配的体素数量
+pub const DEFAULT_MAX_VOXELS: usize = 64 * 1024 * 1024;
+
+/// 紧凑二进制格式开头的魔数
+const BINARY_MAGIC: &[u8; 4] = b"RSOG";
+const BINARY_VERSION: u8 = 1;
+
+/// 覆盖一个空间的三维占用网格，用位集存储
+///
+/// 体素按C顺序存储（x变化最慢，z最快），和`.npy`导出的布局相同
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyGrid {
+    /// 每个轴上的体素数量
+    pub dims: Dim3<u32>,
+    /// 网格最小角的世界坐标
+    pub origin: Dim3<Length>,
+    /// 一个体素的边长
+    pub resolution: Length,
+    bits: Vec<u64>,
+}
+
+/// 给定dims的网格的体素数量，超出usize时为None
+fn checked_voxel_count(dims: Dim3<u32>) -> Option<usize> {
+    (dims.x as usize).checked_mul(dims.y as usize)?.checked_mul(dims.z as usize)
+}
+
+impl OccupancyGrid {
+    /// `dims`必须已经检查过体素数量的上限
+    fn new(dims: Dim3<u32>, origin: Dim3<Length>, resolution: Length) -> Self {
+        let voxels = dims.x as usize * dims.y as usize * dims.z as usize;
+        Self {
+            dims,
+            origin,
+            resolution,
+            bits: vec![0; voxels.div_ceil(64)],
+        }
+    }
+
+    fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        (x as usize * self.dims.y as usize + y as usize) * self.dims.z as usize + z as usize
+    }
+
+    /// 网格中的体素总数
+    pub fn voxel_count(&self) -> usize {
+        self.dims.x as usize * self.dims.y as usize * self.dims.z as usize
+    }
+
+    /// 被占用的体素数量
+    pub fn occupied_count(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// 网格坐标处的体素是否被占用
+    pub fn is_occupied(&self, x: u32, y: u32, z: u32) -> bool {
+        if x >= self.dims.x || y >= self.dims.y || z >= self.dims.z {
+            return false;
+        }
+        let index = self.index(x, y, z);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set(&mut self, x: u32, y: u32, z: u32) {
+        let index = self.index(x, y, z);
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    /// 把盒子[min, max)接触到的所有体素标记为占用
+    fn fill_box(&mut self, min: Dim3<Length>, max: Dim3<Length>) {
+        let range = |min: Length, max: Length, origin: Length, dim: u32| {
+            let res = self.resolution.mm() as u64;
+            let lo = min.mm().saturating_sub(origin.mm()) as u64 / res;
+            let hi = (max.mm().saturating_sub(origin.mm()) as u64).div_ceil(res);
+            (lo.min(dim as u64) as u32, hi.min(dim as u64) as u32)
+        };
+        let (x0, x1) = range(min.x, max.x, self.origin.x, self.dims.x);
+        let (y0, y1) = range(min.y, max.y, self.origin.y, self.dims.y);
+        let (z0, z1) = range(min.z, max.z, self.origin.z, self.dims.z);
+        for x in x0..x1 {
+            for y in y0..y1 {
+                for z in z0..z1 {
+                    self.set(x, y, z);
+                }
+            }
+        }
+    }
+
+    /// 序列化为紧凑的二进制格式：魔数、版本、dims（3 x u32）、原点mm（3 x u32）、分辨率mm（u32），
+    /// 之后是按小端打包成字节的占用位。所有整数都是小端
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(33 + self.voxel_count().div_ceil(8));
+        bytes.extend_from_slice(BINARY_MAGIC);
+        bytes.push(BINARY_VERSION);
+        for value in [
+            self.dims.x,
+            self.dims.y,
+            self.dims.z,
+            self.origin.x.mm(),
+            self.origin.y.mm(),
+            self.origin.z.mm(),
+            self.resolution.mm(),
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let packed: Vec<u8> = self.bits.iter().flat_map(|word| word.to_le_bytes()).collect();
+        bytes.extend_from_slice(&packed[..self.voxel_count().div_ceil(8)]);
+        bytes
+    }
+
+    /// 解析`to_bytes`生成的紧凑二进制格式；超过`DEFAULT_MAX_VOXELS`的网格在分配内存之前就拒绝
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let error = |message: &str| RsmlError::ParseError {
+            field: "OccupancyGrid".to_string(),
+            message: message.to_string(),
+        };
+        if bytes.len() < 33 || &bytes[..4] != BINARY_MAGIC {
+            return Err(error("not an occupancy grid"));
+        }
+        if bytes[4] != BINARY_VERSION {
+            return Err(error(&format!("unsupported version {}", bytes[4])));
+        }
+        let word = |i: usize| u32::from_le_bytes(bytes[5 + i * 4..9 + i * 4].try_into().unwrap());
+        let dims = Dim3::new(word(0), word(1), word(2));
+        let origin = Dim3::new(
+            Length::from_mm(word(3)),
+            Length::from_mm(word(4)),
+            Length::from_mm(word(5)),
+        );
+        // 维度来自不可信的输入：先检查乘积和数据长度，再分配
+        let voxels = checked_voxel_count(dims)
+            .filter(|&voxels| voxels <= DEFAULT_MAX_VOXELS)
+            .ok_or_else(|| {
+                error(&format!(
+                    "{} x {} x {} voxels exceed the limit of {}",
+                    dims.x, dims.y, dims.z, DEFAULT_MAX_VOXELS
+                ))
+            })?;
+        let data = &bytes[33..];
+        if data.len() != voxels.div_ceil(8) {
+            return Err(error("truncated voxel data"));
+        }
+        let mut grid = OccupancyGrid::new(dims, origin, Length::from_mm(word(6)));
+        for (i, chunk) in data.chunks(8).enumerate() {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            grid.bits[i] = u64::from_le_bytes(word);
+        }
+        Ok(grid)
+    }
+
+    /// 把网格写成NumPy的`.npy`格式（形状为(x, y, z)的uint8数组，1表示占用）
+    pub fn write_npy<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut header = format!(
+            "{{'descr': '|u1', 'fortran_order': False, 'shape': ({}, {}, {}), }}",
+            self.dims.x, self.dims.y, self.dims.z
+        );
+        // magic(6) + version(2) + header_len(2) + header must be a multiple of 64
+        let unpadded = 10 + header.len() + 1;
+        header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+        header.push('\n');
+
+        writer.write_all(b"\x93NUMPY\x01\x00")?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        let data: Vec<u8> = (0..self.voxel_count())
+            .map(|i| ((self.bits[i / 64] >> (i % 64)) & 1) as u8)
+            .collect();
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// 把网格保存为`.npy`文件
+    pub fn save_npy(&self, path: &str) -> Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_npy(&mut file)
+    }
+}
+
+impl RenderTree<'_> {
+    /// 按给定的分辨率体素化指定id的空间中的所有物体，必须在`calculate`之后调用
+    pub fn occupancy_grid(&self, space_id: &str, resolution: Length) -> Result<OccupancyGrid> {
+        self.occupancy_grid_with_limit(space_id, resolution, DEFAULT_MAX_VOXELS)
+    }
+
+    /// 和`occupancy_grid`相同，网格超过`max_voxels`个体素时报错
+    pub fn occupancy_grid_with_limit(
+        &self,
+        space_id: &str,
+        resolution: Length,
+        max_voxels: usize,
+    ) -> Result<OccupancyGrid> {
+        if resolution.mm() == 0 {
+            return Err(RsmlError::InvalidAttribute {
+                attribute: "resolution".to_string(),
+                value: resolution.to_string(),
+            });
+        }
+        let space = self
+            .iter()
+            .find(|&node| {
+                let node_ref = self.node(node);
+                node_ref.node_type == RenderNodeType::Space && node_ref.id.as_deref() == Some(space_id)
+            })
+            .ok_or(RsmlError::RenderTree {
+                message: format!("space '{}' not found", space_id),
+            })?;
+        let (origin, size) = computed_box(self.node(space))?;
+
+        let dims = Dim3::new(
+            size.x.mm().div_ceil(resolution.mm()),
+            size.y.mm().div_ceil(resolution.mm()),
+            size.z.mm().div_ceil(resolution.mm()),
+        );
+        let voxels = dims.x as u128 * dims.y as u128 * dims.z as u128;
+        if voxels > max_voxels as u128 {
+            return Err(RsmlError::RenderTree {
+                message: format!(
+                    "occupancy grid for '{}' at {} would need {} voxels (limit {})",
+                    space_id, resolution, voxels, max_voxels
+                ),
+            });
+        }
+
+        let mut grid = OccupancyGrid::new(dims, origin, resolution);
+        for node in self.descendants(space) {
+            let node_ref = self.node(node);
+            if node_ref.node_type == RenderNodeType::Item {
+                let (min, size) = computed_box(node_ref)?;
+                grid.fill_box(min, min + size);
+            }
+        }
+        // Imported fixtures are not part of any space, only the part inside the grid is marked
+        for item in &self.imported {
+            grid.fill_box(item.position(), item.position() + item.size());
+        }
+        Ok(grid)
+    }
+}
+
+/// 布局后节点的世界坐标和尺寸
+fn computed_box(node: &RenderNode) -> Result<(Dim3<Length>, Dim3<Length>)> {
+    let position = node.computed_style.position.get_length();
+    let size = node.computed_style.size.get_length();
+    match (position, size) {
+        (Some(position), Some(size)) => Ok((position, size)),
+        _ => Err(RsmlError::RenderTree {
+            message: format!("{} has not been laid out", node.label()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::{GeomType, Package};
+    use crate::plan::PlanItem;
+    use crate::scene::Builder;
+
+    fn two_box_room() -> Builder {
+        Builder::body().space(|room| {
+            room.id("room")
+                .style("size:1m 1m 1m;flex-direction:x;align-items:flex-start flex-start")
+                .object("table_leg")
+                .object("table_leg")
+        })
+    }
+
+    #[test]
+    fn test_occupancy_grid_two_boxes() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_box_room().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let grid = render_tree
+            .occupancy_grid("room", Length::from_cm(10))
+            .unwrap();
+        assert_eq!(grid.dims, Dim3::new(10, 10, 10));
+        // 两个10cm x 10cm x 50cm的桌腿沿x方向排列
+        assert_eq!(grid.occupied_count(), 10);
+        for z in 0..5 {
+            assert!(grid.is_occupied(0, 0, z));
+            assert!(grid.is_occupied(1, 0, z));
+        }
+        assert!(!grid.is_occupied(2, 0, 0));
+        assert!(!grid.is_occupied(0, 0, 5));
+        assert!(!grid.is_occupied(0, 1, 0));
+
+        // 不对齐的分辨率：部分重叠的体素也算占用
+        let grid = render_tree
+            .occupancy_grid("room", Length::from_cm(15))
+            .unwrap();
+        assert_eq!(grid.dims, Dim3::new(7, 7, 7));
+        assert_eq!(grid.occupied_count(), 2 * 4);
+
+        let bytes = grid.to_bytes();
+        assert_eq!(OccupancyGrid::from_bytes(&bytes).unwrap(), grid);
+    }
+
+    #[test]
+    fn test_occupancy_grid_untrusted_bytes() {
+        let header = |dims: [u32; 3]| {
+            let mut bytes = BINARY_MAGIC.to_vec();
+            bytes.push(BINARY_VERSION);
+            for value in dims.into_iter().chain([0, 0, 0, 10]) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes
+        };
+        // 乘积溢出、超过体素上限、以及数据长度不符都返回错误，不会分配或panic
+        let err = OccupancyGrid::from_bytes(&header([u32::MAX; 3])).unwrap_err();
+        assert!(err.to_string().contains("exceed the limit"), "{}", err);
+        let err = OccupancyGrid::from_bytes(&header([4096, 4096, 4096])).unwrap_err();
+        assert!(err.to_string().contains("exceed the limit"), "{}", err);
+        let err = OccupancyGrid::from_bytes(&header([10, 10, 10])).unwrap_err();
+        assert!(err.to_string().contains("truncated voxel data"), "{}", err);
+
+        let mut bytes = header([10, 10, 10]);
+        bytes.extend_from_slice(&[0; 125]);
+        assert_eq!(OccupancyGrid::from_bytes(&bytes).unwrap().occupied_count(), 0);
+    }
+
+    #[test]
+    fn test_occupancy_grid_imported_items() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_box_room().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+        render_tree.imported.push(PlanItem {
+            name: "base/leg".to_string(),
+            geom_type: GeomType::Box,
+            path: None,
+            position: [500, 500, 0],
+            size: [100, 100, 100],
+            attributes: Vec::new(),
+        });
+
+        let grid = render_tree
+            .occupancy_grid("room", Length::from_cm(10))
+            .unwrap();
+        assert_eq!(grid.occupied_count(), 11);
+        assert!(grid.is_occupied(5, 5, 0));
+    }
+
+    #[test]
+    fn test_occupancy_grid_errors() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_box_room().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        assert!(render_tree.occupancy_grid("kitchen", Length::from_cm(10)).is_err());
+        assert!(render_tree.occupancy_grid("room", Length::from_mm(0)).is_err());
+        assert!(render_tree
+            .occupancy_grid_with_limit("room", Length::from_mm(1), 1_000_000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_occupancy_grid_npy() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_box_room().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+        let grid = render_tree
+            .occupancy_grid("room", Length::from_cm(10))
+            .unwrap();
+
+        let mut npy = Vec::new();
+        grid.write_npy(&mut npy).unwrap();
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (10, 10, 10)"));
+        let data = &npy[10 + header_len..];
+        assert_eq!(data.len(), 1000);
+        assert_eq!(data.iter().filter(|&&v| v == 1).count(), 10);
+        // C顺序：(x=1, y=0, z=4)的下标是(x * 10 + y) * 10 + z
+        assert_eq!(data[10 * 10 + 4], 1);
+    }
+}
@@ -0,0 +1,150 @@
+//! `rsml.lock`：记录一次构建解析出的依赖资源，让下次构建使用相同的资源
+//!
+//! ```toml
+//! [dependencies.cutlery]
+//! category-id = "c7"
+//!
+//! [[dependencies.cutlery.assets]]
+//! id = "a1"
+//! name = "fork"
+//! resource-url = "https://transairobot.com/files/a1.obj"
+//! size-mm = [20, 40, 120]
+//! sha256 = "9f86d081884c7d65..."
+//! ```
+
+use crate::error::{Result, RsmlError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 默认文件名，与`package.toml`放在同一目录
+pub const LOCKFILE_NAME: &str = "rsml.lock";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, LockedDependency>,
+}
+
+/// 依赖解析到的资源分类及其资源，顺序与服务器返回的一致
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedDependency {
+    pub category_id: String,
+    #[serde(default)]
+    pub assets: Vec<LockedAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedAsset {
+    pub id: String,
+    pub name: String,
+    pub resource_url: String,
+    /// x、y、z方向的长度，单位为毫米
+    pub size_mm: [u32; 3],
+    /// 下载文件的十六进制SHA-256
+    pub sha256: String,
+}
+
+impl LockedAsset {
+    /// 检查`path`的内容是否仍与锁定的一致
+    pub fn verify(&self, path: &Path) -> Result<()> {
+        let actual = sha256_file(path)?;
+        if actual != self.sha256 {
+            return Err(RsmlError::HashMismatch {
+                path: path.display().to_string(),
+                expected: self.sha256.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Lockfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| RsmlError::ParseError {
+            field: "lockfile".to_string(),
+            message: format!("Failed to parse lockfile '{}': {}", path.display(), e),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string(self).map_err(|e| RsmlError::ParseError {
+            field: "lockfile".to_string(),
+            message: e.to_string(),
+        })?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// 文件内容的十六进制SHA-256
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(crate::api::hex(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rsmlc_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_lockfile_round_trip() {
+        let mut lockfile = Lockfile::default();
+        lockfile.dependencies.insert(
+            "cutlery".to_string(),
+            LockedDependency {
+                category_id: "c7".to_string(),
+                assets: vec![LockedAsset {
+                    id: "a1".to_string(),
+                    name: "fork".to_string(),
+                    resource_url: "http://localhost/files/a1.obj".to_string(),
+                    size_mm: [20, 40, 120],
+                    sha256: "00".repeat(32),
+                }],
+            },
+        );
+        let path = temp_path("round_trip.lock");
+        lockfile.save(&path).unwrap();
+        let loaded = Lockfile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), lockfile);
+    }
+
+    #[test]
+    fn test_hash_verification() {
+        let path = temp_path("hashed_asset");
+        std::fs::write(&path, "test").unwrap();
+        let sha256 = sha256_file(&path).unwrap();
+        assert_eq!(sha256, "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08");
+        let asset = LockedAsset {
+            id: "a1".to_string(),
+            name: "fork".to_string(),
+            resource_url: String::new(),
+            size_mm: [1, 1, 1],
+            sha256,
+        };
+        asset.verify(&path).unwrap();
+
+        std::fs::write(&path, "tampered").unwrap();
+        let error = asset.verify(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        match error {
+            RsmlError::HashMismatch { expected, actual, .. } => {
+                assert_eq!(expected, asset.sha256);
+                assert_ne!(actual, expected);
+            }
+            other => panic!("expected a hash mismatch, got {}", other),
+        }
+    }
+}
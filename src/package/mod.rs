@@ -1,8 +1,16 @@
+use crate::api::{ApiClient, DownloadOptions};
 use crate::base::Length;
+use crate::diagnostics::{self, Diagnostics};
 use crate::dim3::Dim3;
 use crate::error::RsmlError;
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub mod lock;
+
+pub use lock::{Lockfile, LOCKFILE_NAME};
+use lock::{LockedAsset, LockedDependency};
 
 fn deserialize_size<'de, D>(deserializer: D) -> Result<Dim3<Length>, D::Error>
 where
@@ -77,10 +85,141 @@ impl<'de> Deserialize<'de> for Object {
     }
 }
 
+/// One member placement in a group layout, relative to the group origin
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GroupPlacement {
+    /// Name of a member in the group's objects
+    pub object: String,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub at: Dim3<Length>,
+}
+
+/// A group member resolved from the layout, ready to be emitted
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupMember {
+    pub name: String,
+    pub object: Object,
+    pub offset: Dim3<Length>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Group {
     pub name: String,
+    #[serde(default)]
     pub objects: std::collections::HashMap<String, Object>,
+    /// Names of package objects, other groups or dependencies that also belong to the group
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Composite groups: every placement is emitted, instead of selecting one member
+    #[serde(default)]
+    pub layout: Vec<GroupPlacement>,
+}
+
+impl Group {
+    /// Resolve the layout placements into members with their objects
+    pub fn layout_members(&self) -> Result<Vec<GroupMember>, RsmlError> {
+        self.layout
+            .iter()
+            .map(|placement| {
+                let object = self.objects.get(&placement.object).ok_or_else(|| {
+                    RsmlError::PackageConfigError(format!(
+                        "layout of group '{}' references unknown member '{}'",
+                        self.name, placement.object
+                    ))
+                })?;
+                Ok(GroupMember {
+                    name: placement.object.clone(),
+                    object: object.clone(),
+                    offset: placement.at,
+                })
+            })
+            .collect()
+    }
+
+    /// Slot size of a group with a layout: the bounding box of all placements
+    fn layout_size(&self) -> Option<Dim3<Length>> {
+        let mut size: Option<Dim3<Length>> = None;
+        for placement in &self.layout {
+            let object = self.objects.get(&placement.object)?;
+            let max = placement.at + object.size;
+            size = Some(match size {
+                None => max,
+                Some(current) => current.max_components(max),
+            });
+        }
+        size
+    }
+}
+
+/// A dependency on an asset category: `cutlery = "1.0"`, or
+/// `cutlery = { version = "1.0", size-limit = "30cm 30cm 30cm" }` to warn about assets
+/// larger than the limit
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Dependency {
+    pub version: String,
+    #[serde(rename = "size-limit")]
+    pub size_limit: Option<Dim3<Length>>,
+}
+
+impl From<&str> for Dependency {
+    fn from(version: &str) -> Self {
+        Dependency {
+            version: version.to_string(),
+            size_limit: None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        fn deserialize_limit<'de, D>(deserializer: D) -> Result<Option<Dim3<Length>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserialize_size(deserializer).map(Some)
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum DependencyHelper {
+            Version(String),
+            Detailed {
+                version: String,
+                #[serde(rename = "size-limit", default, deserialize_with = "deserialize_limit")]
+                size_limit: Option<Dim3<Length>>,
+            },
+        }
+
+        Ok(match DependencyHelper::deserialize(deserializer)? {
+            DependencyHelper::Version(version) => Dependency {
+                version,
+                size_limit: None,
+            },
+            DependencyHelper::Detailed { version, size_limit } => Dependency { version, size_limit },
+        })
+    }
+}
+
+/// Where `Package::resolve_dependencies` caches asset files and keeps the lockfile
+#[derive(Debug, Clone)]
+pub struct ResolveOptions {
+    pub cache_dir: PathBuf,
+    pub lockfile: PathBuf,
+    /// Ignore the lockfile and resolve every dependency from the server again
+    pub update: bool,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        ResolveOptions {
+            cache_dir: PathBuf::from(".rsml/cache"),
+            lockfile: PathBuf::from(LOCKFILE_NAME),
+            update: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,55 +234,571 @@ pub struct Package {
     pub objects: std::collections::HashMap<String, Object>,
     pub groups: Vec<Group>,
     #[serde(default)]
-    pub dependencies: std::collections::HashMap<String, String>,
+    pub dependencies: std::collections::HashMap<String, Dependency>,
+    /// Space sizes of the groups, computed by `finalize`
+    #[serde(skip)]
+    group_sizes: std::collections::HashMap<String, Dim3<Length>>,
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Group space sizes computed on this thread, to check that lookups use the cache
+    static GROUP_SIZE_COMPUTATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl std::str::FromStr for Package {
+    type Err = RsmlError;
+
+    /// Same as `Package::from_toml_str`, so `contents.parse::<Package>()` works
+    fn from_str(contents: &str) -> Result<Self, RsmlError> {
+        Self::from_toml_str(contents)
+    }
 }
 
 impl Package {
     /// Load a package from a TOML file
     pub fn from_file(path: &str) -> Result<Self, crate::error::RsmlError> {
         let contents = std::fs::read_to_string(path).map_err(|e| crate::error::RsmlError::Io(e))?;
-        toml::from_str(&contents).map_err(|e| crate::error::RsmlError::ParseError {
+        Self::from_toml_str(&contents).map_err(|e| crate::error::RsmlError::ParseError {
             field: "package".to_string(),
             message: format!("Failed to parse package file '{}': {}", path, e),
         })
     }
 
-    /// Get the space size for an object or group by name.
-    /// For groups, returns the maximum size among all objects in the group.
-    pub fn get_space_size(&self, name: &str) -> Option<Dim3<Length>> {
-        // First, try to find in objects
-        if let Some(object) = self.objects.get(name) {
-            return Some(object.size);
+    /// Parse a package from TOML and check the group layouts and members
+    pub fn from_toml_str(contents: &str) -> Result<Self, crate::error::RsmlError> {
+        let mut package: Package = toml::from_str(contents)?;
+        for group in &package.groups {
+            group.layout_members()?;
+            package.group_objects(&group.name)?;
         }
+        package.finalize();
+        Ok(package)
+    }
 
-        // Then, try to find in groups
-        for group in &self.groups {
-            if group.name == name {
-                // Calculate maximum size among all objects in the group
-                let mut max_size: Option<Dim3<Length>> = None;
-
-                for (_, object) in &group.objects {
-                    match max_size {
-                        None => {
-                            max_size = Some(object.size);
-                        }
-                        Some(current_max) => {
-                            // Update max_size with the component-wise maximum
-                            let new_max = Dim3::new(
-                                std::cmp::max(current_max.x, object.size.x),
-                                std::cmp::max(current_max.y, object.size.y),
-                                std::cmp::max(current_max.z, object.size.z),
-                            );
-                            max_size = Some(new_max);
+    /// Compute the space size of every group once, so `get_space_size` does not collect the
+    /// objects of a group on every lookup.
+    ///
+    /// `from_toml_str` and `resolve_dependencies` call it; call it again after changing
+    /// `objects` or `groups`. Groups whose size is an error are not cached, so their lookups
+    /// still report the missing member or the cycle.
+    pub fn finalize(&mut self) {
+        let sizes = self
+            .groups
+            .iter()
+            .filter_map(|group| Some((group.name.clone(), self.group_space_size(group).ok()?)))
+            .collect();
+        self.group_sizes = sizes;
+    }
+
+    /// Objects a group selects from, in name order: its own objects and, transitively, the
+    /// objects and groups named in its members. Dependencies add nothing until
+    /// `resolve_dependencies` loads them as groups.
+    pub fn group_objects(&self, name: &str) -> Result<Vec<(String, Object)>, RsmlError> {
+        let mut objects = BTreeMap::new();
+        self.collect_group_objects(name, &mut Vec::new(), &mut objects)?;
+        Ok(objects.into_iter().collect())
+    }
+
+    /// `chain` holds the groups being collected, so a group containing itself is an error
+    fn collect_group_objects<'p>(
+        &'p self,
+        name: &str,
+        chain: &mut Vec<&'p str>,
+        objects: &mut BTreeMap<String, Object>,
+    ) -> Result<(), RsmlError> {
+        let group = self.groups.iter().find(|group| group.name == name).ok_or_else(|| {
+            RsmlError::PackageConfigError(format!("unknown group '{}'", name))
+        })?;
+        if chain.contains(&name) {
+            let mut names = chain.to_vec();
+            names.push(name);
+            return Err(RsmlError::PackageConfigError(format!(
+                "group cycle: {}",
+                names.join(" -> ")
+            )));
+        }
+
+        chain.push(&group.name);
+        for (name, object) in &group.objects {
+            objects.entry(name.clone()).or_insert_with(|| object.clone());
+        }
+        for member in &group.members {
+            if let Some(object) = self.objects.get(member) {
+                objects.entry(member.clone()).or_insert_with(|| object.clone());
+            } else if self.groups.iter().any(|group| &group.name == member) {
+                self.collect_group_objects(member, chain, objects)?;
+            } else if !self.dependencies.contains_key(member) {
+                return Err(RsmlError::PackageConfigError(format!(
+                    "group '{}' references unknown member '{}'",
+                    group.name, member
+                )));
+            }
+        }
+        chain.pop();
+        Ok(())
+    }
+
+    /// Download the assets of every dependency from the server of `ApiClient::default()` into
+    /// `cache_dir/<dependency>/<asset-id>` and add each dependency as a group of its assets
+    pub fn resolve_dependencies(&mut self, options: &ResolveOptions) -> Result<Lockfile, RsmlError> {
+        self.resolve_dependencies_from(&ApiClient::default(), options)
+    }
+
+    /// Same as `resolve_dependencies` with the given asset server.
+    ///
+    /// Dependencies recorded in the lockfile use the locked assets and every cached file must
+    /// match its locked hash; the others are fetched from the server and added to the
+    /// lockfile, which is written when it changes. Files already in the cache are not
+    /// downloaded again, and a group with the dependency's name is replaced.
+    pub fn resolve_dependencies_from(
+        &mut self,
+        api: &ApiClient,
+        options: &ResolveOptions,
+    ) -> Result<Lockfile, RsmlError> {
+        let previous = match options.lockfile.exists() && !options.update {
+            true => Lockfile::load(&options.lockfile)?,
+            false => Lockfile::default(),
+        };
+        let mut lockfile = Lockfile::default();
+        let mut names: Vec<String> = self.dependencies.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let dir = options.cache_dir.join(&name);
+            let locked = match previous.dependencies.get(&name) {
+                Some(locked) => {
+                    for asset in &locked.assets {
+                        let path = dir.join(&asset.id);
+                        if path.exists() {
+                            asset.verify(&path)?;
+                        } else {
+                            let download = DownloadOptions {
+                                sha256: Some(asset.sha256.clone()),
+                                ..DownloadOptions::default()
+                            };
+                            api.download_file_verified(&asset.resource_url, &path, &download)?;
                         }
                     }
+                    locked.clone()
                 }
+                None => Self::fetch_dependency_assets(api, &name, &dir)?,
+            };
+
+            let objects = locked
+                .assets
+                .iter()
+                .map(|asset| {
+                    let [x, y, z] = asset.size_mm.map(Length::from_mm);
+                    let object = Object {
+                        geom_type: GeomType::Mesh,
+                        size: Dim3::new(x, y, z),
+                        path: Some(dir.join(&asset.id).to_string_lossy().to_string()),
+                    };
+                    (asset.name.clone(), object)
+                })
+                .collect();
+            self.groups.retain(|group| group.name != name);
+            self.groups.push(Group {
+                name: name.clone(),
+                objects,
+                members: Vec::new(),
+                layout: Vec::new(),
+            });
+            lockfile.dependencies.insert(name, locked);
+        }
+        self.finalize();
 
-                return max_size;
+        if !options.lockfile.exists() || lockfile != Lockfile::load(&options.lockfile)? {
+            lockfile.save(&options.lockfile)?;
+        }
+        Ok(lockfile)
+    }
+
+    /// The size-limit of the dependency an object or group element named `name` places, if any
+    pub fn size_limit(&self, name: &str) -> Option<Dim3<Length>> {
+        self.dependencies.get(name)?.size_limit
+    }
+
+    /// Warn about locked assets larger than the size limit of their dependency on any axis
+    pub fn check_size_limits(&self, lockfile: &Lockfile, diagnostics: &mut Diagnostics) {
+        for (name, locked) in &lockfile.dependencies {
+            let Some(limit) = self.dependencies.get(name).and_then(|dependency| dependency.size_limit) else {
+                continue;
+            };
+            for asset in &locked.assets {
+                let [x, y, z] = asset.size_mm;
+                if !Dim3::new(x, y, z).map(Length::from_mm).fits_within(&limit) {
+                    diagnostics.set_element(Some(format!("{}/{}", name, asset.name)), None);
+                    diagnostics.record(
+                        diagnostics::OVERSIZED_ASSET,
+                        format!(
+                            "asset '{}' of dependency '{}' is {}mm x {}mm x {}mm, larger than its size-limit of {}mm x {}mm x {}mm",
+                            asset.id, name, x, y, z, limit.x.mm(), limit.y.mm(), limit.z.mm()
+                        ),
+                    );
+                }
             }
         }
+        diagnostics.set_element(None, None);
+    }
+
+    /// Fetch the assets of a dependency's category and download the missing files
+    fn fetch_dependency_assets(api: &ApiClient, name: &str, dir: &Path) -> Result<LockedDependency, RsmlError> {
+        let category = api.fetch_dependency(name)?;
+        let mut assets = Vec::new();
+        for asset in api.iter_assets(&category.id) {
+            let asset = asset?;
+            let path = dir.join(&asset.id);
+            let sha256 = match path.exists() {
+                true => lock::sha256_file(&path)?,
+                false => api.download_file_verified(
+                    &asset.resource_url,
+                    &path,
+                    &DownloadOptions::default(),
+                )?,
+            };
+            assets.push(LockedAsset {
+                sha256,
+                size_mm: [asset.x_len, asset.y_len, asset.z_len].map(|mm| mm.round() as u32),
+                id: asset.id,
+                name: asset.name,
+                resource_url: asset.resource_url,
+            });
+        }
+        Ok(LockedDependency {
+            category_id: category.id,
+            assets,
+        })
+    }
+
+    /// Get the space size for an object or group by name.
+    /// For groups, returns the maximum size among all objects in the group, including
+    /// those of nested groups. Unknown names are an error that suggests close matches.
+    pub fn get_space_size(&self, name: &str) -> Result<Dim3<Length>, RsmlError> {
+        // First, try to find in objects
+        if let Some(object) = self.objects.get(name) {
+            return Ok(object.size);
+        }
+        if let Some(size) = self.group_sizes.get(name) {
+            return Ok(*size);
+        }
+
+        // Then, try to find in groups
+        let Some(group) = self.groups.iter().find(|group| group.name == name) else {
+            return Err(RsmlError::PackageConfigError(format!(
+                "object/group '{}' not found in package '{}'{}",
+                name,
+                self.package.name,
+                self.did_you_mean(name)
+            )));
+        };
+        self.group_space_size(group)
+    }
+
+    /// Space size of a group: the bounding box of its layout, or the maximum size among all
+    /// objects it selects from
+    fn group_space_size(&self, group: &Group) -> Result<Dim3<Length>, RsmlError> {
+        #[cfg(test)]
+        GROUP_SIZE_COMPUTATIONS.with(|count| count.set(count.get() + 1));
+        let empty = || RsmlError::PackageConfigError(format!("group '{}' has no objects", group.name));
+        if !group.layout.is_empty() {
+            return group.layout_size().ok_or_else(empty);
+        }
+
+        // Calculate maximum size among all objects in the group
+        self.group_objects(&group.name)?
+            .into_iter()
+            .map(|(_, object)| object.size)
+            .reduce(Dim3::max_components)
+            .ok_or_else(empty)
+    }
+
+    /// `"; did you mean 'a', 'b'?"` listing the similar names, or empty without any
+    pub fn did_you_mean(&self, name: &str) -> String {
+        let similar = self.similar_names(name);
+        if similar.is_empty() {
+            return String::new();
+        }
+        format!("; did you mean '{}'?", similar.join("', '"))
+    }
+
+    /// Object and group names close to `name`, closest first; a third of the name's
+    /// characters (at least one) may differ
+    pub fn similar_names(&self, name: &str) -> Vec<&str> {
+        let limit = std::cmp::max(1, name.chars().count() / 3);
+        let mut similar: Vec<(usize, &str)> = self
+            .objects
+            .keys()
+            .map(String::as_str)
+            .chain(self.groups.iter().map(|group| group.name.as_str()))
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|(distance, _)| *distance <= limit)
+            .collect();
+        similar.sort();
+        similar.into_iter().map(|(_, candidate)| candidate).collect()
+    }
+}
+
+/// Levenshtein distance in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != *cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NESTED: &str = r#"
+        [package]
+        name = "nested"
+        description = "groups of groups"
+
+        [objects]
+        plate = { geom-type = "box", size = "20cm 20cm 2cm" }
+
+        [[groups]]
+        name = "cups"
+        [groups.objects]
+        cup = { geom-type = "box", size = "8cm 8cm 12cm" }
+
+        [[groups]]
+        name = "drinkware"
+        members = ["cups"]
+        [groups.objects]
+        bottle = { geom-type = "box", size = "6cm 6cm 30cm" }
+
+        [[groups]]
+        name = "tableware"
+        members = ["drinkware", "plate", "cutlery"]
+
+        [dependencies]
+        cutlery = { version = "1.0", size-limit = "2cm 1m 1m" }
+    "#;
+
+    fn write_temp(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rsmlc_{}_{}.toml", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_two_level_nested_groups() {
+        let path = write_temp("nested_groups", NESTED);
+        let package = Package::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let names: Vec<String> = package
+            .group_objects("tableware")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["bottle", "cup", "plate"]);
+        // 最大包围盒来自所有层级的物体：plate的x、y和bottle的z
+        let size = package.get_space_size("tableware").unwrap();
+        assert_eq!(size, Dim3::new(Length::from_mm(200), Length::from_mm(200), Length::from_mm(300)));
+        let size = package.get_space_size("drinkware").unwrap();
+        assert_eq!(size, Dim3::new(Length::from_mm(80), Length::from_mm(80), Length::from_mm(300)));
+    }
+
+    #[test]
+    fn test_group_cycle() {
+        let cycle = r#"
+            [package]
+            name = "cycle"
+            description = "a contains b contains a"
+
+            [objects]
+
+            [[groups]]
+            name = "a"
+            members = ["b"]
+
+            [[groups]]
+            name = "b"
+            members = ["a"]
+        "#;
+        let path = write_temp("group_cycle", cycle);
+        let error = Package::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains("group cycle: a -> b -> a"), "{}", error);
+
+        let unknown = NESTED.replace(r#""plate", "cutlery""#, r#""plate", "spoons""#);
+        let error = Package::from_toml_str(&unknown).unwrap_err();
+        assert!(
+            error.to_string().contains("group 'tableware' references unknown member 'spoons'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_group_size_is_computed_once() {
+        let computations = || GROUP_SIZE_COMPUTATIONS.with(std::cell::Cell::get);
+        let before = computations();
+        let mut package = Package::from_toml_str(NESTED).unwrap();
+        assert_eq!(computations() - before, 3);
+
+        let before = computations();
+        for _ in 0..1000 {
+            let size = package.get_space_size("tableware").unwrap();
+            assert_eq!(size, Dim3::new(Length::from_mm(200), Length::from_mm(200), Length::from_mm(300)));
+        }
+        assert_eq!(computations(), before);
+
+        // 缓存之后的修改需要重新finalize，出错的组不缓存，每次查找都报告错误
+        package.groups[0].members.push("tableware".to_string());
+        package.finalize();
+        let error = package.get_space_size("cups").unwrap_err();
+        assert!(error.to_string().contains("group cycle: cups -> tableware -> drinkware"), "{}", error);
+        package.groups[0].members = vec!["spoons".to_string()];
+        package.finalize();
+        let error = package.get_space_size("drinkware").unwrap_err();
+        assert!(error.to_string().contains("references unknown member 'spoons'"), "{}", error);
+    }
+
+    #[test]
+    fn test_space_size_suggests_close_names() {
+        let package = Package::from_file("package.toml").unwrap();
+        let error = package.get_space_size("table_lg").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "package config error: object/group 'table_lg' not found in package 'tiny_example'; \
+             did you mean 'table_leg'?"
+        );
+        let error = package.get_space_size("vase").unwrap_err();
+        assert!(!error.to_string().contains("did you mean"), "{}", error);
+        assert_eq!(edit_distance("bottles", "bottle"), 1);
+        assert_eq!(edit_distance("", "cup"), 3);
+    }
+
+    /// Serves the asset API of one dependency with two pages of one asset each, and counts
+    /// the file downloads
+    fn serve_asset_api() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::io::{BufRead, BufReader, Write};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let downloads = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = downloads.clone();
+        let files = base_url.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                // 读完请求头
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let target = request_line.split_whitespace().nth(1).unwrap_or("");
+                let asset = |id: &str, name: &str, x: f64| {
+                    format!(
+                        r#"{{"id":"{}","name":"{}","resource_url":"{}/files/{}.obj","x_len":{},"y_len":40.0,"z_len":120.4}}"#,
+                        id, name, files, id, x
+                    )
+                };
+                let page = |item: String, page: u32| {
+                    format!(
+                        r#"{{"code":0,"message":"ok","data":{{"items":[{}],"total":2,"total_pages":2,"page":{}}}}}"#,
+                        item, page
+                    )
+                };
+                let body = if target.starts_with("/api/robot-3d-assets/categories/name?name=cutlery") {
+                    r#"{"code":0,"message":"ok","data":{"id":"c7","name":"cutlery","description":"","utime":0,"ctime":0,"asset_count":2}}"#.to_string()
+                } else if target == "/api/robot-3d-assets/assets?category_id=c7&page=1&limit=100" {
+                    page(asset("a1", "fork", 20.0), 1)
+                } else if target == "/api/robot-3d-assets/assets?category_id=c7&page=2&limit=100" {
+                    page(asset("a2", "knife", 25.0), 2)
+                } else if target.starts_with("/files/") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    format!("mesh {}", target)
+                } else {
+                    r#"{"code":404,"message":"not found","data":null}"#.to_string()
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (base_url, downloads)
+    }
+
+    #[test]
+    fn test_resolve_dependencies() {
+        use std::sync::atomic::Ordering;
+
+        let (base_url, downloads) = serve_asset_api();
+        let api = ApiClient::new(base_url);
+        let dir = std::env::temp_dir().join(format!("rsmlc_resolve_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut options = ResolveOptions {
+            cache_dir: dir.join("cache"),
+            lockfile: dir.join("rsml.lock"),
+            update: false,
+        };
+
+        let mut package = Package::from_toml_str(NESTED).unwrap();
+        let lockfile = package.resolve_dependencies_from(&api, &options).unwrap();
+        assert_eq!(downloads.load(Ordering::SeqCst), 2);
+        let fork = options.cache_dir.join("cutlery").join("a1");
+        assert_eq!(std::fs::read_to_string(&fork).unwrap(), "mesh /files/a1.obj");
+        assert_eq!(Lockfile::load(&options.lockfile).unwrap(), lockfile);
+        // knife比size-limit宽5mm
+        let mut diagnostics = Diagnostics::new(diagnostics::SeverityConfig::default());
+        package.check_size_limits(&lockfile, &mut diagnostics);
+        let warnings: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("asset 'a2' of dependency 'cutlery' is 25mm x 40mm x 120mm"), "{}", warnings[0]);
+        let locked = &lockfile.dependencies["cutlery"];
+        assert_eq!(locked.category_id, "c7");
+        assert_eq!(locked.assets[0].sha256, lock::sha256_file(&fork).unwrap());
+
+        let names: Vec<String> = package
+            .group_objects("cutlery")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["fork", "knife"]);
+        let (_, knife) = &package.group_objects("cutlery").unwrap()[1];
+        assert_eq!(knife.size, Dim3::new(Length::from_mm(25), Length::from_mm(40), Length::from_mm(120)));
+        let knife_path = options.cache_dir.join("cutlery").join("a2");
+        assert_eq!(knife.path.as_deref(), Some(knife_path.to_str().unwrap()));
+        // tableware包含cutlery依赖，现在也能选到依赖中的物体
+        assert!(package.group_objects("tableware").unwrap().iter().any(|(name, _)| name == "fork"));
+
+        // 已经缓存的文件不会再下载，lockfile中的依赖不再请求服务器
+        package.resolve_dependencies_from(&ApiClient::new("http://127.0.0.1:1"), &options).unwrap();
+        assert_eq!(downloads.load(Ordering::SeqCst), 2);
+        assert_eq!(package.groups.iter().filter(|group| group.name == "cutlery").count(), 1);
 
-        // Name not found
-        None
+        // 缓存的文件被修改时报告hash不一致，--update重新从服务器解析
+        std::fs::write(&fork, "corrupt").unwrap();
+        let error = package.resolve_dependencies_from(&api, &options).unwrap_err();
+        assert!(matches!(error, RsmlError::HashMismatch { .. }), "{}", error);
+        options.update = true;
+        let updated = package.resolve_dependencies_from(&api, &options).unwrap();
+        assert_eq!(updated.dependencies["cutlery"].assets[0].sha256, lock::sha256_file(&fork).unwrap());
+        assert_eq!(Lockfile::load(&options.lockfile).unwrap(), updated);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,320 @@
+//! User-defined adjustment passes that run after layout and before output.
+
+use crate::base::Length;
+use crate::diagnostics::{self, Diagnostics};
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{NodeId, RenderNode, RenderNodeType};
+use crate::style::SpacePosition;
+use crate::world::{Floor, UpAxis, World};
+use rand::rngs::StdRng;
+use std::cell::RefCell;
+
+/// A pass that adjusts computed positions, registered on `LayoutOptions::passes`.
+pub trait LayoutPass: Send + Sync {
+    /// Name used in error messages
+    fn name(&self) -> &str;
+
+    fn run(&self, scene: &mut MutableScene, ctx: &PassCtx) -> Result<()>;
+}
+
+/// Context shared by all passes of one compilation.
+pub struct PassCtx<'a> {
+    pub world: World,
+    /// Seeded from the render tree's seed, so passes are reproducible under `RenderTree::set_seed`
+    pub rng: &'a RefCell<StdRng>,
+}
+
+/// Mutable view over the laid out items, in tree order.
+///
+/// Moved items are re-checked after all passes ran: leaving the parent space is reported as
+/// a diagnostic, collisions are left to `RenderTree::check_collisions`.
+pub struct MutableScene<'a> {
+    /// All nodes of the render tree
+    nodes: &'a mut [RenderNode],
+    items: Vec<NodeId>,
+    moved: Vec<bool>,
+}
+
+impl<'a> MutableScene<'a> {
+    pub(crate) fn new(nodes: &'a mut [RenderNode], root: NodeId) -> Self {
+        let mut items = Vec::new();
+        collect_items(nodes, root, &mut items);
+        let moved = vec![false; items.len()];
+        MutableScene { nodes, items, moved }
+    }
+
+    fn item(&self, index: usize) -> &RenderNode {
+        &self.nodes[self.items[index].index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn id(&self, index: usize) -> Option<String> {
+        self.item(index).id.clone()
+    }
+
+    pub fn position(&self, index: usize) -> Dim3<Length> {
+        self.item(index)
+            .computed_style
+            .position
+            .get_length()
+            .unwrap_or_default()
+    }
+
+    pub fn size(&self, index: usize) -> Dim3<Length> {
+        self.item(index)
+            .computed_style
+            .size
+            .get_length()
+            .unwrap_or_default()
+    }
+
+    pub fn set_position(&mut self, index: usize, position: Dim3<Length>) {
+        self.nodes[self.items[index].index()].computed_style.position = SpacePosition::from_dim3(position);
+        self.moved[index] = true;
+    }
+
+    /// Move an item by a signed offset in mm; positions cannot become negative
+    pub fn translate(&mut self, index: usize, offset: Dim3<i64>) -> Result<()> {
+        let position = self.position(index);
+        let shift = |value: Length, delta: i64| {
+            u32::try_from(value.mm() as i64 + delta)
+                .map(Length::from_mm)
+                .map_err(|_| RsmlError::RenderTree {
+                    message: format!("item({:?}) moved out of the world", self.id(index)),
+                })
+        };
+        let moved = Dim3::new(
+            shift(position.x, offset.x)?,
+            shift(position.y, offset.y)?,
+            shift(position.z, offset.z)?,
+        );
+        self.set_position(index, moved);
+        Ok(())
+    }
+
+    /// Report moved items that no longer fit inside their parent space
+    pub(crate) fn validate(&self, diagnostics: &mut Diagnostics) {
+        for index in 0..self.items.len() {
+            if !self.moved[index] {
+                continue;
+            }
+            let item = self.item(index);
+            let Some(parent) = item.parent else {
+                continue;
+            };
+            let (min, max) = self.bounds(index);
+            let parent_ref = &self.nodes[parent.index()];
+            let parent_min = parent_ref.computed_style.position.get_length().unwrap_or_default();
+            let parent_max = parent_min + parent_ref.computed_style.size.get_length().unwrap_or_default();
+            let inside = parent_min.x <= min.x
+                && parent_min.y <= min.y
+                && parent_min.z <= min.z
+                && max.x <= parent_max.x
+                && max.y <= parent_max.y
+                && max.z <= parent_max.z;
+            if inside {
+                continue;
+            }
+            diagnostics.set_element(Some(item.label()), item.span.clone());
+            diagnostics.record(
+                diagnostics::ADJUSTED_OVERFLOW,
+                format!("moved outside of {} by a layout pass", parent_ref.label()),
+            );
+        }
+        diagnostics.set_element(None, None);
+    }
+
+    fn bounds(&self, index: usize) -> (Dim3<Length>, Dim3<Length>) {
+        let min = self.position(index);
+        (min, min + self.size(index))
+    }
+}
+
+fn collect_items(nodes: &[RenderNode], root: NodeId, items: &mut Vec<NodeId>) {
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let node_ref = &nodes[node.index()];
+        if node_ref.node_type == RenderNodeType::Item {
+            items.push(node);
+        }
+        stack.extend(node_ref.children.iter().rev());
+    }
+}
+
+/// Built-in pass: lift every item off the floor, e.g. by 2mm to avoid initial contact penetration.
+pub struct FloorOffset {
+    pub offset: Length,
+}
+
+impl LayoutPass for FloorOffset {
+    fn name(&self) -> &str {
+        "floor-offset"
+    }
+
+    fn run(&self, scene: &mut MutableScene, ctx: &PassCtx) -> Result<()> {
+        let delta = match ctx.world.floor {
+            Floor::Min => self.offset.mm() as i64,
+            Floor::Max => -(self.offset.mm() as i64),
+        };
+        let offset = match ctx.world.up {
+            UpAxis::X => Dim3::new(delta, 0, 0),
+            UpAxis::Y => Dim3::new(0, delta, 0),
+            UpAxis::Z => Dim3::new(0, 0, delta),
+        };
+        for index in 0..scene.len() {
+            scene.translate(index, offset)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::render_tree::{LayoutOptions, RenderTree};
+    use crate::scene::Builder;
+    use crate::xml_parser::Element;
+    use rand::Rng;
+    use std::sync::Arc;
+
+    /// 每个物体在水平方向上随机偏移不超过max_mm
+    struct Jitter {
+        max_mm: i64,
+    }
+
+    impl LayoutPass for Jitter {
+        fn name(&self) -> &str {
+            "jitter"
+        }
+
+        fn run(&self, scene: &mut MutableScene, ctx: &PassCtx) -> Result<()> {
+            for index in 0..scene.len() {
+                let mut rng = ctx.rng.borrow_mut();
+                let offset = Dim3::new(
+                    rng.random_range(-self.max_mm..=self.max_mm),
+                    rng.random_range(-self.max_mm..=self.max_mm),
+                    0,
+                );
+                drop(rng);
+                scene.translate(index, offset)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// 把第二个物体移动到第一个物体的位置
+    struct Stack;
+
+    impl LayoutPass for Stack {
+        fn name(&self) -> &str {
+            "stack"
+        }
+
+        fn run(&self, scene: &mut MutableScene, _ctx: &PassCtx) -> Result<()> {
+            let position = scene.position(0);
+            scene.set_position(1, position);
+            Ok(())
+        }
+    }
+
+    /// 把所有物体移动同样的距离
+    struct Shift {
+        offset: Dim3<i64>,
+    }
+
+    impl LayoutPass for Shift {
+        fn name(&self) -> &str {
+            "shift"
+        }
+
+        fn run(&self, scene: &mut MutableScene, _ctx: &PassCtx) -> Result<()> {
+            for index in 0..scene.len() {
+                scene.translate(index, self.offset)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn scene(package: &Package) -> Element {
+        Builder::body()
+            .space(|room| {
+                room.style("size:2m 2m 1m;flex-direction:x;justify-content:space-evenly")
+                    .object_with("table_leg", |leg| leg.id("a"))
+                    .object_with("table_leg", |leg| leg.id("b"))
+            })
+            .build(package)
+            .unwrap()
+    }
+
+    fn positions(options: &LayoutOptions, package: &Package) -> Result<Vec<[u32; 3]>> {
+        let scene = scene(package);
+        let mut render_tree = RenderTree::with_options(&scene, package, Diagnostics::default(), options)?;
+        render_tree.set_seed(1474);
+        render_tree.calculate()?;
+        Ok(render_tree.to_plan()?.items.iter().map(|item| item.position).collect())
+    }
+
+    #[test]
+    fn test_jitter_pass_within_bounds() {
+        let package = Package::from_file("package.toml").unwrap();
+        let original = positions(&LayoutOptions::default(), &package).unwrap();
+
+        let mut options = LayoutOptions::default();
+        options.passes.push(Arc::new(Jitter { max_mm: 10 }));
+        let jittered = positions(&options, &package).unwrap();
+        assert_ne!(original, jittered);
+        for (before, after) in original.iter().zip(&jittered) {
+            for axis in 0..2 {
+                assert!((before[axis] as i64 - after[axis] as i64).abs() <= 10);
+            }
+            assert_eq!(before[2], after[2]);
+        }
+        // 相同seed可以复现
+        assert_eq!(jittered, positions(&options, &package).unwrap());
+    }
+
+    #[test]
+    fn test_adjusted_layout_is_rechecked() {
+        let package = Package::from_file("package.toml").unwrap();
+
+        // 调整之后的碰撞由check_collisions报告，pass本身不会失败
+        let mut options = LayoutOptions::default();
+        options.passes.push(Arc::new(Stack));
+        let scene = scene(&package);
+        let mut render_tree = RenderTree::with_options(&scene, &package, Diagnostics::default(), &options).unwrap();
+        render_tree.calculate().unwrap();
+        assert_eq!(render_tree.check_collisions().len(), 1);
+
+        // 移出父空间的物体作为诊断报告
+        let mut options = LayoutOptions::default();
+        options.passes.push(Arc::new(Shift { offset: Dim3::new(2000, 0, 0) }));
+        let Err(RsmlError::DiagnosticErrors(diagnostics)) = positions(&options, &package) else {
+            panic!("expected diagnostics");
+        };
+        let overflows: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == diagnostics::ADJUSTED_OVERFLOW)
+            .collect();
+        assert_eq!(overflows.len(), 2);
+        assert!(overflows[0].to_string().contains("object#a: moved outside of space by a layout pass"));
+
+        let mut options = LayoutOptions::default();
+        options.passes.push(Arc::new(FloorOffset {
+            offset: Length::from_mm(2),
+        }));
+        let original = positions(&LayoutOptions::default(), &package).unwrap();
+        let lifted = positions(&options, &package).unwrap();
+        for (before, after) in original.iter().zip(&lifted) {
+            assert_eq!(after[2], before[2] + 2);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Choosing the object a group item places.
+//!
+//! A `<group>` item is laid out with the bounding box of its group, but a target emits one
+//! concrete object. `pick` says which one: `first` takes the first member in name order,
+//! `random` draws a member with the node's random number generator, so the layout seed
+//! reproduces the choice, and `name:<object>` names the member. The older `select`
+//! attribute takes the same strategies, with a bare object name instead of `name:`.
+//!
+//! The choice is made when the item is sized and stored as the node's computed object.
+//! Groups with a `layout` place all their members and ignore `pick`.
+
+use crate::error::{Result, RsmlError};
+use crate::package::Object;
+use rand::Rng;
+use rand::prelude::IndexedRandom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Value of the `pick` attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pick {
+    /// The first member in name order
+    First,
+    /// A member drawn with the node's random number generator
+    Random,
+    /// `name:<object>`: the member with this name
+    Name(String),
+}
+
+impl FromStr for Pick {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "first" => Ok(Pick::First),
+            "random" => Ok(Pick::Random),
+            other => match other.strip_prefix("name:").map(str::trim) {
+                Some(name) if !name.is_empty() => Ok(Pick::Name(name.to_string())),
+                _ => Err(RsmlError::InvalidAttribute {
+                    attribute: "pick".to_string(),
+                    value: s.to_string(),
+                }),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Pick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pick::First => write!(f, "first"),
+            Pick::Random => write!(f, "random"),
+            Pick::Name(name) => write!(f, "name:{}", name),
+        }
+    }
+}
+
+impl Pick {
+    /// Value of the `select` attribute: `first`, `random` or a bare object name
+    pub fn from_select(select: &str) -> Pick {
+        match select.trim() {
+            "first" => Pick::First,
+            "random" => Pick::Random,
+            name => Pick::Name(name.to_string()),
+        }
+    }
+
+    /// Choose one of `members`, the objects of `group` in name order
+    pub fn choose<'a>(
+        &self,
+        group: &str,
+        members: &'a [(String, Object)],
+        rng: &mut impl Rng,
+    ) -> Result<Option<&'a Object>> {
+        let object = match self {
+            Pick::First => members.first(),
+            Pick::Random => members.choose(rng),
+            Pick::Name(name) => {
+                let member = members.iter().find(|(member, _)| member == name);
+                if member.is_none() {
+                    let names: Vec<&str> = members.iter().map(|(member, _)| member.as_str()).collect();
+                    return Err(RsmlError::RenderTree {
+                        message: format!(
+                            "group '{}' has no object '{}'; its objects are: {}",
+                            group,
+                            name,
+                            names.join(", ")
+                        ),
+                    });
+                }
+                member
+            }
+        };
+        Ok(object.map(|(_, object)| object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    /// 1m的cube空间中的一个bottles组，由group设置选择方式
+    fn bottles(group: impl FnOnce(Builder) -> Builder) -> Builder {
+        Builder::body().space(|room| {
+            room.style("size:1m 1m 1m;display:cube")
+                .append_child(group(Builder::new_group("bottles", "random").id("bottle").style("pos:0 0 0")))
+        })
+    }
+
+    /// 按seed计算布局后选中的物体，以及它在组中的名称
+    fn picked(scene: Builder, seed: u64) -> Result<Option<String>> {
+        let package = Package::from_file("package.toml").unwrap();
+        let members = package.group_objects("bottles").unwrap();
+        let mut render_tree = scene.render_tree(&package)?;
+        render_tree.set_seed(seed);
+        render_tree.calculate()?;
+        let node = render_tree.node(render_tree.find_by_id("bottle").unwrap());
+        let object = node.computed_style.object.as_ref();
+        Ok(object.map(|object| {
+            let (name, _) = members.iter().find(|(_, member)| member == object).unwrap();
+            name.clone()
+        }))
+    }
+
+    #[test]
+    fn test_parse_pick() {
+        assert_eq!("first".parse::<Pick>().unwrap(), Pick::First);
+        assert_eq!(" random ".parse::<Pick>().unwrap(), Pick::Random);
+        assert_eq!("name: box_bottle".parse::<Pick>().unwrap(), Pick::Name("box_bottle".to_string()));
+        assert_eq!(Pick::Name("box_bottle".to_string()).to_string(), "name:box_bottle");
+        for invalid in ["", "name:", "box_bottle", "last"] {
+            assert!(invalid.parse::<Pick>().is_err(), "{}", invalid);
+        }
+        assert_eq!(Pick::from_select("box_bottle"), Pick::Name("box_bottle".to_string()));
+    }
+
+    #[test]
+    fn test_pick_strategies() {
+        let package = Package::from_file("package.toml").unwrap();
+        let members: Vec<String> = package.group_objects("bottles").unwrap().into_iter().map(|(name, _)| name).collect();
+
+        let first = picked(bottles(|group| group.pick(&Pick::First)), 1).unwrap();
+        assert_eq!(first.as_ref(), members.first());
+        let named = picked(bottles(|group| group.pick(&Pick::Name("box_bottle".to_string()))), 1).unwrap();
+        assert_eq!(named.as_deref(), Some("box_bottle"));
+        // select的写法得到相同的结果
+        assert_eq!(picked(bottles(|group| group.attribute("select", "box_bottle")), 1).unwrap(), named);
+
+        // 相同的seed选中相同的物体，不同的seed能选中不同的物体
+        let random = |seed| picked(bottles(|group| group.pick(&Pick::Random)), seed).unwrap().unwrap();
+        assert_eq!(random(7), random(7));
+        let choices: std::collections::HashSet<String> = (0..20).map(random).collect();
+        assert!(choices.len() > 1, "{:?}", choices);
+        assert!(choices.iter().all(|choice| members.contains(choice)));
+    }
+
+    #[test]
+    fn test_pick_errors() {
+        let err = picked(bottles(|group| group.pick(&Pick::Name("teapot".to_string()))), 1).unwrap_err();
+        assert!(err.to_string().contains("group 'bottles' has no object 'teapot'"), "{}", err);
+        assert!(err.to_string().contains("box_bottle"), "{}", err);
+
+        // 无效的pick只能按原样写入属性
+        let invalid = bottles(|group| group.pick(&Pick::First).attribute("pick", "box_bottle"));
+        let err = picked(invalid, 1).unwrap_err();
+        assert!(err.to_string().contains("Invalid attribute value: pick = box_bottle"), "{}", err);
+
+        let both = bottles(|group| group.pick(&Pick::First).attribute("select", "random"));
+        let err = picked(both, 1).unwrap_err();
+        assert!(err.to_string().contains("either pick or select"), "{}", err);
+    }
+}
@@ -0,0 +1,68 @@
+//! 在立方体空间中随机采样位置
+//!
+//! 立方体空间中`pos`某个轴为`random`的子元素，在没有随机轴的子元素之后按文档顺序依次放置。
+//! 与已放置的兄弟元素重叠的候选位置会被拒绝并重新采样，最多`LayoutOptions::placement_attempts`次。
+//! 每次尝试都使用该空间的随机数生成器，所以结果只取决于种子
+//!
+//! 默认的采样器是`UniformPlacement`；其他采样器（例如泊松圆盘采样）实现`PlacementStrategy`，
+//! 并设置到`LayoutOptions::placement`上
+
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::style::AxisPos;
+use rand::rngs::StdRng;
+
+/// 布局失败前对一个随机子元素采样的默认次数
+pub const DEFAULT_PLACEMENT_ATTEMPTS: usize = 50;
+
+/// 立方体空间中一个待放置的子元素
+#[derive(Debug)]
+pub struct PlacementRequest<'a> {
+    /// 立方体空间的内容尺寸
+    pub container: Dim3<Length>,
+    /// 子元素的尺寸
+    pub size: Dim3<Length>,
+    /// 子元素在各轴上的pos；至少有一个轴是`AxisPos::Random`
+    pub pos: &'a Dim3<AxisPos>,
+    /// 目前已放置的每个兄弟元素的偏移和尺寸，相对于内容框
+    pub placed: &'a [(Dim3<Length>, Dim3<Length>)],
+    /// 该子元素已被拒绝的候选位置数
+    pub attempt: usize,
+}
+
+impl PlacementRequest<'_> {
+    /// 各轴上使子元素保持在容器内的最大偏移
+    pub fn max_offset(&self) -> Dim3<Length> {
+        self.container - self.size
+    }
+}
+
+/// 采样子元素在其立方体空间中的偏移
+pub trait PlacementStrategy: Send + Sync {
+    /// 错误信息中使用的名字
+    fn name(&self) -> &str;
+
+    /// 给出相对于内容框的候选偏移。只使用候选位置的随机轴，每个轴都不能超过
+    /// `request.max_offset()`；布局负责检查候选位置与已放置兄弟元素是否重叠
+    fn propose(&self, request: &PlacementRequest<'_>, rng: &mut StdRng) -> Dim3<Length>;
+}
+
+/// 在随机轴上均匀分布的偏移
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformPlacement;
+
+impl PlacementStrategy for UniformPlacement {
+    fn name(&self) -> &str {
+        "uniform"
+    }
+
+    fn propose(&self, request: &PlacementRequest<'_>, rng: &mut StdRng) -> Dim3<Length> {
+        let max = request.max_offset();
+        // 按x、y、z的顺序取随机数
+        Dim3::new(
+            request.pos.x.absolute_pos(Length::default(), max.x, rng),
+            request.pos.y.absolute_pos(Length::default(), max.y, rng),
+            request.pos.z.absolute_pos(Length::default(), max.z, rng),
+        )
+    }
+}
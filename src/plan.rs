@@ -0,0 +1,340 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::package::GeomType;
+use crate::render_tree::{RenderNode, RenderTree};
+use crate::xml_parser::Element;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Version of the plan file format written by `ScenePlan::save`
+pub const PLAN_VERSION: u32 = 1;
+
+/// One placed item of a computed scene, in world coordinates (millimeters).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanItem {
+    pub name: String,
+    pub geom_type: GeomType,
+    pub path: Option<String>,
+    /// Minimum corner of the item
+    pub position: [u32; 3],
+    pub size: [u32; 3],
+    /// Attributes of the source element the schema does not define, e.g. `data-sku`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<(String, String)>,
+}
+
+impl PlanItem {
+    pub fn position(&self) -> Dim3<Length> {
+        let [x, y, z] = self.position;
+        Dim3::new(Length::from_mm(x), Length::from_mm(y), Length::from_mm(z))
+    }
+
+    pub fn size(&self) -> Dim3<Length> {
+        let [x, y, z] = self.size;
+        Dim3::new(Length::from_mm(x), Length::from_mm(y), Length::from_mm(z))
+    }
+}
+
+/// A saved, fully computed scene that can be imported into another scene as a fixed fixture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenePlan {
+    pub version: u32,
+    pub items: Vec<PlanItem>,
+}
+
+impl ScenePlan {
+    /// Parse a plan from JSON, rejecting other format versions
+    pub fn from_json_str(contents: &str) -> Result<Self> {
+        let plan: ScenePlan = serde_json::from_str(contents).map_err(|e| RsmlError::ParseError {
+            field: "plan".to_string(),
+            message: e.to_string(),
+        })?;
+        if plan.version != PLAN_VERSION {
+            return Err(RsmlError::ParseError {
+                field: "plan".to_string(),
+                message: format!(
+                    "unsupported plan version {} (expected {})",
+                    plan.version, PLAN_VERSION
+                ),
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Load a plan file
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_json_str(&contents).map_err(|e| RsmlError::ParseError {
+            field: "plan".to_string(),
+            message: format!("Failed to load plan file '{}': {}", path, e),
+        })
+    }
+
+    /// Save the plan as JSON
+    pub fn save(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| RsmlError::ParseError {
+            field: "plan".to_string(),
+            message: e.to_string(),
+        })?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl RenderTree<'_> {
+    /// Snapshot the computed world boxes of all items, including imported ones.
+    /// Must be called after `calculate`.
+    pub fn to_plan(&self) -> Result<ScenePlan> {
+        let mut items = Vec::new();
+        for node in self.iter_items() {
+            push_plan_items(self.node(node), &mut items)?;
+        }
+        items.extend(self.imported.iter().cloned());
+        Ok(ScenePlan {
+            version: PLAN_VERSION,
+            items,
+        })
+    }
+}
+
+/// The plan items of one item node, one per placed object
+fn push_plan_items(node_ref: &RenderNode, items: &mut Vec<PlanItem>) -> Result<()> {
+    if node_ref.computed_style.position.get_length().is_none() {
+        return Err(RsmlError::RenderTree {
+            message: format!("node({:?}) has not been laid out", node_ref.id),
+        });
+    }
+    let base_name = node_ref
+        .id
+        .clone()
+        .unwrap_or_else(|| format!("{}_{}", node_ref.tag_name, items.len()));
+    for placed in node_ref.placed_objects() {
+        let name = match &placed.suffix {
+            Some(suffix) => format!("{}/{}", base_name, suffix),
+            None => base_name.clone(),
+        };
+        let (position, size) = (placed.position, placed.object.size);
+        items.push(PlanItem {
+            name,
+            geom_type: placed.object.geom_type.clone(),
+            path: placed.object.path.clone(),
+            position: [position.x.mm(), position.y.mm(), position.z.mm()],
+            size: [size.x.mm(), size.y.mm(), size.z.mm()],
+            attributes: node_ref.extra_attributes.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Resolve every `<import scene="..." at="x y z"/>` element in the DOM.
+///
+/// Imported items are offset by `at` and renamed to `<namespace>/<name>`, where the
+/// namespace is the import element's id or the plan file stem.
+pub fn resolve_imports(dom_element: &Element) -> Result<Vec<PlanItem>> {
+    let mut imports = Vec::new();
+    let mut ids = HashSet::new();
+    collect_imports(dom_element, &mut imports, &mut ids);
+
+    let mut items = Vec::new();
+    for import in imports {
+        let path = import.get_attribute("scene").ok_or(RsmlError::MissingElement {
+            element: "import@scene".to_string(),
+        })?;
+        let at = match import.get_attribute("at") {
+            Some(at) => parse_at(at)?,
+            None => Dim3::default(),
+        };
+        let namespace = match import.get_attribute("id") {
+            Some(id) => id.clone(),
+            None => Path::new(path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone()),
+        };
+
+        let plan_path = scene_path(import, path);
+        for mut item in ScenePlan::load(&plan_path.to_string_lossy())?.items {
+            item.name = format!("{}/{}", namespace, item.name);
+            if !ids.insert(item.name.clone()) {
+                return Err(RsmlError::InvalidStructure {
+                    message: format!("imported item '{}' collides with an existing id", item.name),
+                });
+            }
+            let position = item.position() + at;
+            item.position = [position.x.mm(), position.y.mm(), position.z.mm()];
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+/// Path of the plan an `<import>` names: relative to the file the element was parsed from, like
+/// `<include src>`, and to the current directory for documents parsed from a string
+fn scene_path(import: &Element, scene: &str) -> PathBuf {
+    match import.span.as_ref().and_then(|span| span.file.as_deref()) {
+        Some(file) => Path::new(file).parent().unwrap_or(Path::new(".")).join(scene),
+        None => PathBuf::from(scene),
+    }
+}
+
+fn collect_imports<'e>(
+    root: &'e Element,
+    imports: &mut Vec<&'e Element>,
+    ids: &mut HashSet<String>,
+) {
+    let mut stack = vec![root];
+    while let Some(element) = stack.pop() {
+        if element.name == "import" {
+            imports.push(element);
+            continue;
+        }
+        if let Some(id) = element.get_attribute("id") {
+            ids.insert(id.clone());
+        }
+        stack.extend(element.children.iter().rev());
+    }
+}
+
+fn parse_at(value: &str) -> Result<Dim3<Length>> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 3 {
+        return Err(RsmlError::InvalidAttribute {
+            attribute: "at".to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok(Dim3::new(
+        parts[0].parse()?,
+        parts[1].parse()?,
+        parts[2].parse()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+    use crate::target::MjcfGenerator;
+
+    /// 2m x 2m x 1m的room，子节点沿x轴排列
+    fn scene(package: &Package, children: Vec<Builder>) -> Element {
+        let room = Builder::new_space()
+            .id("room")
+            .style("size:2m 2m 1m;flex-direction:x;align-items:flex-start flex-start");
+        let room = children.into_iter().fold(room, Builder::append_child);
+        Builder::body().append_child(room).build(package).unwrap()
+    }
+
+    fn save_base_plan(package: &Package, file_name: &str) -> String {
+        let base = scene(package, vec![Builder::new_object("table_leg").id("leg")]);
+        let mut render_tree = RenderTree::new(&base, package).unwrap();
+        render_tree.calculate().unwrap();
+        let path = std::env::temp_dir().join(file_name);
+        let path = path.to_string_lossy().to_string();
+        render_tree.to_plan().unwrap().save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_import_plan_as_fixture() {
+        let package = Package::from_file("package.toml").unwrap();
+        let path = save_base_plan(&package, "rsmlc_base_lab.plan");
+
+        let new_scene = scene(
+            &package,
+            vec![
+                Builder::new_object("table_plane").id("plane"),
+                Builder::new_import(&path).attribute("at", "1m 50cm 0"),
+            ],
+        );
+        let mut render_tree = RenderTree::new(&new_scene, &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        assert_eq!(render_tree.imported.len(), 1);
+        let leg = &render_tree.imported[0];
+        assert_eq!(leg.name, "rsmlc_base_lab/leg");
+        assert_eq!(leg.position, [1000, 500, 0]);
+
+        // 导入的节点不参与布局：room中只剩table_plane一个子节点
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        assert_eq!(render_tree.node(room).children.len(), 1);
+
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+        // 导入的物体使用世界坐标，直接放在worldbody中
+        let names: Vec<&str> = mujoco.item_geoms().iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["rsmlc_base_lab/leg", "plane"]);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let package = Package::from_file("package.toml").unwrap();
+        let path = save_base_plan(&package, "rsmlc_collide.plan");
+
+        let colliding = scene(
+            &package,
+            vec![
+                Builder::new_object("table_plane").id("base/leg"),
+                Builder::new_import(&path).id("base"),
+            ],
+        );
+        assert!(RenderTree::new(&colliding, &package).is_err());
+
+        let contents = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"version\": 1", "\"version\": 99");
+        assert!(ScenePlan::from_json_str(&contents).is_err());
+    }
+
+    #[test]
+    fn test_passthrough_attributes() {
+        use crate::diagnostics::{self, Diagnostics, Severity};
+        use crate::render_tree::LayoutOptions;
+
+        let package = Package::from_file("package.toml").unwrap();
+        let leg = Builder::new_object("table_leg")
+            .id("leg")
+            .attribute("data-sku", "A-1042")
+            .attribute("zone", "cold");
+        let mut render_tree = RenderTree::new(&scene(&package, vec![leg]), &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        // data-*只记为note，其他未知属性仍然警告
+        let shown: Vec<&str> = render_tree.diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(shown, vec![diagnostics::UNKNOWN_ATTRIBUTE]);
+        assert_eq!(render_tree.diagnostics.count(Severity::Allow), 1);
+
+        let plan = render_tree.to_plan().unwrap();
+        let expected = vec![
+            ("data-sku".to_string(), "A-1042".to_string()),
+            ("zone".to_string(), "cold".to_string()),
+        ];
+        assert_eq!(plan.items[0].attributes, expected);
+        let json = serde_json::to_string(&plan).unwrap();
+        assert!(json.contains("A-1042"));
+        assert_eq!(ScenePlan::from_json_str(&json).unwrap(), plan);
+
+        let xml = MjcfGenerator::to_xml(&MjcfGenerator::generate(&render_tree).unwrap(), false).unwrap();
+        assert!(xml.contains(r#"<custom><text name="meta:leg:data-sku" data="A-1042"/>"#));
+        assert!(xml.contains(r#"<text name="meta:leg:zone" data="cold"/></custom>"#));
+
+        // 自定义前缀
+        let options = LayoutOptions {
+            passthrough_prefix: "x-".to_string(),
+            ..Default::default()
+        };
+        let leg = Builder::new_object("table_leg").attribute("x-zone", "cold");
+        let render_tree =
+            RenderTree::with_options(&scene(&package, vec![leg]), &package, Diagnostics::default(), &options)
+                .unwrap();
+        assert_eq!(render_tree.diagnostics.iter().count(), 0);
+
+        // 没有元数据时不输出custom
+        let mut render_tree = RenderTree::new(&scene(&package, vec![]), &package).unwrap();
+        render_tree.calculate().unwrap();
+        assert!(MjcfGenerator::generate(&render_tree).unwrap().custom.is_none());
+    }
+}
@@ -0,0 +1,404 @@
+//! Incremental reloading of a package split over several files.
+//!
+//! A package file can name fragments with `include = ["objects/shelves.toml"]`, and the root
+//! package can merge other packages with `[workspace] members = ["kitchen"]`, where a member
+//! is a directory holding a `package.toml` or a path to the file itself. Include paths are
+//! relative to the file that names them, member paths to the root package. Fragments hold
+//! `objects`, `groups` and `dependencies`; only the root's `[package]` table is used.
+//!
+//! `PackageReloader` keeps the parsed TOML and a content hash of every file. `reload` reads
+//! the files again, parses only those whose content changed and merges the result. It
+//! reports the object and group names whose definitions changed, so a caller can tell which
+//! scenes reference them and leave the others alone.
+
+use crate::error::{Result, RsmlError};
+use crate::package::Package;
+use crate::xml_parser::Element;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// How much work the reloader did since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReloadMetrics {
+    /// Files parsed because they were new or their content changed
+    pub parsed: usize,
+    /// Files whose content was unchanged, so their parsed TOML was reused
+    pub reused: usize,
+}
+
+/// What changed in one `reload`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reload {
+    /// Files that were parsed again, in merge order
+    pub reparsed: Vec<PathBuf>,
+    /// Objects and groups that were added, removed or redefined, and groups listing one of
+    /// them as a member
+    pub changed: BTreeSet<String>,
+}
+
+impl Reload {
+    /// Whether an item of the document references a changed object or group
+    pub fn affects(&self, root: &Element) -> bool {
+        let mut stack = vec![root];
+        while let Some(element) = stack.pop() {
+            if matches!(element.name.as_str(), "object" | "group") && self.changed.contains(element.text.trim()) {
+                return true;
+            }
+            stack.extend(&element.children);
+        }
+        false
+    }
+}
+
+/// One file of the package, with the hash of the content it was parsed from
+#[derive(Debug, Clone)]
+struct PackageFile {
+    path: PathBuf,
+    hash: u64,
+    table: toml::Table,
+}
+
+/// A package loaded from its root file, its includes and its workspace members.
+#[derive(Debug)]
+pub struct PackageReloader {
+    root: PathBuf,
+    files: Vec<PackageFile>,
+    package: Package,
+    pub metrics: ReloadMetrics,
+}
+
+impl PackageReloader {
+    /// Load the package rooted at `path`, parsing every file once
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let root = path.as_ref().to_path_buf();
+        let mut metrics = ReloadMetrics::default();
+        let files = read_files(&root, &HashMap::new(), &mut metrics)?;
+        let package = merge(&files)?;
+        Ok(PackageReloader {
+            root,
+            files,
+            package,
+            metrics,
+        })
+    }
+
+    /// The merged package
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    /// Every file of the package in merge order: the root, its includes, then the members
+    pub fn files(&self) -> Vec<&Path> {
+        self.files.iter().map(|file| file.path.as_path()).collect()
+    }
+
+    /// Read the files again, parse the ones whose content changed and merge. On error the
+    /// previously loaded package is kept.
+    pub fn reload(&mut self) -> Result<Reload> {
+        let cache: HashMap<PathBuf, PackageFile> =
+            self.files.iter().map(|file| (file.path.clone(), file.clone())).collect();
+        let files = read_files(&self.root, &cache, &mut self.metrics)?;
+        let reparsed: Vec<PathBuf> = files
+            .iter()
+            .filter(|file| cache.get(&file.path).is_none_or(|cached| cached.hash != file.hash))
+            .map(|file| file.path.clone())
+            .collect();
+        let removed = cache.keys().any(|path| !files.iter().any(|file| &file.path == path));
+        if reparsed.is_empty() && !removed {
+            self.files = files;
+            return Ok(Reload::default());
+        }
+
+        let package = merge(&files)?;
+        let changed = changed_names(&self.files, &files);
+        self.files = files;
+        self.package = package;
+        Ok(Reload { reparsed, changed })
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Read the root, its includes and its members in merge order, reusing the parsed table of
+/// every file in `cache` whose content is unchanged
+fn read_files(
+    root: &Path,
+    cache: &HashMap<PathBuf, PackageFile>,
+    metrics: &mut ReloadMetrics,
+) -> Result<Vec<PackageFile>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    read_file(root, cache, metrics, &mut seen, &mut files)?;
+
+    let members = match files[0].table.get("workspace").and_then(|workspace| workspace.get("members")) {
+        Some(members) => string_list(members, &files[0].path, "workspace.members")?,
+        None => Vec::new(),
+    };
+    let base = root.parent().unwrap_or(Path::new(""));
+    for member in members {
+        let mut path = base.join(member);
+        if path.is_dir() {
+            path.push("package.toml");
+        }
+        read_file(&path, cache, metrics, &mut seen, &mut files)?;
+    }
+    Ok(files)
+}
+
+/// Read `path` and, depth first, the files it includes
+fn read_file(
+    path: &Path,
+    cache: &HashMap<PathBuf, PackageFile>,
+    metrics: &mut ReloadMetrics,
+    seen: &mut HashSet<PathBuf>,
+    files: &mut Vec<PackageFile>,
+) -> Result<()> {
+    if !seen.insert(path.to_path_buf()) {
+        return Err(RsmlError::PackageConfigError(format!(
+            "'{}' is included more than once",
+            path.display()
+        )));
+    }
+    let bytes = std::fs::read(path)?;
+    let hash = content_hash(&bytes);
+    let file = match cache.get(path) {
+        Some(cached) if cached.hash == hash => {
+            metrics.reused += 1;
+            cached.clone()
+        }
+        _ => {
+            metrics.parsed += 1;
+            let contents = String::from_utf8_lossy(&bytes);
+            let table = contents.parse::<toml::Table>().map_err(|e| RsmlError::ParseError {
+                field: "package".to_string(),
+                message: format!("Failed to parse package file '{}': {}", path.display(), e),
+            })?;
+            PackageFile {
+                path: path.to_path_buf(),
+                hash,
+                table,
+            }
+        }
+    };
+    let includes = match file.table.get("include") {
+        Some(includes) => string_list(includes, path, "include")?,
+        None => Vec::new(),
+    };
+    files.push(file);
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    for include in includes {
+        read_file(&base.join(include), cache, metrics, seen, files)?;
+    }
+    Ok(())
+}
+
+fn string_list(value: &toml::Value, path: &Path, key: &str) -> Result<Vec<String>> {
+    let invalid = || {
+        RsmlError::PackageConfigError(format!("{} in '{}' must be a list of paths", key, path.display()))
+    };
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|item| item.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect()
+}
+
+/// Object and group definitions of the files, by name, with the file defining them
+fn definitions(files: &[PackageFile]) -> Result<HashMap<String, (&Path, &toml::Value)>> {
+    let mut definitions = HashMap::new();
+    for file in files {
+        let objects = file.table.get("objects").and_then(toml::Value::as_table).into_iter().flatten();
+        let groups = file
+            .table
+            .get("groups")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|group| Some((group.get("name")?.as_str()?, group)));
+        for (name, value) in objects.map(|(name, value)| (name.as_str(), value)).chain(groups) {
+            if let Some((first, _)) = definitions.insert(name.to_string(), (file.path.as_path(), value)) {
+                return Err(RsmlError::PackageConfigError(format!(
+                    "'{}' is defined in '{}' and again in '{}'",
+                    name,
+                    first.display(),
+                    file.path.display()
+                )));
+            }
+        }
+    }
+    Ok(definitions)
+}
+
+/// Merge the files into one package: the root's `[package]`, and the objects, groups and
+/// dependencies of every file
+fn merge(files: &[PackageFile]) -> Result<Package> {
+    // 同名的定义在合并之前报错，不会被后面的文件覆盖
+    definitions(files)?;
+    let mut merged = toml::Table::new();
+    if let Some(info) = files[0].table.get("package") {
+        merged.insert("package".to_string(), info.clone());
+    }
+    let mut objects = toml::Table::new();
+    let mut groups = Vec::new();
+    let mut dependencies = toml::Table::new();
+    for file in files {
+        if let Some(table) = file.table.get("objects").and_then(toml::Value::as_table) {
+            objects.extend(table.clone());
+        }
+        if let Some(array) = file.table.get("groups").and_then(toml::Value::as_array) {
+            groups.extend(array.iter().cloned());
+        }
+        if let Some(table) = file.table.get("dependencies").and_then(toml::Value::as_table) {
+            dependencies.extend(table.clone());
+        }
+    }
+    merged.insert("objects".to_string(), toml::Value::Table(objects));
+    merged.insert("groups".to_string(), toml::Value::Array(groups));
+    merged.insert("dependencies".to_string(), toml::Value::Table(dependencies));
+
+    Package::from_toml_str(&merged.to_string()).map_err(|e| RsmlError::ParseError {
+        field: "package".to_string(),
+        message: format!("Failed to merge package '{}': {}", files[0].path.display(), e),
+    })
+}
+
+/// Names defined differently in `before` and `after`, plus the groups listing one of them
+fn changed_names(before: &[PackageFile], after: &[PackageFile]) -> BTreeSet<String> {
+    let (Ok(before), Ok(after)) = (definitions(before), definitions(after)) else {
+        return BTreeSet::new();
+    };
+    let mut changed: BTreeSet<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|name| before.get(*name).map(|(_, value)| value) != after.get(*name).map(|(_, value)| value))
+        .cloned()
+        .collect();
+
+    // 组的members按名字引用其他物体和组，被引用的定义变化时组也随之变化
+    loop {
+        let referencing: Vec<String> = after
+            .iter()
+            .filter(|(name, _)| !changed.contains(*name))
+            .filter(|(_, (_, value))| {
+                value
+                    .get("members")
+                    .and_then(toml::Value::as_array)
+                    .is_some_and(|members| members.iter().any(|m| m.as_str().is_some_and(|m| changed.contains(m))))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if referencing.is_empty() {
+            return changed;
+        }
+        changed.extend(referencing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Length;
+    use crate::dim3::Dim3;
+
+    /// 在临时目录中写入一个三个文件的package：根文件包含shelves.toml和bottles.toml
+    fn three_file_package(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(
+            dir.join("package.toml"),
+            r#"
+                include = ["parts/shelves.toml", "parts/bottles.toml"]
+                [package]
+                name = "kitchen"
+                description = ""
+                [objects]
+                table_plane = { geom-type = "box", size = "1m 1m 10cm" }
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/shelves.toml"),
+            "[objects]\nshelf = { geom-type = \"box\", size = \"80cm 30cm 2cm\" }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/bottles.toml"),
+            "[[groups]]\nname = \"bottles\"\n[groups.objects]\nbottle0 = { size = \"5cm 5cm 20cm\" }\n",
+        )
+        .unwrap();
+        dir.join("package.toml")
+    }
+
+    #[test]
+    fn test_reload_parses_only_changed_files() {
+        let root = three_file_package("rsmlc_reload_three_files");
+        let mut reloader = PackageReloader::load(&root).unwrap();
+        assert_eq!(reloader.metrics, ReloadMetrics { parsed: 3, reused: 0 });
+        assert_eq!(reloader.files().len(), 3);
+        assert_eq!(reloader.package().objects.len(), 2);
+        assert_eq!(reloader.package().groups[0].name, "bottles");
+
+        // 没有修改时不解析任何文件
+        assert_eq!(reloader.reload().unwrap(), Reload::default());
+        assert_eq!(reloader.metrics, ReloadMetrics { parsed: 3, reused: 3 });
+
+        let shelves = root.parent().unwrap().join("parts/shelves.toml");
+        std::fs::write(&shelves, "[objects]\nshelf = { geom-type = \"box\", size = \"1m 30cm 2cm\" }\n").unwrap();
+        let reload = reloader.reload().unwrap();
+        assert_eq!(reload.reparsed, vec![shelves]);
+        assert_eq!(reload.changed, BTreeSet::from(["shelf".to_string()]));
+        // 另外两个文件没有重新解析
+        assert_eq!(reloader.metrics, ReloadMetrics { parsed: 4, reused: 5 });
+        assert_eq!(
+            reloader.package().objects["shelf"].size,
+            Dim3::new(Length::from_cm(100), Length::from_cm(30), Length::from_cm(2))
+        );
+
+        let mut scene = Element::new("space".to_string());
+        let mut shelf = Element::new("object".to_string());
+        shelf.text = "shelf".to_string();
+        scene.children.push(shelf);
+        assert!(reload.affects(&scene));
+        scene.children[0].text = "table_plane".to_string();
+        assert!(!reload.affects(&scene));
+    }
+
+    #[test]
+    fn test_workspace_members_and_duplicates() {
+        let root = three_file_package("rsmlc_reload_workspace");
+        let dir = root.parent().unwrap().to_path_buf();
+        std::fs::create_dir_all(dir.join("pantry")).unwrap();
+        std::fs::write(
+            dir.join("pantry/package.toml"),
+            "[package]\nname = \"pantry\"\ndescription = \"\"\n[objects]\njar = { size = \"10cm 10cm 15cm\" }\n",
+        )
+        .unwrap();
+        let contents = std::fs::read_to_string(&root).unwrap();
+        std::fs::write(&root, format!("{}\n[workspace]\nmembers = [\"pantry\"]\n", contents)).unwrap();
+
+        let mut reloader = PackageReloader::load(&root).unwrap();
+        assert_eq!(reloader.files().last().unwrap(), &dir.join("pantry/package.toml"));
+        assert!(reloader.package().objects.contains_key("jar"));
+        // 根文件的[package]优先
+        assert_eq!(reloader.package().package.name, "kitchen");
+
+        // 成员重复定义了另一个文件中的物体：报错并保留之前的package
+        std::fs::write(
+            dir.join("pantry/package.toml"),
+            "[package]\nname = \"pantry\"\ndescription = \"\"\n[objects]\nshelf = { size = \"1m 1m 1m\" }\n",
+        )
+        .unwrap();
+        let err = reloader.reload().unwrap_err();
+        assert!(err.to_string().contains("'shelf' is defined in"), "{}", err);
+        assert!(reloader.package().objects.contains_key("jar"));
+    }
+}
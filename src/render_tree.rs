@@ -1,12 +1,26 @@
+use crate::anchor::Anchor;
 use crate::base::Length;
+use crate::collision::penetration;
+use crate::diagnostics::{self, Diagnostics, Severity};
 use crate::dim3::Dim3;
 use crate::error::{Result, RsmlError};
 use crate::package::{Package, Object};
-use crate::style::{self, FlexDirection, SpacePosition, SpaceSize, Style};
-use crate::xml_parser::Element;
-use rand::prelude::IndexedRandom;
+use crate::pick::Pick;
+use crate::pass::{LayoutPass, MutableScene, PassCtx};
+use crate::placement::{PlacementRequest, PlacementStrategy, UniformPlacement, DEFAULT_PLACEMENT_ATTEMPTS};
+use crate::plan::{self, PlanItem};
+use crate::schema;
+use crate::settings::Settings;
+use crate::template;
+use crate::world::World;
+use crate::style::{
+    self, AxisPos, FlexDirection, FlexWrap, Margin, MarginValue, Padding, SpacePosition, SpaceSize, Style, StyleSheet,
+};
+use crate::xml_parser::{Element, Span};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::collections::HashMap;
+use std::sync::Arc;
 use style::SizeValue;
 
 /// 渲染节点类型枚举
@@ -16,9 +30,97 @@ pub enum RenderNodeType {
     Item,  // Group and Object
 }
 
+/// 未指定align-items时使用的默认对齐方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultAlign {
+    /// 由world推导：水平的交叉轴居中，竖直的交叉轴贴地
+    #[default]
+    World,
+    /// 两个交叉轴都是flex-start
+    FlexStart,
+}
+
+/// 并行计算时一层至少有这么多节点才分给多个线程，更窄的层串行计算
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_LEVEL_WIDTH: usize = 256;
+
+/// 默认的透传属性前缀
+pub const DEFAULT_PASSTHROUGH_PREFIX: &str = "data-";
+
+/// 构建渲染树和计算布局的选项
+#[derive(Clone)]
+pub struct LayoutOptions {
+    pub default_align: DefaultAlign,
+    /// 布局完成后按顺序执行的调整pass
+    pub passes: Vec<Arc<dyn LayoutPass>>,
+    /// 以此为前缀的未知属性只记录为note，不再警告
+    pub passthrough_prefix: String,
+    /// 随机选择和随机位置使用的seed，None时每次运行结果不同
+    pub seed: Option<u64>,
+    /// 严格模式：未知的样式属性和超过依赖size-limit的item记为error而不是警告
+    pub strict_styles: bool,
+    /// 允许大于100%的百分比尺寸，例如穿过房间的长管；默认不允许，这样的样式会被忽略并警告。
+    /// 超出父空间的部分仍然受父空间的overflow约束，需要overflow:clamp才能伸出
+    pub allow_oversized_percentages: bool,
+    /// 宽松的嵌套规则：混合item和space、item有子元素时只警告
+    pub lenient_nesting: bool,
+    /// 用rayon并行计算尺寸和位置，结果和串行计算相同；需要parallel feature，启用时默认为true
+    pub parallel: bool,
+    /// cube空间中random位置的采样方式
+    pub placement: Arc<dyn PlacementStrategy>,
+    /// random位置与已放置的兄弟元素重叠时最多采样的次数，用完时布局失败；0表示不检查重叠
+    pub placement_attempts: usize,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            default_align: DefaultAlign::default(),
+            passes: Vec::new(),
+            passthrough_prefix: DEFAULT_PASSTHROUGH_PREFIX.to_string(),
+            seed: None,
+            strict_styles: false,
+            allow_oversized_percentages: false,
+            lenient_nesting: false,
+            parallel: cfg!(feature = "parallel"),
+            placement: Arc::new(UniformPlacement),
+            placement_attempts: DEFAULT_PLACEMENT_ATTEMPTS,
+        }
+    }
+}
 
+impl std::fmt::Debug for LayoutOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayoutOptions")
+            .field("default_align", &self.default_align)
+            .field("passthrough_prefix", &self.passthrough_prefix)
+            .field("seed", &self.seed)
+            .field("strict_styles", &self.strict_styles)
+            .field("allow_oversized_percentages", &self.allow_oversized_percentages)
+            .field("lenient_nesting", &self.lenient_nesting)
+            .field("parallel", &self.parallel)
+            .field("placement", &self.placement.name())
+            .field("placement_attempts", &self.placement_attempts)
+            .field(
+                "passes",
+                &self.passes.iter().map(|pass| pass.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
 
+/// 节点在渲染树中的下标
+///
+/// 所有节点都由`RenderTree`持有，父子关系通过下标表示；下标只在创建它的渲染树中有效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
 
+impl NodeId {
+    /// 节点在`RenderTree`节点数组中的位置，可以用来索引按节点保存的数据
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
 
 /// 渲染节点结构体
 #[derive(Debug)]
@@ -28,6 +130,8 @@ pub struct RenderNode {
 
     /// 节点ID（如果有的话）
     pub id: Option<String>,
+    /// class属性中的类名，按出现顺序
+    pub classes: Vec<String>,
     /// 节点名称/标签名
     pub tag_name: String,
 
@@ -40,37 +144,114 @@ pub struct RenderNode {
     /// 计算后的样式
     pub computed_style: style::ComputedStyle,
 
-    /// Select attribute for groups (if applicable)
-    pub select_attr: Option<String>,
+    /// group的pick或select属性：选择组中的哪个物体
+    pub pick: Option<Pick>,
+
+    /// object/group的dynamic属性：为true时在MJCF中是可以自由运动的body，默认false为静态几何体
+    pub dynamic: bool,
+
+    /// anchor属性：计算完位置之后放到另一个元素的顶面上
+    pub anchor: Option<Anchor>,
+
+    /// schema中未定义的属性，按名称排序，原样输出到元数据中
+    pub extra_attributes: Vec<(String, String)>,
 
-    /// 父节点（弱引用，避免循环引用）
-    pub parent: Weak<RefCell<RenderNode>>,
+    /// 元素在源文件中的位置，错误信息中输出
+    pub span: Option<Span>,
+
+    /// 父节点，根节点为None
+    pub parent: Option<NodeId>,
+
+    /// 从根节点到这个节点的路径（每一层在父节点中的序号）的哈希，和seed一起决定节点的随机数
+    path_hash: u64,
 
     /// 子节点
-    pub children: Vec<Rc<RefCell<RenderNode>>>,
+    pub children: Vec<NodeId>,
+}
+
+/// 节点最终输出的一个物体
+#[derive(Debug)]
+pub struct PlacedObject<'a> {
+    /// 组合物体的成员名称后缀，普通物体为None
+    pub suffix: Option<String>,
+    pub object: &'a Object,
+    /// 世界坐标位置
+    pub position: Dim3<Length>,
 }
 
 impl RenderNode {
+    /// 节点输出的物体：普通物体输出自身，带layout的group输出每个成员
+    pub fn placed_objects(&self) -> Vec<PlacedObject<'_>> {
+        let origin = self.computed_style.position.get_length().unwrap_or_default();
+        if let Some(object) = &self.computed_style.object {
+            return vec![PlacedObject {
+                suffix: None,
+                object,
+                position: origin,
+            }];
+        }
+        self.computed_style
+            .members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| PlacedObject {
+                suffix: Some(format!("{}_{}", member.name, index)),
+                object: &member.object,
+                position: origin + member.offset,
+            })
+            .collect()
+    }
+
     /// 创建新的渲染节点
     pub fn new(tag_name: String, node_type: RenderNodeType) -> Self {
         RenderNode {
             node_type,
             id: None,
+            classes: Vec::new(),
             tag_name,
             text_content: String::new(),
             specified_style: Style::new(),
             computed_style: style::ComputedStyle::default(),
-            select_attr: None,
-            parent: Weak::new(),
+            pick: None,
+            dynamic: false,
+            anchor: None,
+            extra_attributes: Vec::new(),
+            span: None,
+            parent: None,
+            path_hash: 0,
             children: Vec::new(),
         }
     }
 
+    /// 诊断和错误信息中的元素名称：`tag`或`tag#id`
+    pub fn label(&self) -> String {
+        match &self.id {
+            Some(id) => format!("{}#{}", self.tag_name, id),
+            None => self.tag_name.clone(),
+        }
+    }
+
+    /// 计算完成后节点的绝对尺寸；还有轴是auto或百分比时报错，错误信息中带上节点名称
+    pub fn absolute_size(&self) -> Result<Dim3<Length>> {
+        self.computed_style.size.get_length().ok_or_else(|| RsmlError::RenderTree {
+            message: format!(
+                "size of {} is not resolved: {}",
+                self.label(),
+                self.computed_style.size
+            ),
+        })
+    }
+
     /// 设置节点ID
     pub fn set_id(&mut self, id: String) {
         self.id = Some(id);
     }
 
+    /// 是否带有某个类名
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes.iter().any(|c| c == class)
+    }
+
     /// 设置文本内容
     pub fn set_text_content(&mut self, text: String) {
         self.text_content = text;
@@ -81,160 +262,680 @@ impl RenderNode {
         self.specified_style = style;
     }
 
-    /// 添加子节点
-    pub fn append_child(node: &Rc<RefCell<RenderNode>>, child: Rc<RefCell<RenderNode>>) {
-        // 设置子节点的父节点引用
-        child.borrow_mut().parent = Rc::downgrade(node);
-        node.borrow_mut().children.push(child);
-    }
-
     /// 获取父节点
-    pub fn parent(&self) -> Option<Rc<RefCell<RenderNode>>> {
-        self.parent.upgrade()
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
     }
 }
 
+/// 渲染树：按创建顺序保存所有节点，父节点总是在子节点之前
 pub struct RenderTree<'a> {
-    pub root: Rc<RefCell<RenderNode>>,
+    nodes: Vec<RenderNode>,
+    pub root: NodeId,
     package: &'a Package,
+    /// 构建渲染树时收集到的诊断信息
+    pub diagnostics: Diagnostics,
+    /// 通过<import>导入的固定物体，不参与布局，坐标已经是世界坐标
+    pub imported: Vec<PlanItem>,
+    /// <head>中声明的世界坐标系
+    pub world: World,
+    /// <head>中声明的灯光和地面设置
+    pub settings: Settings,
+    /// 布局中所有随机选择的seed；每个节点的随机数由seed和节点的路径决定，
+    /// 所以结果和计算顺序无关
+    seed: u64,
+    /// 并行计算宽的层
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+    /// calculate之后执行的调整pass
+    passes: Vec<Arc<dyn LayoutPass>>,
+    /// cube空间中random位置的采样方式和最多采样次数
+    placement: Arc<dyn PlacementStrategy>,
+    placement_attempts: usize,
+    /// id到节点的索引，重复的id指向第一次定义的节点
+    ids: HashMap<String, NodeId>,
 }
 
 impl<'a> RenderTree<'a> {
     pub fn new(dom_element: &Element, package: &'a Package) -> Result<Self> {
-        let root = Self::build_node_recursive(dom_element)?;
-        Ok(Self { root, package })
+        Self::with_diagnostics(dom_element, package, Diagnostics::default())
+    }
+
+    /// 使用指定的诊断收集器（携带severity配置）构建渲染树
+    pub fn with_diagnostics(
+        dom_element: &Element,
+        package: &'a Package,
+        diagnostics: Diagnostics,
+    ) -> Result<Self> {
+        Self::with_options(dom_element, package, diagnostics, &LayoutOptions::default())
+    }
+
+    /// 使用指定的诊断收集器和编译选项构建渲染树
+    pub fn with_options(
+        dom_element: &Element,
+        package: &'a Package,
+        mut diagnostics: Diagnostics,
+        options: &LayoutOptions,
+    ) -> Result<Self> {
+        // <use>展开为<define>的副本之后再构建
+        let expanded = template::expand(dom_element)?;
+        let dom_element = &expanded;
+        let world = World::from_element(dom_element)?;
+        let settings = Settings::from_element(dom_element)?;
+        let imported = plan::resolve_imports(dom_element)?;
+        let sheet = StyleSheet::from_document(dom_element).map_err(|e| RsmlError::ParseError {
+            field: "layout".to_string(),
+            message: e.to_string(),
+        })?;
+        if options.strict_styles {
+            diagnostics.set_severity(diagnostics::UNKNOWN_STYLE_PROPERTY, Severity::Error);
+            diagnostics.set_severity(diagnostics::OVERSIZED_ASSET, Severity::Error);
+        }
+        if options.lenient_nesting {
+            diagnostics.set_severity(diagnostics::MIXED_CHILDREN, Severity::Warning);
+            diagnostics.set_severity(diagnostics::ITEM_CHILDREN, Severity::Warning);
+        }
+        let nodes = Self::build_nodes(dom_element, &world, &sheet, options, &mut diagnostics)?;
+        let root = NodeId(0);
+        let mut tree = Self {
+            nodes,
+            root,
+            package,
+            diagnostics,
+            imported,
+            world,
+            settings,
+            // 没有指定seed时使用系统熵
+            seed: options.seed.unwrap_or_else(|| rand::rng().random()),
+            #[cfg(feature = "parallel")]
+            parallel: options.parallel,
+            passes: options.passes.clone(),
+            placement: options.placement.clone(),
+            placement_attempts: options.placement_attempts,
+            ids: HashMap::new(),
+        };
+        tree.check_nesting(root);
+        tree.index_ids(root);
+        tree.check_items(root);
+        Ok(tree)
+    }
+
+    /// 使用固定的seed，之后的calculate结果可以复现
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// 随机选择和随机位置使用的seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 计算布局
+    ///
+    /// 构建时记录的error（package中不存在的物体、无法解析的样式等）不中断布局，
+    /// 布局完成后和布局本身的错误一起作为`RsmlError::DiagnosticErrors`返回
+    pub fn calculate(&mut self) -> Result<()> {
+        let result = self.calculate_layout();
+        if !self.diagnostics.has_errors() {
+            return result;
+        }
+        let mut diagnostics = self.diagnostics.clone();
+        if let Err(error) = result {
+            diagnostics.set_element(None, error.span().cloned());
+            diagnostics.record(diagnostics::LAYOUT_FAILED, error.inner().to_string());
+        }
+        Err(RsmlError::DiagnosticErrors(Box::new(diagnostics)))
     }
 
-    pub fn calculate(&self) -> Result<()> {
+    fn calculate_layout(&mut self) -> Result<()> {
         // Find the body node and start position calculation from there
-        if let Some(body_node) = self.find_body_node(&self.root) {
+        if let Some(body_node) = self.find_body_node() {
             {
-                let len = SizeValue::Length(Length::from_m(100.0));
-                body_node.borrow_mut().computed_style.size = SpaceSize {
-                    x: len.clone(),
-                    y: len.clone(),
-                    z: len.clone(),
+                // body上指定的长度优先，其余的轴使用100m
+                let body_ref = self.node_mut(body_node);
+                let fallback = |value: &SizeValue| match value {
+                    SizeValue::Length(length) => SizeValue::Length(*length),
+                    _ => SizeValue::Length(Length::from_m(100.0)),
+                };
+                let specified = body_ref.specified_style.size.clone();
+                body_ref.computed_style.size = SpaceSize {
+                    x: fallback(&specified.x),
+                    y: fallback(&specified.y),
+                    z: fallback(&specified.z),
                 };
-                body_node.borrow_mut().computed_style.position = SpacePosition::zero();
+                body_ref.computed_style.position = SpacePosition::zero();
             }
             // 计算size，完成之后还会有部分percentage的size没有计算，因为他们的parent的size是auto
-            self.calculate_size_by_parent_recursive(&body_node)?;
+            self.calculate_size_by_parent(body_node)?;
             // 根据child计算auto的size，完成之后不应该再存在auto
-            self.calculate_size_by_child_recursive(&body_node)?;
+            self.calculate_size_by_child(body_node)?;
             // 再根据parent节点计算Percentage的size
-            self.calculate_size_by_parent_recursive(&body_node)?;
+            self.calculate_size_by_parent(body_node)?;
+            // 按flex-grow/flex-shrink分配主轴上剩余或不足的空间
+            self.calculate_flex_grow(body_node)?;
+            // 到这里所有尺寸都应该是Length
+            self.check_resolved_size(body_node)?;
+            self.check_size_limits(body_node);
 
             // 计算pos
-            self.calculate_pos_recursive(&body_node)?;
-            self.print_computed();
+            self.calculate_pos(body_node)?;
+            // 所有位置确定之后再放到anchor的目标上
+            self.resolve_anchors(body_node)?;
+            self.run_passes(body_node)?;
         }
         Ok(())
     }
 
-    /// Find the body node in the render tree
-    pub fn find_body_node(&self, node: &Rc<RefCell<RenderNode>>) -> Option<Rc<RefCell<RenderNode>>> {
-        let node_ref = node.borrow();
-
-        // Check if current node is body
-        if node_ref.tag_name == "body" {
-            return Some(node.clone());
+    /// 按id索引节点；重复的id会生成同名的geom，报告两处定义的位置
+    fn index_ids(&mut self, root: NodeId) {
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            self.index_id(id);
         }
+    }
 
-        // Recursively search in children
-        for child in &node_ref.children {
-            if let Some(body_node) = self.find_body_node(child) {
-                return Some(body_node);
+    fn index_id(&mut self, id: NodeId) {
+        let node_ref = &self.nodes[id.0];
+        if let Some(node_id) = &node_ref.id {
+            match self.ids.get(node_id) {
+                Some(first) => {
+                    let location = |node_ref: &RenderNode| match &node_ref.span {
+                        Some(span) => span.to_string(),
+                        None => node_ref.label(),
+                    };
+                    self.diagnostics.set_element(None, node_ref.span.clone());
+                    self.diagnostics.record(
+                        diagnostics::DUPLICATE_DEFINITION,
+                        format!(
+                            "id '{}' is defined at {} and again at {}",
+                            node_id,
+                            location(&self.nodes[first.0]),
+                            location(node_ref)
+                        ),
+                    );
+                    self.diagnostics.set_element(None, None);
+                }
+                None => {
+                    self.ids.insert(node_id.clone(), id);
+                }
             }
         }
+    }
 
-        None
+    /// 嵌套规则：space中不能同时有item和space，也不能有多个item；item是叶子节点
+    fn check_nesting(&mut self, root: NodeId) {
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            self.check_node_nesting(id);
+        }
     }
 
-    fn build_node_recursive(dom_element: &Element) -> Result<Rc<RefCell<RenderNode>>> {
-        let node_type = determine_node_type(&dom_element.name);
-        let mut render_node = RenderNode::new(dom_element.name.clone(), node_type);
+    fn check_node_nesting(&mut self, id: NodeId) {
+        let node_ref = &self.nodes[id.0];
+        let items = node_ref
+            .children
+            .iter()
+            .filter(|child| self.nodes[child.0].node_type == RenderNodeType::Item)
+            .count();
+        let spaces = node_ref.children.len() - items;
+        let error = match node_ref.node_type {
+            RenderNodeType::Item if !node_ref.children.is_empty() => Some((
+                diagnostics::ITEM_CHILDREN,
+                format!("{} cannot contain other elements", node_ref.tag_name),
+            )),
+            RenderNodeType::Space if items > 0 && spaces > 0 => Some((
+                diagnostics::MIXED_CHILDREN,
+                format!("space contains both items ({}) and spaces ({})", items, spaces),
+            )),
+            RenderNodeType::Space if items > 1 => Some((
+                diagnostics::MULTIPLE_ITEMS,
+                format!("space contains {} items", items),
+            )),
+            _ => None,
+        };
+        if let Some((code, message)) = error {
+            self.diagnostics.set_element(Some(node_ref.label()), node_ref.span.clone());
+            self.diagnostics.record(code, message);
+            self.diagnostics.set_element(None, None);
+        }
+    }
 
-        if let Some(id) = dom_element.get_attribute("id") {
-            render_node.set_id(id.clone());
+    /// 在布局之前检查所有object/group引用的名称，记录每一个问题，并把有问题的节点从渲染树中移除，
+    /// 其余的节点仍然可以布局
+    fn check_items(&mut self, root: NodeId) {
+        // 先序检查，移除的节点的后代不再检查
+        let mut removed = vec![false; self.nodes.len()];
+        let mut stack = self.nodes[root.0].children.iter().rev().copied().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            let node_ref = &self.nodes[id.0];
+            let error = match node_ref.node_type {
+                RenderNodeType::Item => Self::item_reference_error(self.package, &node_ref.text_content),
+                RenderNodeType::Space => None,
+            };
+            if let Some((code, message)) = error {
+                self.diagnostics.set_element(Some(node_ref.label()), node_ref.span.clone());
+                self.diagnostics.record(code, message);
+                removed[id.0] = true;
+                continue;
+            }
+            stack.extend(node_ref.children.iter().rev());
+        }
+        self.diagnostics.set_element(None, None);
+        for node_ref in &mut self.nodes {
+            node_ref.children.retain(|child| !removed[child.0]);
         }
+    }
 
-        // Extract the select attribute for groups
-        if let Some(select) = dom_element.get_attribute("select") {
-            render_node.select_attr = Some(select.clone());
+    /// Item引用的名称：不能为空，依赖包中的物体还不能直接引用，其余的必须在package中定义
+    fn item_reference_error(package: &Package, name: &str) -> Option<(&'static str, String)> {
+        if name.is_empty() {
+            return Some((
+                diagnostics::EMPTY_ITEM,
+                "object/group element has no name of a package object or group".to_string(),
+            ));
+        }
+        if package.get_space_size(name).is_ok() {
+            return None;
         }
+        let dependency = name.split_once('/').map_or(name, |(dependency, _)| dependency);
+        let message = if package.dependencies.contains_key(dependency) {
+            format!(
+                "'{}' refers to dependency '{}', which is not loaded; only objects and groups of package '{}' can be placed",
+                name, dependency, package.package.name
+            )
+        } else {
+            format!(
+                "object/group not found in package '{}': '{}'{}",
+                package.package.name,
+                name,
+                package.did_you_mean(name)
+            )
+        };
+        Some((diagnostics::UNKNOWN_ITEM, message))
+    }
 
-        if !dom_element.text.trim().is_empty() {
-            render_node.set_text_content(dom_element.text.trim().to_string());
+    /// 放置依赖的item的尺寸不能超过依赖的size-limit，超过时记录诊断，布局继续
+    fn check_size_limits(&mut self, root: NodeId) {
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            let node_ref = &self.nodes[id.0];
+            if node_ref.node_type != RenderNodeType::Item {
+                continue;
+            }
+            let name = &node_ref.text_content;
+            let (Some(limit), Some(size)) = (self.package.size_limit(name), node_ref.computed_style.size.get_length())
+            else {
+                continue;
+            };
+            if size.fits_within(&limit) {
+                continue;
+            }
+            self.diagnostics.set_element(Some(node_ref.label()), node_ref.span.clone());
+            self.diagnostics.record(
+                diagnostics::OVERSIZED_ASSET,
+                format!(
+                    "dependency '{}' is placed at {}mm x {}mm x {}mm, larger than its size-limit of {}mm x {}mm x {}mm",
+                    name, size.x.mm(), size.y.mm(), size.z.mm(), limit.x.mm(), limit.y.mm(), limit.z.mm()
+                ),
+            );
         }
-        let space_style = "display:flex;flex-direction:z-reverse";
-        let item_style = "display:flex";
-        let body_style = "display:flex;flex-direction:z-reverse;size:10m 10m 10m";
+        self.diagnostics.set_element(None, None);
+    }
 
-        let mut style = dom_element
-            .attributes
-            .get("style")
-            .cloned()
-            .unwrap_or_default();
-        if render_node.tag_name == "body" {
-            style = body_style.to_owned();
+    /// 检查所有节点的尺寸都已经解析为长度，否则一次报告所有未解析的节点、轴和可能的原因，
+    /// 位置指向第一个节点
+    fn check_resolved_size(&self, id: NodeId) -> Result<()> {
+        let mut unresolved = Vec::new();
+        let mut first_span = None;
+        for node in self.descendants(id) {
+            let node_ref = self.node(node);
+            // 同一个节点上原因相同的轴合并成一行
+            let mut lines: Vec<(Vec<&str>, String)> = Vec::new();
+            for (axis, name) in ["x", "y", "z"].into_iter().enumerate() {
+                let value = size_axis(&node_ref.computed_style.size, axis);
+                if value.is_length() {
+                    continue;
+                }
+                let reason = self.unresolved_reason(node, axis, value);
+                match lines.iter_mut().find(|(_, other)| *other == reason) {
+                    Some((axes, _)) => axes.push(name),
+                    None => lines.push((vec![name], reason)),
+                }
+            }
+            if !lines.is_empty() && first_span.is_none() {
+                first_span = Some(node_ref.span.clone());
+            }
+            for (axes, reason) in lines {
+                unresolved.push(format!("  {} ({}): {}", node_ref.label(), axes.join(", "), reason));
+            }
         }
-        match render_node.node_type {
-            RenderNodeType::Space => style = format!("{};{}", space_style, style),
-            RenderNodeType::Item => style = format!("{};{}", item_style, style),
+        match first_span {
+            None => Ok(()),
+            Some(span) => Err(RsmlError::StyleComputation {
+                message: format!("unresolved size after layout:\n{}", unresolved.join("\n")),
+            }
+            .at(span.as_ref())),
         }
+    }
 
-        match Style::from_style_string(&style) {
-            Ok(style) => render_node.set_specified_style(style),
-            Err(e) => eprintln!(
-                "Warning: Failed to parse style for element '{}': {}",
-                dom_element.name, e
-            ),
+    /// 某个轴的尺寸没有解析的原因
+    fn unresolved_reason(&self, id: NodeId, axis: usize, value: &SizeValue) -> String {
+        let node_ref = self.node(id);
+        if let SizeValue::Percentage(_) = value {
+            return match node_ref.parent {
+                Some(parent) => format!("{} of {}, whose size is unresolved", value, self.node(parent).label()),
+                None => format!("{} without a parent", value),
+            };
+        }
+        let children = &node_ref.children;
+        if children.is_empty() {
+            return "auto size and no children".to_string();
+        }
+        let percentages = children
+            .iter()
+            .all(|child| matches!(size_axis(&self.node(*child).computed_style.size, axis), SizeValue::Percentage(_)));
+        match percentages {
+            // 子元素的百分比又依赖这个auto的尺寸
+            true => "auto size and only percentage-sized children".to_string(),
+            false => "auto size and no sized children".to_string(),
+        }
+    }
+
+    /// 依次执行注册的pass，之后重新检查被移动的物体
+    fn run_passes(&mut self, body_node: NodeId) -> Result<()> {
+        if self.passes.is_empty() {
+            return Ok(());
+        }
+        let rng = RefCell::new(StdRng::seed_from_u64(self.seed));
+        let mut scene = MutableScene::new(&mut self.nodes, body_node);
+        let ctx = PassCtx {
+            world: self.world,
+            rng: &rng,
+        };
+        for pass in &self.passes {
+            pass.run(&mut scene, &ctx).map_err(|e| RsmlError::RenderTree {
+                message: format!("pass '{}' failed: {}", pass.name(), e),
+            })?;
+        }
+        // 碰撞由check_collisions在调整之后的布局上检查
+        scene.validate(&mut self.diagnostics);
+        Ok(())
+    }
+
+    /// 布局计算中只读的部分
+    fn layout(&self) -> Layout<'_> {
+        Layout {
+            nodes: &self.nodes,
+            package: self.package,
+            seed: self.seed,
+            placement: self.placement.as_ref(),
+            placement_attempts: self.placement_attempts,
         }
+    }
+
+    /// 下标对应的节点
+    pub fn node(&self, id: NodeId) -> &RenderNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut RenderNode {
+        &mut self.nodes[id.0]
+    }
+
+    /// 同时借用节点（可变）和它的父节点；父节点总是先创建，下标比子节点小
+    fn node_and_parent_mut(&mut self, id: NodeId) -> (&mut RenderNode, Option<&RenderNode>) {
+        let (before, rest) = self.nodes.split_at_mut(id.0);
+        let node = &mut rest[0];
+        let parent = node.parent.map(|parent| &before[parent.0]);
+        (node, parent)
+    }
+
+    /// 相对于父节点的位置；computed_style.position已经是世界坐标
+    pub fn relative_position(&self, id: NodeId) -> Option<Dim3<Length>> {
+        let node_ref = self.node(id);
+        let position = node_ref.computed_style.position.get_length()?;
+        let parent_position = match node_ref.parent {
+            Some(parent) => self.node(parent).computed_style.position.get_length()?,
+            None => Dim3::default(),
+        };
+        Some(position - parent_position)
+    }
+
+    /// id为`id`的节点；id重复时是第一次定义的节点
+    pub fn find_by_id(&self, id: &str) -> Option<NodeId> {
+        self.ids.get(id).copied()
+    }
+
+    /// 场景中用到的所有id
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.ids.keys().map(String::as_str)
+    }
 
-        let rc_node = Rc::new(RefCell::new(render_node));
+    /// 从根节点开始深度优先、先序遍历所有节点
+    ///
+    /// 遍历期间借用渲染树，需要修改节点时先收集下标
+    pub fn iter(&self) -> Nodes<'_> {
+        self.descendants(self.root)
+    }
 
-        for child_element in &dom_element.children {
-            let child_render_node = Self::build_node_recursive(child_element)?;
-            RenderNode::append_child(&rc_node, child_render_node);
+    /// 先序遍历`id`和它的所有后代
+    pub fn descendants(&self, id: NodeId) -> Nodes<'_> {
+        Nodes {
+            nodes: &self.nodes,
+            stack: vec![id],
         }
+    }
 
-        Ok(rc_node)
+    /// 先序遍历中的Item节点（object和group）
+    pub fn iter_items(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter()
+            .filter(|&node| self.node(node).node_type == RenderNodeType::Item)
     }
-    fn cal_flex_child_size(node_ref: &RenderNode) -> SpaceSize {
-        let mut child_total_size = node_ref.specified_style.size.create_self_by_auto_to_zero();
 
-        println!(
-            "id={:?} raw_size={} create_size={}",
-            node_ref.id, node_ref.specified_style.size, child_total_size
-        );
-        let children = &node_ref.children;
-        let flex_direction = node_ref.specified_style.flex_direction.clone();
+    /// 节点总数，包括根节点
+    pub fn nodes_count(&self) -> usize {
+        self.iter().count()
+    }
 
-        for child in children {
-            let child_ref = child.borrow();
-            let child_size = &child_ref.computed_style.size;
-            match flex_direction {
-                FlexDirection::X | FlexDirection::ReverseX => {
-                    child_total_size.x.add(&child_size.x);
-                    child_total_size.y.max(&child_size.y);
-                    child_total_size.z.max(&child_size.z);
-                }
-                FlexDirection::Y | FlexDirection::ReverseY => {
-                    child_total_size.x.max(&child_size.x);
-                    child_total_size.y.add(&child_size.y);
-                    child_total_size.z.max(&child_size.z);
+    /// Find the body node in the render tree
+    pub fn find_body_node(&self) -> Option<NodeId> {
+        self.iter().find(|&node| self.node(node).tag_name == "body")
+    }
+
+    /// 按先序为元素和它的所有后代创建节点，根元素是第一个节点
+    ///
+    /// 用栈代替递归，很深的文档也不会栈溢出；父节点总在子节点之前
+    fn build_nodes(
+        root: &Element,
+        world: &World,
+        sheet: &StyleSheet,
+        options: &LayoutOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Vec<RenderNode>> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![(root, None)];
+        while let Some((dom_element, parent)) = stack.pop() {
+            let id = Self::build_node(&mut nodes, parent, dom_element, world, sheet, options, diagnostics)?;
+            // <import>在构建前已经解析，不进入渲染树
+            let children = dom_element.children.iter().rev().filter(|c| c.name != "import");
+            stack.extend(children.map(|child| (child, Some(id))));
+        }
+        Ok(nodes)
+    }
+
+    /// 为元素创建节点并加入父节点的子节点，不处理子元素
+    fn build_node(
+        nodes: &mut Vec<RenderNode>,
+        parent: Option<NodeId>,
+        dom_element: &Element,
+        world: &World,
+        sheet: &StyleSheet,
+        options: &LayoutOptions,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<NodeId> {
+        let node_type = determine_node_type(&dom_element.name);
+        let mut render_node = RenderNode::new(dom_element.name.clone(), node_type);
+        render_node.span = dom_element.span.clone();
+        render_node.parent = parent;
+
+        if let Some(id) = dom_element.get_attribute("id") {
+            render_node.set_id(id.clone());
+        }
+
+        render_node.classes = dom_element.classes();
+
+        // Extract the pick attribute for groups; select is the older spelling with bare object names
+        let pick = dom_element
+            .get_attribute("pick")
+            .filter(|_| schema::is_known_attribute(&dom_element.name, "pick"));
+        match (pick, dom_element.get_attribute("select")) {
+            (Some(_), Some(_)) => {
+                return Err(RsmlError::StyleComputation {
+                    message: format!("{} sets both pick and select; use either pick or select", render_node.label()),
                 }
-                FlexDirection::Z | FlexDirection::ReverseZ => {
-                    child_total_size.x.max(&child_size.x);
-                    child_total_size.y.max(&child_size.y);
-                    child_total_size.z.add(&child_size.z);
+                .at(dom_element.span.as_ref()));
+            }
+            (Some(pick), None) => {
+                render_node.pick = Some(pick.parse().map_err(|e: RsmlError| e.at(dom_element.span.as_ref()))?);
+            }
+            (None, Some(select)) => render_node.pick = Some(Pick::from_select(select)),
+            (None, None) => {}
+        }
+
+        // dynamic只对object和group有效，其他元素上作为未知属性处理
+        match dom_element.get_attribute("dynamic") {
+            Some(dynamic) if schema::is_known_attribute(&dom_element.name, "dynamic") => {
+                render_node.dynamic = match dynamic.trim() {
+                    "true" => true,
+                    "false" => false,
+                    _ => {
+                        return Err(RsmlError::InvalidAttribute {
+                            attribute: "dynamic".to_string(),
+                            value: dynamic.clone(),
+                        }
+                        .at(dom_element.span.as_ref()));
+                    }
+                };
+            }
+            _ => {}
+        }
+
+        // anchor只对space、object和group有效
+        if let Some(anchor) = dom_element.get_attribute("anchor")
+            && schema::is_known_attribute(&dom_element.name, "anchor")
+        {
+            render_node.anchor = Some(anchor.parse().map_err(|e: RsmlError| e.at(dom_element.span.as_ref()))?);
+        }
+
+        // 未知属性不丢弃，带透传前缀的只记为note
+        let mut extra_attributes: Vec<(String, String)> = dom_element
+            .attributes
+            .iter()
+            .filter(|(key, _)| !schema::is_known_attribute(&dom_element.name, key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        extra_attributes.sort();
+        for (key, _) in &extra_attributes {
+            let code = if key.starts_with(&options.passthrough_prefix) {
+                diagnostics::PASSTHROUGH_ATTRIBUTE
+            } else {
+                diagnostics::UNKNOWN_ATTRIBUTE
+            };
+            diagnostics.record(
+                code,
+                format!("unknown attribute '{}' on element '{}'", key, dom_element.name),
+            );
+        }
+        render_node.extra_attributes = extra_attributes;
+
+        if !dom_element.text.trim().is_empty() {
+            render_node.set_text_content(dom_element.text.trim().to_string());
+        }
+        // 默认样式：排列方向由world的up轴和地面决定，body是10m的容器；默认值不算作显式声明
+        let mut style = Style::new();
+        if render_node.node_type == RenderNodeType::Space {
+            style.flex_direction = world.default_flex_direction();
+        }
+        if render_node.tag_name == "body" {
+            style.size = SpaceSize::from_dim3_length(Dim3::new(Length::from_m(10.0), Length::from_m(10.0), Length::from_m(10.0)));
+        }
+
+        // 依次合并样式表中匹配的规则和style属性，后面显式声明的属性覆盖前面的
+        let mut sources: Vec<&str> = sheet
+            .matching_rules(&dom_element.name, render_node.id.as_deref(), &render_node.classes)
+            .iter()
+            .map(|rule| rule.declarations.as_str())
+            .collect();
+        if let Some(inline_style) = dom_element.get_attribute("style") {
+            sources.push(inline_style);
+        }
+        // 样式的诊断信息带上元素和它在源文件中的位置，便于定位
+        let element_label = render_node.label();
+        diagnostics.set_element(Some(element_label.clone()), dom_element.span.clone());
+        for source in sources {
+            match Style::from_style_string_with_diagnostics(source, diagnostics) {
+                Ok(specified) => match specified.oversized_percentage() {
+                    Some((property, percentage)) if !options.allow_oversized_percentages => {
+                        diagnostics.record(
+                            diagnostics::INVALID_STYLE,
+                            format!(
+                                "failed to parse style: {} of {} is larger than 100%",
+                                property, percentage
+                            ),
+                        )
+                    }
+                    _ => style.merge(&specified),
+                },
+                Err(e) => diagnostics.record(
+                    diagnostics::INVALID_STYLE,
+                    format!("failed to parse style: {}", e),
+                ),
+            }
+        }
+        diagnostics.set_element(None, None);
+
+        // 旋转只影响物体的输出朝向，space旋转后子节点的布局没有定义；缩放的是package中的尺寸，space没有
+        for property in ["rotate", "scale"] {
+            if render_node.node_type == RenderNodeType::Space && style.is_declared(property) {
+                return Err(RsmlError::StyleComputation {
+                    message: format!(
+                        "{} is only supported on objects and groups, not on '{}'",
+                        property, element_label
+                    ),
                 }
+                .at(dom_element.span.as_ref()));
+            }
+        }
+        // 物体的尺寸来自package，比例只能约束space
+        if render_node.node_type == RenderNodeType::Item && style.is_declared("aspect-ratio") {
+            return Err(RsmlError::StyleComputation {
+                message: format!("aspect-ratio is only supported on spaces, not on '{}'", element_label),
             }
+            .at(dom_element.span.as_ref()));
         }
 
-        return child_total_size;
-    }
+        // 未显式指定的可继承属性沿用父节点显式指定或继承到的值；body是根，不继承
+        if let (Some(parent), false) = (parent, render_node.tag_name == "body") {
+            style.inherit_from(&nodes[parent.0].specified_style);
+        }
+        // 显式指定或继承的align-items优先；
+        // body是固定的10m容器，不参与默认对齐，场景原点保持不变
+        let explicit_align = style.is_declared("align-items") || render_node.tag_name == "body";
+        if !explicit_align && options.default_align == DefaultAlign::World {
+            style.align_items = world.default_align_items(&style.flex_direction);
+        }
+        // 沿up轴反向排列时默认贴地，body上也一样，场景原点保持不变
+        if !style.is_declared("justify-content") {
+            style.justify_content = world.default_justify_content(&style.flex_direction);
+        }
+        render_node.set_specified_style(style);
 
+        let id = NodeId(nodes.len());
+        if let Some(parent) = parent {
+            let parent_ref = &mut nodes[parent.0];
+            render_node.path_hash = path_hash(parent_ref.path_hash, parent_ref.children.len());
+            parent_ref.children.push(id);
+        }
+        nodes.push(render_node);
+        Ok(id)
+    }
     /// Helper function to calculate a single dimension size based on parent size
     fn calculate_dimension_size(
         size_value: &SizeValue,
@@ -244,7 +945,7 @@ impl<'a> RenderTree<'a> {
             SizeValue::Length(length) => SizeValue::Length(*length),
             SizeValue::Percentage(percentage) => {
                 if let SizeValue::Length(parent_len) = parent_size_value {
-                    SizeValue::Length(*parent_len * percentage.value() / 100)
+                    SizeValue::Length(percentage.of(*parent_len))
                 } else {
                     SizeValue::Auto
                 }
@@ -253,26 +954,38 @@ impl<'a> RenderTree<'a> {
         }
     }
 
-    fn calculate_size_by_parent_recursive(&self, node: &Rc<RefCell<RenderNode>>) -> Result<()> {
-        let mut node_ref = node.borrow_mut();
-        let parent = node_ref.parent().ok_or(RsmlError::RenderTree {
+    /// 先序按父节点计算`root`和它的所有后代的尺寸
+    fn calculate_size_by_parent(&mut self, root: NodeId) -> Result<()> {
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            self.calculate_node_size_by_parent(id)?;
+        }
+        Ok(())
+    }
+
+    fn calculate_node_size_by_parent(&mut self, id: NodeId) -> Result<()> {
+        let (node_ref, parent) = self.node_and_parent_mut(id);
+        let parent_ref = parent.ok_or(RsmlError::RenderTree {
             message: "Parent node not found".to_string(),
         })?;
-        let parent_ref = parent.borrow();
 
-        let parent_size = &parent_ref.computed_style.size;
+        // 百分比相对于父空间扣除内边距后的内容盒
+        let parent_size = &content_size(&parent_ref.computed_style.size, &parent_ref.specified_style.padding);
         let size = node_ref.specified_style.size.clone();
 
         // Calculate sizes for each dimension using the helper function
-        let new_x = Self::calculate_dimension_size(&size.x, &parent_size.x);
-        let new_y = Self::calculate_dimension_size(&size.y, &parent_size.y);
-        let new_z = Self::calculate_dimension_size(&size.z, &parent_size.z);
+        // parent的尺寸还没有确定时保留百分比，等parent确定之后再解析，而不是当作auto
+        let resolve = |value: &SizeValue, parent: &SizeValue| match Self::calculate_dimension_size(value, parent) {
+            SizeValue::Auto => value.clone(),
+            resolved => resolved,
+        };
+        let new_x = resolve(&size.x, &parent_size.x);
+        let new_y = resolve(&size.y, &parent_size.y);
+        let new_z = resolve(&size.z, &parent_size.z);
 
         node_ref
             .computed_style
             .size
             .assign_priority(SpaceSize::new(new_x, new_y, new_z));
-        println!("id={:?} size={}", node_ref.id, node_ref.computed_style.size);
         match parent_ref.specified_style.display {
             // parent是flex，就是用flex-basis计算size
             style::Display::Flex => {
@@ -290,421 +1003,1124 @@ impl<'a> RenderTree<'a> {
             }
             style::Display::Cube => {}
         }
-        let children = node_ref.children.clone();
-        drop(node_ref);
-        for child in children {
-            self.calculate_size_by_parent_recursive(&child)?;
+        let mut size = node_ref.computed_style.size.clone();
+        Self::apply_aspect_ratio(node_ref, &mut size)?;
+        Self::clamp_size(node_ref, &mut size, parent_size)?;
+        node_ref.computed_style.size = size;
+        Ok(())
+    }
+
+    /// 按aspect-ratio由已经确定的轴推出参与比例的其余轴
+    ///
+    /// 显式指定了长度或百分比的轴是约束，两个约束的轴不符合比例时报错；其余的轴由第一个约束的轴推出，
+    /// 没有约束的轴时参照第一个已经是长度的轴（由子元素或flex-basis决定）。
+    /// 还没有可以参照的轴时保持不变，之后的步骤确定尺寸后再推出
+    fn apply_aspect_ratio(node_ref: &RenderNode, size: &mut SpaceSize) -> Result<()> {
+        let Some(aspect_ratio) = node_ref.specified_style.aspect_ratio else {
+            return Ok(());
+        };
+        let proportions = aspect_ratio.proportions();
+        let specified = &node_ref.specified_style.size;
+        // 参与比例的已经是长度的轴，以及每一份的长度
+        let units: Vec<(usize, bool, f64)> = (0..3)
+            .filter_map(|axis| {
+                let proportion = proportions[axis]?;
+                let SizeValue::Length(length) = size_axis(size, axis) else {
+                    return None;
+                };
+                let constrained = *size_axis(specified, axis) != SizeValue::Auto;
+                Some((axis, constrained, length.mm() as f64 / proportion))
+            })
+            .collect();
+        let mut constrained = units.iter().filter(|(_, constrained, _)| *constrained);
+        let Some(&(_, _, unit)) = constrained.clone().next().or(units.first()) else {
+            return Ok(());
+        };
+
+        // 按比例推出的长度取整到毫米，相差不超过1mm视为一致
+        if constrained.any(|&(axis, _, other)| ((other - unit) * proportions[axis].unwrap_or(0.0)).abs() > 1.0) {
+            return Err(RsmlError::StyleComputation {
+                message: format!(
+                    "aspect-ratio:{} of {} conflicts with its size {}",
+                    aspect_ratio,
+                    node_ref.label(),
+                    size
+                ),
+            }
+            .at(node_ref.span.as_ref()));
+        }
+        for (axis, proportion) in proportions.into_iter().enumerate() {
+            if let Some(proportion) = proportion
+                && *size_axis(specified, axis) == SizeValue::Auto
+            {
+                *size_axis_mut(size, axis) = SizeValue::Length(mm_to_length((unit * proportion).round() as i64));
+            }
         }
         Ok(())
     }
 
-    /// Set the object in the computed style for Item nodes
-    fn set_computed_object(&self, node_ref: &mut RenderNode) -> Result<()> {
-        // Clone the text_content to avoid borrowing issues
-        let name = node_ref.text_content.clone();
-        
-        // Set the object in the computed style
-        // First check if it's a direct object
-        if let Some(object) = self.package.objects.get(&name) {
-            node_ref.computed_style.object = Some(object.clone());
-        } else {
-            // It's a group, so we need to handle the select attribute
-            if let Some(select_attr) = &node_ref.select_attr {
-                // Find the group in the package
-                if let Some(group) = self.package.groups.iter().find(|g| g.name == name) {
-                    // Select an object from the group based on the select attribute
-                    match select_attr.as_str() {
-                        "first" => {
-                            // Select the first object in the group
-                            if let Some((_, object)) = group.objects.iter().next() {
-                                node_ref.computed_style.object = Some(object.clone());
-                            }
-                        }
-                        "random" => {
-                            // Select a random object from the group
-                            use rand::rng;
-                            let mut rng = rng();
-                            let object_vec: Vec<&Object> = group.objects.values().collect();
-                            if let Some(object) = object_vec.choose(&mut rng) {
-                                node_ref.computed_style.object = Some((*object).clone());
-                            }
-                        }
-                        _ => {
-                            // Try to select by specific name
-                            if let Some(object) = group.objects.get(select_attr) {
-                                node_ref.computed_style.object = Some(object.clone());
-                            }
-                        }
-                    }
+    /// 按min-size/max-size限制空间的计算尺寸，百分比相对于父空间的内容盒
+    ///
+    /// 尚未确定的轴保持不变；object/group的尺寸来自package，不受限制
+    fn clamp_size(node_ref: &RenderNode, size: &mut SpaceSize, parent_size: &SpaceSize) -> Result<()> {
+        if node_ref.node_type != RenderNodeType::Space {
+            return Ok(());
+        }
+        let min_size = &node_ref.specified_style.min_size;
+        let max_size = &node_ref.specified_style.max_size;
+        for (axis, value, min, max, parent) in [
+            ("x", &mut size.x, &min_size.x, &max_size.x, &parent_size.x),
+            ("y", &mut size.y, &min_size.y, &max_size.y, &parent_size.y),
+            ("z", &mut size.z, &min_size.z, &max_size.z, &parent_size.z),
+        ] {
+            let min = Self::calculate_dimension_size(min, parent);
+            let max = Self::calculate_dimension_size(max, parent);
+            if let (SizeValue::Length(min), SizeValue::Length(max)) = (&min, &max)
+                && min > max
+            {
+                return Err(RsmlError::StyleComputation {
+                    message: format!(
+                        "{} has min-size {} larger than max-size {} on the {} axis",
+                        node_ref.label(),
+                        min,
+                        max,
+                        axis
+                    ),
+                }
+                .at(node_ref.span.as_ref()));
+            }
+            if let SizeValue::Length(length) = value {
+                if let SizeValue::Length(min) = min {
+                    *length = (*length).max(min);
+                }
+                if let SizeValue::Length(max) = max {
+                    *length = (*length).min(max);
                 }
             }
         }
-        
         Ok(())
     }
 
-    fn calculate_size_by_child_recursive(&self, node: &Rc<RefCell<RenderNode>>) -> Result<()> {
-        for child in &node.borrow().children {
-            self.calculate_size_by_child_recursive(child)?;
+    /// 按flex-grow/flex-shrink调整子空间在主轴上的尺寸，从上往下处理，父空间扩大后子空间可以继续扩大
+    ///
+    /// 剩余空间按grow权重分配；空间不足时按 shrink × 原尺寸 的权重收缩，最小为0。
+    /// object/group的尺寸来自package，不参与伸缩
+    fn calculate_flex_grow(&mut self, root: NodeId) -> Result<()> {
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            self.calculate_children_flex_grow(id)?;
         }
+        Ok(())
+    }
 
-        let mut node_ref = node.borrow_mut();
+    fn calculate_children_flex_grow(&mut self, id: NodeId) -> Result<()> {
+        let node_ref = self.node(id);
+        let children = node_ref.children.clone();
+        let (main_axis, _, _) = flex_axes(&node_ref.specified_style.flex_direction);
+        let container = content_size(&node_ref.computed_style.size, &node_ref.specified_style.padding)
+            .get_length()
+            .map(|size| dim3_mm(&size)[main_axis]);
+        let is_flex = node_ref.specified_style.display == style::Display::Flex;
+        let wrap = node_ref.specified_style.flex_wrap == FlexWrap::Wrap;
+        let gap = node_ref.specified_style.gap.mm() as i64;
 
-        match &node_ref.node_type {
-            RenderNodeType::Item => {
-                // Clone the text_content to avoid borrowing issues
-                let name = node_ref.text_content.clone();
-                node_ref.computed_style.size =
-                    SpaceSize::from_dim3_length(self.package.get_space_size(&name).ok_or(
-                        RsmlError::PackageConfigError(format!(
-                            "object/group not found. name={}",
-                            name
-                        )),
-                    )?);
-                
-                // Set the object in the computed style
-                self.set_computed_object(&mut node_ref)?;
-            }
-            RenderNodeType::Space => match node_ref.specified_style.display {
-                style::Display::Flex => {
-                    if !node_ref.computed_style.size.all_length() {
-                        let child_total_size = Self::cal_flex_child_size(&node_ref);
-                        println!("tag={:?} child_size={}", node_ref.id, child_total_size);
-                        node_ref
-                            .computed_style
-                            .size
-                            .assign_priority(child_total_size);
-                    }
+        // 子元素在主轴上的外边距盒尺寸
+        let sizes: Option<Vec<i64>> = children
+            .iter()
+            .map(|child| outer_mm(self.node(*child)).map(|size| size[main_axis]))
+            .collect();
+
+        if let (true, Some(container), Some(sizes)) = (is_flex, container, sizes) {
+            // 换行时每一行单独分配
+            let order = self.layout().flex_order(&children);
+            let lines = if wrap {
+                flex_lines(container, &sizes, &order, gap)
+            } else {
+                vec![order]
+            };
+            for line in lines {
+                let free_space =
+                    container - line.iter().map(|&i| sizes[i]).sum::<i64>() - gap * gap_count(line.len());
+                let weights: Vec<i64> = line
+                    .iter()
+                    .map(|&i| {
+                        let child_ref = self.node(children[i]);
+                        let style = &child_ref.specified_style;
+                        if child_ref.node_type != RenderNodeType::Space {
+                            0
+                        } else if free_space > 0 {
+                            (style.flex_grow * 1000.0).round() as i64
+                        } else {
+                            (style.flex_shrink * 1000.0).round() as i64 * sizes[i]
+                        }
+                    })
+                    .collect();
+
+                if free_space == 0 || weights.iter().all(|weight| *weight == 0) {
+                    continue;
                 }
-                style::Display::Cube => {
-                    if node_ref.computed_style.size.has_auto() {
-                        return Err(RsmlError::CubeSizeError);
+                let deltas = distribute_largest_remainder(free_space, &weights);
+                for (child, delta) in line.iter().map(|&i| children[i]).zip(deltas) {
+                    if delta == 0 {
+                        continue;
+                    }
+                    let child_ref = self.node_mut(child);
+                    let mut size = child_ref.computed_style.size.get_length().unwrap_or_default();
+                    let resize = |length: &mut Length| *length = mm_to_length(length.mm() as i64 + delta);
+                    match main_axis {
+                        0 => resize(&mut size.x),
+                        1 => resize(&mut size.y),
+                        _ => resize(&mut size.z),
+                    }
+                    child_ref.computed_style.size = SpaceSize::from_dim3_length(size);
+                    // 子元素的百分比尺寸需要按新的尺寸重新计算
+                    for grandchild in child_ref.children.clone() {
+                        self.calculate_size_by_parent(grandchild)?;
                     }
                 }
-            },
+            }
         }
-
         Ok(())
     }
 
-    fn calculate_pos_recursive(&self, node: &Rc<RefCell<RenderNode>>) -> Result<()> {
-        let mut node_ref = node.borrow_mut();
-
-        // 不会有auto，全是Length
-        match node_ref.specified_style.display {
-            style::Display::Flex => {
-                // 计算Flex布局中子元素的位置
-                self.calculate_flex_child_positions(&mut node_ref)?;
-            }
-            style::Display::Cube => todo!(),
+    /// 后序按子节点计算`root`和它的所有后代的尺寸
+    fn calculate_size_by_child(&mut self, root: NodeId) -> Result<()> {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            let compute = |layout: &Layout<'_>, id| layout.size_by_child(id);
+            return self.calculate_by_level(root, true, compute, |nodes, id, computed| {
+                nodes[id.0].computed_style = computed;
+            });
+        }
+        for id in self.post_order(root) {
+            let computed = self.layout().size_by_child(id)?;
+            self.nodes[id.0].computed_style = computed;
         }
+        Ok(())
+    }
 
-        let children = node_ref.children.clone();
-        drop(node_ref);
-        for child in children {
-            self.calculate_pos_recursive(&child)?;
+    /// `root`和它的所有后代，子节点从左到右的后序
+    fn post_order(&self, root: NodeId) -> Vec<NodeId> {
+        // 先序中子节点从右到左，反过来就是从左到右的后序
+        let mut order = Vec::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(&self.node(id).children);
         }
+        order.reverse();
+        order
+    }
 
+    /// 先序计算`root`的所有后代的位置
+    fn calculate_pos(&mut self, root: NodeId) -> Result<()> {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            let compute = |layout: &Layout<'_>, id| layout.child_positions(id);
+            return self.calculate_by_level(root, false, compute, Self::set_positions);
+        }
+        for id in self.descendants(root).collect::<Vec<_>>() {
+            let positions = self.layout().child_positions(id)?;
+            Self::set_positions(&mut self.nodes, id, positions);
+        }
         Ok(())
     }
 
-    /// 计算Flex布局中子元素的位置
-    fn calculate_flex_child_positions(&self, node_ref: &mut RenderNode) -> Result<()> {
-        let flex_direction = &node_ref.specified_style.flex_direction;
-        let justify_content = &node_ref.specified_style.justify_content;
-        let align_items = &node_ref.specified_style.align_items;
-        let node_size = &node_ref.computed_style.size;
-
-        // 获取节点的尺寸（转换为Length）
-        let node_length = node_size.get_length().ok_or(RsmlError::RenderTree {
-            message: format!("Unable to calculate the size of node({:?})", node_ref.id),
-        })?;
+    fn set_positions(nodes: &mut [RenderNode], _id: NodeId, positions: Vec<(NodeId, SpacePosition)>) {
+        for (child, position) in positions {
+            nodes[child.0].computed_style.position = position;
+        }
+    }
 
-        // 计算子元素的总尺寸
-        let mut total_child_size =
-            Dim3::new(Length::from_mm(0), Length::from_mm(0), Length::from_mm(0));
-        let mut child_lengths = Vec::new();
+    /// 按层计算`root`和它的所有后代：同一层的节点互不依赖，宽的层用rayon并行计算，
+    /// 再按顺序写回
+    ///
+    /// `bottom_up`时从最深的一层开始，节点依赖它的子节点；否则从`root`开始，节点依赖它的父节点。
+    /// 依赖的节点失败时不再计算；有多个错误时返回串行计算（后序或先序）最先遇到的那个，
+    /// 所以结果和串行计算相同
+    #[cfg(feature = "parallel")]
+    fn calculate_by_level<T: Send>(
+        &mut self,
+        root: NodeId,
+        bottom_up: bool,
+        compute: impl Fn(&Layout<'_>, NodeId) -> Result<T> + Sync,
+        apply: impl Fn(&mut [RenderNode], NodeId, T),
+    ) -> Result<()> {
+        use rayon::prelude::*;
 
-        // 收集子元素的尺寸信息
-        for child in &node_ref.children {
-            let child_ref = child.borrow();
-            let child_size =
-                &child_ref
-                    .computed_style
-                    .size
-                    .get_length()
-                    .ok_or(RsmlError::RenderTree {
-                        message: format!(
-                            "Unable to calculate the size of node({:?})",
-                            child_ref.id
-                        ),
-                    })?;
-            total_child_size.x += child_size.x;
-            total_child_size.y += child_size.y;
-            total_child_size.z += child_size.z;
-            child_lengths.push(child_size.clone());
+        let order = if bottom_up {
+            self.post_order(root)
+        } else {
+            self.descendants(root).collect()
+        };
+        let mut rank = vec![0; self.nodes.len()];
+        let mut depth = vec![0; self.nodes.len()];
+        let mut levels: Vec<Vec<NodeId>> = Vec::new();
+        for id in self.descendants(root) {
+            if let (Some(parent), true) = (self.node(id).parent, id != root) {
+                depth[id.0] = depth[parent.0] + 1;
+            }
+            if levels.len() <= depth[id.0] {
+                levels.push(Vec::new());
+            }
+            levels[depth[id.0]].push(id);
+        }
+        for (index, id) in order.iter().enumerate() {
+            rank[id.0] = index;
+        }
+        if bottom_up {
+            levels.reverse();
         }
 
-        // 根据flex_direction、justify_content和align_items计算子元素的位置
-        let mut child_positions = Vec::new();
-
-        match flex_direction {
-            FlexDirection::X | FlexDirection::ReverseX => {
-                // 主轴是X轴，交叉轴是Y和Z
-                let free_space = node_length.x.mm() as f64 - total_child_size.x.mm() as f64;
-                let mut positions = self.calculate_positions_on_axis(
-                    free_space,
-                    &child_lengths
-                        .iter()
-                        .map(|dim| dim.x.mm() as f64)
-                        .collect::<Vec<_>>(),
-                    justify_content,
-                );
+        let mut failed = vec![false; self.nodes.len()];
+        let mut first_error: Option<(usize, RsmlError)> = None;
+        for level in levels {
+            let mut ready = Vec::with_capacity(level.len());
+            for id in level {
+                let node_ref = self.node(id);
+                failed[id.0] = if bottom_up {
+                    node_ref.children.iter().any(|child| failed[child.0])
+                } else {
+                    id != root && node_ref.parent.is_some_and(|parent| failed[parent.0])
+                };
+                if !failed[id.0] {
+                    ready.push(id);
+                }
+            }
 
-                // 如果是ReverseX，需要反转位置
-                if matches!(flex_direction, FlexDirection::ReverseX) {
-                    positions.reverse();
+            let layout = self.layout();
+            let results: Vec<Result<T>> = if ready.len() >= PARALLEL_MIN_LEVEL_WIDTH {
+                ready.par_iter().map(|&id| compute(&layout, id)).collect()
+            } else {
+                ready.iter().map(|&id| compute(&layout, id)).collect()
+            };
+            for (id, result) in ready.into_iter().zip(results) {
+                match result {
+                    Ok(value) => apply(&mut self.nodes, id, value),
+                    Err(error) => {
+                        failed[id.0] = true;
+                        if first_error.as_ref().is_none_or(|(first, _)| rank[id.0] < *first) {
+                            first_error = Some((rank[id.0], error));
+                        }
+                    }
                 }
+            }
+        }
+        match first_error {
+            Some((_, error)) => Err(error),
+            None => Ok(()),
+        }
+    }
 
-                // 计算每个子元素的完整位置
-                for (i, child_x_pos) in positions.iter().enumerate() {
-                    let mut pos = Dim3::new(
-                        Length::from_mm(*child_x_pos as u32),
-                        Length::from_mm(0),
-                        Length::from_mm(0),
-                    );
+    /// 计算带外边距的子元素在主轴上的位置
+    ///
+    /// 与CSS一致：存在auto外边距时，剩余空间先平分给所有auto外边距，justify-content不再生效；
+    /// 否则按外边距盒的尺寸用justify-content分配，再偏移起始一侧的外边距。
+    /// 相邻子元素之间至少间隔gap
+    fn calculate_main_axis_with_margins(
+        container: i64,
+        child_sizes: &[i64],
+        margins: &[(MarginValue, MarginValue)],
+        gap: i64,
+        justify_content: &style::JustifyContent,
+    ) -> Vec<i64> {
+        let outer_sizes: Vec<i64> = child_sizes
+            .iter()
+            .zip(margins)
+            .map(|(size, (min, max))| size + min.fixed_mm() + max.fixed_mm())
+            .collect();
+        let free_space = container - outer_sizes.iter().sum::<i64>();
 
-                    // 根据align-items计算Y和Z轴位置
-                    if i < child_lengths.len() {
-                        let child_size = child_lengths[i];
-
-                        // 计算Y轴位置（第一个交叉轴）
-                        pos.y = match align_items.cross1 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.y.mm() - child_size.y.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.y.mm() - child_size.y.mm()) / 2) as u32,
-                            ),
-                        };
-
-                        // 计算Z轴位置（第二个交叉轴）
-                        pos.z = match align_items.cross2 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.z.mm() - child_size.z.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.z.mm() - child_size.z.mm()) / 2) as u32,
-                            ),
-                        };
-                    }
+        let auto_count = margins
+            .iter()
+            .map(|(min, max)| min.is_auto() as usize + max.is_auto() as usize)
+            .sum::<usize>();
+        if auto_count == 0 {
+            return Self::calculate_positions_on_axis(free_space, &outer_sizes, gap, justify_content)
+                .iter()
+                .zip(margins)
+                .map(|(pos, (min, _))| pos + min.fixed_mm())
+                .collect();
+        }
 
-                    child_positions.push(pos);
-                }
+        // 空间不足时auto外边距为0
+        let free_space = free_space - gap * gap_count(child_sizes.len());
+        let mut shares = distribute_largest_remainder(free_space.max(0), &vec![1; auto_count])
+            .into_iter();
+        let mut resolve = |margin: &MarginValue| match margin {
+            MarginValue::Auto => shares.next().unwrap_or(0),
+            MarginValue::Length(length) => length.mm() as i64,
+        };
+        let mut positions = Vec::with_capacity(child_sizes.len());
+        let mut pos = 0;
+        for (size, (min, max)) in child_sizes.iter().zip(margins) {
+            pos += resolve(min);
+            positions.push(pos);
+            pos += size + resolve(max) + gap;
+        }
+        positions
+    }
+
+    /// 根据可用空间和子元素尺寸计算在主轴上的位置
+    ///
+    /// 全部使用整数毫米计算：free_space按justify-content的权重拆分到各个间隔上，
+    /// 除不尽的毫米用最大余数法分配，保证子元素尺寸与间隔之和恰好等于容器尺寸。
+    /// 相邻子元素之间先留出gap，再分配剩余的空间，所以gap是最小间距。
+    /// 空间不足时（只在overflow:clamp时出现）不论justify-content都从起点排列，
+    /// 位置不会是负数，超出部分留在容器末尾之外
+    fn calculate_positions_on_axis(
+        free_space: i64,
+        child_sizes: &[i64],
+        gap: i64,
+        justify_content: &style::JustifyContent,
+    ) -> Vec<i64> {
+        let free_space = (free_space - gap * gap_count(child_sizes.len())).max(0);
+        let weights = justify_content.gap_weights(child_sizes.len());
+        let shares = distribute_largest_remainder(free_space, &weights);
+
+        let mut positions = Vec::with_capacity(child_sizes.len());
+        let mut pos = 0;
+        for (i, (&size, &share)) in child_sizes.iter().zip(&shares).enumerate() {
+            if i > 0 {
+                pos += gap;
             }
-            FlexDirection::Y | FlexDirection::ReverseY => {
-                // 主轴是Y轴，交叉轴是X和Z
-                let free_space = node_length.y.mm() as f64 - total_child_size.y.mm() as f64;
-                let mut positions = self.calculate_positions_on_axis(
-                    free_space,
-                    &child_lengths
-                        .iter()
-                        .map(|dim| dim.y.mm() as f64)
-                        .collect::<Vec<_>>(),
-                    justify_content,
-                );
+            pos += share;
+            positions.push(pos);
+            pos += size;
+        }
 
-                // 如果是ReverseY，需要反转位置
-                if matches!(flex_direction, FlexDirection::ReverseY) {
-                    positions.reverse();
-                }
+        positions
+    }
 
-                // 计算每个子元素的完整位置
-                for (i, child_y_pos) in positions.iter().enumerate() {
-                    let mut pos = Dim3::new(
-                        Length::from_m(0.0),
-                        Length::from_mm(*child_y_pos as u32),
-                        Length::from_m(0.0),
-                    );
+    pub fn print_computed(&self) {
+        let body = self.find_body_node().unwrap();
+        print_render_tree_computed(self, body, 0);
+    }
+}
 
-                    // 根据align-items计算X和Z轴位置
-                    if i < child_lengths.len() {
-                        let child_size = child_lengths[i];
-
-                        // 计算X轴位置（第一个交叉轴）
-                        pos.x = match align_items.cross1 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.x.mm() - child_size.x.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.x.mm() - child_size.x.mm()) / 2) as u32,
-                            ),
-                        };
-
-                        // 计算Z轴位置（第二个交叉轴）
-                        pos.z = match align_items.cross2 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.z.mm() - child_size.z.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.z.mm() - child_size.z.mm()) / 2) as u32,
-                            ),
-                        };
-                    }
+/// 布局计算中只读的部分：节点、package、seed和采样方式，可以在线程之间共享
+#[derive(Clone, Copy)]
+struct Layout<'t> {
+    nodes: &'t [RenderNode],
+    package: &'t Package,
+    seed: u64,
+    placement: &'t dyn PlacementStrategy,
+    placement_attempts: usize,
+}
+
+impl<'t> Layout<'t> {
+    fn node(&self, id: NodeId) -> &'t RenderNode {
+        &self.nodes[id.0]
+    }
+
+    /// 节点的随机数生成器，由seed和节点在树中的路径决定，和计算顺序无关
+    fn node_rng(&self, id: NodeId) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ self.node(id).path_hash)
+    }
+
+    fn cal_flex_child_size(&self, id: NodeId) -> Result<SpaceSize> {
+        let node_ref = self.node(id);
+        let mut child_total_size = node_ref.specified_style.size.create_self_by_auto_to_zero();
 
-                    child_positions.push(pos);
+        let children = &node_ref.children;
+        let flex_direction = node_ref.specified_style.flex_direction.clone();
+        let (main_axis, cross1_axis, _) = flex_axes(&flex_direction);
+
+        // 长度之和超出u32毫米范围时报错，饱和的结果会让布局悄悄出错
+        let checked_add = |total: &SizeValue, other: &SizeValue, axis: usize| {
+            total.checked_add(other).ok_or_else(|| RsmlError::StyleComputation {
+                message: format!(
+                    "size of node({:?}) overflows on the {} axis",
+                    node_ref.id,
+                    ["x", "y", "z"][axis]
+                ),
+            })
+        };
+
+        // 每个轴上是否有子元素的尺寸已经是长度
+        let mut sized = [false; 3];
+        for child in children {
+            let child_ref = self.node(*child);
+            // 子元素按外边距盒参与计算
+            let margin = child_ref.specified_style.margin.fixed_sum();
+            let margin = [margin.x, margin.y, margin.z];
+            for axis in 0..3 {
+                let child_size = size_axis(&child_ref.computed_style.size, axis);
+                sized[axis] |= child_size.is_length();
+                let child_size = checked_add(child_size, &SizeValue::Length(margin[axis]), axis)?;
+                let total = size_axis_mut(&mut child_total_size, axis);
+                if axis == main_axis {
+                    *total = checked_add(total, &child_size, axis)?;
+                } else {
+                    total.max(&child_size);
                 }
             }
-            FlexDirection::Z | FlexDirection::ReverseZ => {
-                // 主轴是Z轴，交叉轴是X和Y
-                let free_space = node_length.z.mm() as f64 - total_child_size.z.mm() as f64;
-                let mut positions = self.calculate_positions_on_axis(
-                    free_space,
-                    &child_lengths
-                        .iter()
-                        .map(|dim| dim.z.mm() as f64)
-                        .collect::<Vec<_>>(),
-                    justify_content,
-                );
+        }
 
-                // 如果是ReverseZ，需要反转位置
-                if matches!(flex_direction, FlexDirection::ReverseZ) {
-                    positions.reverse();
-                }
+        // 没有任何子元素决定的auto轴保持auto，由check_resolved_size报告，而不是悄悄变成0
+        for (axis, sized) in sized.into_iter().enumerate() {
+            if !sized && *size_axis(&node_ref.specified_style.size, axis) == SizeValue::Auto {
+                *size_axis_mut(&mut child_total_size, axis) = SizeValue::Auto;
+            }
+        }
 
-                // 计算每个子元素的完整位置
-                for (i, child_z_pos) in positions.iter().enumerate() {
-                    let mut pos = Dim3::new(
-                        Length::from_m(0.0),
-                        Length::from_m(0.0),
-                        Length::from_mm(*child_z_pos as u32),
-                    );
+        // 相邻子元素之间的gap计入主轴
+        let gap = SizeValue::Length(node_ref.specified_style.gap * gap_count(children.len()) as u32);
+        let total = size_axis_mut(&mut child_total_size, main_axis);
+        *total = checked_add(total, &gap, main_axis)?;
 
-                    // 根据align-items计算X和Y轴位置
-                    if i < child_lengths.len() {
-                        let child_size = child_lengths[i];
-
-                        // 计算X轴位置（第一个交叉轴）
-                        pos.x = match align_items.cross1 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.x.mm() - child_size.x.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.x.mm() - child_size.x.mm()) / 2) as u32,
-                            ),
-                        };
-
-                        // 计算Y轴位置（第二个交叉轴）
-                        pos.y = match align_items.cross2 {
-                            style::AlignItem::FlexStart => Length::from_m(0.0),
-                            style::AlignItem::FlexEnd => {
-                                Length::from_mm((node_length.y.mm() - child_size.y.mm()) as u32)
-                            }
-                            style::AlignItem::Center => Length::from_mm(
-                                ((node_length.y.mm() - child_size.y.mm()) / 2) as u32,
-                            ),
-                        };
-                    }
+        // 换行时第一个交叉轴的尺寸是各行高度之和，需要主轴尺寸已经确定
+        let content = content_size(&node_ref.computed_style.size, &node_ref.specified_style.padding);
+        let outer_sizes: Option<Vec<[i64; 3]>> = children.iter().map(|child| outer_mm(self.node(*child))).collect();
+        if let (FlexWrap::Wrap, SizeValue::Length(container), Some(outer_sizes)) = (
+            &node_ref.specified_style.flex_wrap,
+            size_axis(&content, main_axis),
+            outer_sizes,
+        ) {
+            let gap = node_ref.specified_style.gap.mm() as i64;
+            let lines = flex_lines(
+                container.mm() as i64,
+                &outer_sizes.iter().map(|size| size[main_axis]).collect::<Vec<_>>(),
+                &self.flex_order(children),
+                gap,
+            );
+            let line_heights: i64 = lines
+                .iter()
+                .map(|line| line.iter().map(|&i| outer_sizes[i][cross1_axis]).max().unwrap_or(0))
+                .sum();
+            let cross1 = size_axis_mut(&mut child_total_size, cross1_axis);
+            if cross1.is_length() {
+                *cross1 = SizeValue::Length(mm_to_length(line_heights + gap * gap_count(lines.len())));
+            }
+        }
 
-                    child_positions.push(pos);
-                }
+        // 内边距包在子元素外面
+        let padding = node_ref.specified_style.padding.total();
+        for (axis, padding) in [padding.x, padding.y, padding.z].into_iter().enumerate() {
+            let total = size_axis_mut(&mut child_total_size, axis);
+            *total = checked_add(total, &SizeValue::Length(padding), axis)?;
+        }
+
+        // 指定了长度的轴不由子元素决定
+        let specified = &node_ref.specified_style.size;
+        for axis in 0..3 {
+            if let SizeValue::Length(length) = size_axis(specified, axis) {
+                *size_axis_mut(&mut child_total_size, axis) = SizeValue::Length(*length);
             }
         }
-        let base_pos = node_ref.computed_style.position.clone();
-        // 更新子元素的位置
-        for (i, child) in node_ref.children.iter().enumerate() {
-            if i < child_positions.len() {
-                let mut pos = style::SpacePosition::from_dim3(child_positions[i]);
-                pos.add(&base_pos);
-                child.borrow_mut().computed_style.position = pos;
+
+        Ok(child_total_size)
+    }
+
+    /// Set the object in the computed style for Item nodes
+    fn set_computed_object(&self, id: NodeId, computed: &mut style::ComputedStyle) -> Result<()> {
+        let node_ref = self.node(id);
+        // Clone the text_content to avoid borrowing issues
+        let name = node_ref.text_content.clone();
+        
+        // Set the object in the computed style
+        // First check if it's a direct object
+        if let Some(object) = self.package.objects.get(&name) {
+            computed.object = Some(object.clone());
+        } else if let Some(group) = self
+            .package
+            .groups
+            .iter()
+            .find(|g| g.name == name && !g.layout.is_empty())
+        {
+            // 组合物体：展开为layout中的所有成员，忽略select
+            computed.members = group.layout_members()?;
+        } else {
+            // 普通组：按pick选择一个成员，嵌套组的物体也参与选择，按名字排序
+            if let Some(pick) = &node_ref.pick
+                && self.package.groups.iter().any(|g| g.name == name)
+            {
+                let members = self.package.group_objects(&name)?;
+                let object = pick.choose(&name, &members, &mut self.node_rng(id))?;
+                computed.object = object.cloned();
             }
         }
 
         Ok(())
     }
 
-    /// 根据可用空间和子元素尺寸计算在主轴上的位置
-    fn calculate_positions_on_axis(
-        &self,
-        free_space: f64,
-        child_sizes: &[f64],
-        justify_content: &style::JustifyContent,
-    ) -> Vec<f64> {
-        let mut positions = Vec::new();
-
-        match justify_content {
-            style::JustifyContent::FlexStart => {
-                // 从起始位置开始排列
-                let mut pos = 0.0;
-                for &size in child_sizes {
-                    positions.push(pos);
-                    pos += size;
+    /// 按子节点计算的样式：item的尺寸和物体来自package，flex空间的auto尺寸由子节点决定
+    fn size_by_child(&self, id: NodeId) -> Result<style::ComputedStyle> {
+        let node_ref = self.node(id);
+        let span = node_ref.span.clone();
+        let mut computed = node_ref.computed_style.clone();
+
+        match &node_ref.node_type {
+            RenderNodeType::Item => {
+                let size = self
+                    .package
+                    .get_space_size(&node_ref.text_content)
+                    .map_err(|e| e.at(span.as_ref()))?;
+                let scale = node_ref.specified_style.scale;
+                computed.size = SpaceSize::from_dim3_length(scale.apply(size));
+
+                // Set the object in the computed style
+                self.set_computed_object(id, &mut computed).map_err(|e| e.at(span.as_ref()))?;
+                // 输出的物体和组合物体成员的偏移按同样的倍数缩放
+                if !scale.is_identity() {
+                    if let Some(object) = &mut computed.object {
+                        object.size = scale.apply(object.size);
+                    }
+                    for member in &mut computed.members {
+                        member.object.size = scale.apply(member.object.size);
+                        member.offset = scale.apply(member.offset);
+                    }
                 }
             }
-            style::JustifyContent::FlexEnd => {
-                // 从结束位置开始排列
-                let mut pos = free_space;
-                for &size in child_sizes {
-                    positions.push(pos);
-                    pos += size;
+            RenderNodeType::Space => match node_ref.specified_style.display {
+                style::Display::Flex => {
+                    if !computed.size.all_length() {
+                        computed.size.assign_priority(self.cal_flex_child_size(id)?);
+                    }
                 }
-            }
-            style::JustifyContent::Center => {
-                // 居中排列
-                let mut pos = free_space / 2.0;
-                for &size in child_sizes {
-                    positions.push(pos);
-                    pos += size;
+                style::Display::Cube => {
+                    if computed.size.has_auto() {
+                        return Err(RsmlError::CubeSizeError.at(span.as_ref()));
+                    }
                 }
+            },
+        }
+
+        if let Some(parent) = node_ref.parent {
+            let parent_ref = self.node(parent);
+            let parent_size = content_size(&parent_ref.computed_style.size, &parent_ref.specified_style.padding);
+            RenderTree::apply_aspect_ratio(node_ref, &mut computed.size)?;
+            RenderTree::clamp_size(node_ref, &mut computed.size, &parent_size)?;
+        }
+        Ok(computed)
+    }
+
+    /// `id`的子元素的位置
+    fn child_positions(&self, id: NodeId) -> Result<Vec<(NodeId, SpacePosition)>> {
+        let node_ref = self.node(id);
+        let span = node_ref.span.clone();
+
+        // 不会有auto，全是Length；子元素位置的错误报告在容器上
+        match node_ref.specified_style.display {
+            style::Display::Flex => {
+                // 计算Flex布局中子元素的位置
+                self.flex_child_positions(id).map_err(|e| e.at(span.as_ref()))
+            }
+            style::Display::Cube => {
+                self.cube_child_positions(id).map_err(|e| e.at(span.as_ref()))
             }
-            style::JustifyContent::SpaceBetween => {
-                // 两端对齐，项目间的间隔都相等
-                if child_sizes.len() > 1 {
-                    let spacing = free_space / (child_sizes.len() - 1) as f64;
-                    let mut pos = 0.0;
-                    for &size in child_sizes {
-                        positions.push(pos);
-                        pos += size + spacing;
+        }
+    }
+
+    /// 计算Cube布局中子元素的位置
+    ///
+    /// 每个轴按子元素的pos定位：min/max贴住容器的两侧，random在两侧之间随机，
+    /// 长度是相对于容器起点的偏移。未指定pos的轴无法确定位置，直接报错
+    fn cube_child_positions(&self, id: NodeId) -> Result<Vec<(NodeId, SpacePosition)>> {
+        let node_ref = &self.nodes[id.0];
+        let padding = node_ref.specified_style.padding;
+        let node_length = content_size(&node_ref.computed_style.size, &padding)
+            .get_length()
+            .ok_or(RsmlError::CubeSizeError)?;
+        let base_pos = node_ref.computed_style.position.get_length().unwrap_or_default() + padding.0;
+        let mut rng = self.node_rng(id);
+
+        // 先检查每个子元素的pos和尺寸，不是random的轴直接确定
+        let mut children = Vec::with_capacity(node_ref.children.len());
+        for &child in &node_ref.children {
+            let child_ref = &self.nodes[child.0];
+            let child_size = child_ref.absolute_size()?;
+            let pos = child_ref.specified_style.position.clone();
+            let error = |message: String| RsmlError::StyleComputation {
+                message: format!(
+                    "child({:?}) of cube node({:?}) {}",
+                    child_ref.id, node_ref.id, message
+                ),
+            };
+
+            let axes = [
+                ("x", &pos.x, node_length.x, child_size.x),
+                ("y", &pos.y, node_length.y, child_size.y),
+                ("z", &pos.z, node_length.z, child_size.z),
+            ];
+            let mut axis_pos = Vec::with_capacity(3);
+            let mut offset = [Length::default(); 3];
+            for (i, (axis, value, container, size)) in axes.into_iter().enumerate() {
+                let value = value
+                    .axis_pos()
+                    .ok_or_else(|| error(format!("has no pos on the {} axis", axis)))?;
+                if size > container {
+                    return Err(error(format!(
+                        "is larger than the container on the {} axis ({} > {})",
+                        axis, size, container
+                    )));
+                }
+                let max = container - size;
+                if value != AxisPos::Random {
+                    // 不是random时不取随机数
+                    let resolved = value.absolute_pos(Length::default(), max, &mut rng);
+                    if resolved > max {
+                        return Err(error(format!(
+                            "at pos {} leaves the container on the {} axis by {}",
+                            value,
+                            axis,
+                            resolved - max
+                        )));
                     }
-                } else {
-                    // 只有一个元素时，居中显示
-                    positions.push(free_space / 2.0);
+                    offset[i] = resolved;
+                }
+                axis_pos.push(value);
+            }
+            let [x, y, z]: [AxisPos; 3] = axis_pos.try_into().expect("three axes");
+            children.push((child, child_size, Dim3::new(x, y, z), Dim3::new(offset[0], offset[1], offset[2])));
+        }
+
+        // 没有random轴的子元素先放置，其余的按文档顺序采样，与已放置的兄弟元素重叠时重新采样
+        let is_random = |pos: &Dim3<AxisPos>| [&pos.x, &pos.y, &pos.z].contains(&&AxisPos::Random);
+        let mut placed: Vec<(Dim3<Length>, Dim3<Length>)> = children
+            .iter()
+            .filter(|(_, _, pos, _)| !is_random(pos))
+            .map(|&(_, size, _, offset)| (offset, size))
+            .collect();
+        let mut offsets = Vec::with_capacity(children.len());
+        for (child, size, pos, fixed) in &children {
+            if !is_random(pos) {
+                offsets.push(*fixed);
+                continue;
+            }
+            let request = PlacementRequest {
+                container: node_length,
+                size: *size,
+                pos,
+                placed: &placed,
+                attempt: 0,
+            };
+            let offset = self.place_random_child(*child, request, *fixed, &mut rng)?;
+            placed.push((offset, *size));
+            offsets.push(offset);
+        }
+
+        Ok(children
+            .iter()
+            .zip(offsets)
+            .map(|(&(child, ..), offset)| (child, SpacePosition::from_dim3(base_pos + offset)))
+            .collect())
+    }
+
+    /// 用采样方式为有random轴的子元素找一个不与已放置的兄弟元素重叠的位置，其余的轴使用`fixed`中的值
+    fn place_random_child(
+        &self,
+        child: NodeId,
+        mut request: PlacementRequest<'_>,
+        fixed: Dim3<Length>,
+        rng: &mut StdRng,
+    ) -> Result<Dim3<Length>> {
+        let child_ref = self.node(child);
+        let (pos, size, max) = (request.pos, request.size, request.max_offset());
+        loop {
+            let proposal = self.placement.propose(&request, rng);
+            let choose = |pos: &AxisPos, proposed: Length, fixed: Length| match pos {
+                AxisPos::Random => proposed,
+                _ => fixed,
+            };
+            let offset = Dim3::new(
+                choose(&pos.x, proposal.x, fixed.x),
+                choose(&pos.y, proposal.y, fixed.y),
+                choose(&pos.z, proposal.z, fixed.z),
+            );
+            if !offset.fits_within(&max) {
+                return Err(RsmlError::StyleComputation {
+                    message: format!(
+                        "{} placement put {} outside its container at {}",
+                        self.placement.name(),
+                        child_ref.label(),
+                        offset
+                    ),
+                });
+            }
+            request.attempt += 1;
+            let overlaps = |&(other, other_size): &(Dim3<Length>, Dim3<Length>)| {
+                penetration((offset, offset + size), (other, other + other_size)).is_some()
+            };
+            if self.placement_attempts == 0 || !request.placed.iter().any(overlaps) {
+                return Ok(offset);
+            }
+            if request.attempt >= self.placement_attempts {
+                return Err(RsmlError::StyleComputation {
+                    message: format!(
+                        "could not place {} without overlap after {} attempts",
+                        child_ref.label(),
+                        request.attempt
+                    ),
+                });
+            }
+        }
+    }
+
+    /// 计算Flex布局中子元素的位置
+    fn flex_child_positions(&self, id: NodeId) -> Result<Vec<(NodeId, SpacePosition)>> {
+        let node_ref = &self.nodes[id.0];
+        let flex_direction = &node_ref.specified_style.flex_direction;
+        let justify_content = &node_ref.specified_style.justify_content;
+        let align_items = &node_ref.specified_style.align_items;
+        let padding = node_ref.specified_style.padding;
+        let node_size = &content_size(&node_ref.computed_style.size, &padding);
+        // 只有子元素指定了pos时才需要随机数
+        let mut rng = None;
+
+        // 获取节点内容盒的尺寸（转换为Length）
+        let node_length = node_size.get_length().ok_or(RsmlError::RenderTree {
+            message: format!("Unable to calculate the size of node({:?})", node_ref.id),
+        })?;
+
+        // 收集子元素的尺寸和外边距
+        let mut child_lengths = Vec::new();
+        let mut child_margins = Vec::new();
+        let mut child_pos = Vec::new();
+        let mut child_align = Vec::new();
+        for child in &node_ref.children {
+            let child_ref = self.node(*child);
+            let child_size = child_ref.absolute_size()?;
+            child_lengths.push(child_size);
+            child_margins.push(child_ref.specified_style.margin);
+            child_pos.push(child_ref.specified_style.position.clone());
+            child_align.push(child_ref.specified_style.align_self.clone());
+        }
+
+        // 主轴和两个交叉轴（0:x 1:y 2:z）
+        let (main_axis, cross1_axis, cross2_axis) = flex_axes(flex_direction);
+        let node_mm = dim3_mm(&node_length);
+        let child_mm: Vec<[i64; 3]> = child_lengths.iter().map(dim3_mm).collect();
+        let margins: Vec<[(MarginValue, MarginValue); 3]> =
+            child_margins.iter().map(margin_sides).collect();
+        let outer = |i: usize, axis: usize| {
+            let (min, max) = margins[i][axis];
+            child_mm[i][axis] + min.fixed_mm() + max.fixed_mm()
+        };
+        let gap = node_ref.specified_style.gap.mm() as i64;
+
+        // 子元素按order排列；换行时按主轴尺寸把子元素分成多行，否则所有子元素在同一行
+        let order = self.flex_order(&node_ref.children);
+        let wrap = node_ref.specified_style.flex_wrap == FlexWrap::Wrap;
+        let lines = if wrap {
+            flex_lines(
+                node_mm[main_axis],
+                &(0..child_mm.len()).map(|i| outer(i, main_axis)).collect::<Vec<_>>(),
+                &order,
+                gap,
+            )
+        } else {
+            vec![order]
+        };
+
+        if node_ref.specified_style.overflow == style::Overflow::Error {
+            self.check_overflow(node_ref, &node_mm, &child_mm, &margins, &lines, wrap)?;
+        }
+
+        // 主轴位置，以及每个子元素所在行在第一个交叉轴上的起点和高度
+        let mut main_positions = vec![0; child_mm.len()];
+        let mut line_ranges = vec![(0, node_mm[cross1_axis]); child_mm.len()];
+        let mut line_start = 0;
+        // 反向排列时主轴的起点在max一侧：在镜像的坐标中正向排列（两侧的外边距交换），再镜像回来
+        let reverse = matches!(
+            flex_direction,
+            FlexDirection::ReverseX | FlexDirection::ReverseY | FlexDirection::ReverseZ
+        );
+        for line in &lines {
+            let sizes: Vec<i64> = line.iter().map(|&i| child_mm[i][main_axis]).collect();
+            let line_margins: Vec<(MarginValue, MarginValue)> = line
+                .iter()
+                .map(|&i| match margins[i][main_axis] {
+                    (min, max) if reverse => (max, min),
+                    sides => sides,
+                })
+                .collect();
+            let mut positions =
+                RenderTree::calculate_main_axis_with_margins(node_mm[main_axis], &sizes, &line_margins, gap, justify_content);
+            if reverse {
+                // 超出容器时以这一行的末端镜像，和正向一样从容器的min一侧开始超出
+                let line_end = positions
+                    .iter()
+                    .zip(&sizes)
+                    .zip(&line_margins)
+                    .map(|((position, size), (_, max))| position + size + max.fixed_mm())
+                    .fold(node_mm[main_axis], i64::max);
+                for (position, size) in positions.iter_mut().zip(&sizes) {
+                    *position = line_end - size - *position;
                 }
             }
-            style::JustifyContent::SpaceAround => {
-                // 每个项目两侧的间隔相等
-                let spacing = free_space / child_sizes.len() as f64;
-                let mut pos = spacing / 2.0;
-                for &size in child_sizes {
-                    positions.push(pos);
-                    pos += size + spacing;
+            let line_height = if wrap {
+                line.iter().map(|&i| outer(i, cross1_axis)).max().unwrap_or(0)
+            } else {
+                node_mm[cross1_axis]
+            };
+            for (&i, position) in line.iter().zip(positions) {
+                main_positions[i] = position;
+                line_ranges[i] = (line_start, line_height);
+            }
+            line_start += line_height + gap;
+        }
+
+        // 交叉轴位置由子元素的align-self或父空间的align-items决定，auto外边距优先；
+        // 换行时第一个交叉轴在行内对齐
+        let mut child_positions = Vec::new();
+        for (i, main_pos) in main_positions.iter().enumerate() {
+            let align_items = child_align[i].as_ref().unwrap_or(align_items);
+            let mut pos = [0i64; 3];
+            pos[main_axis] = *main_pos;
+            let (line_start, line_height) = line_ranges[i];
+            pos[cross1_axis] = line_start
+                + cross_axis_position(line_height, child_mm[i][cross1_axis], margins[i][cross1_axis], &align_items.cross1);
+            pos[cross2_axis] = cross_axis_position(
+                node_mm[cross2_axis],
+                child_mm[i][cross2_axis],
+                margins[i][cross2_axis],
+                &align_items.cross2,
+            );
+            // pos中指定的轴覆盖flex计算的位置，auto的轴保持不变；
+            // min/max/random在容器尺寸减去子元素尺寸的范围内取值
+            let explicit = [&child_pos[i].x, &child_pos[i].y, &child_pos[i].z];
+            for (axis, value) in explicit.into_iter().enumerate() {
+                if let Some(axis_pos) = value.axis_pos() {
+                    let range = Length::from_mm((node_mm[axis] - child_mm[i][axis]).max(0) as u32);
+                    let rng = rng.get_or_insert_with(|| self.node_rng(id));
+                    let resolved = axis_pos.absolute_pos(Length::default(), range, rng);
+                    pos[axis] = resolved.mm() as i64;
                 }
             }
-            style::JustifyContent::SpaceEvenly => {
-                // 每个项目周围分配相等的空间
-                let spacing = free_space / (child_sizes.len() + 1) as f64;
-                let mut pos = spacing;
-                for &size in child_sizes {
-                    positions.push(pos);
-                    pos += size + spacing;
+            child_positions.push(Dim3::new(pos[0], pos[1], pos[2]).map(mm_to_length));
+        }
+        let mut base_pos = node_ref.computed_style.position.clone();
+        base_pos.add(&SpacePosition::from_dim3(padding.0));
+        let positions = node_ref.children.iter().zip(child_positions).map(|(&child, position)| {
+            let mut pos = style::SpacePosition::from_dim3(position);
+            pos.add(&base_pos);
+            (child, pos)
+        });
+        Ok(positions.collect())
+    }
+
+    /// 子元素（含外边距）在主轴上每一行的总长度或在交叉轴上的长度超出容器时报错；
+    /// 换行时第一个交叉轴检查所有行的总高度
+    fn check_overflow(
+        &self,
+        node_ref: &RenderNode,
+        node_mm: &[i64; 3],
+        child_mm: &[[i64; 3]],
+        margins: &[[(MarginValue, MarginValue); 3]],
+        lines: &[Vec<usize>],
+        wrap: bool,
+    ) -> Result<()> {
+        let (main_axis, cross1_axis, _) = flex_axes(&node_ref.specified_style.flex_direction);
+        let gap = node_ref.specified_style.gap.mm() as i64;
+        let outer = |i: usize, axis: usize| {
+            let (min, max) = margins[i][axis];
+            child_mm[i][axis] + min.fixed_mm() + max.fixed_mm()
+        };
+        let overflow = |axis: usize, excess: i64, what: String| RsmlError::StyleComputation {
+            message: format!(
+                "{} overflow {} on the {} axis by {} (set overflow:clamp to allow)",
+                what,
+                node_ref.label(),
+                ["x", "y", "z"][axis],
+                Length::from_mm(excess as u32)
+            ),
+        };
+
+        for line in lines {
+            let total: i64 =
+                line.iter().map(|&i| outer(i, main_axis)).sum::<i64>() + gap * gap_count(line.len());
+            if total > node_mm[main_axis] {
+                return Err(overflow(
+                    main_axis,
+                    total - node_mm[main_axis],
+                    "children".to_string(),
+                ));
+            }
+        }
+        if wrap {
+            let total: i64 = lines
+                .iter()
+                .map(|line| line.iter().map(|&i| outer(i, cross1_axis)).max().unwrap_or(0))
+                .sum::<i64>()
+                + gap * gap_count(lines.len());
+            if total > node_mm[cross1_axis] {
+                return Err(overflow(
+                    cross1_axis,
+                    total - node_mm[cross1_axis],
+                    "lines".to_string(),
+                ));
+            }
+        }
+        for (i, child) in node_ref.children.iter().enumerate() {
+            for axis in (0..3).filter(|axis| *axis != main_axis && !(wrap && *axis == cross1_axis)) {
+                if outer(i, axis) > node_mm[axis] {
+                    return Err(overflow(
+                        axis,
+                        outer(i, axis) - node_mm[axis],
+                        format!("child({:?})", self.node(*child).id),
+                    ));
                 }
             }
         }
+        Ok(())
+    }
 
-        positions
+    /// flex布局中子元素的排列顺序：按(order, 文档顺序)排序后的下标
+    fn flex_order(&self, children: &[NodeId]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..children.len()).collect();
+        order.sort_by_key(|&i| (self.node(children[i]).specified_style.order, i));
+        order
     }
+}
 
-    pub fn print_computed(&self) {
-        let body = self.find_body_node(&self.root).unwrap();
-        print_render_tree_computed(&body, 0);
+/// 子节点路径的哈希：父节点路径的哈希加上在父节点中的序号（splitmix64），在所有平台上相同
+fn path_hash(parent: u64, index: usize) -> u64 {
+    let mut hash = parent ^ (index as u64).wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// 按权重把total毫米分配到各个槽位（最大余数法）
+///
+/// 先按比例向下取整，剩余的毫米依次分给余数最大的槽位（余数相同时靠前的优先），
+/// 返回值之和恰好等于total；total为负数时同样成立
+fn distribute_largest_remainder(total: i64, weights: &[i64]) -> Vec<i64> {
+    let weight_sum: i64 = weights.iter().sum();
+    if weight_sum == 0 {
+        return vec![0; weights.len()];
+    }
+
+    let mut shares = Vec::with_capacity(weights.len());
+    let mut remainders = Vec::with_capacity(weights.len());
+    for (i, &weight) in weights.iter().enumerate() {
+        let quota = total * weight;
+        shares.push(quota.div_euclid(weight_sum));
+        remainders.push((quota.rem_euclid(weight_sum), i));
+    }
+
+    let leftover = total - shares.iter().sum::<i64>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for &(_, i) in remainders.iter().take(leftover as usize) {
+        shares[i] += 1;
+    }
+
+    shares
+}
+
+/// 交叉轴上的位置：两侧都是auto时居中，只有一侧是auto时贴向另一侧，否则按align-items对齐
+fn cross_axis_position(
+    container: i64,
+    size: i64,
+    (min, max): (MarginValue, MarginValue),
+    align: &style::AlignItem,
+) -> i64 {
+    let align = match (min.is_auto(), max.is_auto()) {
+        (true, true) => &style::AlignItem::Center,
+        (true, false) => &style::AlignItem::FlexEnd,
+        (false, true) => &style::AlignItem::FlexStart,
+        (false, false) => align,
+    };
+    let (min, max) = (min.fixed_mm(), max.fixed_mm());
+    match align {
+        style::AlignItem::FlexStart => min,
+        style::AlignItem::FlexEnd => container - size - max,
+        style::AlignItem::Center => min + (container - min - max - size) / 2,
+    }
+}
+
+/// 扣除两侧内边距后的内容盒尺寸，auto和百分比保持不变
+fn content_size(size: &SpaceSize, padding: &Padding) -> SpaceSize {
+    let padding = padding.total();
+    let shrink = |value: &SizeValue, padding: Length| match value {
+        SizeValue::Length(length) => SizeValue::Length(mm_to_length(length.mm() as i64 - padding.mm() as i64)),
+        other => other.clone(),
+    };
+    SpaceSize::new(
+        shrink(&size.x, padding.x),
+        shrink(&size.y, padding.y),
+        shrink(&size.z, padding.z),
+    )
+}
+
+/// flex-direction对应的主轴和两个交叉轴（0:x 1:y 2:z）
+fn flex_axes(direction: &FlexDirection) -> (usize, usize, usize) {
+    match direction {
+        FlexDirection::X | FlexDirection::ReverseX => (0, 1, 2),
+        FlexDirection::Y | FlexDirection::ReverseY => (1, 0, 2),
+        FlexDirection::Z | FlexDirection::ReverseZ => (2, 0, 1),
+    }
+}
+
+/// 按主轴尺寸贪心地把子元素分成多行，返回每行子元素的下标
+///
+/// 子元素按sequence的顺序放置，当前行放不下下一个子元素时换行；单个子元素超过容器时独占一行
+fn flex_lines(container: i64, sizes: &[i64], sequence: &[usize], gap: i64) -> Vec<Vec<usize>> {
+    let mut lines: Vec<Vec<usize>> = Vec::new();
+    let mut used = 0;
+    for &i in sequence {
+        let size = sizes[i];
+        match lines.last_mut() {
+            Some(line) if used + gap + size <= container => {
+                line.push(i);
+                used += gap + size;
+            }
+            _ => {
+                lines.push(vec![i]);
+                used = size;
+            }
+        }
+    }
+    lines
+}
+
+/// 子元素外边距盒的尺寸，尺寸未确定时返回None
+fn outer_mm(node_ref: &RenderNode) -> Option<[i64; 3]> {
+    let size = dim3_mm(&node_ref.computed_style.size.get_length()?);
+    let margin = dim3_mm(&node_ref.specified_style.margin.fixed_sum());
+    Some([size[0] + margin[0], size[1] + margin[1], size[2] + margin[2]])
+}
+
+fn size_axis(size: &SpaceSize, axis: usize) -> &SizeValue {
+    match axis {
+        0 => &size.x,
+        1 => &size.y,
+        _ => &size.z,
+    }
+}
+
+fn size_axis_mut(size: &mut SpaceSize, axis: usize) -> &mut SizeValue {
+    match axis {
+        0 => &mut size.x,
+        1 => &mut size.y,
+        _ => &mut size.z,
     }
 }
 
+/// n个子元素之间gap的个数
+fn gap_count(children: usize) -> i64 {
+    children.saturating_sub(1) as i64
+}
+
+fn dim3_mm(dim: &Dim3<Length>) -> [i64; 3] {
+    let Dim3 { x, y, z } = dim.map(|length| length.mm() as i64);
+    [x, y, z]
+}
+
+/// 按轴排列的 (min, max) 外边距
+fn margin_sides(margin: &Margin) -> [(MarginValue, MarginValue); 3] {
+    [
+        (margin.min.x, margin.max.x),
+        (margin.min.y, margin.max.y),
+        (margin.min.z, margin.max.z),
+    ]
+}
+
+/// 将主轴上的毫米坐标转换为Length，超出容器起点的负值会被截断为0
+fn mm_to_length(mm: i64) -> Length {
+    Length::from_mm(mm.clamp(0, u32::MAX as i64) as u32)
+}
+
 /// 根据标签名确定节点类型
 fn determine_node_type(tag_name: &str) -> RenderNodeType {
     match tag_name.to_lowercase().as_str() {
@@ -713,27 +2129,46 @@ fn determine_node_type(tag_name: &str) -> RenderNodeType {
     }
 }
 
-/// 打印渲染树
-pub fn print_render_tree_computed(node: &Rc<RefCell<RenderNode>>, depth: usize) {
-    let node_ref = node.borrow();
-    let indent = "  ".repeat(depth);
+/// `RenderTree::iter`的先序遍历迭代器
+pub struct Nodes<'t> {
+    nodes: &'t [RenderNode],
+    /// 待访问的节点，栈顶是下一个节点
+    stack: Vec<NodeId>,
+}
 
-    print!("{}{}", indent, node_ref.tag_name);
+impl Iterator for Nodes<'_> {
+    type Item = NodeId;
 
-    if let Some(id) = &node_ref.id {
-        print!(" #{}", id);
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(self.nodes[node.0].children.iter().rev());
+        Some(node)
     }
+}
 
-    if !node_ref.text_content.is_empty() {
-        print!(" {}", node_ref.text_content);
-    }
+/// 打印渲染树
+pub fn print_render_tree_computed(render_tree: &RenderTree, node: NodeId, depth: usize) {
+    // 先序遍历，栈顶是下一个打印的节点
+    let mut stack = vec![(node, depth)];
+    while let Some((node, depth)) = stack.pop() {
+        let node_ref = render_tree.node(node);
+        let indent = "  ".repeat(depth);
+
+        print!("{}{}", indent, node_ref.tag_name);
 
-    println!();
+        if let Some(id) = &node_ref.id {
+            print!(" #{}", id);
+        }
+
+        if !node_ref.text_content.is_empty() {
+            print!(" {}", node_ref.text_content);
+        }
+
+        println!();
 
-    print_computed_style_info(&node_ref, depth + 1);
+        print_computed_style_info(node_ref, depth + 1);
 
-    for child in &node_ref.children {
-        print_render_tree_computed(child, depth + 1);
+        stack.extend(node_ref.children.iter().rev().map(|&child| (child, depth + 1)));
     }
 }
 
@@ -755,6 +2190,7 @@ fn print_computed_style_info(node_ref: &RenderNode, depth: usize) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scene::Builder;
     use crate::xml_parser::Element as DomElement;
 
     #[test]
@@ -793,12 +2229,1932 @@ mod tests {
 
         let package = Package::from_file("package.toml").unwrap();
         let render_tree = RenderTree::new(&element, &package).unwrap();
-        let node = render_tree.root.borrow();
+        let node = render_tree.node(render_tree.root);
 
         assert_eq!(node.tag_name, "space");
         assert_eq!(node.id, Some("main".to_string()));
         assert_eq!(node.node_type, RenderNodeType::Space);
     }
 
-    
+    #[test]
+    fn test_diagnostics_severity_config() {
+        use crate::diagnostics::SeverityConfig;
+
+        let mut element = DomElement::new("space".to_string());
+        element
+            .attributes
+            .insert("style".to_string(), "display:flex;colour:red".to_string());
+        let package = Package::from_file("package.toml").unwrap();
+
+        let render_tree = RenderTree::new(&element, &package).unwrap();
+        assert_eq!(render_tree.diagnostics.iter().count(), 1);
+        assert!(!render_tree.diagnostics.has_errors());
+
+        let config = SeverityConfig::from_toml_str("[diagnostics]\nW0110 = \"error\"").unwrap();
+        let render_tree =
+            RenderTree::with_diagnostics(&element, &package, Diagnostics::new(config)).unwrap();
+        assert!(render_tree.diagnostics.has_errors());
+
+        let config = SeverityConfig::from_toml_str("[diagnostics]\nW0110 = \"allow\"").unwrap();
+        let render_tree =
+            RenderTree::with_diagnostics(&element, &package, Diagnostics::new(config)).unwrap();
+        assert_eq!(render_tree.diagnostics.iter().count(), 0);
+        assert_eq!(render_tree.diagnostics.count(crate::diagnostics::Severity::Allow), 1);
+
+        let mut config = SeverityConfig::default();
+        config.deny_warnings = true;
+        let render_tree =
+            RenderTree::with_diagnostics(&element, &package, Diagnostics::new(config)).unwrap();
+        assert!(render_tree.diagnostics.has_errors());
+    }
+
+    #[test]
+    fn test_unknown_style_property_diagnostics() {
+        let mut element = DomElement::new("space".to_string());
+        element.attributes.insert("id".to_string(), "shelf".to_string());
+        element
+            .attributes
+            .insert("style".to_string(), "display:flex; colour:red".to_string());
+        let package = Package::from_file("package.toml").unwrap();
+
+        let render_tree = RenderTree::new(&element, &package).unwrap();
+        let diagnostics: Vec<_> = render_tree.diagnostics.iter().collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostics::UNKNOWN_STYLE_PROPERTY);
+        assert_eq!(diagnostics[0].element.as_deref(), Some("space#shelf"));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning[W0110]: space#shelf: unknown style property 'colour' in declaration 'colour:red'"
+        );
+
+        // 严格模式下未知属性是error，即使配置中允许了它
+        let config =
+            crate::diagnostics::SeverityConfig::from_toml_str("[diagnostics]\nW0110 = \"allow\"")
+                .unwrap();
+        let options = LayoutOptions {
+            strict_styles: true,
+            ..Default::default()
+        };
+        let render_tree =
+            RenderTree::with_options(&element, &package, Diagnostics::new(config), &options)
+                .unwrap();
+        assert_eq!(render_tree.diagnostics.count(Severity::Error), 1);
+    }
+
+    #[test]
+    fn test_dependency_size_limit_is_checked_after_sizing() {
+        // cutlery依赖已经解析为同名的组，knife比size-limit高2cm
+        let package = Package::from_toml_str(
+            r#"
+            objects = {}
+            [package]
+            name = "kitchen"
+            description = ""
+
+            [[groups]]
+            name = "cutlery"
+            [groups.objects]
+            fork = { size = "2cm 3cm 9cm" }
+            knife = { size = "25mm 4cm 12cm" }
+
+            [[groups]]
+            name = "forks"
+            [groups.objects]
+            fork = { size = "2cm 3cm 9cm" }
+
+            [dependencies]
+            cutlery = { version = "1.0", size-limit = "30cm 30cm 10cm" }
+            forks = { version = "1.0", size-limit = "30cm 30cm 10cm" }
+            "#,
+        )
+        .unwrap();
+        let scene = |name: &str| {
+            let mut item = DomElement::new("group".to_string());
+            item.attributes.insert("id".to_string(), "drawer".to_string());
+            item.attributes.insert("select".to_string(), "first".to_string());
+            item.text = name.to_string();
+            let mut space = DomElement::new("space".to_string());
+            space.children.push(item);
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(space);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        let mut render_tree = RenderTree::new(&scene("forks"), &package).unwrap();
+        render_tree.calculate().unwrap();
+        assert_eq!(render_tree.diagnostics.iter().count(), 0);
+
+        // 默认只警告，布局照常完成
+        let mut render_tree = RenderTree::new(&scene("cutlery"), &package).unwrap();
+        render_tree.calculate().unwrap();
+        let diagnostics: Vec<_> = render_tree.diagnostics.iter().collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostics::OVERSIZED_ASSET);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning[W0150]: group#drawer: dependency 'cutlery' is placed at 25mm x 40mm x 120mm, \
+             larger than its size-limit of 300mm x 300mm x 100mm"
+        );
+
+        // 严格模式下是error
+        let options = LayoutOptions {
+            strict_styles: true,
+            ..Default::default()
+        };
+        let mut render_tree =
+            RenderTree::with_options(&scene("cutlery"), &package, Diagnostics::default(), &options).unwrap();
+        let Err(RsmlError::DiagnosticErrors(diagnostics)) = render_tree.calculate() else {
+            panic!("oversized dependency should fail in strict mode");
+        };
+        assert_eq!(diagnostics.count(Severity::Error), 1);
+        assert!(diagnostics.iter().any(|diagnostic| diagnostic.code == diagnostics::OVERSIZED_ASSET));
+    }
+
+    #[test]
+    fn test_group_layout_expands_members() {
+        let package = Package::from_toml_str(
+            r#"
+            objects = {}
+            [package]
+            name = "furniture"
+            description = ""
+
+            [[groups]]
+            name = "table"
+            [groups.objects]
+            plane = { geom-type = "box", size = "1m 1m 10cm" }
+            leg = { geom-type = "box", size = "10cm 10cm 50cm" }
+            [[groups.layout]]
+            object = "plane"
+            at = "0 0 50cm"
+            [[groups.layout]]
+            object = "leg"
+            at = "0 0 0"
+            [[groups.layout]]
+            object = "leg"
+            at = "90cm 0 0"
+            [[groups.layout]]
+            object = "leg"
+            at = "0 90cm 0"
+            [[groups.layout]]
+            object = "leg"
+            at = "90cm 90cm 0"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            package.get_space_size("table").unwrap(),
+            Dim3::new(Length::from_m(1.0), Length::from_m(1.0), Length::from_cm(60))
+        );
+
+        let mut table = DomElement::new("group".to_string());
+        table.attributes.insert("id".to_string(), "table".to_string());
+        table.text = "table".to_string();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert(
+            "style".to_string(),
+            "size:3m 3m 1m;flex-direction:x;justify-content:flex-end;align-items:flex-start flex-start".to_string(),
+        );
+        room.children.push(table);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let plan = render_tree.to_plan().unwrap();
+
+        let placed: Vec<(&str, [u32; 3])> = plan
+            .items
+            .iter()
+            .map(|item| (item.name.as_str(), item.position))
+            .collect();
+        // 桌子占据room中x方向最后1m的slot
+        assert_eq!(
+            placed,
+            vec![
+                ("table/plane_0", [2000, 0, 500]),
+                ("table/leg_1", [2000, 0, 0]),
+                ("table/leg_2", [2900, 0, 0]),
+                ("table/leg_3", [2000, 900, 0]),
+                ("table/leg_4", [2900, 900, 0]),
+            ]
+        );
+
+        let bad_layout = r#"
+            objects = {}
+            [package]
+            name = "broken"
+            description = ""
+            [[groups]]
+            name = "table"
+            [groups.objects]
+            plane = { geom-type = "box", size = "1m 1m 10cm" }
+            [[groups.layout]]
+            object = "leg"
+            at = "0 0 0"
+        "#;
+        assert!(Package::from_toml_str(bad_layout).is_err());
+    }
+
+    #[test]
+    fn test_default_align_from_world() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut leg = DomElement::new("object".to_string());
+        leg.attributes.insert("id".to_string(), "leg".to_string());
+        leg.text = "table_leg".to_string();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m".to_string());
+        room.children.push(leg);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let leg_position = |default_align: DefaultAlign| {
+            let options = LayoutOptions {
+                default_align,
+                ..Default::default()
+            };
+            let mut render_tree =
+                RenderTree::with_options(&root, &package, Diagnostics::default(), &options).unwrap();
+            render_tree.calculate().unwrap();
+            render_tree.to_plan().unwrap().items[0].position
+        };
+
+        // 新的默认值：水平方向居中，竖直方向仍然按z-reverse从上往下排列
+        let centered = leg_position(DefaultAlign::World);
+        let start = leg_position(DefaultAlign::FlexStart);
+        assert_eq!([centered[0], centered[1]], [450, 450]);
+        assert_eq!([start[0], start[1]], [0, 0]);
+        assert_eq!(centered[2], start[2]);
+    }
+
+    #[test]
+    fn test_distribute_largest_remainder() {
+        assert_eq!(distribute_largest_remainder(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(distribute_largest_remainder(11, &[1, 2, 1]), vec![3, 5, 3]);
+        assert_eq!(distribute_largest_remainder(-7, &[1, 1]), vec![-3, -4]);
+        assert_eq!(distribute_largest_remainder(5, &[0, 0]), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_positions_on_axis_sum_to_container() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        use style::JustifyContent;
+
+        let mut rng = StdRng::seed_from_u64(1460);
+        for _ in 0..500 {
+            let count = rng.random_range(1..12);
+            let child_sizes: Vec<i64> = (0..count).map(|_| rng.random_range(1..997)).collect();
+            let total: i64 = child_sizes.iter().sum();
+            let container = total + rng.random_range(0..5000);
+            let free_space = container - total;
+
+            for justify_content in [
+                JustifyContent::FlexStart,
+                JustifyContent::FlexEnd,
+                JustifyContent::Center,
+                JustifyContent::SpaceBetween,
+                JustifyContent::SpaceAround,
+                JustifyContent::SpaceEvenly,
+            ] {
+                let positions =
+                    RenderTree::calculate_positions_on_axis(free_space, &child_sizes, 0, &justify_content);
+                let last_end = positions[count - 1] + child_sizes[count - 1];
+                let gaps = distribute_largest_remainder(
+                    free_space,
+                    &justify_content.gap_weights(count),
+                );
+                assert_eq!(last_end + gaps[count], container);
+                if justify_content == JustifyContent::FlexStart {
+                    assert_eq!(positions[0], 0);
+                    assert_eq!(last_end, total);
+                }
+                if count > 1 && justify_content == JustifyContent::SpaceBetween {
+                    assert_eq!(last_end, container);
+                }
+                if justify_content == JustifyContent::SpaceEvenly {
+                    let min = gaps.iter().min().unwrap();
+                    let max = gaps.iter().max().unwrap();
+                    assert!(max - min <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_positions_on_axis_degenerate_inputs() {
+        use style::JustifyContent;
+
+        // 零尺寸容器、零个子元素：结果必须是确定的，而不是把所有东西都塌缩到原点
+        for justify_content in [JustifyContent::SpaceAround, JustifyContent::SpaceEvenly] {
+            assert!(RenderTree::calculate_positions_on_axis(0, &[], 0, &justify_content).is_empty());
+            assert!(RenderTree::calculate_positions_on_axis(500, &[], 0, &justify_content).is_empty());
+            assert_eq!(
+                RenderTree::calculate_positions_on_axis(0, &[0, 0], 0, &justify_content),
+                vec![0, 0]
+            );
+        }
+        // 空间不足时从起点排列，不会出现负的位置
+        for justify_content in [
+            JustifyContent::FlexStart,
+            JustifyContent::FlexEnd,
+            JustifyContent::Center,
+            JustifyContent::SpaceBetween,
+            JustifyContent::SpaceAround,
+            JustifyContent::SpaceEvenly,
+        ] {
+            assert_eq!(
+                RenderTree::calculate_positions_on_axis(-200, &[300, 300], 0, &justify_content),
+                vec![0, 300]
+            );
+            // gap仍然保留
+            assert_eq!(
+                RenderTree::calculate_positions_on_axis(-200, &[300, 300], 10, &justify_content),
+                vec![0, 310]
+            );
+            // 只有gap放不下时也一样
+            assert_eq!(
+                RenderTree::calculate_positions_on_axis(5, &[300, 300], 10, &justify_content),
+                vec![0, 310]
+            );
+        }
+    }
+
+    #[test]
+    fn test_overflow_with_every_justify_content() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 三个桌腿（1dm）放进x方向只有25cm的房间，gap为1cm，overflow:clamp
+        let positions = |style: &str| {
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert(
+                "style".to_string(),
+                format!("size:25cm 1m 1m;gap:1cm;overflow:clamp;align-items:flex-start flex-start;{}", style),
+            );
+            for _ in 0..3 {
+                let mut leg = DomElement::new("object".to_string());
+                leg.text = "table_leg".to_string();
+                room.children.push(leg);
+            }
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            let mut render_tree = RenderTree::new(&root, &package).unwrap();
+            render_tree.calculate().unwrap();
+            let items = render_tree.to_plan().unwrap().items;
+            items.iter().map(|item| item.position[0]).collect::<Vec<_>>()
+        };
+
+        for justify in ["flex-start", "flex-end", "center", "space-between", "space-around", "space-evenly"] {
+            // 从容器的起点开始，超出的5cm留在末尾之外
+            let forward = positions(&format!("flex-direction:x;justify-content:{}", justify));
+            assert_eq!(forward, vec![0, 110, 220], "{}", justify);
+            // 反向时第一个桌腿在末端，同样不会出现负的位置
+            let reverse = positions(&format!("flex-direction:x-reverse;justify-content:{}", justify));
+            assert_eq!(reverse, vec![220, 110, 0], "{}", justify);
+        }
+    }
+
+    #[test]
+    fn test_zero_size_container_layout() {
+        let package = Package::from_file("package.toml").unwrap();
+        let space = |id: &str, style: &str| {
+            let mut space = DomElement::new("space".to_string());
+            space.attributes.insert("id".to_string(), id.to_string());
+            space.attributes.insert("style".to_string(), style.to_string());
+            space
+        };
+
+        // 零尺寸容器，子元素是它的百分比以及一个没有子元素的空间
+        let mut empty = space(
+            "empty",
+            "size:0m 0m 0m;flex-direction:x;justify-content:space-around;align-items:flex-start flex-start",
+        );
+        empty.children.push(space("half", "size:50% 50% 50%"));
+        empty.children.push(space("none", "size:0m 0m 0m;justify-content:space-evenly"));
+        let mut leg = DomElement::new("object".to_string());
+        leg.attributes.insert("id".to_string(), "leg".to_string());
+        leg.text = "table_leg".to_string();
+        let mut room = space(
+            "room",
+            "size:1m 1m 1m;flex-direction:x;justify-content:space-around;align-items:flex-start flex-start",
+        );
+        room.children.push(empty);
+        room.children.push(leg);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let options = LayoutOptions {
+            lenient_nesting: true,
+            ..Default::default()
+        };
+        let mut render_tree = RenderTree::with_options(&root, &package, Diagnostics::default(), &options).unwrap();
+        render_tree.calculate().unwrap();
+
+        let computed = |id: &str| {
+            let node_ref = render_tree.node(render_tree.find_by_id(id).unwrap());
+            (
+                node_ref.computed_style.position.get_length().unwrap(),
+                node_ref.computed_style.size.get_length().unwrap(),
+            )
+        };
+
+        let (empty_pos, empty_size) = computed("empty");
+        assert_eq!(empty_size, Dim3::default());
+        for id in ["half", "none"] {
+            let (pos, size) = computed(id);
+            assert_eq!(size, Dim3::default());
+            assert_eq!(pos, empty_pos);
+        }
+
+        // 场景的其他部分不受影响：1m里放一个1dm的桌腿，space-around后在675mm处
+        let leg = render_tree.to_plan().unwrap().items[0].clone();
+        assert_eq!(leg.name, "leg");
+        assert_eq!(leg.position[0], 675);
+        assert_eq!(empty_pos.x, Length::from_mm(225));
+    }
+
+    /// 1m见方的房间，沿x排列若干个桌腿（1dm x 1dm x 5dm），返回每个桌腿的位置
+    fn margin_positions(room_style: &str, leg_styles: &[&str]) -> Vec<[u32; 3]> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert(
+            "style".to_string(),
+            format!("size:1m 1m 1m;flex-direction:x;align-items:flex-start flex-start;{}", room_style),
+        );
+        for (i, leg_style) in leg_styles.iter().enumerate() {
+            let mut leg = DomElement::new("object".to_string());
+            leg.attributes.insert("id".to_string(), format!("leg{}", i));
+            leg.attributes.insert("style".to_string(), leg_style.to_string());
+            leg.text = "table_leg".to_string();
+            room.children.push(leg);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        render_tree.to_plan().unwrap().items.iter().map(|item| item.position).collect()
+    }
+
+    #[test]
+    fn test_auto_margins_on_main_axis() {
+        // 起始一侧auto：贴到末尾
+        assert_eq!(margin_positions("", &["margin:auto 0 0 0 0 0"])[0][0], 900);
+        // 两侧auto：居中
+        assert_eq!(margin_positions("", &["margin:auto auto 0 0 0 0"])[0][0], 450);
+        // 两个子元素都是两侧auto：剩余800mm平分成4份，间隔为200 400 200
+        let positions = margin_positions("", &["margin:auto auto 0 0 0 0"; 2]);
+        assert_eq!([positions[0][0], positions[1][0]], [200, 700]);
+        // auto外边距优先于justify-content
+        let positions = margin_positions(
+            "justify-content:flex-end",
+            &["margin:0 auto 0 0 0 0", "margin:0"],
+        );
+        assert_eq!([positions[0][0], positions[1][0]], [0, 900]);
+        // 固定外边距按外边距盒参与justify-content
+        let positions = margin_positions("justify-content:flex-end", &["margin:0 10cm 0 0 0 0"]);
+        assert_eq!(positions[0][0], 800);
+        // 三个值的外边距作用在每个轴的两侧
+        let positions = margin_positions("", &["margin:10cm 5cm 0"; 2]);
+        assert_eq!([positions[0][0], positions[1][0]], [100, 400]);
+        assert_eq!([positions[0][1], positions[1][1]], [50, 50]);
+    }
+
+    /// 2m的房间中，带一个桌腿的corridor空间计算后的尺寸
+    fn corridor_size(corridor_style: &str) -> Result<[u32; 3]> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut corridor = DomElement::new("space".to_string());
+        corridor.attributes.insert("id".to_string(), "corridor".to_string());
+        corridor.attributes.insert("style".to_string(), corridor_style.to_string());
+        let mut leg = DomElement::new("object".to_string());
+        leg.text = "table_leg".to_string();
+        corridor.children.push(leg);
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:2m 2m 2m;flex-direction:x".to_string());
+        room.children.push(corridor);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package)?;
+        render_tree.calculate()?;
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        let size = render_tree.node(render_tree.node(room).children[0]).computed_style.size.get_length().unwrap();
+        Ok([size.x.mm(), size.y.mm(), size.z.mm()])
+    }
+
+    #[test]
+    fn test_min_max_size() {
+        // 自动尺寸至少1m，其余轴仍由子元素决定
+        assert_eq!(corridor_size("min-size:1m auto auto").unwrap(), [1000, 100, 500]);
+        // 最小尺寸不影响已经足够大的轴
+        assert_eq!(corridor_size("size:1m 1m 1m;min-size:50cm 50cm 50cm").unwrap(), [1000, 1000, 1000]);
+        // 百分比相对于父空间
+        assert_eq!(corridor_size("size:80% 1m 1m;max-size:50% auto 60cm").unwrap(), [1000, 1000, 600]);
+        assert_eq!(corridor_size("min-size:auto 25% auto").unwrap(), [100, 500, 500]);
+
+        let err = corridor_size("min-size:1m auto auto;max-size:50cm auto auto").unwrap_err();
+        assert!(err.to_string().contains("space#corridor has min-size"), "{}", err);
+        assert!(err.to_string().contains("on the x axis"));
+        assert!(Style::from_style_string("min-size:1m").is_err());
+    }
+
+    #[test]
+    fn test_gap() {
+        let main = |room_style: &str, count: usize| {
+            margin_positions(room_style, &vec![""; count])
+                .iter()
+                .map(|position| position[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(main("gap:10cm", 1), [0]);
+        assert_eq!(main("gap:10cm;justify-content:center", 1), [450]);
+
+        assert_eq!(main("gap:10cm", 2), [0, 200]);
+        assert_eq!(main("gap:10cm;justify-content:center", 2), [350, 550]);
+        assert_eq!(main("gap:10cm;justify-content:space-between", 2), [0, 900]);
+        // 自动尺寸包含gap，否则会超出容器
+        assert_eq!(main("size:auto 1m 1m;gap:10cm;justify-content:flex-end", 2), [0, 200]);
+
+        assert_eq!(main("gap:10cm", 5), [0, 200, 400, 600, 800]);
+        // gap是最小间距：剩余的100mm再按space-evenly分配
+        assert_eq!(main("gap:10cm;justify-content:space-evenly", 5), [17, 234, 451, 668, 884]);
+        assert_eq!(main("gap:10cm;justify-content:space-between", 5), [0, 225, 450, 675, 900]);
+
+        // gap计入主轴溢出检查
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m;flex-direction:x;gap:20cm".to_string());
+        for _ in 0..5 {
+            let mut leg = DomElement::new("object".to_string());
+            leg.text = "table_leg".to_string();
+            room.children.push(leg);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+        let err = RenderTree::new(&root, &package).unwrap().calculate().unwrap_err();
+        assert!(err.to_string().contains("on the x axis by 3dm"), "{}", err);
+        assert!(Style::from_style_string("gap:10%").is_err());
+    }
+
+    #[test]
+    fn test_align_self() {
+        let positions = margin_positions(
+            "",
+            &["", "align-self:center flex-end", "align-self:auto"],
+        );
+        assert_eq!(positions[0], [0, 0, 0]);
+        // 1m的容器中10cm x 50cm的桌腿：y轴居中，z轴贴到末尾
+        assert_eq!(positions[1], [100, 450, 500]);
+        assert_eq!(positions[2], [200, 0, 0]);
+        assert!(Style::from_style_string("align-self:center").is_err());
+    }
+
+    /// 指定body样式时，50%的空间和其中桌腿的计算结果
+    fn body_layout(body_style: Option<&str>) -> ([u32; 3], [u32; 3]) {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:50% 50% 50%".to_string());
+        let mut leg = DomElement::new("object".to_string());
+        leg.text = "table_leg".to_string();
+        room.children.push(leg);
+        let mut body = DomElement::new("body".to_string());
+        if let Some(body_style) = body_style {
+            body.attributes.insert("style".to_string(), body_style.to_string());
+        }
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let body_size = render_tree.node(body).computed_style.size.get_length().unwrap();
+        let room = render_tree.node(body).children[0];
+        let room_size = render_tree.node(room).computed_style.size.get_length().unwrap();
+        (
+            [body_size.x.mm(), body_size.y.mm(), body_size.z.mm()],
+            [room_size.x.mm(), room_size.y.mm(), room_size.z.mm()],
+        )
+    }
+
+    /// 房间#main中放两个class为leg的桌腿，返回桌腿的位置
+    fn sheet_positions(sheet: &str, room_style: &str, leg_style: &str) -> Vec<[u32; 3]> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut layout = DomElement::new("layout".to_string());
+        layout.text = sheet.to_string();
+        let mut head = DomElement::new("head".to_string());
+        head.children.push(layout);
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("id".to_string(), "main".to_string());
+        room.attributes.insert("style".to_string(), room_style.to_string());
+        for i in 0..2 {
+            let mut leg = DomElement::new("object".to_string());
+            leg.attributes.insert("id".to_string(), format!("leg{}", i));
+            leg.attributes.insert("class".to_string(), "leg".to_string());
+            leg.attributes.insert("style".to_string(), leg_style.to_string());
+            leg.text = "table_leg".to_string();
+            room.children.push(leg);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(head);
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        render_tree.to_plan().unwrap().items.iter().map(|item| item.position).collect()
+    }
+
+    #[test]
+    fn test_head_style_sheet() {
+        let room = "size:2m 1m 1m;flex-direction:x;justify-content:space-between;align-items:center flex-start";
+        let inline = sheet_positions("", room, "margin:5cm");
+        let from_sheet = sheet_positions(
+            &format!("#main {{ {} }} .leg {{ margin:5cm }}", room),
+            "",
+            "",
+        );
+        assert_eq!(inline, from_sheet);
+        assert_eq!(inline[1][0], 1850);
+
+        // id > class > 标签，style属性最后覆盖
+        let sheet = "#leg0 { margin:1cm } .leg { margin:5cm } object { margin:2dm } #main { size:2m 1m 1m;flex-direction:x }";
+        let positions = sheet_positions(sheet, "", "");
+        assert_eq!([positions[0][0], positions[1][0]], [10, 170]);
+        let positions = sheet_positions(sheet, "justify-content:flex-end", "margin:0");
+        assert_eq!([positions[0][0], positions[1][0]], [1800, 1900]);
+    }
+
+    #[test]
+    fn test_class_rules() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut layout = DomElement::new("layout".to_string());
+        layout.text = ".shelf { size:1m 2m 60cm } .tall { size:1m 2m 2m; flex-direction:x } .shelf { justify-content:flex-end }"
+            .to_string();
+        let mut head = DomElement::new("head".to_string());
+        head.children.push(layout);
+        let mut body = DomElement::new("body".to_string());
+        for (class, style) in [("shelf", ""), ("shelf  tall", ""), ("tall shelf", "size:50cm 50cm 50cm")] {
+            let mut shelf = DomElement::new("space".to_string());
+            shelf.attributes.insert("class".to_string(), class.to_string());
+            shelf.attributes.insert("style".to_string(), style.to_string());
+            let mut leg = DomElement::new("object".to_string());
+            leg.text = "table_leg".to_string();
+            shelf.children.push(leg);
+            body.children.push(shelf);
+        }
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(head);
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let shelves: Vec<_> = render_tree.node(body).children.clone();
+        let size = |i: usize| {
+            let size = render_tree.node(shelves[i]).computed_style.size.get_length().unwrap();
+            [size.x.mm(), size.y.mm(), size.z.mm()]
+        };
+        assert_eq!(render_tree.node(shelves[1]).classes, ["shelf", "tall"]);
+        assert!(render_tree.node(shelves[2]).has_class("tall"));
+        assert_eq!(size(0), [1000, 2000, 600]);
+        // 多个类名的规则按声明顺序合并，与class属性中的顺序无关
+        assert_eq!(size(1), [1000, 2000, 2000]);
+        assert_eq!(render_tree.node(shelves[1]).specified_style.flex_direction, FlexDirection::X);
+        assert_eq!(
+            render_tree.node(shelves[1]).specified_style.justify_content,
+            style::JustifyContent::FlexEnd
+        );
+        // style属性覆盖类规则
+        assert_eq!(size(2), [500, 500, 500]);
+    }
+
+    #[test]
+    fn test_invalid_style_sheet() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut layout = DomElement::new("layout".to_string());
+        layout.text = "#main { size:1m 1m 1m".to_string();
+        let mut head = DomElement::new("head".to_string());
+        head.children.push(layout);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(head);
+        root.children.push(DomElement::new("body".to_string()));
+        let err = RenderTree::new(&root, &package).err().unwrap();
+        assert!(err.to_string().contains("layout"), "{}", err);
+    }
+
+    #[test]
+    fn test_body_style() {
+        assert_eq!(
+            body_layout(Some("size:5m 4m 3m")),
+            ([5000, 4000, 3000], [2500, 2000, 1500])
+        );
+        // 没有指定时使用默认的10m
+        assert_eq!(body_layout(None), ([10000, 10000, 10000], [5000, 5000, 5000]));
+        // 只覆盖部分属性时保留其余的默认值；auto的轴回退到100m
+        assert_eq!(
+            body_layout(Some("size:auto 2m 2m")),
+            ([100000, 2000, 2000], [50000, 1000, 1000])
+        );
+    }
+
+    #[test]
+    fn test_inherited_flex_direction() {
+        let package = Package::from_file("package.toml").unwrap();
+        // body > outer > middle > inner，每层两个桌腿，只有body指定了方向；middle改为y
+        let mut inner = DomElement::new("space".to_string());
+        inner.attributes.insert("id".to_string(), "inner".to_string());
+        let mut middle = DomElement::new("space".to_string());
+        middle.attributes.insert("id".to_string(), "middle".to_string());
+        middle.attributes.insert("style".to_string(), "flex-direction:y".to_string());
+        let mut outer = DomElement::new("space".to_string());
+        outer.attributes.insert("id".to_string(), "outer".to_string());
+        outer.attributes.insert("style".to_string(), "size:auto auto 50cm".to_string());
+        for space in [&mut inner, &mut middle, &mut outer] {
+            for _ in 0..2 {
+                let mut leg = DomElement::new("object".to_string());
+                leg.text = "table_leg".to_string();
+                space.children.push(leg);
+            }
+        }
+        middle.children.push(inner);
+        outer.children.push(middle);
+        let mut body = DomElement::new("body".to_string());
+        body.attributes.insert(
+            "style".to_string(),
+            "flex-direction:x;align-items:flex-start flex-start".to_string(),
+        );
+        body.children.push(outer);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        // 每层混合了item和space，嵌套规则只作为警告
+        let options = LayoutOptions {
+            lenient_nesting: true,
+            ..Default::default()
+        };
+        let mut render_tree = RenderTree::with_options(&root, &package, Diagnostics::default(), &options).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let outer = render_tree.node(body).children[0];
+        let middle = render_tree.node(outer).children[2];
+        let inner = render_tree.node(middle).children[2];
+        let direction = |node: NodeId| render_tree.node(node).specified_style.flex_direction.clone();
+        assert_eq!(direction(outer), FlexDirection::X);
+        assert_eq!(direction(middle), FlexDirection::Y);
+        assert_eq!(direction(inner), FlexDirection::Y);
+        assert_eq!(render_tree.node(outer).specified_style.align_items, style::AlignItems::default());
+        // size不继承
+        assert_eq!(render_tree.node(middle).specified_style.size, SpaceSize::default());
+
+        let size = |node: NodeId| {
+            let size = render_tree.node(node).computed_style.size.get_length().unwrap();
+            [size.x.mm(), size.y.mm(), size.z.mm()]
+        };
+        assert_eq!(size(inner), [100, 200, 500]);
+        assert_eq!(size(middle), [100, 400, 500]);
+        assert_eq!(size(outer), [300, 400, 500]);
+    }
+
+    #[test]
+    fn test_percentage_in_auto_parent() {
+        let package = Package::from_file("package.toml").unwrap();
+        let space = |id: &str, style: &str| {
+            let mut space = DomElement::new("space".to_string());
+            space.attributes.insert("id".to_string(), id.to_string());
+            space.attributes.insert("style".to_string(), style.to_string());
+            space
+        };
+        let mut child = space("child", "size:10cm 50% 50%");
+        let mut leg = DomElement::new("object".to_string());
+        leg.text = "table_leg".to_string();
+        child.children.push(leg);
+        let mut parent = space("parent", "flex-direction:x");
+        parent.children.push(space("fixed", "size:1m 1m 1m"));
+        parent.children.push(child);
+        // cube的尺寸在父空间确定之后才能解析，中间过程不能当作auto报错
+        parent.children.push(space("cube", "display:cube;size:10cm 50% 25%"));
+        let mut grandparent = space("grandparent", "flex-direction:x");
+        grandparent.children.push(parent);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(grandparent);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let grandparent = render_tree.node(body).children[0];
+        let parent = render_tree.node(grandparent).children[0];
+        let size = |node: NodeId| {
+            let size = render_tree.node(node).computed_style.size.get_length().unwrap();
+            [size.x.mm(), size.y.mm(), size.z.mm()]
+        };
+        assert_eq!(size(grandparent), [1200, 1000, 1000]);
+        assert_eq!(size(parent), [1200, 1000, 1000]);
+        assert_eq!(size(render_tree.node(parent).children[1]), [100, 500, 500]);
+        assert_eq!(size(render_tree.node(parent).children[2]), [100, 500, 250]);
+    }
+
+    #[test]
+    fn test_fractional_percentages() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = |child_style: &str, count: usize| {
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert(
+                "style".to_string(),
+                "size:3m 1m 1m;flex-direction:x".to_string(),
+            );
+            for _ in 0..count {
+                let mut child = DomElement::new("space".to_string());
+                child.attributes.insert("style".to_string(), child_style.to_string());
+                room.children.push(child);
+            }
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        // 三个33.33%的子空间基本占满父空间
+        let mut render_tree = RenderTree::new(&scene("size:33.33% 100% 100%", 3), &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        for &child in &render_tree.node(room).children {
+            let size = render_tree.node(child).computed_style.size.get_length().unwrap();
+            assert_eq!(size.x.mm(), 1000);
+        }
+
+        // 大于100%的百分比默认被忽略，需要显式允许
+        let root = scene("size:50% 150% 100%", 1);
+        let render_tree = RenderTree::new(&root, &package).unwrap();
+        let diagnostics: Vec<_> = render_tree.diagnostics.iter().collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostics::INVALID_STYLE);
+        assert!(diagnostics[0].message.contains("size of 150% is larger than 100%"));
+
+        let options = LayoutOptions {
+            allow_oversized_percentages: true,
+            ..Default::default()
+        };
+        let render_tree =
+            RenderTree::with_options(&root, &package, Diagnostics::default(), &options).unwrap();
+        assert!(render_tree.diagnostics.is_empty());
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        let child = render_tree.node(room).children[0];
+        assert_eq!(
+            render_tree.node(child).specified_style.size.y,
+            SizeValue::Percentage(crate::base::Percentage::new(150))
+        );
+    }
+
+    #[test]
+    fn test_oversized_percentage_protrudes() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = |overflow: &str| {
+            let mut pipe = DomElement::new("space".to_string());
+            pipe.attributes.insert("id".to_string(), "pipe".to_string());
+            pipe.attributes
+                .insert("style".to_string(), "size:150% 10% 10%".to_string());
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert(
+                "style".to_string(),
+                format!("size:2m 2m 2m;flex-direction:x;overflow:{}", overflow),
+            );
+            room.children.push(pipe);
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+        let options = LayoutOptions {
+            allow_oversized_percentages: true,
+            ..Default::default()
+        };
+
+        // 150%的2m是3m，clamp时从容器起点伸出1m
+        let mut render_tree =
+            RenderTree::with_options(&scene("clamp"), &package, Diagnostics::default(), &options)
+                .unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        let pipe = render_tree.node(room).children[0];
+        let size = render_tree.node(pipe).computed_style.size.get_length().unwrap();
+        assert_eq!([size.x.mm(), size.y.mm(), size.z.mm()], [3000, 200, 200]);
+        let room_pos = render_tree.node(room).computed_style.position.get_length().unwrap();
+        let pipe_pos = render_tree.node(pipe).computed_style.position.get_length().unwrap();
+        assert_eq!(pipe_pos.x, room_pos.x);
+
+        // 默认的overflow:error报告伸出的长度
+        let mut render_tree =
+            RenderTree::with_options(&scene("error"), &package, Diagnostics::default(), &options)
+                .unwrap();
+        let err = render_tree.calculate().unwrap_err();
+        assert!(err.to_string().contains("on the x axis by 1m"), "{}", err);
+
+        assert!(Style::from_style_string("flex-basis:150%").is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let default = margin_positions("justify-content:space-between", &["", "", ""]);
+        let reversed = margin_positions(
+            "justify-content:space-between",
+            &["order:1", "order:0", "order:-1"],
+        );
+        assert_eq!(default.iter().map(|p| p[0]).collect::<Vec<_>>(), [0, 450, 900]);
+        assert_eq!(reversed.iter().map(|p| p[0]).collect::<Vec<_>>(), [900, 450, 0]);
+        // order相同时保持文档顺序
+        let positions = margin_positions("", &["order:2", "", "order:2"]);
+        assert_eq!(positions.iter().map(|p| p[0]).collect::<Vec<_>>(), [100, 0, 200]);
+        // 换行按排序后的顺序
+        let positions = margin_positions("size:20cm 1m 1m;flex-wrap:wrap", &["order:1", "", ""]);
+        assert_eq!([positions[0][1], positions[1][1], positions[2][0]], [100, 0, 100]);
+        assert!(Style::from_style_string("order:1.5").is_err());
+    }
+
+    /// 1m见方的房间中按`room_style`排列1dm和2dm见方的两个空间，返回它们相对于房间的位置
+    fn direction_positions(room_style: &str, child_styles: [&str; 2]) -> Vec<[u32; 3]> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert(
+            "style".to_string(),
+            format!("size:1m 1m 1m;align-items:flex-start flex-start;{}", room_style),
+        );
+        for (size, child_style) in ["10cm 10cm 10cm", "20cm 20cm 20cm"].into_iter().zip(child_styles) {
+            let mut child = DomElement::new("space".to_string());
+            child.attributes.insert("style".to_string(), format!("size:{};{}", size, child_style));
+            room.children.push(child);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let room = render_tree.node(render_tree.find_body_node().unwrap()).children[0];
+        render_tree
+            .node(room)
+            .children
+            .iter()
+            .map(|&child| dim3_mm(&render_tree.relative_position(child).unwrap()).map(|mm| mm as u32))
+            .collect()
+    }
+
+    #[test]
+    fn test_reverse_directions() {
+        // 主轴上的位置：正向从min一侧开始，反向从max一侧开始，第一个子元素在起点
+        let cases = [
+            ("flex-start", [0, 100], [900, 700]),
+            ("flex-end", [700, 800], [200, 0]),
+            ("space-between", [0, 800], [900, 0]),
+        ];
+        for (axis, name) in ["x", "y", "z"].into_iter().enumerate() {
+            for (justify, forward, reverse) in cases {
+                for (direction, expected) in [(name.to_string(), forward), (format!("{}-reverse", name), reverse)] {
+                    let style = format!("flex-direction:{};justify-content:{}", direction, justify);
+                    let positions = direction_positions(&style, ["", ""]);
+                    let main: Vec<u32> = positions.iter().map(|p| p[axis]).collect();
+                    assert_eq!(main, expected, "{}", style);
+                    // 交叉轴都是flex-start
+                    assert!(positions.iter().all(|p| (0..3).all(|other| other == axis || p[other] == 0)), "{}", style);
+                }
+            }
+        }
+
+        // 反向时外边距仍然在原来的一侧：x-max一侧的外边距在起点一侧，x-min一侧的在后面，两者不合并
+        let positions = direction_positions("flex-direction:x-reverse", ["margin:5cm 0 0 0 0 0", "margin:0 5cm 0 0 0 0"]);
+        assert_eq!([positions[0][0], positions[1][0]], [900, 600]);
+        let positions = direction_positions("flex-direction:x-reverse", ["margin:0 5cm 0 0 0 0", ""]);
+        assert_eq!([positions[0][0], positions[1][0]], [850, 650]);
+    }
+
+    #[test]
+    fn test_flex_wrap_grid() {
+        // 9个10cm的桌腿在30cm x 30cm的容器中排成3x3
+        let positions = margin_positions("size:30cm 30cm 1m;flex-wrap:wrap", &[""; 9]);
+        for (i, position) in positions.iter().enumerate() {
+            assert_eq!(*position, [(i as u32 % 3) * 100, (i as u32 / 3) * 100, 0]);
+        }
+        // gap同时作用在行内和行之间
+        let positions = margin_positions("size:40cm 40cm 1m;flex-wrap:wrap;gap:5cm", &[""; 9]);
+        assert_eq!(positions[4], [150, 150, 0]);
+        assert_eq!(positions[8], [300, 300, 0]);
+        // 自动尺寸的交叉轴由各行高度决定
+        let positions = margin_positions("size:30cm auto 1m;flex-wrap:wrap", &[""; 9]);
+        assert_eq!(positions[8], [200, 200, 0]);
+        // 反向排列在每一行内反转
+        let positions = margin_positions("size:30cm 30cm 1m;flex-direction:x-reverse;flex-wrap:wrap", &[""; 9]);
+        assert_eq!([positions[0], positions[3]], [[200, 0, 0], [200, 100, 0]]);
+        // 不换行时仍然报主轴溢出
+        let package = Package::from_file("package.toml").unwrap();
+        let overflow = |room_style: &str, count: usize| {
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert("style".to_string(), room_style.to_string());
+            for _ in 0..count {
+                let mut leg = DomElement::new("object".to_string());
+                leg.text = "table_leg".to_string();
+                room.children.push(leg);
+            }
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            RenderTree::new(&root, &package).unwrap().calculate().unwrap_err().to_string()
+        };
+        let err = overflow("size:30cm 30cm 1m;flex-direction:x", 9);
+        assert!(err.contains("children overflow space on the x axis by 6dm"), "{}", err);
+        let err = overflow("size:30cm 30cm 1m;flex-direction:x;flex-wrap:wrap", 10);
+        assert!(err.contains("lines overflow space on the y axis by 1dm"), "{}", err);
+    }
+
+    #[test]
+    fn test_padding_layout() {
+        // 内容盒为x轴100..900
+        let position = margin_positions("padding:10cm;justify-content:center", &[""])[0];
+        assert_eq!(position, [450, 100, 100]);
+        let positions = margin_positions("padding:10cm 0 0;justify-content:space-between", &["", ""]);
+        assert_eq!([positions[0][0], positions[1][0]], [100, 800]);
+        assert_eq!(positions[0][1], 0);
+        // 内边距占用的空间不能放子元素
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m;padding:46cm".to_string());
+        let mut leg = DomElement::new("object".to_string());
+        leg.text = "table_leg".to_string();
+        room.children.push(leg.clone());
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body.clone());
+        let err = RenderTree::new(&root, &package).unwrap().calculate().unwrap_err();
+        assert!(err.to_string().contains("on the z axis by 42cm"), "{}", err);
+
+        // 自动尺寸包含两侧内边距
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("id".to_string(), "room".to_string());
+        room.attributes.insert("style".to_string(), "padding:5cm 0 1cm".to_string());
+        room.children.push(leg);
+        body.children = vec![room];
+        root.children = vec![body];
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let size = render_tree.node(render_tree.node(body).children[0]).computed_style.size.get_length().unwrap();
+        assert_eq!([size.x.mm(), size.y.mm(), size.z.mm()], [200, 100, 520]);
+    }
+
+    #[test]
+    fn test_auto_margins_on_cross_axis() {
+        let position = margin_positions("", &["margin:0 0 auto auto 0 0"])[0];
+        assert_eq!([position[1], position[2]], [450, 0]);
+        let position = margin_positions("", &["margin:0 0 auto 0 0 0"])[0];
+        assert_eq!(position[1], 900);
+        let position = margin_positions("align-items:center center", &["margin:0 0 0 auto 0 0"])[0];
+        assert_eq!([position[1], position[2]], [0, 250]);
+        let position = margin_positions("", &["margin:0 0 5cm 0 10cm 0"])[0];
+        assert_eq!([position[1], position[2]], [50, 100]);
+    }
+
+    #[test]
+    fn test_overflow_in_each_direction() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 三个桌腿（1dm x 1dm x 5dm）放进主轴只有25cm的房间
+        let room = |direction: &str, overflow: &str| {
+            let size = match direction.trim_end_matches("-reverse") {
+                "x" => "25cm 1m 1m",
+                "y" => "1m 25cm 1m",
+                _ => "1m 1m 25cm",
+            };
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert("id".to_string(), "room".to_string());
+            room.attributes.insert(
+                "style".to_string(),
+                format!("size:{};flex-direction:{};{}", size, direction, overflow),
+            );
+            for _ in 0..3 {
+                let mut leg = DomElement::new("object".to_string());
+                leg.text = "table_leg".to_string();
+                room.children.push(leg);
+            }
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        for direction in ["x", "y", "x-reverse", "y-reverse"] {
+            let mut render_tree = RenderTree::new(&room(direction, ""), &package).unwrap();
+            let err = render_tree.calculate().unwrap_err();
+            assert!(matches!(err, RsmlError::StyleComputation { .. }), "{}", direction);
+            let axis = &direction[..1];
+            assert!(
+                err.to_string().contains(&format!("space#room on the {} axis by 5cm", axis)),
+                "{}",
+                err
+            );
+
+            // clamp：位置截断到容器起点，所有坐标都在合理范围内
+            let mut render_tree = RenderTree::new(&room(direction, "overflow:clamp"), &package).unwrap();
+            render_tree.calculate().unwrap();
+            for item in render_tree.to_plan().unwrap().items {
+                assert!(item.position.iter().all(|mm| *mm <= 1000), "{:?}", item.position);
+            }
+        }
+
+        // z轴上桌腿高5dm，单个桌腿就超出了25cm的房间
+        for direction in ["z", "z-reverse"] {
+            let mut render_tree = RenderTree::new(&room(direction, ""), &package).unwrap();
+            let err = render_tree.calculate().unwrap_err();
+            assert!(err.to_string().contains("on the z axis by 125cm"), "{}", err);
+        }
+
+        // 交叉轴超出：x方向排列，但房间只有25cm高
+        let mut root = room("x", "");
+        root.children[0].children[0].attributes.insert(
+            "style".to_string(),
+            "size:1m 1m 25cm;flex-direction:x".to_string(),
+        );
+        root.children[0].children[0].children.truncate(1);
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        let err = render_tree.calculate().unwrap_err();
+        assert!(err.to_string().contains("child(None) overflow"), "{}", err);
+        assert!(err.to_string().contains("on the z axis by 25cm"), "{}", err);
+    }
+
+    /// 1m宽的flex容器中各子空间计算后的尺寸，单位mm
+    fn flex_sizes(child_styles: &[&str]) -> Vec<[u32; 3]> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert(
+            "style".to_string(),
+            "size:1m 1m 1m;flex-direction:x;overflow:clamp".to_string(),
+        );
+        for child_style in child_styles {
+            let mut child = DomElement::new("space".to_string());
+            child.attributes.insert("style".to_string(), child_style.to_string());
+            let mut inner = DomElement::new("space".to_string());
+            inner.attributes.insert("style".to_string(), "size:50% 100% 100%".to_string());
+            child.children.push(inner);
+            room.children.push(child);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        render_tree
+            .node(room)
+            .children
+            .iter()
+            .map(|&child| {
+                let child_ref = render_tree.node(child);
+                let size = child_ref.computed_style.size.get_length().unwrap();
+                // 子空间的百分比尺寸跟随伸缩后的尺寸，四舍五入到毫米
+                let inner = render_tree.node(child_ref.children[0]).computed_style.size.get_length().unwrap();
+                assert_eq!(inner.x.mm(), size.x.mm().div_ceil(2));
+                [size.x.mm(), size.y.mm(), size.z.mm()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flex_grow() {
+        // grow全为0时尺寸不变
+        let sizes = flex_sizes(&["size:20cm 1m 1m", "size:30cm 1m 1m"]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [200, 300]);
+        // 唯一的grow:1占满剩余空间
+        let sizes = flex_sizes(&["size:20cm 1m 1m;flex-grow:1", "size:30cm 1m 1m"]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [700, 300]);
+        assert_eq!(sizes[0][1], 1000);
+        // 剩余500mm按1:3分配
+        let sizes = flex_sizes(&["size:20cm 1m 1m;flex-grow:1", "size:30cm 1m 1m;flex-grow:3"]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [325, 675]);
+    }
+
+    /// 4m的flex容器中各子空间计算后的尺寸，单位mm；子空间都是空的，尺寸只来自样式
+    fn aspect_sizes(child_styles: &[&str]) -> Result<Vec<[u32; 3]>> {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert(
+            "style".to_string(),
+            "size:4m 4m 4m;flex-direction:x;overflow:clamp".to_string(),
+        );
+        for child_style in child_styles {
+            let mut child = DomElement::new("space".to_string());
+            child.attributes.insert("style".to_string(), child_style.to_string());
+            room.children.push(child);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package)?;
+        render_tree.calculate()?;
+        let room = render_tree.node(render_tree.find_body_node().unwrap()).children[0];
+        let sizes = render_tree
+            .node(room)
+            .children
+            .iter()
+            .map(|&child| {
+                let size = render_tree.node(child).computed_style.size.get_length().unwrap();
+                [size.x.mm(), size.y.mm(), size.z.mm()]
+            })
+            .collect();
+        Ok(sizes)
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        // 每一对轴都可以由任意一侧推出另一侧
+        let sizes = aspect_sizes(&[
+            "size:2m auto 15cm;aspect-ratio:x/y 2",
+            "size:auto 50cm 10cm;aspect-ratio:x/y 2",
+            "size:30cm 10cm auto;aspect-ratio:z/x 0.5",
+            "size:auto 10cm 60cm;aspect-ratio:x/z 0.5",
+            "size:10cm 40cm auto;aspect-ratio:y/z 4",
+            "size:10cm auto 30cm;aspect-ratio:y/z 2",
+        ])
+        .unwrap();
+        assert_eq!(
+            sizes,
+            vec![
+                [2000, 1000, 150],
+                [1000, 500, 100],
+                [300, 100, 150],
+                [300, 100, 600],
+                [100, 400, 100],
+                [100, 600, 300],
+            ]
+        );
+
+        // 三个轴的比例，由唯一指定的轴推出另外两个
+        assert_eq!(aspect_sizes(&["size:1m auto auto;aspect-ratio:2 1 0.15"]).unwrap(), vec![[1000, 500, 75]]);
+
+        // 百分比和flex-basis先解析，比例最后应用
+        assert_eq!(
+            aspect_sizes(&["size:50% auto 10cm;aspect-ratio:x/y 4"]).unwrap(),
+            vec![[2000, 500, 100]]
+        );
+        assert_eq!(
+            aspect_sizes(&["flex-basis:1m;size:auto auto 10cm;aspect-ratio:x/y 2"]).unwrap(),
+            vec![[1000, 500, 100]]
+        );
+        // 推出的轴仍然受max-size限制
+        assert_eq!(
+            aspect_sizes(&["size:2m auto 10cm;max-size:auto 80cm auto;aspect-ratio:x/y 2"]).unwrap(),
+            vec![[2000, 800, 100]]
+        );
+    }
+
+    #[test]
+    fn test_aspect_ratio_errors() {
+        // 两个轴都指定了尺寸但不符合比例
+        let error = aspect_sizes(&["size:2m 2m 1m;aspect-ratio:x/y 2"]).unwrap_err();
+        assert!(
+            error.to_string().contains("aspect-ratio:x/y 2 of space conflicts with its size 2m 2m 1m"),
+            "{}",
+            error
+        );
+        // 四舍五入到毫米的误差不算冲突
+        assert_eq!(
+            aspect_sizes(&["size:1m 333mm 1m;aspect-ratio:x/y 3"]).unwrap(),
+            vec![[1000, 333, 1000]]
+        );
+        let error = aspect_sizes(&["size:1m 50% 1m;aspect-ratio:2 1 1"]).unwrap_err();
+        assert!(error.to_string().contains("conflicts with its size 1m 2m 1m"), "{}", error);
+
+        // 物体的尺寸来自package
+        let package = Package::from_file("package.toml").unwrap();
+        let mut leg = DomElement::new("object".to_string());
+        leg.attributes.insert("style".to_string(), "aspect-ratio:x/y 2".to_string());
+        leg.text = "table_leg".to_string();
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(leg);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+        let error = RenderTree::new(&root, &package).err().unwrap();
+        assert!(error.to_string().contains("aspect-ratio is only supported on spaces, not on 'object'"), "{}", error);
+    }
+
+    #[test]
+    fn test_flex_shrink() {
+        // 不足的200mm按 shrink × 原尺寸 分配：600:600，各收缩100mm
+        let sizes = flex_sizes(&[
+            "size:60cm 1m 1m;flex-shrink:1",
+            "size:60cm 1m 1m;flex-shrink:1",
+        ]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [500, 500]);
+        // 只有一个可以收缩
+        let sizes = flex_sizes(&["size:60cm 1m 1m;flex-shrink:1", "size:60cm 1m 1m"]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [400, 600]);
+        // 权重包含原尺寸：800:1000，共收缩300mm
+        let sizes = flex_sizes(&[
+            "size:80cm 1m 1m;flex-shrink:1",
+            "size:50cm 1m 1m;flex-shrink:2",
+        ]);
+        assert_eq!([sizes[0][0], sizes[1][0]], [667, 333]);
+
+        assert!(Style::from_style_string("flex-grow:-1").is_err());
+        assert!(Style::from_style_string("flex-shrink:abc").is_err());
+    }
+
+    /// 3m见方的房间末尾放一个`size`大小的cube空间，其中的桌腿依次使用`positions`
+    fn cube_scene(package: &Package, size: &str, positions: &[&str]) -> DomElement {
+        let cube = positions.iter().enumerate().fold(
+            Builder::new_space().id("cube").style(&format!("display:cube;size:{}", size)),
+            |cube, (index, pos)| {
+                cube.object_with("table_leg", |leg| {
+                    leg.id(&format!("leg{}", index)).style(&format!("pos:{}", pos))
+                })
+            },
+        );
+        Builder::body()
+            .space(|room| {
+                room.style("size:3m 3m 3m;flex-direction:x;justify-content:flex-end;align-items:flex-start flex-start")
+                    .append_child(cube)
+            })
+            .build(package)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_absolute_size() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = cube_scene(&package, "1m 1m 1m", &["min min min", "max max max"]);
+        let mut render_tree = RenderTree::new(&scene, &package).unwrap();
+
+        let cube = render_tree.find_by_id("cube").unwrap();
+        let error = render_tree.node(cube).absolute_size().unwrap_err();
+        assert!(error.to_string().contains("space#cube"), "{}", error);
+
+        render_tree.calculate().unwrap();
+        for id in render_tree.descendants(render_tree.find_body_node().unwrap()) {
+            let node_ref = render_tree.node(id);
+            assert_eq!(
+                node_ref.absolute_size().unwrap(),
+                node_ref.computed_style.size.get_length().unwrap()
+            );
+        }
+        assert_eq!(
+            render_tree.node(cube).absolute_size().unwrap(),
+            Dim3::new(Length::from_cm(100), Length::from_cm(100), Length::from_cm(100))
+        );
+    }
+
+    #[test]
+    fn test_rotate_only_on_items() {
+        let package = Package::from_file("package.toml").unwrap();
+        let scene = |space_style: &str| {
+            let mut leg = DomElement::new("object".to_string());
+            leg.attributes.insert("style".to_string(), "rotate:90 0 45".to_string());
+            leg.text = "table_leg".to_string();
+            let mut room = DomElement::new("space".to_string());
+            room.attributes.insert("id".to_string(), "room".to_string());
+            room.attributes.insert("style".to_string(), space_style.to_string());
+            room.children.push(leg);
+            let mut body = DomElement::new("body".to_string());
+            body.children.push(room);
+            let mut root = DomElement::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        let render_tree = RenderTree::new(&scene("size:1m 1m 1m"), &package).unwrap();
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        let leg = render_tree.node(room).children[0];
+        assert_eq!(
+            render_tree.node(leg).specified_style.rotate,
+            style::Rotation(Dim3::new(90.0, 0.0, 45.0))
+        );
+
+        let error = RenderTree::new(&scene("size:1m 1m 1m;rotate:0 0 90"), &package)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("not on 'space#room'"), "{}", error);
+    }
+
+    #[test]
+    fn test_item_references_checked_before_layout() {
+        let mut package = Package::from_file("package.toml").unwrap();
+        package.dependencies.insert("furniture".to_string(), "1.0".into());
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m".to_string());
+        let items = [
+            ("a", "table_leg"),
+            ("b", "no_such_object"),
+            ("c", "table_leg"),
+            ("d", ""),
+            ("e", "furniture/chair"),
+        ];
+        for (id, name) in items {
+            let mut item = DomElement::new("object".to_string());
+            item.attributes.insert("id".to_string(), id.to_string());
+            item.text = name.to_string();
+            room.children.push(item);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        let diagnostics: Vec<_> = render_tree
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.element.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (diagnostics::UNKNOWN_ITEM, "object#b"),
+                (diagnostics::EMPTY_ITEM, "object#d"),
+                (diagnostics::UNKNOWN_ITEM, "object#e"),
+            ]
+        );
+        let messages: Vec<_> = render_tree.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages[0], "object/group not found in package 'tiny_example': 'no_such_object'");
+        assert!(messages[2].contains("refers to dependency 'furniture', which is not loaded"));
+
+        // 其余的物体仍然完成布局，calculate最后返回全部诊断
+        match render_tree.calculate() {
+            Err(RsmlError::DiagnosticErrors(diagnostics)) => assert_eq!(diagnostics.iter().count(), 3),
+            other => panic!("expected diagnostic errors, got {:?}", other),
+        }
+        let body = render_tree.find_body_node().unwrap();
+        let room = render_tree.node(body).children[0];
+        let ids: Vec<_> = render_tree.node(room).children.iter().map(|&c| render_tree.node(c).id.clone().unwrap()).collect();
+        assert_eq!(ids, ["a", "c"]);
+        assert!(render_tree.node(render_tree.node(room).children[1]).computed_style.position.get_length().is_some());
+    }
+
+    #[test]
+    fn test_build_errors_have_source_location() {
+        let package = Package::from_file("package.toml").unwrap();
+        let xml = "<rsml>\n  <head></head>\n  <body>\n    <space style=\"size:1m 1m 1m\">\n      <object dynamic=\"maybe\">table_leg</object>\n    </space>\n  </body>\n</rsml>";
+        let dom = crate::xml_parser::parse_xml_str(xml).unwrap();
+        let error = RenderTree::new(&dom, &package).err().unwrap();
+        assert!(matches!(error.inner(), RsmlError::InvalidAttribute { .. }));
+        assert_eq!(error.span().map(|span| span.line), Some(5));
+        assert!(error.to_string().starts_with("5:7: Invalid attribute value"), "{}", error);
+
+        // 布局阶段的错误使用节点保存的位置
+        let xml = xml
+            .replace("size:1m 1m 1m", "size:1m 1m 1m;min-size:2m auto auto;max-size:1m auto auto")
+            .replace(" dynamic=\"maybe\"", "");
+        let dom = crate::xml_parser::parse_xml_str(&xml).unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        let error = render_tree.calculate().unwrap_err();
+        assert!(matches!(error.inner(), RsmlError::StyleComputation { .. }));
+        assert_eq!(error.span().map(|span| span.line), Some(4));
+    }
+
+    #[test]
+    fn test_cube_layout() {
+        let package = Package::from_file("package.toml").unwrap();
+        let layout = |positions: &[&str], seed: u64| {
+            let mut render_tree = RenderTree::new(&cube_scene(&package, "1m 1m 1m", positions), &package).unwrap();
+            render_tree.set_seed(seed);
+            render_tree.calculate().map(|_| {
+                render_tree
+                    .to_plan()
+                    .unwrap()
+                    .items
+                    .iter()
+                    .map(|item| item.position)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // cube位于房间x方向的末尾(2m, 0, 0)，桌腿1dm x 1dm x 5dm
+        let positions = layout(&["min min min", "max max max"], 0).unwrap();
+        assert_eq!(positions, vec![[2000, 0, 0], [2900, 900, 500]]);
+
+        let positions = layout(&["10cm 20cm max"], 0).unwrap();
+        assert_eq!(positions, vec![[2100, 200, 500]]);
+
+        let first = layout(&["random random min"], 1503).unwrap();
+        assert_eq!(first, layout(&["random random min"], 1503).unwrap());
+        assert!((2000..=2900).contains(&first[0][0]) && first[0][1] <= 900);
+
+        let err = layout(&["min auto min"], 0).unwrap_err();
+        assert!(err.to_string().contains("has no pos on the y axis"), "{}", err);
+        let err = layout(&["95cm min min"], 0).unwrap_err();
+        assert!(err.to_string().contains("leaves the container on the x axis by 5cm"), "{}", err);
+    }
+
+    #[test]
+    fn test_nested_positions_are_world_coordinates() {
+        let package = Package::from_file("package.toml").unwrap();
+        let space = |id: &str, style: &str| {
+            let mut space = DomElement::new("space".to_string());
+            space.attributes.insert("id".to_string(), id.to_string());
+            space.attributes.insert(
+                "style".to_string(),
+                format!("{};align-items:flex-start flex-start", style),
+            );
+            space
+        };
+        // room在body中的偏移为0，outer在room中沿x偏移2m，inner在outer中沿y偏移50cm，桌腿在inner中沿z偏移5dm
+        let mut leg = DomElement::new("object".to_string());
+        leg.attributes.insert("id".to_string(), "leg".to_string());
+        leg.text = "table_leg".to_string();
+        let mut inner = space("inner", "size:1m 1m 1m;flex-direction:z;justify-content:flex-end");
+        inner.children.push(leg);
+        let mut outer = space("outer", "size:1m 15dm 1m;flex-direction:y;justify-content:flex-end");
+        outer.children.push(inner);
+        let mut room = space("room", "size:3m 3m 3m;flex-direction:x;justify-content:flex-end");
+        room.children.push(outer);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let relative = |id: &str| render_tree.relative_position(render_tree.find_by_id(id).unwrap()).unwrap();
+        assert_eq!(relative("outer"), Dim3::new(Length::from_m(2.0), Length::from_mm(0), Length::from_mm(0)));
+        assert_eq!(relative("inner"), Dim3::new(Length::from_mm(0), Length::from_cm(50), Length::from_mm(0)));
+        assert_eq!(relative("leg"), Dim3::new(Length::from_mm(0), Length::from_mm(0), Length::from_dm(5)));
+
+        // 世界坐标是各级偏移之和，MJCF使用物体中心
+        let leg = render_tree.find_by_id("leg").unwrap();
+        let world = render_tree.node(leg).computed_style.position.get_length().unwrap();
+        assert_eq!(world, relative("room") + relative("outer") + relative("inner") + relative("leg"));
+        assert_eq!(world, Dim3::new(Length::from_m(2.0), Length::from_cm(50), Length::from_dm(5)));
+        let flat = crate::target::MjcfOptions {
+            flat: true,
+            ..Default::default()
+        };
+        let mujoco = crate::target::MjcfGenerator::generate_with(&render_tree, &flat).unwrap();
+        assert_eq!(mujoco.item_geoms()[0].pos, "2.05 0.55 0.75");
+    }
+
+    /// `size`大小的cube空间中`count`个随机位置的桌腿
+    fn crowded_cube(package: &Package, size: &str, count: usize) -> DomElement {
+        cube_scene(package, size, &vec!["random random 0"; count])
+    }
+
+    fn placed_legs(scene: &DomElement, package: &Package, options: &LayoutOptions, seed: u64) -> Result<Vec<[u32; 3]>> {
+        let mut render_tree = RenderTree::with_options(scene, package, Diagnostics::default(), options)?;
+        render_tree.set_seed(seed);
+        render_tree.calculate()?;
+        assert!(render_tree.check_collisions().is_empty());
+        Ok(render_tree.to_plan()?.items.iter().map(|item| item.position).collect())
+    }
+
+    #[test]
+    fn test_random_children_are_placed_without_overlap() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 60cm x 60cm的空间里放8个1dm x 1dm的桌腿，不重新采样时几乎总有重叠
+        let scene = crowded_cube(&package, "60cm 60cm 1m", 8);
+        let options = LayoutOptions::default();
+        for seed in 0..20 {
+            let positions = placed_legs(&scene, &package, &options, seed).unwrap();
+            assert_eq!(positions.len(), 8);
+            assert_eq!(positions, placed_legs(&scene, &package, &options, seed).unwrap());
+        }
+
+        // 30cm x 30cm的空间最多放下9个，第10个无论怎么采样都会重叠
+        let scene = crowded_cube(&package, "30cm 30cm 1m", 10);
+        let err = placed_legs(&scene, &package, &options, 0).unwrap_err();
+        assert!(matches!(err, RsmlError::StyleComputation { .. }), "{}", err);
+        let message = err.to_string();
+        assert!(message.contains("could not place object#leg"), "{}", message);
+        assert!(message.contains("without overlap after 50 attempts"), "{}", message);
+
+        let options = LayoutOptions {
+            placement_attempts: 5,
+            ..Default::default()
+        };
+        let err = placed_legs(&scene, &package, &options, 0).unwrap_err();
+        assert!(err.to_string().contains("without overlap after 5 attempts"), "{}", err);
+    }
+
+    /// 沿x轴依次排列，不使用随机数
+    struct RowPlacement;
+
+    impl PlacementStrategy for RowPlacement {
+        fn name(&self) -> &str {
+            "row"
+        }
+
+        fn propose(&self, request: &PlacementRequest<'_>, _rng: &mut StdRng) -> Dim3<Length> {
+            let x = request.placed.iter().map(|(offset, size)| offset.x + size.x).max();
+            Dim3::new(x.unwrap_or_default(), Length::default(), Length::default())
+        }
+    }
+
+    #[test]
+    fn test_custom_placement_strategy() {
+        let package = Package::from_file("package.toml").unwrap();
+        let options = LayoutOptions {
+            placement: Arc::new(RowPlacement),
+            ..Default::default()
+        };
+        // cube位于房间x方向的末尾(2m, 0, 0)
+        let scene = crowded_cube(&package, "1m 1m 1m", 3);
+        let positions = placed_legs(&scene, &package, &options, 0).unwrap();
+        assert_eq!(positions, vec![[2000, 0, 0], [2100, 0, 0], [2200, 0, 0]]);
+        assert_eq!(positions, placed_legs(&scene, &package, &options, 1).unwrap());
+
+        // 超出容器的提议是错误
+        let scene = crowded_cube(&package, "25cm 1m 1m", 3);
+        let err = placed_legs(&scene, &package, &options, 0).unwrap_err();
+        assert!(
+            err.to_string().contains("row placement put object#leg2 outside its container at 2dm 0m 0m"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_pos_overrides_flex_position() {
+        // 两个桌腿沿x排列，第二个桌腿在部分轴上指定pos
+        let positions = margin_positions("", &["", "pos:10cm 20cm auto"]);
+        assert_eq!(positions[0], [0, 0, 0]);
+        assert_eq!(positions[1], [100, 200, 0]);
+
+        let positions = margin_positions("justify-content:flex-end", &["", "pos:auto auto 50cm"]);
+        assert_eq!(positions[0], [800, 0, 0]);
+        assert_eq!(positions[1], [900, 0, 500]);
+
+        // 全部为auto时与不指定pos相同
+        assert_eq!(
+            margin_positions("", &["pos:auto auto auto"]),
+            margin_positions("", &[""])
+        );
+    }
+
+    #[test]
+    fn test_pos_min_max_random_in_flex() {
+        let positions = margin_positions("", &["pos:max min max"]);
+        assert_eq!(positions[0], [900, 0, 500]);
+
+        // 随机位置在可用范围内，并且可以通过seed复现
+        let package = Package::from_file("package.toml").unwrap();
+        let mut leg = DomElement::new("object".to_string());
+        leg.attributes.insert("style".to_string(), "pos:random random 0cm".to_string());
+        leg.text = "table_leg".to_string();
+        let mut room = DomElement::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m".to_string());
+        room.children.push(leg);
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(room);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let random_position = |seed: u64| {
+            let mut render_tree = RenderTree::new(&root, &package).unwrap();
+            render_tree.set_seed(seed);
+            render_tree.calculate().unwrap();
+            render_tree.to_plan().unwrap().items[0].position
+        };
+        let positions: Vec<[u32; 3]> = (0..8).map(random_position).collect();
+        for position in &positions {
+            assert!(position[0] <= 900 && position[1] <= 900);
+            assert_eq!(position[2], 0);
+        }
+        assert_eq!(positions[3], random_position(3));
+        assert!(positions.iter().any(|position| *position != positions[0]));
+    }
+
+    #[test]
+    fn test_seed_option_reproduces_output() {
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = crate::xml_parser::parse_xml_file("rsml_example.xml").unwrap();
+        let compile = |seed: u64| {
+            let options = LayoutOptions {
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let mut render_tree =
+                RenderTree::with_options(&dom, &package, Diagnostics::default(), &options).unwrap();
+            render_tree.calculate().unwrap();
+            let mujoco = crate::target::MjcfGenerator::generate(&render_tree).unwrap();
+            crate::target::MjcfGenerator::to_xml(&mujoco, false).unwrap()
+        };
+        assert_eq!(compile(42), compile(42));
+    }
+
+    /// 按嵌套规则检查的诊断：(code, severity, element)
+    fn nesting_diagnostics(body: &str, lenient: bool, deny_multiple_items: bool) -> Vec<(&'static str, Severity, String)> {
+        let package = Package::from_file("package.toml").unwrap();
+        let xml = format!("<rsml><head></head><body>{}</body></rsml>", body);
+        let dom = crate::xml_parser::parse_xml_str(&xml).unwrap();
+        let mut severity = crate::diagnostics::SeverityConfig::default();
+        if deny_multiple_items {
+            severity.set(diagnostics::MULTIPLE_ITEMS, Severity::Error);
+        }
+        let options = LayoutOptions {
+            lenient_nesting: lenient,
+            ..Default::default()
+        };
+        let render_tree = RenderTree::with_options(&dom, &package, Diagnostics::new(severity), &options).unwrap();
+        render_tree
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.severity, d.element.clone().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_space_mixing_items_and_spaces() {
+        let body = r#"<space id="room" style="size:1m 1m 1m">
+            <object>table_leg</object>
+            <space id="shelf"><object>table_leg</object></space>
+        </space>"#;
+        assert_eq!(
+            nesting_diagnostics(body, false, false),
+            [(diagnostics::MIXED_CHILDREN, Severity::Error, "space#room".to_string())]
+        );
+        // 宽松模式下降为警告
+        assert_eq!(
+            nesting_diagnostics(body, true, false),
+            [(diagnostics::MIXED_CHILDREN, Severity::Warning, "space#room".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_space_with_multiple_items() {
+        let body = r#"<space id="pair" style="size:1m 1m 1m">
+            <object>table_leg</object><object>table_leg</object>
+        </space>"#;
+        // flex布局中一个space放多个item很常见，默认不报告
+        assert!(nesting_diagnostics(body, false, false).is_empty());
+        assert_eq!(
+            nesting_diagnostics(body, false, true),
+            [(diagnostics::MULTIPLE_ITEMS, Severity::Error, "space#pair".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_items_are_leaves() {
+        let body = r#"<space style="size:1m 1m 1m">
+            <group id="set">bottles<object>table_leg</object></group>
+        </space>"#;
+        assert_eq!(
+            nesting_diagnostics(body, false, false),
+            [(diagnostics::ITEM_CHILDREN, Severity::Error, "group#set".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_valid_deep_nesting() {
+        let body = r#"<space id="room" style="size:2m 2m 2m">
+            <space id="a"><space id="b"><space id="c"><space id="d">
+                <object>table_leg</object>
+            </space></space></space></space>
+            <space id="e"><group select="first">bottles</group></space>
+        </space>"#;
+        assert!(nesting_diagnostics(body, false, true).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_ids_and_find_by_id() {
+        let package = Package::from_file("package.toml").unwrap();
+        let xml = r#"<rsml><head></head><body>
+            <space id="table" style="size:1m 1m 1m">
+                <object id="leg">table_leg</object>
+            </space>
+            <space id="shelf" style="size:1m 1m 1m">
+                <object id="table">table_leg</object>
+            </space>
+        </body></rsml>"#;
+        let dom = crate::xml_parser::parse_xml_str(xml).unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+
+        let duplicates: Vec<_> = render_tree
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == diagnostics::DUPLICATE_DEFINITION)
+            .collect();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].message, "id 'table' is defined at 2:13 and again at 6:17");
+        assert_eq!(duplicates[0].span.as_ref().map(|span| span.line), Some(6));
+        assert!(matches!(render_tree.calculate(), Err(RsmlError::DiagnosticErrors(_))));
+
+        // 重复的id指向第一次定义的节点
+        assert_eq!(render_tree.node(render_tree.find_by_id("table").unwrap()).tag_name, "space");
+        assert_eq!(render_tree.node(render_tree.find_by_id("leg").unwrap()).text_content, "table_leg");
+        assert!(render_tree.find_by_id("no_such_id").is_none());
+        let mut ids: Vec<_> = render_tree.ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["leg", "shelf", "table"]);
+    }
+
+    #[test]
+    fn test_iter_is_pre_order() {
+        let package = Package::from_file("package.toml").unwrap();
+        let xml = r#"<rsml><head></head><body>
+            <space id="room" style="size:2m 2m 2m">
+                <space id="a"><object id="a1">table_leg</object></space>
+                <space id="b"><object id="b1">table_leg</object><object id="b2">table_leg</object></space>
+            </space>
+        </body></rsml>"#;
+        let dom = crate::xml_parser::parse_xml_str(xml).unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let labels: Vec<String> = render_tree.iter().map(|node| render_tree.node(node).label()).collect();
+        assert_eq!(
+            labels,
+            ["rsml", "head", "body", "space#room", "space#a", "object#a1", "space#b", "object#b1", "object#b2"]
+        );
+        assert_eq!(render_tree.nodes_count(), 9);
+        let items: Vec<_> = render_tree.iter_items().map(|node| render_tree.node(node).id.clone().unwrap()).collect();
+        assert_eq!(items, ["a1", "b1", "b2"]);
+
+        // 收集下标之后可以修改节点
+        let items: Vec<_> = render_tree.iter_items().collect();
+        for node in items {
+            render_tree.node_mut(node).text_content = "moved".to_string();
+        }
+        assert_eq!(render_tree.node(render_tree.find_by_id("b2").unwrap()).text_content, "moved");
+        let position = render_tree.node(render_tree.find_by_id("b1").unwrap()).computed_style.position.get_length();
+        assert!(position.is_some());
+    }
+
+    #[test]
+    fn test_children_size_overflow_is_reported() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 两个3000km的子空间之和超出u32毫米的范围
+        let mut row = DomElement::new("space".to_string());
+        row.attributes.insert("id".to_string(), "row".to_string());
+        row.attributes.insert("style".to_string(), "flex-direction:x".to_string());
+        for _ in 0..2 {
+            let mut space = DomElement::new("space".to_string());
+            space.attributes.insert("style".to_string(), "size:3000km 1m 1m".to_string());
+            row.children.push(space);
+        }
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(row);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        let err = render_tree.calculate().unwrap_err();
+        assert!(matches!(err, RsmlError::StyleComputation { .. }), "{}", err);
+        assert!(
+            err.to_string().contains("size of node(Some(\"row\")) overflows on the x axis"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_deep_chain_does_not_overflow_the_stack() {
+        // 10万层嵌套的space，递归实现会在测试线程的栈上溢出
+        let package = Package::from_file("package.toml").unwrap();
+        let mut chain = DomElement::new("object".to_string());
+        chain.text = "table_leg".to_string();
+        for _ in 0..100_000 {
+            let mut space = DomElement::new("space".to_string());
+            space.children.push(chain);
+            chain = space;
+        }
+        chain.attributes.insert("id".to_string(), "outer".to_string());
+        let mut body = DomElement::new("body".to_string());
+        body.children.push(chain);
+        let mut root = DomElement::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        assert_eq!(render_tree.nodes_count(), 100_003);
+        let leg = render_tree.iter_items().next().unwrap();
+        let outer = render_tree.find_by_id("outer").unwrap();
+        let size = |node: NodeId| render_tree.node(node).computed_style.size.get_length().unwrap();
+        assert_eq!(size(outer), size(leg));
+    }
 }
@@ -0,0 +1,323 @@
+//! Self-contained HTML report of a compiled scene, for reviewers who do not read MJCF.
+//!
+//! The report has a stats table, the diagnostics list, a top-down SVG projection of the
+//! item footprints of every top-level space and a collapsible tree of computed boxes.
+//! Everything is inline; collapsing uses `<details>`, so no script is needed.
+
+use crate::base::Length;
+use crate::diagnostics::Severity;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{NodeId, RenderNodeType, RenderTree};
+use crate::world::UpAxis;
+use crate::xml_parser::escape;
+use std::fmt::Write;
+
+/// Width of every projection in pixels; the height follows the footprint's aspect ratio
+const SVG_WIDTH: f64 = 480.0;
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub title: String,
+    /// Items drawn per projection; larger spaces get a note instead of the rest
+    pub max_items: usize,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            title: "RSML scene report".to_string(),
+            max_items: 500,
+        }
+    }
+}
+
+/// One item footprint in a projection
+struct Footprint {
+    label: String,
+    /// Package object or group name, used for the color
+    object: String,
+    position: Dim3<Length>,
+    size: Dim3<Length>,
+}
+
+/// Render the report of a calculated render tree
+pub fn render_html(render_tree: &RenderTree, options: &ReportOptions) -> Result<String> {
+    let body = render_tree
+        .find_body_node()
+        .ok_or(RsmlError::MissingElement {
+            element: "body".to_string(),
+        })?;
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\"/>\n");
+    let _ = writeln!(html, "<title>{}</title>", escape(&options.title));
+    html.push_str(STYLE);
+    html.push_str("</head>\n<body>\n");
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&options.title));
+
+    write_stats(&mut html, render_tree, body);
+    write_diagnostics(&mut html, render_tree);
+
+    html.push_str("<h2>Top-down view</h2>\n");
+    for &space in &render_tree.node(body).children {
+        if render_tree.node(space).node_type == RenderNodeType::Space {
+            write_projection(&mut html, render_tree, space, options.max_items);
+        }
+    }
+
+    html.push_str("<h2>Computed layout</h2>\n");
+    write_tree(&mut html, render_tree, body);
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ccc; padding: 2px 8px; text-align: left; }
+.error { color: #b00020; }
+.warning { color: #a66300; }
+.note { color: #666; font-style: italic; }
+svg { border: 1px solid #999; background: #fafafa; }
+details { margin-left: 1.2em; }
+</style>
+";
+
+fn write_stats(html: &mut String, render_tree: &RenderTree, body: NodeId) {
+    let (mut spaces, mut items, mut objects) = (0, 0, 0);
+    for node in render_tree.descendants(body) {
+        let node = render_tree.node(node);
+        match node.node_type {
+            RenderNodeType::Space => spaces += 1,
+            RenderNodeType::Item => {
+                items += 1;
+                objects += node.placed_objects().len();
+            }
+        }
+    }
+    // body本身不计入空间数
+    let rows = [
+        ("Spaces", spaces - 1),
+        ("Items", items),
+        ("Placed objects", objects),
+        ("Imported objects", render_tree.imported.len()),
+        ("Errors", render_tree.diagnostics.count(Severity::Error)),
+        ("Warnings", render_tree.diagnostics.count(Severity::Warning)),
+        ("Allowed diagnostics", render_tree.diagnostics.count(Severity::Allow)),
+    ];
+
+    html.push_str("<h2>Stats</h2>\n<table>\n");
+    for (name, value) in rows {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+    }
+    html.push_str("</table>\n");
+}
+
+fn write_diagnostics(html: &mut String, render_tree: &RenderTree) {
+    html.push_str("<h2>Diagnostics</h2>\n");
+    let diagnostics: Vec<_> = render_tree.diagnostics.iter().collect();
+    if diagnostics.is_empty() {
+        html.push_str("<p class=\"note\">No diagnostics.</p>\n");
+        return;
+    }
+    html.push_str("<ul>\n");
+    for diagnostic in diagnostics {
+        let _ = writeln!(
+            html,
+            "<li class=\"{}\">{}</li>",
+            diagnostic.severity,
+            escape(&diagnostic.to_string())
+        );
+    }
+    html.push_str("</ul>\n");
+}
+
+/// The two horizontal axes of the world, as (right, forward)
+fn horizontal(up: UpAxis, dim: Dim3<Length>) -> (f64, f64) {
+    let (a, b) = match up {
+        UpAxis::X => (dim.y, dim.z),
+        UpAxis::Y => (dim.x, dim.z),
+        UpAxis::Z => (dim.x, dim.y),
+    };
+    (a.mm() as f64, b.mm() as f64)
+}
+
+fn write_projection(
+    html: &mut String,
+    render_tree: &RenderTree,
+    space: NodeId,
+    max_items: usize,
+) {
+    let space_ref = render_tree.node(space);
+    let origin = space_ref.computed_style.position.get_length().unwrap_or_default();
+    let size = space_ref.computed_style.size.get_length().unwrap_or_default();
+    let name = space_ref.id.clone().unwrap_or_else(|| space_ref.tag_name.clone());
+    let _ = writeln!(html, "<h3>{} ({} x {} x {})</h3>", escape(&name), size.x, size.y, size.z);
+
+    let mut footprints = Vec::new();
+    for node in render_tree.descendants(space) {
+        let node = render_tree.node(node);
+        if node.node_type != RenderNodeType::Item {
+            continue;
+        }
+        let label = node.id.clone().unwrap_or_else(|| node.text_content.clone());
+        for placed in node.placed_objects() {
+            footprints.push(Footprint {
+                label: match &placed.suffix {
+                    Some(suffix) => format!("{}/{}", label, suffix),
+                    None => label.clone(),
+                },
+                object: node.text_content.clone(),
+                position: placed.position,
+                size: placed.object.size,
+            });
+        }
+    }
+
+    let up = render_tree.world.up;
+    let (origin_u, origin_v) = horizontal(up, origin);
+    let (width, depth) = horizontal(up, size);
+    if width == 0.0 || depth == 0.0 {
+        html.push_str("<p class=\"note\">Empty footprint.</p>\n");
+        return;
+    }
+    let scale = SVG_WIDTH / width;
+    let height = depth * scale;
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.1} {:.1}\">",
+        SVG_WIDTH, height, SVG_WIDTH, height
+    );
+    for footprint in footprints.iter().take(max_items) {
+        let (u, v) = horizontal(up, footprint.position);
+        let (w, d) = horizontal(up, footprint.size);
+        let x = (u - origin_u) * scale;
+        // SVG的y轴向下，翻转后第二个水平轴朝上
+        let y = height - (v - origin_v + d) * scale;
+        let _ = writeln!(
+            html,
+            "<g><title>{label}</title><rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{w:.1}\" height=\"{h:.1}\" fill=\"{fill}\" fill-opacity=\"0.7\" stroke=\"#333\"/><text x=\"{tx:.1}\" y=\"{ty:.1}\" font-size=\"10\" text-anchor=\"middle\">{label}</text></g>",
+            label = escape(&footprint.label),
+            w = w * scale,
+            h = d * scale,
+            fill = color(&footprint.object),
+            tx = x + w * scale / 2.0,
+            ty = y + d * scale / 2.0,
+        );
+    }
+    html.push_str("</svg>\n");
+    if footprints.len() > max_items {
+        let _ = writeln!(
+            html,
+            "<p class=\"note\">Showing {} of {} items.</p>",
+            max_items,
+            footprints.len()
+        );
+    }
+}
+
+fn write_tree(html: &mut String, render_tree: &RenderTree, node: NodeId) {
+    let node_ref = render_tree.node(node);
+    let mut summary = node_ref.tag_name.clone();
+    if let Some(id) = &node_ref.id {
+        let _ = write!(summary, " #{}", id);
+    }
+    if !node_ref.text_content.is_empty() {
+        let _ = write!(summary, " {}", node_ref.text_content);
+    }
+    if let (Some(size), Some(position)) = (
+        node_ref.computed_style.size.get_length(),
+        node_ref.computed_style.position.get_length(),
+    ) {
+        let _ = write!(
+            summary,
+            ": {} x {} x {} @ ({}, {}, {})",
+            size.x, size.y, size.z, position.x, position.y, position.z
+        );
+    }
+
+    if node_ref.children.is_empty() {
+        let _ = writeln!(html, "<div>{}</div>", escape(&summary));
+        return;
+    }
+    let _ = writeln!(html, "<details open=\"open\"><summary>{}</summary>", escape(&summary));
+    for child in &node_ref.children {
+        write_tree(html, render_tree, *child);
+    }
+    html.push_str("</details>\n");
+}
+
+/// Stable color per package object name
+pub(crate) fn color(name: &str) -> String {
+    let hash = name
+        .bytes()
+        .fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+    format!("hsl({}, 60%, 65%)", hash % 360)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::xml_parser::parse_xml_file;
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+
+    /// 检查标签是否配对、属性是否合法
+    fn assert_well_formed(html: &str) {
+        let mut reader = Reader::from_str(html);
+        let mut depth = 0i32;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(start)) => {
+                    depth += 1;
+                    for attribute in start.attributes() {
+                        attribute.unwrap();
+                    }
+                }
+                Ok(Event::End(_)) => depth -= 1,
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("malformed report at {}: {}", reader.buffer_position(), e),
+            }
+        }
+        assert_eq!(depth, 0);
+    }
+
+    fn example_report(options: &ReportOptions) -> String {
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = parse_xml_file("rsml_example.xml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.set_seed(1479);
+        render_tree.calculate().unwrap();
+        render_html(&render_tree, options).unwrap()
+    }
+
+    #[test]
+    fn test_report_for_example_scene() {
+        let html = example_report(&ReportOptions::default());
+        assert_well_formed(&html);
+
+        for label in ["leg1", "leg4", "table_plane", "floor", "bottles"] {
+            assert!(html.contains(&format!("<title>{}</title>", label)), "{}", label);
+        }
+        assert!(html.contains("<h3>main_room (10m x 10m x 10m)</h3>"));
+        assert!(html.contains("<tr><th>Items</th><td>8</td></tr>"));
+        assert!(html.contains("<summary>space #table-legs"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_report_caps_items() {
+        let options = ReportOptions {
+            max_items: 3,
+            ..Default::default()
+        };
+        let html = example_report(&options);
+        assert_well_formed(&html);
+        assert_eq!(html.matches("<rect ").count(), 3);
+        assert!(html.contains("Showing 3 of 8 items."));
+    }
+}
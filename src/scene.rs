@@ -0,0 +1,392 @@
+//! 不经过XML，直接在代码中构建场景。
+//!
+//! ```ignore
+//! let dom = Builder::body()
+//!     .space(|s| s.id("room").style("size:5m 4m 3m").object("table_plane"))
+//!     .build(&package)?;
+//! let render_tree = RenderTree::new(&dom, &package)?;
+//! ```
+//!
+//! 子树也可以单独构建再添加进来，属性可以使用类型化的值：
+//!
+//! ```ignore
+//! let shelf = Builder::new_space().id("shelf").with_style(&style).append_child(Builder::new_object("bottle"));
+//! let mut render_tree = Builder::body().append_child(shelf).render_tree(&package)?;
+//! ```
+
+use crate::anchor::Anchor;
+use crate::diagnostics::{self, Diagnostics};
+use crate::error::{Result, RsmlError};
+use crate::package::Package;
+use crate::pick::Pick;
+use crate::render_tree::RenderTree;
+use crate::style::Style;
+use crate::world::World;
+use crate::xml_parser::{escape, Element};
+
+/// 场景中一个元素的链式构建器，`Builder::body()`开始一个场景
+#[derive(Debug, Clone)]
+pub struct Builder {
+    element: Element,
+    /// 文档`<head>`中的元素，也包括添加的子元素上的
+    head: Vec<Element>,
+}
+
+impl Builder {
+    fn new(name: &str) -> Self {
+        Builder {
+            element: Element::new(name.to_string()),
+            head: Vec::new(),
+        }
+    }
+
+    /// 从`<body>`开始一个场景
+    pub fn body() -> Self {
+        Self::new("body")
+    }
+
+    /// 单独的`<space>`，用`append_child`添加
+    pub fn new_space() -> Self {
+        Self::new("space")
+    }
+
+    /// 引用package中物体的`<object>`，用`append_child`添加
+    pub fn new_object(name: &str) -> Self {
+        let mut object = Self::new("object");
+        object.element.text = name.to_string();
+        object
+    }
+
+    /// 引用package中组的`<group>`，用`append_child`添加
+    pub fn new_group(name: &str, select: &str) -> Self {
+        let mut group = Self::new("group").attribute("select", select);
+        group.element.text = name.to_string();
+        group
+    }
+
+    /// 文档head中的`<settings>`，用`head`添加
+    pub fn new_settings() -> Self {
+        Self::new("settings")
+    }
+
+    /// 文档head中的`<world>`，用`head`添加
+    pub fn new_world(world: &World) -> Self {
+        Self::new("world")
+            .attribute("up", &world.up.to_string())
+            .attribute("floor", &world.floor.to_string())
+    }
+
+    /// 导入已保存的场景布局的`<import>`，用`append_child`添加；`at`或`id`用`attribute`设置
+    pub fn new_import(scene: &str) -> Self {
+        Self::new("import").attribute("scene", scene)
+    }
+
+    /// 添加单独构建的元素
+    pub fn append_child(mut self, child: Builder) -> Self {
+        self.element.children.push(child.element);
+        self.head.extend(child.head);
+        self
+    }
+
+    /// 向文档的`<head>`添加元素，例如`new_world()`或`new_settings()`
+    pub fn head(mut self, element: Builder) -> Self {
+        self.head.push(element.element);
+        self.head.extend(element.head);
+        self
+    }
+
+    pub fn id(mut self, id: &str) -> Self {
+        self.element.attributes.insert("id".to_string(), id.to_string());
+        self
+    }
+
+    pub fn style(mut self, style: &str) -> Self {
+        self.element.attributes.insert("style".to_string(), style.to_string());
+        self
+    }
+
+    /// 用类型化的`Style`设置样式：包括声明过的属性和所有不同于默认值的字段
+    pub fn with_style(self, style: &Style) -> Self {
+        let mut style = style.clone();
+        style.declare_non_default();
+        self.style(&style.to_string())
+    }
+
+    pub fn class(mut self, class: &str) -> Self {
+        self.element.attributes.insert("class".to_string(), class.to_string());
+        self
+    }
+
+    /// object或group在MJCF中是否是自由运动的body
+    pub fn dynamic(mut self, dynamic: bool) -> Self {
+        self.element.attributes.insert("dynamic".to_string(), dynamic.to_string());
+        self
+    }
+
+    pub fn anchor(mut self, anchor: &Anchor) -> Self {
+        self.element.attributes.insert("anchor".to_string(), anchor.to_string());
+        self
+    }
+
+    /// group选择物体的方式，替换`new_group`设置的`select`
+    pub fn pick(mut self, pick: &Pick) -> Self {
+        self.element.attributes.remove("select");
+        self.element.attributes.insert("pick".to_string(), pick.to_string());
+        self
+    }
+
+    /// 其他任意属性，例如group上的`select`
+    pub fn attribute(mut self, key: &str, value: &str) -> Self {
+        self.element.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// 添加子元素`<space>`
+    pub fn space(self, f: impl FnOnce(Builder) -> Builder) -> Self {
+        self.append_child(f(Self::new_space()))
+    }
+
+    /// 添加引用package中物体的子元素`<object>`
+    pub fn object(self, name: &str) -> Self {
+        self.object_with(name, |o| o)
+    }
+
+    /// 添加子元素`<object>`，在闭包中设置id和样式
+    pub fn object_with(self, name: &str, f: impl FnOnce(Builder) -> Builder) -> Self {
+        self.append_child(f(Self::new_object(name)))
+    }
+
+    /// 添加引用package中组的子元素`<group>`
+    pub fn group(self, name: &str, select: &str) -> Self {
+        self.append_child(Self::new_group(name, select))
+    }
+
+    /// 向`<settings>`添加`<light>`，在闭包中设置属性
+    pub fn light(self, f: impl FnOnce(Builder) -> Builder) -> Self {
+        self.append_child(f(Self::new("light")))
+    }
+
+    /// 按package检查之后把body包装成`<rsml>`文档。
+    /// 错误信息带有出错节点在构建器中的路径，例如`body > space#room > object[0]`
+    pub fn build(self, package: &Package) -> Result<Element> {
+        validate(&self.element, package, "body")?;
+        let mut head = Element::new("head".to_string());
+        head.children = self.head;
+        let mut root = Element::new("rsml".to_string());
+        root.children.push(head);
+        root.children.push(self.element);
+        Ok(root)
+    }
+
+    /// 一步构建场景和它的渲染树，结果和对解析出的相同文档调用`RenderTree::new`一样
+    pub fn render_tree(self, package: &Package) -> Result<RenderTree<'_>> {
+        RenderTree::new(&self.build(package)?, package)
+    }
+}
+
+fn validate(element: &Element, package: &Package, path: &str) -> Result<()> {
+    let located = |message: String| RsmlError::InvalidStructure {
+        message: format!("{}: {}", path, message),
+    };
+
+    if let Some(style) = element.get_attribute("style") {
+        let mut diagnostics = Diagnostics::default();
+        Style::from_style_string_with_diagnostics(style, &mut diagnostics)
+            .map_err(|e| located(format!("invalid style '{}': {}", style, e)))?;
+        // 未知属性是错误，舍入之类的警告不影响构建
+        let unknown = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code == diagnostics::UNKNOWN_STYLE_PROPERTY);
+        if let Some(diagnostic) = unknown {
+            return Err(located(diagnostic.message.clone()));
+        }
+    }
+
+    if element.name == "object" || element.name == "group" {
+        let name = element.text.as_str();
+        let known = package.objects.contains_key(name)
+            || package.groups.iter().any(|group| group.name == name);
+        if !known {
+            return Err(located(format!("unknown object or group '{}'", name)));
+        }
+    }
+
+    for (index, child) in element.children.iter().enumerate() {
+        let child_path = match child.get_attribute("id") {
+            Some(id) => format!("{} > {}#{}", path, child.name, id),
+            None => format!("{} > {}[{}]", path, child.name, index),
+        };
+        validate(child, package, &child_path)?;
+    }
+    Ok(())
+}
+
+/// 把元素树序列化为RSML XML，属性按名称排序
+pub fn to_rsml_xml(element: &Element) -> String {
+    let mut out = String::new();
+    write_element(element, 0, &mut out);
+    out
+}
+
+fn write_element(element: &Element, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}<{}", indent, element.name));
+    let mut keys: Vec<&String> = element.attributes.keys().collect();
+    keys.sort();
+    for key in keys {
+        out.push_str(&format!(" {}=\"{}\"", key, escape(&element.attributes[key])));
+    }
+
+    let text = element.text.trim();
+    if element.children.is_empty() {
+        if text.is_empty() {
+            out.push_str("/>\n");
+        } else {
+            out.push_str(&format!(">{}</{}>\n", escape(text), element.name));
+        }
+        return;
+    }
+
+    out.push_str(">\n");
+    if !text.is_empty() {
+        out.push_str(&format!("{}  {}\n", indent, escape(text)));
+    }
+    for child in &element.children {
+        write_element(child, depth + 1, out);
+    }
+    out.push_str(&format!("{}</{}>\n", indent, element.name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Length;
+    use crate::dim3::Dim3;
+    use crate::xml_parser::parse_xml_file;
+
+    fn room() -> Builder {
+        Builder::body().space(|s| {
+            s.id("room")
+                .style("size:5m 4m 3m;flex-direction:x")
+                .object("table_plane")
+                .object_with("table_leg", |o| o.id("leg"))
+                .group("bottles", "bottle3")
+        })
+    }
+
+    fn snapshot(dom: &Element, package: &Package) -> crate::plan::ScenePlan {
+        let mut render_tree = RenderTree::new(dom, package).unwrap();
+        render_tree.calculate().unwrap();
+        render_tree.to_plan().unwrap()
+    }
+
+    fn parse_str(xml: &str, file_name: &str) -> Element {
+        let path = std::env::temp_dir().join(file_name);
+        std::fs::write(&path, xml).unwrap();
+        parse_xml_file(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_builder_matches_xml() {
+        let package = Package::from_file("package.toml").unwrap();
+        let built = room().build(&package).unwrap();
+
+        let xml = r#"<rsml>
+            <head></head>
+            <body>
+                <space id="room" style="size:5m 4m 3m;flex-direction:x">
+                    <object>table_plane</object>
+                    <object id="leg">table_leg</object>
+                    <group select="bottle3">bottles</group>
+                </space>
+            </body>
+        </rsml>"#;
+        let parsed = parse_str(xml, "rsmlc_builder_scene.xml");
+        assert_eq!(snapshot(&built, &package), snapshot(&parsed, &package));
+
+        // 导出的XML重新解析后结果一致
+        let archived = parse_str(&to_rsml_xml(&built), "rsmlc_builder_archived.xml");
+        assert_eq!(snapshot(&built, &package), snapshot(&archived, &package));
+    }
+
+    #[test]
+    fn test_head_elements() {
+        let package = Package::from_file("package.toml").unwrap();
+        // 子元素上添加的head元素也放到文档的head中
+        let room = Builder::new_space()
+            .head(Builder::new_settings().light(|light| light.attribute("pos", "0 0 3m")));
+        let built = Builder::body().append_child(room).build(&package).unwrap();
+        let settings = &built.children[0].children[0];
+        assert_eq!(settings.name, "settings");
+        assert_eq!(settings.children[0].get_attribute("pos").unwrap(), "0 0 3m");
+        assert_eq!(built.children[1].children[0].children.len(), 0);
+    }
+
+    #[test]
+    fn test_typed_builder_matches_xml_fixture() {
+        use crate::style::{FlexDirection, JustifyContent, SpaceSize};
+        use crate::target::{MjcfGenerator, MjcfOptions};
+
+        let package = Package::from_file("package.toml").unwrap();
+        let mut kitchen = Style::new();
+        kitchen.size = SpaceSize::from_dim3_length(Dim3::new(Length::from_m(4.0), Length::from_m(3.0), Length::from_m(2.0)));
+        kitchen.flex_direction = FlexDirection::X;
+        kitchen.justify_content = JustifyContent::SpaceBetween;
+        let counter = Builder::new_space()
+            .id("counter")
+            .style("size:1m 1m 1m;display:cube")
+            .append_child(
+                Builder::new_group("bottles", "box_bottle")
+                    .id("bottle")
+                    .style("pos:45cm 45cm 0")
+                    .anchor(&Anchor::TopOf("top".to_string())),
+            )
+            .object_with("table_plane", |o| o.id("top").style("pos:0 0 90cm"));
+        let legs = Builder::new_space()
+            .id("legs")
+            .style("flex-direction:y;gap:10cm")
+            .object_with("table_leg", |o| o.id("leg0").dynamic(true))
+            .object_with("table_leg", |o| o.id("leg1"));
+        let kitchen = Builder::new_space()
+            .id("kitchen")
+            .with_style(&kitchen)
+            .append_child(counter)
+            .append_child(legs);
+        let mut built = Builder::body().append_child(kitchen).render_tree(&package).unwrap();
+        built.calculate().unwrap();
+
+        let fixture = parse_xml_file("tests/fixtures/builder_kitchen.xml").unwrap();
+        let mut parsed = RenderTree::new(&fixture, &package).unwrap();
+        parsed.calculate().unwrap();
+
+        let mjcf = |render_tree: &RenderTree| MjcfGenerator::to_checked_xml(render_tree, &MjcfOptions::default()).unwrap();
+        assert_eq!(mjcf(&built), mjcf(&parsed));
+        assert!(mjcf(&built).contains(r#"name="bottle""#));
+    }
+
+    #[test]
+    fn test_builder_located_errors() {
+        let package = Package::from_file("package.toml").unwrap();
+
+        let err = Builder::body()
+            .space(|s| s.id("room").object("table_plan"))
+            .build(&package)
+            .unwrap_err();
+        assert!(err.to_string().contains("body > space#room > object[0]"));
+        assert!(err.to_string().contains("table_plan"));
+
+        let err = Builder::body()
+            .space(|s| s.style("size:5m 4m"))
+            .build(&package)
+            .unwrap_err();
+        assert!(err.to_string().contains("body > space[0]"));
+
+        let err = Builder::body()
+            .space(|s| s.style("colour:red"))
+            .build(&package)
+            .unwrap_err();
+        assert!(err.to_string().contains("colour"));
+        // 舍入到毫米的长度只是警告
+        assert!(Builder::body().space(|s| s.style("size:1500um 1m 1m")).build(&package).is_ok());
+    }
+}
@@ -0,0 +1,317 @@
+//! 编译器接受的输入的机器可读描述，供编辑器工具使用。
+//!
+//! `validate_structure`也按这里的元素表检查，样式表和诊断表就是解析器和`Diagnostics`
+//! 使用的注册表，所以导出的schema不会和编译器不一致。
+
+use crate::diagnostics::{self, DiagnosticCode, Diagnostics};
+use crate::error::{Result, RsmlError};
+use crate::style::{self, StyleProperty};
+use crate::xml_parser::Element;
+use serde::Serialize;
+
+/// 导出的schema文档的版本
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 一个元素接受的内容
+#[derive(Debug, Serialize)]
+pub struct ElementSchema {
+    pub name: &'static str,
+    pub attributes: &'static [&'static str],
+    pub children: &'static [&'static str],
+    /// 必须存在的子元素
+    pub required_children: &'static [&'static str],
+    /// 元素有文本内容时，文本的含义
+    pub text: Option<&'static str>,
+}
+
+/// package.toml中的一个键；`<name>`表示用户取的名字，`[]`表示数组中的一项
+#[derive(Debug, Serialize)]
+pub struct PackageKey {
+    pub key: &'static str,
+    pub grammar: &'static str,
+    pub required: bool,
+}
+
+const ITEM_CHILDREN: &[&str] = &["space", "object", "group", "import", "include", "use"];
+
+pub const ELEMENTS: &[ElementSchema] = &[
+    ElementSchema {
+        name: "rsml",
+        attributes: &[],
+        children: &["head", "body"],
+        required_children: &["head", "body"],
+        text: None,
+    },
+    ElementSchema {
+        name: "head",
+        attributes: &[],
+        children: &["style", "layout", "world", "settings", "define"],
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "style",
+        attributes: &[],
+        children: &[],
+        required_children: &[],
+        text: Some("style sheet"),
+    },
+    ElementSchema {
+        name: "layout",
+        attributes: &[],
+        children: &[],
+        required_children: &[],
+        text: Some("selector { property:value; ... } rules"),
+    },
+    ElementSchema {
+        name: "world",
+        attributes: &["up", "floor"],
+        children: &[],
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "settings",
+        attributes: &["plane"],
+        children: &["light"],
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "light",
+        attributes: &["name", "pos", "type", "diffuse"],
+        children: &[],
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "body",
+        attributes: &[],
+        children: ITEM_CHILDREN,
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "space",
+        attributes: &["id", "style", "anchor"],
+        children: ITEM_CHILDREN,
+        required_children: &[],
+        text: None,
+    },
+    ElementSchema {
+        name: "object",
+        attributes: &["id", "style", "dynamic", "anchor"],
+        children: &[],
+        required_children: &[],
+        text: Some("package object name"),
+    },
+    ElementSchema {
+        name: "group",
+        attributes: &["id", "style", "pick", "select", "dynamic", "anchor"],
+        children: &[],
+        required_children: &[],
+        text: Some("package group name"),
+    },
+    ElementSchema {
+        name: "import",
+        attributes: &["id", "scene", "at"],
+        children: &[],
+        required_children: &[],
+        text: None,
+    },
+    // 解析时替换为被包含文件的根元素，其余属性覆盖根元素的属性
+    ElementSchema {
+        name: "include",
+        attributes: &["src"],
+        children: &[],
+        required_children: &[],
+        text: None,
+    },
+    // 可以重复使用的子树，只包含一个元素
+    ElementSchema {
+        name: "define",
+        attributes: &["name"],
+        children: ITEM_CHILDREN,
+        required_children: &[],
+        text: None,
+    },
+    // 构建渲染树时替换为ref引用的define的副本
+    ElementSchema {
+        name: "use",
+        attributes: &[],
+        children: &[],
+        required_children: &[],
+        text: None,
+    },
+];
+
+pub const PACKAGE_KEYS: &[PackageKey] = &[
+    PackageKey { key: "package.name", grammar: "string", required: true },
+    PackageKey { key: "package.description", grammar: "string", required: true },
+    PackageKey { key: "objects.<name>.geom-type", grammar: "box | mesh", required: false },
+    PackageKey { key: "objects.<name>.size", grammar: "<length>{3}", required: true },
+    PackageKey { key: "objects.<name>.path", grammar: "string", required: false },
+    PackageKey { key: "groups[].name", grammar: "string", required: true },
+    PackageKey { key: "groups[].objects.<name>", grammar: "object", required: false },
+    PackageKey { key: "groups[].members[]", grammar: "object, group or dependency name", required: false },
+    PackageKey { key: "groups[].layout[].object", grammar: "member name", required: true },
+    PackageKey { key: "groups[].layout[].at", grammar: "<length>{3}", required: true },
+    PackageKey { key: "dependencies.<name>", grammar: "version string | { version, size-limit }", required: false },
+    PackageKey { key: "dependencies.<name>.size-limit", grammar: "<length>{3}", required: false },
+];
+
+/// 查找元素的schema
+pub fn element(name: &str) -> Option<&'static ElementSchema> {
+    ELEMENTS.iter().find(|element| element.name == name)
+}
+
+/// 所有元素都可以带有、不会被报告为未知的属性
+pub const COMMON_ATTRIBUTES: &[&str] = &["id", "class", "style", "ref", "count"];
+
+/// schema是否为`element`定义了`attribute`
+pub fn is_known_attribute(element: &str, attribute: &str) -> bool {
+    COMMON_ATTRIBUTES.contains(&attribute)
+        || self::element(element).is_some_and(|schema| schema.attributes.contains(&attribute))
+}
+
+/// 按元素的schema检查文档：根元素必须是`<rsml>`，每个元素都必须有要求的子元素
+pub fn validate_structure(root: &Element) -> Result<()> {
+    match structure_errors(root).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// 记录文档所有的结构问题，而不是在第一个问题处停止
+pub fn check_structure(root: &Element, diagnostics: &mut Diagnostics) {
+    for error in structure_errors(root) {
+        diagnostics.set_element(None, error.span().cloned());
+        diagnostics.record(diagnostics::INVALID_STRUCTURE, error.inner().to_string());
+    }
+    diagnostics.set_element(None, None);
+}
+
+fn structure_errors(root: &Element) -> Vec<RsmlError> {
+    let mut errors = Vec::new();
+    if root.name != "rsml" {
+        errors.push(
+            RsmlError::InvalidStructure {
+                message: format!("根元素应该是'rsml'，但实际是'{}'", root.name),
+            }
+            .at(root.span.as_ref()),
+        );
+    }
+    collect_missing_children(root, &mut errors);
+    errors
+}
+
+fn collect_missing_children(element: &Element, errors: &mut Vec<RsmlError>) {
+    if let Some(schema) = self::element(&element.name) {
+        for required in schema.required_children {
+            if element.find_child(required).is_none() {
+                errors.push(
+                    RsmlError::MissingElement {
+                        element: required.to_string(),
+                    }
+                    .at(element.span.as_ref()),
+                );
+            }
+        }
+    }
+    for child in &element.children {
+        collect_missing_children(child, errors);
+    }
+}
+
+/// 完整的schema文档
+#[derive(Debug, Serialize)]
+pub struct Schema {
+    pub version: u32,
+    pub elements: &'static [ElementSchema],
+    pub style_properties: &'static [StyleProperty],
+    pub package_keys: &'static [PackageKey],
+    pub diagnostics: &'static [DiagnosticCode],
+}
+
+/// 描述编译器接受的所有内容
+pub fn describe() -> Schema {
+    Schema {
+        version: SCHEMA_VERSION,
+        elements: ELEMENTS,
+        style_properties: style::STYLE_PROPERTIES,
+        package_keys: PACKAGE_KEYS,
+        diagnostics: diagnostics::REGISTRY,
+    }
+}
+
+/// 把schema序列化为JSON
+pub fn to_json() -> Result<String> {
+    serde_json::to_string_pretty(&describe()).map_err(|e| RsmlError::ParseError {
+        field: "schema".to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_schema_json() {
+        let schema: Value = serde_json::from_str(&to_json().unwrap()).unwrap();
+        assert_eq!(schema["version"], SCHEMA_VERSION);
+
+        let size = schema["style_properties"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|property| property["name"] == "size")
+            .unwrap();
+        assert_eq!(size["grammar"], "<length|percentage|auto>{3}");
+
+        let space = schema["elements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|element| element["name"] == "space")
+            .unwrap();
+        let children: Vec<&str> = space["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|child| child.as_str().unwrap())
+            .collect();
+        assert_eq!(children, vec!["space", "object", "group", "import", "include", "use"]);
+
+        let codes: Vec<&str> = schema["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|code| code["code"].as_str().unwrap())
+            .collect();
+        assert!(codes.contains(&diagnostics::UNKNOWN_STYLE_PROPERTY));
+        assert_eq!(schema["diagnostics"][0]["severity"], "warning");
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let mut root = Element::new("rsml".to_string());
+        root.children.push(Element::new("head".to_string()));
+        assert!(matches!(
+            validate_structure(&root),
+            Err(RsmlError::MissingElement { element }) if element == "body"
+        ));
+        root.children.push(Element::new("body".to_string()));
+        assert!(validate_structure(&root).is_ok());
+        assert!(validate_structure(&Element::new("scene".to_string())).is_err());
+
+        // 所有问题都会记录下来，而不是只有第一个：根元素错误，rsml缺少head和body
+        let mut scene = Element::new("scene".to_string());
+        scene.children.push(Element::new("rsml".to_string()));
+        let mut diagnostics = Diagnostics::default();
+        check_structure(&scene, &mut diagnostics);
+        assert_eq!(diagnostics.count(diagnostics::Severity::Error), 3);
+        assert!(diagnostics.iter().all(|d| d.code == diagnostics::INVALID_STRUCTURE));
+    }
+}
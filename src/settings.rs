@@ -0,0 +1,181 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::style::Color;
+use crate::xml_parser::Element;
+use std::str::FromStr;
+
+/// 灯光类型，MuJoCo中不是directional的灯光都是聚光灯
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightType {
+    #[default]
+    Spot,
+    Directional,
+}
+
+impl FromStr for LightType {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "spot" => Ok(LightType::Spot),
+            "directional" => Ok(LightType::Directional),
+            _ => Err(RsmlError::InvalidAttribute {
+                attribute: "type".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// `<light name="sun" pos="5m 5m 8m" type="directional" diffuse="#ffffff"/>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightSettings {
+    pub name: String,
+    /// 世界坐标中的位置
+    pub pos: Dim3<Length>,
+    pub light_type: LightType,
+    /// 漫反射颜色，只使用rgb；没有时使用目标格式的默认值
+    pub diffuse: Option<Color>,
+}
+
+/// 输出场景的设置：`<head><settings plane="true"><light .../></settings></head>`
+///
+/// 没有声明灯光时使用生成器的默认灯光；默认输出和<body>一样大的地面。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub lights: Vec<LightSettings>,
+    pub ground_plane: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            lights: Vec::new(),
+            ground_plane: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the `<settings>` declaration from `<head>`, falling back to the defaults
+    pub fn from_element(root: &Element) -> Result<Self> {
+        let Some(settings) = root.find_child("head").and_then(|head| head.find_child("settings"))
+        else {
+            return Ok(Settings::default());
+        };
+        let mut result = Settings::default();
+        if let Some(plane) = settings.get_attribute("plane") {
+            result.ground_plane = match plane.trim() {
+                "true" => true,
+                "false" => false,
+                _ => {
+                    return Err(RsmlError::InvalidAttribute {
+                        attribute: "plane".to_string(),
+                        value: plane.clone(),
+                    }
+                    .at(settings.span.as_ref()));
+                }
+            };
+        }
+        for (index, light) in settings.find_children("light").into_iter().enumerate() {
+            result.lights.push(Self::parse_light(light, index).map_err(|e| e.at(light.span.as_ref()))?);
+        }
+        Ok(result)
+    }
+
+    fn parse_light(light: &Element, index: usize) -> Result<LightSettings> {
+        let name = light
+            .get_attribute("name")
+            .cloned()
+            .unwrap_or_else(|| format!("light_{}", index));
+        let invalid = |attribute: &str, value: &str| RsmlError::InvalidAttribute {
+            attribute: attribute.to_string(),
+            value: value.to_string(),
+        };
+
+        let pos = light.get_attribute("pos").ok_or_else(|| RsmlError::InvalidStructure {
+            message: format!("light '{}' has no pos", name),
+        })?;
+        let lengths = pos
+            .split_whitespace()
+            .map(Length::from_str)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid("pos", pos))?;
+        let [x, y, z] = lengths[..] else {
+            return Err(invalid("pos", pos));
+        };
+
+        let light_type = match light.get_attribute("type") {
+            Some(light_type) => light_type.parse()?,
+            None => LightType::default(),
+        };
+        let diffuse = match light.get_attribute("diffuse") {
+            Some(diffuse) => Some(Color::from_str(diffuse).map_err(|_| invalid("diffuse", diffuse))?),
+            None => None,
+        };
+        Ok(LightSettings {
+            name,
+            pos: Dim3::new(x, y, z),
+            light_type,
+            diffuse,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    fn root_with_settings(settings: Builder) -> Element {
+        let package = Package::from_file("package.toml").unwrap();
+        Builder::body().head(settings).build(&package).unwrap()
+    }
+
+    fn light(attributes: &[(&str, &str)]) -> impl FnOnce(Builder) -> Builder {
+        move |light| attributes.iter().fold(light, |light, (key, value)| light.attribute(key, value))
+    }
+
+    #[test]
+    fn test_settings_from_element() {
+        let root = Element::new("rsml".to_string());
+        assert_eq!(Settings::from_element(&root).unwrap(), Settings::default());
+        assert!(Settings::default().ground_plane);
+
+        let settings = Builder::new_settings()
+            .attribute("plane", "false")
+            .light(light(&[
+                ("name", "sun"),
+                ("pos", "5m 5m 8m"),
+                ("type", "directional"),
+                ("diffuse", "0.8 0.8 0.8"),
+            ]))
+            .light(light(&[("pos", "0 0 3m")]));
+        let parsed = Settings::from_element(&root_with_settings(settings)).unwrap();
+        assert!(!parsed.ground_plane);
+        assert_eq!(parsed.lights.len(), 2);
+        assert_eq!(parsed.lights[0].name, "sun");
+        assert_eq!(parsed.lights[0].pos.z, Length::from_m(8.0));
+        assert_eq!(parsed.lights[0].light_type, LightType::Directional);
+        assert_eq!(parsed.lights[0].diffuse.unwrap().r, 0.8);
+        assert_eq!(parsed.lights[1].name, "light_1");
+        assert_eq!(parsed.lights[1].light_type, LightType::Spot);
+        assert_eq!(parsed.lights[1].diffuse, None);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let with_light = |attributes: &[(&str, &str)]| {
+            Settings::from_element(&root_with_settings(Builder::new_settings().light(light(attributes))))
+        };
+        assert!(with_light(&[("name", "lamp")]).is_err());
+        assert!(with_light(&[("pos", "1m 1m")]).is_err());
+        assert!(with_light(&[("pos", "1m 1m 1m"), ("type", "area")]).is_err());
+        assert!(with_light(&[("pos", "1m 1m 1m"), ("diffuse", "bright")]).is_err());
+
+        let settings = Builder::new_settings().attribute("plane", "yes");
+        assert!(Settings::from_element(&root_with_settings(settings)).is_err());
+    }
+}
@@ -29,7 +29,11 @@ impl FromStr for FlexBasis {
         
         // Try to parse as percentage first (ends with %)
         if s.ends_with('%') {
+            // 主轴上的基准尺寸超过容器没有意义，大于100%总是报错
             match Percentage::from_str(&s) {
+                Ok(percentage) if percentage.exceeds_full() => {
+                    Err(anyhow!("flex-basis cannot be greater than 100%: {}", s))
+                }
                 Ok(percentage) => Ok(FlexBasis::Percentage(percentage)),
                 Err(e) => Err(anyhow!("Invalid percentage value: {}", e)),
             }
@@ -43,6 +47,16 @@ impl FromStr for FlexBasis {
     }
 }
 
+impl std::fmt::Display for FlexBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlexBasis::Length(length) => write!(f, "{}", length),
+            FlexBasis::Percentage(percentage) => write!(f, "{}", percentage),
+            FlexBasis::Auto => write!(f, "auto"),
+        }
+    }
+}
+
 impl FlexBasis {
     /// Convert FlexBasis to SpaceSize based on FlexDirection
     /// Only the dimension corresponding to the flex direction will have the flex-basis value,
@@ -85,6 +99,17 @@ impl FromStr for AlignItem {
     }
 }
 
+impl std::fmt::Display for AlignItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            AlignItem::FlexStart => "flex-start",
+            AlignItem::FlexEnd => "flex-end",
+            AlignItem::Center => "center",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// align-items属性，支持两个交叉轴的对齐方式
 #[derive(Debug, Clone, PartialEq)]
 pub struct AlignItems {
@@ -118,6 +143,12 @@ impl FromStr for AlignItems {
     }
 }
 
+impl std::fmt::Display for AlignItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.cross1, self.cross2)
+    }
+}
+
 /// justify-content属性枚举
 #[derive(Debug, Clone, PartialEq)]
 pub enum JustifyContent {
@@ -135,6 +166,55 @@ impl Default for JustifyContent {
     }
 }
 
+impl JustifyContent {
+    /// 主轴剩余空间在各个间隔上的权重
+    /// 返回 child_count + 1 个权重：第一个在首个子元素之前，最后一个在末尾子元素之后
+    pub fn gap_weights(&self, child_count: usize) -> Vec<i64> {
+        let mut weights = vec![0; child_count + 1];
+        if child_count == 0 {
+            return weights;
+        }
+        match self {
+            JustifyContent::FlexStart => weights[child_count] = 1,
+            JustifyContent::FlexEnd => weights[0] = 1,
+            JustifyContent::Center => {
+                weights[0] = 1;
+                weights[child_count] = 1;
+            }
+            JustifyContent::SpaceBetween => {
+                if child_count == 1 {
+                    // 只有一个元素时，居中显示
+                    weights[0] = 1;
+                    weights[1] = 1;
+                } else {
+                    weights[1..child_count].fill(1);
+                }
+            }
+            JustifyContent::SpaceAround => {
+                weights.fill(2);
+                weights[0] = 1;
+                weights[child_count] = 1;
+            }
+            JustifyContent::SpaceEvenly => weights.fill(1),
+        }
+        weights
+    }
+}
+
+impl std::fmt::Display for JustifyContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            JustifyContent::FlexStart => "flex-start",
+            JustifyContent::FlexEnd => "flex-end",
+            JustifyContent::Center => "center",
+            JustifyContent::SpaceBetween => "space-between",
+            JustifyContent::SpaceAround => "space-around",
+            JustifyContent::SpaceEvenly => "space-evenly",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl FromStr for JustifyContent {
     type Err = anyhow::Error;
 
@@ -168,6 +248,20 @@ impl Default for FlexDirection {
     }
 }
 
+impl std::fmt::Display for FlexDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            FlexDirection::X => "x",
+            FlexDirection::Y => "y",
+            FlexDirection::Z => "z",
+            FlexDirection::ReverseX => "x-reverse",
+            FlexDirection::ReverseY => "y-reverse",
+            FlexDirection::ReverseZ => "z-reverse",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl FromStr for FlexDirection {
     type Err = anyhow::Error;
 
@@ -184,6 +278,35 @@ impl FromStr for FlexDirection {
     }
 }
 
+/// flex-wrap属性枚举：主轴放满后是否沿第一个交叉轴换行
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FlexWrap {
+    #[default]
+    NoWrap,
+    Wrap,
+}
+
+impl std::fmt::Display for FlexWrap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlexWrap::NoWrap => write!(f, "nowrap"),
+            FlexWrap::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
+impl FromStr for FlexWrap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "nowrap" => Ok(FlexWrap::NoWrap),
+            "wrap" => Ok(FlexWrap::Wrap),
+            _ => Err(anyhow!("Invalid flex-wrap value: {}", s)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,6 +406,14 @@ mod tests {
         assert!(FlexDirection::from_str("invalid").is_err());
     }
 
+    #[test]
+    fn test_flex_wrap_from_str() {
+        assert_eq!(FlexWrap::from_str("nowrap").unwrap(), FlexWrap::NoWrap);
+        assert_eq!(FlexWrap::from_str("Wrap").unwrap(), FlexWrap::Wrap);
+        assert_eq!(FlexWrap::default(), FlexWrap::NoWrap);
+        assert!(FlexWrap::from_str("wrap-reverse").is_err());
+    }
+
     #[test]
     fn test_flex_basis_to_space_size() {
         // Test with Length
@@ -1,13 +1,17 @@
 use crate::base::{Length, Percentage};
+use crate::diagnostics::{self, Diagnostics};
 use crate::dim3::Dim3;
-use crate::package::Object;
+use crate::package::{GroupMember, Object};
 use anyhow::{Result, anyhow};
 use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::str::FromStr;
 
 mod flex;
-pub use flex::{AlignItem, AlignItems, FlexBasis, FlexDirection, JustifyContent};
+pub use flex::{AlignItem, AlignItems, FlexBasis, FlexDirection, FlexWrap, JustifyContent};
+pub mod sheet;
+pub use sheet::StyleSheet;
 
 /// Enum for size values, supporting Length, Percentage, and Auto.
 #[derive(Debug, Clone, PartialEq)]
@@ -66,19 +70,31 @@ impl fmt::Display for SizeValue {
 }
 
 /// Enum for position values, supporting Length and Auto.
+/// Min, Max and Random are only meaningful for children of a cube space.
 #[derive(Debug, Clone, PartialEq)]
 pub enum PositionValue {
     Length(Length),
+    Min,
+    Max,
+    Random,
     Auto,
 }
 
 impl PositionValue {
     pub fn add(&mut self, other: &Self) {
-        if let Self::Length(self_length) = self {
-            match other {
-                Self::Length(other_length) => *self_length += *other_length,
-                Self::Auto => {}
-            }
+        if let (Self::Length(self_length), Self::Length(other_length)) = (&mut *self, other) {
+            *self_length += *other_length;
+        }
+    }
+
+    /// 转换为cube布局使用的AxisPos，auto返回None
+    pub fn axis_pos(&self) -> Option<AxisPos> {
+        match self {
+            Self::Length(length) => Some(AxisPos::Length(*length)),
+            Self::Min => Some(AxisPos::Min),
+            Self::Max => Some(AxisPos::Max),
+            Self::Random => Some(AxisPos::Random),
+            Self::Auto => None,
         }
     }
 }
@@ -94,10 +110,13 @@ impl FromStr for PositionValue {
 
     fn from_str(s: &str) -> Result<Self> {
         let s = s.trim().to_lowercase();
-        if s == "auto" {
-            return Ok(PositionValue::Auto);
+        match s.as_str() {
+            "auto" => Ok(PositionValue::Auto),
+            "min" => Ok(PositionValue::Min),
+            "max" => Ok(PositionValue::Max),
+            "random" => Ok(PositionValue::Random),
+            _ => Ok(PositionValue::Length(Length::from_str(&s)?)),
         }
-        Ok(PositionValue::Length(Length::from_str(&s)?))
     }
 }
 
@@ -112,6 +131,9 @@ impl fmt::Display for PositionValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PositionValue::Length(l) => write!(f, "{l}"),
+            PositionValue::Min => write!(f, "min"),
+            PositionValue::Max => write!(f, "max"),
+            PositionValue::Random => write!(f, "random"),
             PositionValue::Auto => write!(f, "auto"),
         }
     }
@@ -137,6 +159,16 @@ impl SizeValue {
         }
     }
 
+    /// 与add相同，但长度之和超出u32毫米范围时返回None而不是饱和
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Length(self_length), Self::Length(other_length)) => {
+                self_length.checked_add(*other_length).map(Self::Length)
+            }
+            _ => Some(self.clone()),
+        }
+    }
+
     pub fn max(&mut self, other: &Self) {
         if let Self::Length(self_length) = self {
             match other {
@@ -166,6 +198,24 @@ impl fmt::Display for SpaceSize {
     }
 }
 
+impl FromStr for SpaceSize {
+    type Err = anyhow::Error;
+
+    /// 解析尺寸，格式如 "10m 50% auto"
+    fn from_str(s: &str) -> Result<Self> {
+        let size_parts: Vec<&str> = s.split_whitespace().collect();
+        if size_parts.len() != 3 {
+            return Err(anyhow!("Size must have exactly 3 values (x, y, z)"));
+        }
+
+        let x = SizeValue::from_str(size_parts[0])?;
+        let y = SizeValue::from_str(size_parts[1])?;
+        let z = SizeValue::from_str(size_parts[2])?;
+
+        Ok(SpaceSize::new(x, y, z))
+    }
+}
+
 impl Default for SpaceSize {
     fn default() -> Self {
         Self {
@@ -271,6 +321,37 @@ impl fmt::Display for Display {
     }
 }
 
+/// overflow属性：子元素超出容器时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// 报告StyleComputation错误，指出超出的节点、轴和长度
+    #[default]
+    Error,
+    /// 位置截断到容器起点，超出部分留在容器之外
+    Clamp,
+}
+
+impl FromStr for Overflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(Overflow::Error),
+            "clamp" => Ok(Overflow::Clamp),
+            _ => Err(anyhow!("Invalid overflow value: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overflow::Error => write!(f, "error"),
+            Overflow::Clamp => write!(f, "clamp"),
+        }
+    }
+}
+
 /// Position属性枚举，支持每个轴的定位
 #[derive(Debug, Clone, PartialEq)]
 pub enum AxisPos {
@@ -293,19 +374,17 @@ impl fmt::Display for AxisPos {
 }
 
 impl AxisPos {
-    pub fn absolute_pos(&self, min: Length, max: Length) -> Length {
+    /// 在[min, max]内的绝对位置，Length是相对于min的偏移
+    pub fn absolute_pos(&self, min: Length, max: Length, rng: &mut impl Rng) -> Length {
         match self {
             AxisPos::Min => min,
             AxisPos::Max => max,
             AxisPos::Random => {
                 // 生成min和max之间的随机值
-                let min_val = min.mm();
-                let max_val = max.mm();
-                let mut rng = rand::thread_rng();
-                let rand_val = rng.gen_range(min_val..=max_val);
+                let rand_val = rng.random_range(min.mm()..=max.mm());
                 Length::from_mm(rand_val)
             }
-            AxisPos::Length(length) => *length,
+            AxisPos::Length(length) => min + *length,
         }
     }
 }
@@ -385,28 +464,621 @@ impl fmt::Display for SpacePosition {
     }
 }
 
+impl FromStr for SpacePosition {
+    type Err = anyhow::Error;
+
+    /// 解析位置，格式如 "min max 10cm" 或 "auto auto auto"
+    fn from_str(s: &str) -> Result<Self> {
+        let pos_parts: Vec<&str> = s.split_whitespace().collect();
+        if pos_parts.len() != 3 {
+            return Err(anyhow!("Position must have exactly 3 values (x, y, z)"));
+        }
+
+        let x = PositionValue::from_str(pos_parts[0])?;
+        let y = PositionValue::from_str(pos_parts[1])?;
+        let z = PositionValue::from_str(pos_parts[2])?;
+
+        Ok(SpacePosition { x, y, z })
+    }
+}
+
+/// 单侧的外边距：固定长度或auto
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarginValue {
+    Length(Length),
+    /// 主轴上平分剩余空间，交叉轴上覆盖align-items
+    Auto,
+}
+
+impl Default for MarginValue {
+    fn default() -> Self {
+        MarginValue::Length(Length::from_mm(0))
+    }
+}
+
+impl MarginValue {
+    pub fn is_auto(&self) -> bool {
+        matches!(self, MarginValue::Auto)
+    }
+
+    /// 固定长度的毫米数，auto按0计算
+    pub fn fixed_mm(&self) -> i64 {
+        match self {
+            MarginValue::Length(length) => length.mm() as i64,
+            MarginValue::Auto => 0,
+        }
+    }
+}
+
+impl FromStr for MarginValue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if s == "auto" {
+            return Ok(MarginValue::Auto);
+        }
+        if s.ends_with('%') {
+            return Err(anyhow!("Percentage margin is not supported: {}", s));
+        }
+        Ok(MarginValue::Length(Length::from_str(&s)?))
+    }
+}
+
+impl fmt::Display for MarginValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarginValue::Length(l) => write!(f, "{l}"),
+            MarginValue::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// margin属性，顺序为 -x x -y y -z z
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margin {
+    /// 坐标较小一侧的外边距
+    pub min: Dim3<MarginValue>,
+    /// 坐标较大一侧的外边距
+    pub max: Dim3<MarginValue>,
+}
+
+impl Margin {
+    /// 两侧固定外边距之和，auto按0计算
+    pub fn fixed_sum(&self) -> Dim3<Length> {
+        self.min.zip_with(self.max, |min, max| {
+            Length::from_mm((min.fixed_mm() + max.fixed_mm()) as u32)
+        })
+    }
+}
+
+impl FromStr for Margin {
+    type Err = anyhow::Error;
+
+    /// 一个值表示六个方向相同，三个值依次为x y z轴两侧，六个值依次为 -x x -y y -z z
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split_whitespace()
+            .map(MarginValue::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        match values[..] {
+            [all] => Ok(Margin {
+                min: Dim3::new(all, all, all),
+                max: Dim3::new(all, all, all),
+            }),
+            [x, y, z] => Ok(Margin {
+                min: Dim3::new(x, y, z),
+                max: Dim3::new(x, y, z),
+            }),
+            [min_x, max_x, min_y, max_y, min_z, max_z] => Ok(Margin {
+                min: Dim3::new(min_x, min_y, min_z),
+                max: Dim3::new(max_x, max_y, max_z),
+            }),
+            _ => Err(anyhow!(
+                "Margin must have 1, 3 (x y z) or 6 values (-x x -y y -z z)"
+            )),
+        }
+    }
+}
+
+/// 输出最短的写法：六个方向相同时一个值，两侧相同时三个值，否则六个值
+impl fmt::Display for Margin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, max) = (self.min, self.max);
+        if min != max {
+            return write!(
+                f,
+                "{} {} {} {} {} {}",
+                min.x, max.x, min.y, max.y, min.z, max.z
+            );
+        }
+        if min.x == min.y && min.y == min.z {
+            write!(f, "{}", min.x)
+        } else {
+            write!(f, "{} {} {}", min.x, min.y, min.z)
+        }
+    }
+}
+
+/// padding属性：空间每个轴两侧的内边距，子元素在扣除内边距后的内容盒中布局
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Padding(pub Dim3<Length>);
+
+impl Padding {
+    /// 每个轴上两侧内边距之和
+    pub fn total(&self) -> Dim3<Length> {
+        self.0 + self.0
+    }
+}
+
+impl FromStr for Padding {
+    type Err = anyhow::Error;
+
+    /// 一个值表示三个轴相同，三个值依次为x y z轴
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split_whitespace()
+            .map(|value| {
+                if value.ends_with('%') {
+                    return Err(anyhow!("Percentage padding is not supported: {}", value));
+                }
+                Ok(Length::from_str(value)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match values[..] {
+            [all] => Ok(Padding(Dim3::new(all, all, all))),
+            [x, y, z] => Ok(Padding(Dim3::new(x, y, z))),
+            _ => Err(anyhow!("Padding must have 1 or 3 values (x y z)")),
+        }
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Dim3 { x, y, z } = self.0;
+        if x == y && y == z {
+            write!(f, "{}", x)
+        } else {
+            write!(f, "{} {} {}", x, y, z)
+        }
+    }
+}
+
+/// color属性：RGBA颜色，每个分量在0到1之间
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    /// 支持 "#rrggbb"、"#rrggbbaa" 和 "r g b [a]" 两种写法，alpha省略时为1
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let components: Vec<f64> = if let Some(hex) = s.strip_prefix('#') {
+            if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(anyhow!("Invalid hex color: {}", s));
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map(|value| value as f64 / 255.0))
+                .collect::<std::result::Result<_, _>>()?
+        } else {
+            s.split_whitespace()
+                .map(|value| match value.parse::<f64>() {
+                    Ok(component) if (0.0..=1.0).contains(&component) => Ok(component),
+                    _ => Err(anyhow!("Color component must be between 0 and 1: {}", value)),
+                })
+                .collect::<Result<_>>()?
+        };
+        match components[..] {
+            [r, g, b] => Ok(Color { r, g, b, a: 1.0 }),
+            [r, g, b, a] => Ok(Color { r, g, b, a }),
+            _ => Err(anyhow!("Color must be #rrggbb or have 3 or 4 values (r g b a)")),
+        }
+    }
+}
+
+/// 分量都能用两位十六进制精确表示时输出 "#rrggbb[aa]"，否则输出 "r g b a"
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components = [self.r, self.g, self.b, self.a];
+        let hex = components.map(|component| (component * 255.0).round() as u8);
+        let exact = components
+            .iter()
+            .zip(hex)
+            .all(|(component, byte)| byte as f64 / 255.0 == *component);
+        if !exact {
+            return write!(f, "{} {} {} {}", self.r, self.g, self.b, self.a);
+        }
+        write!(f, "#{:02x}{:02x}{:02x}", hex[0], hex[1], hex[2])?;
+        if hex[3] != 255 {
+            write!(f, "{:02x}", hex[3])?;
+        }
+        Ok(())
+    }
+}
+
+/// rotate属性：绕固定的x、y、z轴依次旋转的角度（度），与URDF的rpy顺序相同
+///
+/// 旋转不参与flex布局，尺寸仍按未旋转的包围盒计算，只影响输出的朝向；只支持物体，不支持space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rotation(pub Dim3<f64>);
+
+impl Rotation {
+    pub fn is_zero(&self) -> bool {
+        self.0.x == 0.0 && self.0.y == 0.0 && self.0.z == 0.0
+    }
+
+    /// 弧度表示的(roll, pitch, yaw)
+    pub fn radians(&self) -> Dim3<f64> {
+        Dim3::new(self.0.x.to_radians(), self.0.y.to_radians(), self.0.z.to_radians())
+    }
+
+    /// 单位四元数 [w, x, y, z]
+    pub fn quaternion(&self) -> [f64; 4] {
+        let angles = self.radians();
+        let (sr, cr) = (angles.x / 2.0).sin_cos();
+        let (sp, cp) = (angles.y / 2.0).sin_cos();
+        let (sy, cy) = (angles.z / 2.0).sin_cos();
+        [
+            cr * cp * cy + sr * sp * sy,
+            sr * cp * cy - cr * sp * sy,
+            cr * sp * cy + sr * cp * sy,
+            cr * cp * sy - sr * sp * cy,
+        ]
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.0.x, self.0.y, self.0.z)
+    }
+}
+
+/// scale属性：物体相对于package中尺寸的缩放倍数，一个数对三个轴相同，或者分别给出x、y、z
+///
+/// 布局使用缩放后的尺寸，目标格式中box的尺寸和mesh的缩放都按它输出；只支持物体，不支持space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale(pub Dim3<f64>);
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale(Dim3::new(1.0, 1.0, 1.0))
+    }
+}
+
+impl Scale {
+    pub fn is_identity(&self) -> bool {
+        *self == Scale::default()
+    }
+
+    /// 缩放一个尺寸或偏移，四舍五入到毫米
+    pub fn apply(&self, size: Dim3<Length>) -> Dim3<Length> {
+        let scale = |length: Length, factor: f64| Length::from_mm((length.mm() as f64 * factor).round() as u32);
+        Dim3::new(scale(size.x, self.0.x), scale(size.y, self.0.y), scale(size.z, self.0.z))
+    }
+}
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Dim3 { x, y, z } = self.0;
+        if x == y && y == z {
+            write!(f, "{}", x)
+        } else {
+            write!(f, "{} {} {}", x, y, z)
+        }
+    }
+}
+
+impl FromStr for Scale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let factors = s
+            .split_whitespace()
+            .map(|value| match value.parse::<f64>() {
+                Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+                _ => Err(anyhow!("Invalid scale factor, must be a positive number: {}", value)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match factors[..] {
+            [factor] => Ok(Scale(Dim3::new(factor, factor, factor))),
+            [x, y, z] => Ok(Scale(Dim3::new(x, y, z))),
+            _ => Err(anyhow!("Scale must be one factor or three factors (x y z)")),
+        }
+    }
+}
+
+/// aspect-ratio属性：轴之间固定的比例，由已经确定的轴推出其余的轴
+///
+/// `x/y 2`表示x是y的2倍，`2 1 0.15`表示x:y:z=2:1:0.15；样式声明用冒号分隔属性名和值，所以比例不写成`x:y:z`。
+/// 在百分比和flex-basis之后应用，结果仍受min-size/max-size限制，flex-grow/flex-shrink之后不再重新计算
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AspectRatio {
+    /// 第一个轴与第二个轴的比值，轴用0、1、2表示x、y、z
+    Pair(usize, usize, f64),
+    /// x、y、z三个轴的比例
+    Proportions(Dim3<f64>),
+}
+
+const AXIS_NAMES: [&str; 3] = ["x", "y", "z"];
+
+impl AspectRatio {
+    /// 每个轴在比例中的份数，不参与比例的轴是None
+    pub fn proportions(&self) -> [Option<f64>; 3] {
+        match *self {
+            AspectRatio::Pair(first, second, ratio) => {
+                let mut proportions = [None; 3];
+                proportions[first] = Some(ratio);
+                proportions[second] = Some(1.0);
+                proportions
+            }
+            AspectRatio::Proportions(dim) => [Some(dim.x), Some(dim.y), Some(dim.z)],
+        }
+    }
+}
+
+impl fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AspectRatio::Pair(first, second, ratio) => {
+                write!(f, "{}/{} {}", AXIS_NAMES[*first], AXIS_NAMES[*second], ratio)
+            }
+            AspectRatio::Proportions(dim) => write!(f, "{} {} {}", dim.x, dim.y, dim.z),
+        }
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let number = |value: &str| match value.parse::<f64>() {
+            Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+            _ => Err(anyhow!("Invalid aspect ratio: {}", value)),
+        };
+        let axis = |name: &str| {
+            AXIS_NAMES
+                .iter()
+                .position(|axis| name.trim().eq_ignore_ascii_case(axis))
+                .ok_or_else(|| anyhow!("Invalid aspect ratio axis: {}", name))
+        };
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [axes, ratio] => {
+                let (first, second) = axes
+                    .split_once('/')
+                    .ok_or_else(|| anyhow!("Invalid aspect ratio axes: {}", axes))?;
+                let (first, second) = (axis(first)?, axis(second)?);
+                if first == second {
+                    return Err(anyhow!("aspect-ratio needs two different axes: {}", axes));
+                }
+                Ok(AspectRatio::Pair(first, second, number(ratio)?))
+            }
+            [x, y, z] => Ok(AspectRatio::Proportions(Dim3::new(number(x)?, number(y)?, number(z)?))),
+            _ => Err(anyhow!(
+                "aspect-ratio must be '<axis>/<axis> <ratio>' or three proportions (x y z): {}",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let angles = s
+            .split_whitespace()
+            .map(|value| match value.parse::<f64>() {
+                Ok(angle) if angle.is_finite() => Ok(angle),
+                _ => Err(anyhow!("Invalid rotation angle: {}", value)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match angles[..] {
+            [x, y, z] => Ok(Rotation(Dim3::new(x, y, z))),
+            _ => Err(anyhow!("Rotation must have exactly 3 angles in degrees (x y z)")),
+        }
+    }
+}
+
 /// Style结构体，包含所有支持的样式属性
+/// 一个样式属性的描述，style解析器和schema导出共用
+#[derive(Debug, Serialize)]
+pub struct StyleProperty {
+    pub name: &'static str,
+    /// 值的语法
+    pub grammar: &'static str,
+    /// 一个合法的示例值
+    pub example: &'static str,
+}
+
+/// 所有支持的样式属性
+/// 未显式指定时沿用父节点值的属性，其余属性（size、pos、flex-basis等）不继承
+pub const INHERITED_PROPERTIES: &[&str] = &["flex-direction", "align-items", "justify-content"];
+
+pub const STYLE_PROPERTIES: &[StyleProperty] = &[
+    StyleProperty {
+        name: "size",
+        grammar: "<length|percentage|auto>{3}",
+        example: "10m 50% auto",
+    },
+    StyleProperty {
+        name: "min-size",
+        grammar: "<length|percentage|auto>{3}",
+        example: "1m auto auto",
+    },
+    StyleProperty {
+        name: "max-size",
+        grammar: "<length|percentage|auto>{3}",
+        example: "auto 50% 2m",
+    },
+    StyleProperty {
+        name: "aspect-ratio",
+        grammar: "<axis>/<axis> <number> | <number>{3}",
+        example: "x/y 2",
+    },
+    StyleProperty {
+        name: "display",
+        grammar: "flex | cube",
+        example: "flex",
+    },
+    StyleProperty {
+        name: "justify-content",
+        grammar: "flex-start | flex-end | center | space-between | space-around | space-evenly",
+        example: "space-between",
+    },
+    StyleProperty {
+        name: "align-items",
+        grammar: "<flex-start|flex-end|center>{2}",
+        example: "center flex-start",
+    },
+    StyleProperty {
+        name: "flex-direction",
+        grammar: "x | y | z | x-reverse | y-reverse | z-reverse",
+        example: "z-reverse",
+    },
+    StyleProperty {
+        name: "pos",
+        grammar: "<length|min|max|random|auto>{3}",
+        example: "10cm auto auto",
+    },
+    StyleProperty {
+        name: "flex-basis",
+        grammar: "<length|percentage|auto>",
+        example: "50%",
+    },
+    StyleProperty {
+        name: "align-self",
+        grammar: "auto | <align-item>{2}",
+        example: "center flex-end",
+    },
+    StyleProperty {
+        name: "order",
+        grammar: "<integer>",
+        example: "-1",
+    },
+    StyleProperty {
+        name: "flex-wrap",
+        grammar: "nowrap | wrap",
+        example: "wrap",
+    },
+    StyleProperty {
+        name: "flex-grow",
+        grammar: "<number>",
+        example: "1",
+    },
+    StyleProperty {
+        name: "flex-shrink",
+        grammar: "<number>",
+        example: "0.5",
+    },
+    StyleProperty {
+        name: "padding",
+        grammar: "<length> | <length>{3}",
+        example: "5cm 5cm 0",
+    },
+    StyleProperty {
+        name: "gap",
+        grammar: "<length>",
+        example: "10cm",
+    },
+    StyleProperty {
+        name: "overflow",
+        grammar: "error | clamp",
+        example: "clamp",
+    },
+    StyleProperty {
+        name: "color",
+        grammar: "#rrggbb | #rrggbbaa | <number>{3,4}",
+        example: "#ff8000",
+    },
+    StyleProperty {
+        name: "material",
+        grammar: "<name>",
+        example: "wood",
+    },
+    StyleProperty {
+        name: "rotate",
+        grammar: "<number>{3}",
+        example: "0 90 0",
+    },
+    StyleProperty {
+        name: "scale",
+        grammar: "<number> | <number>{3}",
+        example: "0.5",
+    },
+    StyleProperty {
+        name: "margin",
+        grammar: "<length|auto> | <length|auto>{3} | <length|auto>{6}",
+        example: "auto auto 10cm 10cm 0 0",
+    },
+];
+
+/// 查找样式属性
+pub fn style_property(name: &str) -> Option<&'static StyleProperty> {
+    STYLE_PROPERTIES.iter().find(|property| property.name == name)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Style {
     pub size: SpaceSize,                 // size: 三个维度的尺寸 (x, y, z)
+    pub min_size: SpaceSize,             // min-size: 每个轴的最小尺寸，auto表示不限制
+    pub max_size: SpaceSize,             // max-size: 每个轴的最大尺寸，auto表示不限制
+    pub aspect_ratio: Option<AspectRatio>, // aspect-ratio: 轴之间的比例，None表示不限制
     pub display: Display,                // display: block 或 flex
     pub justify_content: JustifyContent, // justify-content: 对齐方式
     pub align_items: AlignItems,         // align-items: 交叉轴对齐方式
     pub flex_direction: FlexDirection,   // flex-direction: x, y, z
     pub position: SpacePosition,         // pos: 三个轴的定位
     pub flex_basis: FlexBasis,
+    pub align_self: Option<AlignItems>,  // align-self: 覆盖父空间的align-items，None表示auto
+    pub order: i32,                      // order: flex布局中的排列顺序，相同时按文档顺序
+    pub flex_wrap: FlexWrap,             // flex-wrap: 主轴放满后沿第一个交叉轴换行
+    pub flex_grow: f32,                  // flex-grow: 分配主轴剩余空间的权重
+    pub flex_shrink: f32,                // flex-shrink: 主轴空间不足时收缩的权重，默认0不收缩
+    pub margin: Margin,                  // margin: -x x -y y -z z 六个方向的外边距
+    pub padding: Padding,                // padding: x y z 三个轴两侧的内边距
+    pub gap: Length,                     // gap: 主轴上相邻子元素之间的最小间距
+    pub overflow: Overflow,              // overflow: 子元素超出容器时的处理方式
+    pub color: Option<Color>,            // color: 输出的颜色，None表示使用目标格式的默认值
+    pub material: Option<String>,        // material: 输出的材质名称
+    pub rotate: Rotation,                // rotate: 绕x、y、z轴的旋转角度，不影响布局
+    pub scale: Scale,                    // scale: 物体相对于package尺寸的缩放倍数
+    pub declared: Vec<&'static str>,     // 显式声明或继承到的属性名，用于继承
 }
 
 impl Default for Style {
     fn default() -> Self {
         Style {
             size: SpaceSize::default(),
+            min_size: SpaceSize::default(),
+            max_size: SpaceSize::default(),
+            aspect_ratio: None,
             display: Display::Flex,                     // default flex
             justify_content: JustifyContent::default(), // default
             align_items: AlignItems::default(),         // default
             flex_direction: FlexDirection::default(),   // default ReverseZ
             position: SpacePosition::default(),         // 默认位置为auto
             flex_basis: FlexBasis::default(),
+            align_self: None,                   // 默认使用父空间的align-items
+            order: 0,
+            flex_wrap: FlexWrap::default(),
+            flex_grow: 0.0,
+            flex_shrink: 0.0,
+            margin: Margin::default(),          // 默认没有外边距
+            padding: Padding::default(),        // 默认没有内边距
+            gap: Length::from_mm(0),            // 默认子元素之间没有间距
+            overflow: Overflow::default(),      // 默认超出时报错
+            color: None,
+            material: None,
+            rotate: Rotation::default(),
+            scale: Scale::default(),
+            declared: Vec::new(),
         }
     }
 }
@@ -447,9 +1119,147 @@ impl Style {
         &self.position.z
     }
 
-    /// 从样式字符串解析Style对象
+    /// 从样式字符串解析Style对象，同时返回解析时产生的诊断（如未知属性）
     /// 支持格式如: "size:10m 10m 10m;display:flex;justify-content:flex-end;"
-    pub fn from_style_string(style_str: &str) -> Result<Self> {
+    pub fn from_style_string(style_str: &str) -> Result<(Self, Diagnostics)> {
+        let mut diagnostics = Diagnostics::default();
+        let style = Self::from_style_string_with_diagnostics(style_str, &mut diagnostics)?;
+        Ok((style, diagnostics))
+    }
+
+    /// 第一个大于100%的尺寸百分比和它所在的属性；flex-basis在解析时已经限制在100%以内
+    pub fn oversized_percentage(&self) -> Option<(&'static str, Percentage)> {
+        let sizes = [
+            ("size", &self.size),
+            ("min-size", &self.min_size),
+            ("max-size", &self.max_size),
+        ];
+        sizes
+            .into_iter()
+            .flat_map(|(property, size)| [&size.x, &size.y, &size.z].map(|value| (property, value)))
+            .filter_map(|(property, value)| match value {
+                SizeValue::Percentage(percentage) => Some((property, *percentage)),
+                _ => None,
+            })
+            .find(|(_, percentage)| percentage.exceeds_full())
+    }
+
+    /// 属性是否显式声明过或从父节点继承
+    pub fn is_declared(&self, property: &str) -> bool {
+        self.declared.contains(&property)
+    }
+
+    /// 合并另一个样式中显式声明的属性，other中声明的属性覆盖本样式的值
+    pub fn merge(&mut self, other: &Style) {
+        for &property in &other.declared {
+            self.copy_property(other, property);
+        }
+    }
+
+    /// 从父节点继承本节点没有声明、而父节点声明过或继承到的可继承属性
+    pub fn inherit_from(&mut self, parent: &Style) {
+        for &property in INHERITED_PROPERTIES {
+            if !self.is_declared(property) && parent.is_declared(property) {
+                self.copy_property(parent, property);
+            }
+        }
+    }
+
+    /// 从other复制一个属性的值，并标记为已声明
+    fn copy_property(&mut self, other: &Style, property: &'static str) {
+        match property {
+            "size" => self.size = other.size.clone(),
+            "min-size" => self.min_size = other.min_size.clone(),
+            "max-size" => self.max_size = other.max_size.clone(),
+            "aspect-ratio" => self.aspect_ratio = other.aspect_ratio,
+            "display" => self.display = other.display.clone(),
+            "justify-content" => self.justify_content = other.justify_content.clone(),
+            "align-items" => self.align_items = other.align_items.clone(),
+            "flex-direction" => self.flex_direction = other.flex_direction.clone(),
+            "pos" => self.position = other.position.clone(),
+            "flex-basis" => self.flex_basis = other.flex_basis.clone(),
+            "align-self" => self.align_self = other.align_self.clone(),
+            "order" => self.order = other.order,
+            "flex-wrap" => self.flex_wrap = other.flex_wrap.clone(),
+            "flex-grow" => self.flex_grow = other.flex_grow,
+            "flex-shrink" => self.flex_shrink = other.flex_shrink,
+            "margin" => self.margin = other.margin,
+            "padding" => self.padding = other.padding,
+            "gap" => self.gap = other.gap,
+            "overflow" => self.overflow = other.overflow,
+            "color" => self.color = other.color,
+            "material" => self.material = other.material.clone(),
+            "rotate" => self.rotate = other.rotate,
+            "scale" => self.scale = other.scale,
+            _ => unreachable!("style property {} is not handled", property),
+        }
+        self.declare(property);
+    }
+
+    /// 标记属性为已声明，declared按STYLE_PROPERTIES的顺序排列，与声明的先后无关
+    fn declare(&mut self, property: &'static str) {
+        if self.is_declared(property) {
+            return;
+        }
+        let rank = |name: &str| STYLE_PROPERTIES.iter().position(|spec| spec.name == name);
+        let index = self
+            .declared
+            .iter()
+            .position(|declared| rank(declared) > rank(property))
+            .unwrap_or(self.declared.len());
+        self.declared.insert(index, property);
+    }
+
+    /// 把值与默认样式不同的属性标记为已声明。直接给字段赋值构造的样式需要这样才能
+    /// 输出为样式字符串；值等于默认值的属性仍然视为未声明
+    pub fn declare_non_default(&mut self) {
+        let default = Style::default();
+        for property in STYLE_PROPERTIES {
+            if self.property_value(property.name) != default.property_value(property.name) {
+                self.declare(property.name);
+            }
+        }
+    }
+
+    /// 一个已声明属性在样式字符串中的值；color和material未设置时没有值
+    fn property_value(&self, property: &str) -> Option<String> {
+        let value = match property {
+            "size" => self.size.to_string(),
+            "min-size" => self.min_size.to_string(),
+            "max-size" => self.max_size.to_string(),
+            "aspect-ratio" => self.aspect_ratio?.to_string(),
+            "display" => self.display.to_string(),
+            "justify-content" => self.justify_content.to_string(),
+            "align-items" => self.align_items.to_string(),
+            "flex-direction" => self.flex_direction.to_string(),
+            "pos" => self.position.to_string(),
+            "flex-basis" => self.flex_basis.to_string(),
+            "align-self" => match &self.align_self {
+                Some(align_self) => align_self.to_string(),
+                None => "auto".to_string(),
+            },
+            "order" => self.order.to_string(),
+            "flex-wrap" => self.flex_wrap.to_string(),
+            "flex-grow" => self.flex_grow.to_string(),
+            "flex-shrink" => self.flex_shrink.to_string(),
+            "margin" => self.margin.to_string(),
+            "padding" => self.padding.to_string(),
+            "gap" => self.gap.to_string(),
+            "overflow" => self.overflow.to_string(),
+            "color" => self.color?.to_string(),
+            "material" => self.material.clone()?,
+            "rotate" => self.rotate.to_string(),
+            "scale" => self.scale.to_string(),
+            _ => unreachable!("style property {} is not handled", property),
+        };
+        Some(value)
+    }
+
+    /// 从样式字符串解析Style对象，未知属性等警告记录到diagnostics中
+    pub fn from_style_string_with_diagnostics(
+        style_str: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Self> {
         let mut style = Style::new();
 
         // 分割样式声明
@@ -468,19 +1278,48 @@ impl Style {
             let property = parts[0].trim();
             let value = parts[1].trim();
 
+            let Some(spec) = style_property(property) else {
+                // 忽略未知属性而不是报错，以提高兼容性
+                diagnostics.record(
+                    diagnostics::UNKNOWN_STYLE_PROPERTY,
+                    format!(
+                        "unknown style property '{}' in declaration '{}'",
+                        property, declaration
+                    ),
+                );
+                continue;
+            };
+            style.declare(spec.name);
+
+            // 长度只精确到毫米，书写的值因此改变时提示用户
+            for token in value.split_whitespace() {
+                if let Ok(length) = Length::from_str(token)
+                    && length.is_rounded()
+                {
+                    diagnostics.record(
+                        diagnostics::ROUNDED_LENGTH,
+                        format!(
+                            "length {} in declaration '{}' is rounded to {}mm",
+                            token,
+                            declaration,
+                            length.mm()
+                        ),
+                    );
+                }
+            }
+
             match property {
                 "size" => {
-                    // 解析尺寸，格式如 "10m 50% auto"
-                    let size_parts: Vec<&str> = value.split_whitespace().collect();
-                    if size_parts.len() != 3 {
-                        return Err(anyhow!("Size must have exactly 3 values (x, y, z)"));
-                    }
-
-                    let x = SizeValue::from_str(size_parts[0])?;
-                    let y = SizeValue::from_str(size_parts[1])?;
-                    let z = SizeValue::from_str(size_parts[2])?;
-
-                    style.size = SpaceSize::new(x, y, z);
+                    style.size = SpaceSize::from_str(value)?;
+                }
+                "min-size" => {
+                    style.min_size = SpaceSize::from_str(value)?;
+                }
+                "max-size" => {
+                    style.max_size = SpaceSize::from_str(value)?;
+                }
+                "aspect-ratio" => {
+                    style.aspect_ratio = Some(AspectRatio::from_str(value)?);
                 }
                 "display" => {
                     style.display = Display::from_str(value)?;
@@ -495,24 +1334,71 @@ impl Style {
                     style.flex_direction = FlexDirection::from_str(value)?;
                 }
                 "pos" => {
-                    // 解析位置，格式如 "min max 10cm" 或 "auto auto auto"
-                    let pos_parts: Vec<&str> = value.split_whitespace().collect();
-                    if pos_parts.len() != 3 {
-                        return Err(anyhow!("Position must have exactly 3 values (x, y, z)"));
-                    }
-
-                    let x = PositionValue::from_str(pos_parts[0])?;
-                    let y = PositionValue::from_str(pos_parts[1])?;
-                    let z = PositionValue::from_str(pos_parts[2])?;
-
-                    style.position = SpacePosition { x, y, z };
+                    style.position = SpacePosition::from_str(value)?;
                 }
                 "flex-basis" => {
                     style.flex_basis = FlexBasis::from_str(value)?;
                 }
+                "align-self" => {
+                    style.align_self = match value.trim().to_lowercase().as_str() {
+                        "auto" => None,
+                        _ => Some(AlignItems::from_str(value)?),
+                    };
+                }
+                "order" => {
+                    style.order = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid order value: {}", value))?;
+                }
+                "flex-wrap" => {
+                    style.flex_wrap = FlexWrap::from_str(value)?;
+                }
+                "flex-grow" => {
+                    style.flex_grow = parse_flex_factor(property, value)?;
+                }
+                "flex-shrink" => {
+                    style.flex_shrink = parse_flex_factor(property, value)?;
+                }
+                "margin" => {
+                    style.margin = Margin::from_str(value)?;
+                }
+                "padding" => {
+                    style.padding = Padding::from_str(value)?;
+                }
+                "gap" => {
+                    if value.trim().ends_with('%') {
+                        return Err(anyhow!("Percentage gap is not supported: {}", value));
+                    }
+                    style.gap = Length::from_str(value)?;
+                }
+                "overflow" => {
+                    style.overflow = Overflow::from_str(value)?;
+                }
+                "color" => {
+                    style.color = Some(Color::from_str(value)?);
+                }
+                "material" => {
+                    let valid = !value.is_empty()
+                        && value
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                    if !valid {
+                        return Err(anyhow!("Invalid material name: {}", value));
+                    }
+                    style.material = Some(value.to_string());
+                }
+                "rotate" => {
+                    style.rotate = Rotation::from_str(value)?;
+                }
+                "scale" => {
+                    style.scale = Scale::from_str(value)?;
+                }
                 _ => {
-                    // 忽略未知属性而不是报错，以提高兼容性
-                    eprintln!("Warning: Unknown style property '{}'", property);
+                    return Err(anyhow!(
+                        "style property '{}' is registered but not supported",
+                        property
+                    ));
                 }
             }
         }
@@ -521,12 +1407,79 @@ impl Style {
     }
 }
 
+/// 规范的样式字符串：按STYLE_PROPERTIES的顺序输出声明过的属性，
+/// 例如 "size:10m 50% auto;display:flex"，from_style_string解析后得到相同的Style
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let declarations = self.declared.iter().filter_map(|&property| {
+            self.property_value(property)
+                .map(|value| format!("{}:{}", property, value))
+        });
+        for (index, declaration) in declarations.enumerate() {
+            if index > 0 {
+                f.write_str(";")?;
+            }
+            f.write_str(&declaration)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Style {
+    type Err = anyhow::Error;
+
+    /// 与from_style_string相同，但未知属性是错误而不是警告
+    fn from_str(s: &str) -> Result<Self> {
+        let mut diagnostics = Diagnostics::default();
+        let style = Self::from_style_string_with_diagnostics(s, &mut diagnostics)?;
+        let unknown = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code == diagnostics::UNKNOWN_STYLE_PROPERTY);
+        if let Some(diagnostic) = unknown {
+            return Err(anyhow!("{}", diagnostic.message));
+        }
+        Ok(style)
+    }
+}
+
+/// 序列化为与style属性相同写法的字符串，反序列化时按FromStr解析
+macro_rules! serde_as_style_string {
+    ($($ty:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+                    let s = String::deserialize(deserializer)?;
+                    <$ty>::from_str(&s).map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+serde_as_style_string!(Style, SpaceSize, SpacePosition, FlexBasis, AlignItems);
+
+/// 解析flex-grow/flex-shrink的权重，必须是非负数
+fn parse_flex_factor(property: &str, value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(factor) if factor.is_finite() && factor >= 0.0 => Ok(factor),
+        _ => Err(anyhow!("Invalid {} value: {}", property, value)),
+    }
+}
+
 /// 计算后的样式，包含绝对的尺寸和位置
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
     pub size: SpaceSize,
     pub position: SpacePosition,
     pub object: Option<Object>,
+    /// 带layout的group展开后的成员物体，偏移相对于节点位置
+    pub members: Vec<GroupMember>,
 }
 
 impl Default for ComputedStyle {
@@ -535,6 +1488,7 @@ impl Default for ComputedStyle {
             size: SpaceSize::default(),
             position: SpacePosition::default(),
             object: None,
+            members: Vec::new(),
         }
     }
 }
@@ -544,6 +1498,20 @@ mod tests {
     use super::*;
     use crate::base::Percentage;
 
+    #[test]
+    fn test_registered_properties_parse() {
+        for property in STYLE_PROPERTIES {
+            let mut diagnostics = Diagnostics::default();
+            let declaration = format!("{}:{}", property.name, property.example);
+            assert!(
+                Style::from_style_string_with_diagnostics(&declaration, &mut diagnostics).is_ok(),
+                "{}",
+                declaration
+            );
+            assert!(diagnostics.is_empty(), "{}", declaration);
+        }
+    }
+
     #[test]
     fn test_display_parsing() {
         assert_eq!(Display::from_str("cube").unwrap(), Display::Cube);
@@ -584,14 +1552,14 @@ mod tests {
     fn test_align_items_parsing() {
         // 测试align-items属性的解析
         let style_str = "align-items:flex-start center";
-        let style = Style::from_style_string(style_str).unwrap();
+        let style = Style::from_style_string(style_str).unwrap().0;
 
         assert_eq!(style.align_items.cross1, AlignItem::FlexStart);
         assert_eq!(style.align_items.cross2, AlignItem::Center);
 
         // 测试默认值
         let style_str = "display:flex";
-        let style = Style::from_style_string(style_str).unwrap();
+        let style = Style::from_style_string(style_str).unwrap().0;
 
         assert_eq!(style.align_items.cross1, AlignItem::FlexStart);
         assert_eq!(style.align_items.cross2, AlignItem::FlexStart);
@@ -605,6 +1573,59 @@ mod tests {
         assert!(FlexDirection::from_str("invalid").is_err());
     }
 
+    #[test]
+    fn test_style_inheritance() {
+        let parent = Style::from_style_string("flex-direction:x;justify-content:center;size:1m 1m 1m").unwrap().0;
+        assert!(parent.is_declared("flex-direction") && parent.is_declared("size"));
+        assert!(!parent.is_declared("align-items"));
+
+        let mut child = Style::from_style_string("justify-content:flex-end").unwrap().0;
+        child.inherit_from(&parent);
+        assert_eq!(child.flex_direction, FlexDirection::X);
+        assert_eq!(child.justify_content, JustifyContent::FlexEnd);
+        // 父节点没有声明的属性和不可继承的属性保持默认值
+        assert_eq!(child.align_items, AlignItems::default());
+        assert_eq!(child.size, SpaceSize::default());
+        assert!(child.is_declared("flex-direction") && !child.is_declared("size"));
+    }
+
+    #[test]
+    fn test_margin_parsing() {
+        let margin = Margin::from_str("auto 10cm 0 0 auto 1m").unwrap();
+        assert_eq!(margin.min.x, MarginValue::Auto);
+        assert_eq!(margin.max.x, MarginValue::Length(Length::from_cm(10)));
+        assert_eq!(margin.min.z, MarginValue::Auto);
+        assert_eq!(margin.fixed_sum(), Dim3::new(Length::from_cm(10), Length::from_mm(0), Length::from_m(1.0)));
+
+        let margin = Margin::from_str("auto").unwrap();
+        assert!(margin.min.y.is_auto() && margin.max.z.is_auto());
+        let margin = Margin::from_str("5cm 0 10cm").unwrap();
+        assert_eq!(margin.min, margin.max);
+        assert_eq!(margin.fixed_sum(), Dim3::new(Length::from_cm(10), Length::from_mm(0), Length::from_cm(20)));
+
+        assert!(Margin::from_str("auto auto").is_err());
+        let padding = Padding::from_str("5cm 0 1m").unwrap();
+        assert_eq!(padding.total(), Dim3::new(Length::from_cm(10), Length::from_mm(0), Length::from_m(2.0)));
+        assert!(Padding::from_str("5cm 5cm").is_err());
+        assert!(Padding::from_str("auto").is_err());
+        assert!(Padding::from_str("5%").is_err());
+
+        let err = Margin::from_str("10%").unwrap_err();
+        assert!(err.to_string().contains("Percentage margin is not supported"));
+        assert!(Margin::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_pos_keywords_parsing() {
+        let style = Style::from_style_string("pos:random min 0cm").unwrap().0;
+        assert_eq!(style.position.x, PositionValue::Random);
+        assert_eq!(style.position.y, PositionValue::Min);
+        assert_eq!(style.position.z, PositionValue::Length(Length::from_mm(0)));
+        assert_eq!(style.position.to_string(), "random min 0cm");
+        assert_eq!(PositionValue::Max.axis_pos(), Some(AxisPos::Max));
+        assert_eq!(PositionValue::Auto.axis_pos(), None);
+    }
+
     #[test]
     fn test_axis_pos_parsing() {
         assert_eq!(AxisPos::from_str("min").unwrap(), AxisPos::Min);
@@ -639,7 +1660,7 @@ mod tests {
     fn test_style_parsing() {
         // 测试完整的样式字符串解析
         let style_str = "size:10m 50% auto;display:flex;justify-content:flex-end;align-items:flex-start center;flex-direction:x;pos:10cm 20cm 30cm;flex-basis:50%";
-        let style = Style::from_style_string(style_str).unwrap();
+        let style = Style::from_style_string(style_str).unwrap().0;
 
         // 验证size
         assert_eq!(style.size_x(), &SizeValue::Length(Length::from_m(10.0)));
@@ -681,7 +1702,7 @@ mod tests {
     fn test_style_parsing_with_defaults() {
         // 测试只包含部分属性的样式字符串
         let style_str = "display:flex";
-        let style = Style::from_style_string(style_str).unwrap();
+        let style = Style::from_style_string(style_str).unwrap().0;
 
         // 验证默认值
         assert_eq!(style.display, Display::Flex);
@@ -703,7 +1724,7 @@ mod tests {
     fn test_space_position_parsing() {
         // 测试位置值的解析
         let style_str = "pos:10cm 20cm auto";
-        let style = Style::from_style_string(style_str).unwrap();
+        let style = Style::from_style_string(style_str).unwrap().0;
 
         // 验证position值
         assert_eq!(
@@ -778,6 +1799,90 @@ mod tests {
         assert!(Style::from_style_string("invalid").is_err());
         assert!(Style::from_style_string("size:10m 5m").is_err()); // 少于3个值
         assert!(Style::from_style_string("display:invalid").is_err());
+
+        // 未知属性不报错，作为诊断随样式返回
+        let (style, diagnostics) = Style::from_style_string("colour:red;size:1m 1m 1m").unwrap();
+        assert_eq!(style.size.x, SizeValue::Length(Length::from_m(1.0)));
+        let diagnostics: Vec<_> = diagnostics.iter().collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostics::UNKNOWN_STYLE_PROPERTY);
+
+        let (style, diagnostics) = Style::from_style_string("size:1500um 1m 2.5mm").unwrap();
+        assert_eq!(style.size.to_string(), "1500um 1m 2.5mm");
+        let messages: Vec<_> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "length 1500um in declaration 'size:1500um 1m 2.5mm' is rounded to 2mm",
+                "length 2.5mm in declaration 'size:1500um 1m 2.5mm' is rounded to 3mm",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_color_parsing() {
+        let orange = Color::from_str("#FF8000").unwrap();
+        assert_eq!((orange.r, orange.g, orange.b, orange.a), (1.0, 128.0 / 255.0, 0.0, 1.0));
+        assert_eq!(Color::from_str("#00000080").unwrap().a, 128.0 / 255.0);
+        assert_eq!(
+            Color::from_str("0.2 0.4 0.6").unwrap(),
+            Color { r: 0.2, g: 0.4, b: 0.6, a: 1.0 }
+        );
+        assert_eq!(Color::from_str("0 0 0 0.5").unwrap().a, 0.5);
+
+        assert!(Color::from_str("#ff80").is_err());
+        assert!(Color::from_str("#gg8000").is_err());
+        assert!(Color::from_str("0.2 0.4").is_err());
+        assert!(Color::from_str("1 2 0").is_err());
+        assert!(Style::from_style_string("material:dark wood").is_err());
+    }
+
+    #[test]
+    fn test_scale_parsing() {
+        assert_eq!(Scale::from_str("0.5").unwrap(), Scale(Dim3::new(0.5, 0.5, 0.5)));
+        assert_eq!(Scale::from_str("0.5").unwrap().to_string(), "0.5");
+        assert_eq!(Scale::from_str("2 1 0.25").unwrap().to_string(), "2 1 0.25");
+        assert!(Scale::from_str("1 1 1").unwrap().is_identity());
+        assert_eq!(
+            Scale(Dim3::new(0.5, 2.0, 0.333)).apply(Dim3::new(Length::from_mm(105), Length::from_cm(10), Length::from_mm(100))),
+            Dim3::new(Length::from_mm(53), Length::from_cm(20), Length::from_mm(33))
+        );
+
+        for invalid in ["0", "-1", "1 1", "1 0 1", "nan", "x"] {
+            assert!(Scale::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_aspect_ratio_parsing() {
+        assert_eq!(AspectRatio::from_str("x/y 2").unwrap(), AspectRatio::Pair(0, 1, 2.0));
+        assert_eq!(AspectRatio::from_str(" Z/X 0.5 ").unwrap().to_string(), "z/x 0.5");
+        let proportions = AspectRatio::from_str("2 1 0.15").unwrap();
+        assert_eq!(proportions.to_string(), "2 1 0.15");
+        assert_eq!(proportions.proportions(), [Some(2.0), Some(1.0), Some(0.15)]);
+        assert_eq!(AspectRatio::Pair(2, 0, 4.0).proportions(), [Some(1.0), None, Some(4.0)]);
+
+        for invalid in ["x/x 2", "x/w 2", "x 2", "x/y 0", "x/y -1", "2 1", "1 1 inf", "x:y 2"] {
+            assert!(AspectRatio::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_rotation_parsing() {
+        let rotation = Rotation::from_str("0 90 0").unwrap();
+        assert_eq!(rotation, Rotation(Dim3::new(0.0, 90.0, 0.0)));
+        assert_eq!(Rotation::from_str("0 -45 180").unwrap().0.y, -45.0);
+        assert!(Rotation::from_str("0 90").is_err());
+        assert!(Rotation::from_str("0 ninety 0").is_err());
+
+        // 绕y轴90度：(cos45, 0, sin45, 0)
+        let [w, x, y, z] = rotation.quaternion();
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        for (actual, expected) in [(w, half), (x, 0.0), (y, half), (z, 0.0)] {
+            assert!((actual - expected).abs() < 1e-12, "{:?}", rotation.quaternion());
+        }
+        assert_eq!(Rotation::default().quaternion(), [1.0, 0.0, 0.0, 0.0]);
+        assert!(Rotation::default().is_zero());
     }
 
     #[test]
@@ -833,4 +1938,156 @@ mod tests {
         assert_eq!(position1.y, PositionValue::Length(Length::from_m(5.0))); // Should remain unchanged
         assert_eq!(position1.z, PositionValue::Auto); // Auto + Length = Auto (current implementation)
     }
+
+    #[test]
+    fn test_merge_precedence() {
+        let mut style = Style::new();
+        style.flex_direction = FlexDirection::X;
+
+        let sheet = Style::from_style_string("size:2m 2m 1m; align-items:center center; order:2").unwrap().0;
+        let inline = Style::from_style_string("size:1m 1m 1m; margin:1cm").unwrap().0;
+        style.merge(&sheet);
+        style.merge(&inline);
+
+        // 后合并的显式声明覆盖前面的值
+        assert_eq!(style.size, SpaceSize::from_str("1m 1m 1m").unwrap());
+        assert_eq!(style.margin, Style::from_style_string("margin:1cm").unwrap().0.margin);
+        // 没有被覆盖的声明保留
+        assert_eq!(style.align_items, AlignItems::from_str("center center").unwrap());
+        assert_eq!(style.order, 2);
+        // 未声明的属性保留原来的默认值，也不算作声明
+        assert_eq!(style.flex_direction, FlexDirection::X);
+        assert!(!style.is_declared("flex-direction"));
+        assert!(style.is_declared("size") && style.is_declared("margin"));
+        assert_eq!(style.declared.iter().filter(|p| **p == "size").count(), 1);
+    }
+
+    #[test]
+    fn test_merge_registered_properties() {
+        for property in STYLE_PROPERTIES {
+            let declaration = format!("{}:{}", property.name, property.example);
+            let specified = Style::from_style_string(&declaration).unwrap().0;
+            let mut style = Style::new();
+            style.merge(&specified);
+            assert_eq!(style, specified, "{}", declaration);
+        }
+    }
+
+    #[test]
+    fn test_style_display_is_canonical() {
+        let style = Style::from_style_string(
+            "display:cube; size:10m 50% auto; margin:1cm 2cm 1cm 2cm 1cm 2cm; color:#FF8000",
+        )
+        .unwrap()
+        .0;
+        // 按注册顺序输出，与声明的先后无关
+        assert_eq!(
+            style.to_string(),
+            "size:10m 50% auto;display:cube;color:#ff8000;margin:1cm 2cm 1cm 2cm 1cm 2cm"
+        );
+        assert_eq!(Style::new().to_string(), "");
+        // 直接赋值的字段不算声明，declare_non_default之后输出
+        let mut style = Style::new();
+        style.gap = Length::from_cm(5);
+        style.overflow = Overflow::Clamp;
+        assert_eq!(style.to_string(), "");
+        style.declare_non_default();
+        assert_eq!(style.to_string(), "gap:5cm;overflow:clamp");
+        assert_eq!(
+            Style::from_style_string("margin:1cm 1cm 1cm;padding:5cm 5cm 5cm").unwrap().0.to_string(),
+            "padding:5cm;margin:1cm"
+        );
+        assert_eq!(
+            Style::from_style_string("align-self:auto;color:0.1 0.2 0.3").unwrap().0.to_string(),
+            "align-self:auto;color:0.1 0.2 0.3 1"
+        );
+    }
+
+    #[test]
+    fn test_registered_properties_round_trip() {
+        for property in STYLE_PROPERTIES {
+            let declaration = format!("{}:{}", property.name, property.example);
+            let style = Style::from_style_string(&declaration).unwrap().0;
+            assert_eq!(Style::from_style_string(&style.to_string()).unwrap().0, style, "{}", declaration);
+        }
+    }
+
+    /// 随机组合每个属性的取值构造Style，检查Display和serde都能还原
+    #[test]
+    fn test_random_styles_round_trip() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+
+        let values: &[(&str, &[&str])] = &[
+            ("size", &["10m 50% auto", "1.5cm 33.33% 500cm", "auto auto auto"]),
+            ("min-size", &["1m auto auto", "12.5% 7mm 0"]),
+            ("max-size", &["auto 50% 2m", "1km 100% 2500um"]),
+            ("aspect-ratio", &["x/y 2", "z/x 0.25", "2 1 0.15"]),
+            ("display", &["flex", "cube"]),
+            ("justify-content", &["flex-start", "space-between", "space-evenly"]),
+            ("align-items", &["center flex-start", "flex-end flex-end"]),
+            ("flex-direction", &["x", "z-reverse"]),
+            ("pos", &["10cm auto auto", "min max random", "0 1.25m 3dm"]),
+            ("flex-basis", &["50%", "auto", "30cm"]),
+            ("align-self", &["auto", "center flex-end"]),
+            ("order", &["-1", "0", "2147483647"]),
+            ("flex-wrap", &["nowrap", "wrap"]),
+            ("flex-grow", &["1", "0.1", "2.5"]),
+            ("flex-shrink", &["0", "0.3333"]),
+            ("padding", &["5cm 5cm 0", "1mm"]),
+            ("gap", &["10cm", "0"]),
+            ("overflow", &["error", "clamp"]),
+            ("color", &["#ff8000", "#00000080", "0.1 0.2 0.3", "1 1 1 0.25"]),
+            ("material", &["wood", "brushed_steel-2"]),
+            ("rotate", &["0 90 0", "-45.5 0.1 180"]),
+            ("scale", &["0.5", "2 1 0.25"]),
+            ("margin", &["auto auto 10cm 10cm 0 0", "1cm", "auto 0 2mm"]),
+        ];
+        assert_eq!(values.len(), STYLE_PROPERTIES.len());
+
+        let mut rng = StdRng::seed_from_u64(1564);
+        for _ in 0..500 {
+            let mut declarations: Vec<String> = values
+                .iter()
+                .filter_map(|(property, choices)| {
+                    let choice = choices[rng.random_range(0..choices.len())];
+                    rng.random_bool(0.5).then(|| format!("{}:{}", property, choice))
+                })
+                .collect();
+            // 打乱声明顺序，规范字符串与顺序无关
+            for index in (1..declarations.len()).rev() {
+                declarations.swap(index, rng.random_range(0..=index));
+            }
+            let declaration = declarations.join(";");
+            let style = Style::from_style_string(&declaration).unwrap().0;
+
+            let canonical = style.to_string();
+            assert_eq!(Style::from_style_string(&canonical).unwrap().0, style, "{}", declaration);
+            assert_eq!(Style::from_str(&canonical).unwrap().to_string(), canonical);
+
+            let json = serde_json::to_string(&style).unwrap();
+            assert_eq!(serde_json::from_str::<Style>(&json).unwrap(), style, "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_style_values_serde() {
+        let size = SpaceSize::from_str("10m 50% auto").unwrap();
+        assert_eq!(serde_json::to_string(&size).unwrap(), r#""10m 50% auto""#);
+        assert_eq!(serde_json::from_str::<SpaceSize>(r#""10m 50% auto""#).unwrap(), size);
+
+        let position = SpacePosition::from_str("min 10cm auto").unwrap();
+        assert_eq!(serde_json::to_string(&position).unwrap(), r#""min 10cm auto""#);
+        assert_eq!(serde_json::from_str::<SpacePosition>(r#""min 10cm auto""#).unwrap(), position);
+
+        assert_eq!(serde_json::to_string(&FlexBasis::Percentage(Percentage::new(50))).unwrap(), r#""50%""#);
+        assert_eq!(serde_json::from_str::<FlexBasis>(r#""auto""#).unwrap(), FlexBasis::Auto);
+
+        let align_items = AlignItems::from_str("center flex-end").unwrap();
+        assert_eq!(serde_json::to_string(&align_items).unwrap(), r#""center flex-end""#);
+        assert_eq!(serde_json::from_str::<AlignItems>(r#""center flex-end""#).unwrap(), align_items);
+
+        // 反序列化时未知属性和非法值都是错误
+        assert!(serde_json::from_str::<Style>(r#""size:1m 1m 1m;colour:red""#).is_err());
+        assert!(serde_json::from_str::<SpaceSize>(r#""1m 1m""#).is_err());
+    }
 }
@@ -0,0 +1,194 @@
+use crate::xml_parser::Element;
+use anyhow::{Result, anyhow};
+
+/// 简单选择器：`#id`、`.class` 或标签名
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Id(String),
+    Class(String),
+    Tag(String),
+}
+
+impl Selector {
+    fn parse(s: &str) -> Result<Self> {
+        let valid = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        let selector = if let Some(id) = s.strip_prefix('#') {
+            Selector::Id(id.to_string())
+        } else if let Some(class) = s.strip_prefix('.') {
+            Selector::Class(class.to_string())
+        } else {
+            Selector::Tag(s.to_string())
+        };
+        match &selector {
+            Selector::Id(name) | Selector::Class(name) | Selector::Tag(name) if valid(name) => {
+                Ok(selector)
+            }
+            _ => Err(anyhow!("Invalid selector: '{}'", s)),
+        }
+    }
+
+    /// 优先级：id > class > 标签
+    fn specificity(&self) -> u8 {
+        match self {
+            Selector::Id(_) => 2,
+            Selector::Class(_) => 1,
+            Selector::Tag(_) => 0,
+        }
+    }
+
+    fn matches(&self, tag: &str, id: Option<&str>, classes: &[String]) -> bool {
+        match self {
+            Selector::Id(name) => id == Some(name.as_str()),
+            Selector::Class(name) => classes.iter().any(|class| class == name),
+            Selector::Tag(name) => name == tag,
+        }
+    }
+}
+
+/// 一条规则：`selector { property:value; ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub selector: Selector,
+    /// 原样保留的声明，和style属性使用同一个解析器
+    pub declarations: String,
+}
+
+/// `<head><layout>` 中的样式表
+///
+/// 匹配的规则按优先级从低到高合并，优先级相同时靠后的规则优先；元素上的style属性最后合并，总是优先
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleSheet {
+    pub rules: Vec<Rule>,
+}
+
+impl StyleSheet {
+    /// 解析样式表，支持 `/* */` 注释和逗号分隔的多个选择器
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = strip_comments(text)?;
+        let mut rules = Vec::new();
+        let mut rest = text.as_str();
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..]
+                .find('}')
+                .map(|close| open + close)
+                .ok_or_else(|| anyhow!("Missing '}}' after '{}'", rest[..open].trim()))?;
+            let declarations = rest[open + 1..close].trim().to_string();
+            if declarations.contains('{') {
+                return Err(anyhow!("Nested '{{' in rule '{}'", rest[..open].trim()));
+            }
+            for selector in rest[..open].split(',') {
+                rules.push(Rule {
+                    selector: Selector::parse(selector.trim())?,
+                    declarations: declarations.clone(),
+                });
+            }
+            rest = &rest[close + 1..];
+        }
+        if !rest.trim().is_empty() {
+            return Err(anyhow!("Unexpected text in style sheet: '{}'", rest.trim()));
+        }
+        Ok(StyleSheet { rules })
+    }
+
+    /// 读取文档 `<head>` 中所有 `<layout>` 的样式表，按文档顺序合并
+    pub fn from_document(root: &Element) -> Result<Self> {
+        let mut sheet = StyleSheet::default();
+        if let Some(head) = root.find_child("head") {
+            for layout in head.find_children("layout") {
+                sheet.rules.extend(StyleSheet::parse(&layout.text)?.rules);
+            }
+        }
+        Ok(sheet)
+    }
+
+    /// 元素匹配的所有规则，按优先级从低到高排列，依次合并即可
+    pub fn matching_rules(&self, tag: &str, id: Option<&str>, classes: &[String]) -> Vec<&Rule> {
+        let mut matched: Vec<(u8, usize, &Rule)> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.selector.matches(tag, id, classes))
+            .map(|(order, rule)| (rule.selector.specificity(), order, rule))
+            .collect();
+        matched.sort_by_key(|(specificity, order, _)| (*specificity, *order));
+        matched.into_iter().map(|(_, _, rule)| rule).collect()
+    }
+
+    /// 元素匹配的所有声明，按优先级从低到高拼接
+    pub fn declarations_for(&self, tag: &str, id: Option<&str>, classes: &[String]) -> String {
+        self.matching_rules(tag, id, classes)
+            .iter()
+            .map(|rule| rule.declarations.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+fn strip_comments(text: &str) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("*/")
+            .ok_or_else(|| anyhow!("Unterminated comment in style sheet"))?;
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style_sheet() {
+        let sheet = StyleSheet::parse(
+            "/* 房间 */ #main { size:2m 2m 1m; flex-direction:x }
+             space, .shelf { size:auto }
+             object {}",
+        )
+        .unwrap();
+        assert_eq!(sheet.rules.len(), 4);
+        assert_eq!(sheet.rules[0].selector, Selector::Id("main".to_string()));
+        assert_eq!(sheet.rules[0].declarations, "size:2m 2m 1m; flex-direction:x");
+        assert_eq!(sheet.rules[1].selector, Selector::Tag("space".to_string()));
+        assert_eq!(sheet.rules[2].selector, Selector::Class("shelf".to_string()));
+        assert_eq!(sheet.rules[3].declarations, "");
+
+        assert!(StyleSheet::parse("#main { size:1m 1m 1m").is_err());
+        assert!(StyleSheet::parse("#main size:1m 1m 1m }").is_err());
+        assert!(StyleSheet::parse("# { size:1m 1m 1m }").is_err());
+        assert!(StyleSheet::parse("space > object { size:1m 1m 1m }").is_err());
+        assert!(StyleSheet::parse("/* open").is_err());
+    }
+
+    #[test]
+    fn test_specificity_ordering() {
+        let sheet = StyleSheet::parse(
+            "#leg { pos:min min min }
+             .legs { pos:max max max }
+             object { pos:auto auto auto }
+             .legs { margin:1cm }
+             object { margin:2cm }",
+        )
+        .unwrap();
+        let classes = vec!["legs".to_string()];
+        // 标签 < class < id，相同优先级按声明顺序
+        assert_eq!(
+            sheet.declarations_for("object", Some("leg"), &classes),
+            "pos:auto auto auto;margin:2cm;pos:max max max;margin:1cm;pos:min min min"
+        );
+        assert_eq!(
+            sheet.declarations_for("object", None, &[]),
+            "pos:auto auto auto;margin:2cm"
+        );
+        assert_eq!(sheet.declarations_for("space", Some("other"), &[]), "");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::diagnostics::{self, Diagnostics};
+use crate::package::Package;
+use crate::xml_parser::{Element, Span};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 元素文本可以直接引用的名字（object和group）所在的命名空间
+pub const ROOT_NAMESPACE: &str = "";
+/// package依赖的命名空间
+pub const DEPENDENCY_NAMESPACE: &str = "dependencies";
+/// 元素id的命名空间
+pub const ID_NAMESPACE: &str = "#";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Object,
+    Group,
+    GroupMember,
+    Dependency,
+    Id,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Object => write!(f, "object"),
+            SymbolKind::Group => write!(f, "group"),
+            SymbolKind::GroupMember => write!(f, "group-member"),
+            SymbolKind::Dependency => write!(f, "dependency"),
+            SymbolKind::Id => write!(f, "id"),
+        }
+    }
+}
+
+/// 符号定义的位置：文件以及文件中的路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub path: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub namespace: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+    /// 定义该符号的元素在源文件中的位置；package中的符号没有位置
+    pub span: Option<Span>,
+}
+
+/// 加载package和文档时引入的所有名字，按定义的顺序
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    fn add(&mut self, name: &str, namespace: &str, kind: SymbolKind, file: &str, path: String) {
+        self.add_at(name, namespace, kind, file, path, None);
+    }
+
+    fn add_at(
+        &mut self,
+        name: &str,
+        namespace: &str,
+        kind: SymbolKind,
+        file: &str,
+        path: String,
+        span: Option<Span>,
+    ) {
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            kind,
+            location: Location {
+                file: file.to_string(),
+                path,
+            },
+            span,
+        });
+    }
+
+    /// 登记package中的object、group、group成员和依赖
+    pub fn add_package(&mut self, package: &Package, file: &str) {
+        let mut objects: Vec<&String> = package.objects.keys().collect();
+        objects.sort();
+        for name in objects {
+            self.add(name, ROOT_NAMESPACE, SymbolKind::Object, file, format!("objects.{}", name));
+        }
+
+        for (index, group) in package.groups.iter().enumerate() {
+            let group_path = format!("groups[{}]", index);
+            self.add(&group.name, ROOT_NAMESPACE, SymbolKind::Group, file, group_path.clone());
+
+            let mut members: Vec<&String> = group.objects.keys().collect();
+            members.sort();
+            for name in members {
+                self.add(
+                    name,
+                    &group.name,
+                    SymbolKind::GroupMember,
+                    file,
+                    format!("{}.objects.{}", group_path, name),
+                );
+            }
+        }
+
+        let mut dependencies: Vec<&String> = package.dependencies.keys().collect();
+        dependencies.sort();
+        for name in dependencies {
+            self.add(
+                name,
+                DEPENDENCY_NAMESPACE,
+                SymbolKind::Dependency,
+                file,
+                format!("dependencies.{}", name),
+            );
+        }
+    }
+
+    /// 登记文档中元素的id
+    pub fn add_document(&mut self, root: &Element, file: &str) {
+        self.add_element_ids(root, file, root.name.clone());
+    }
+
+    /// 按先序登记element和它所有后代的id，用栈代替递归，很深的文档也不会栈溢出
+    fn add_element_ids(&mut self, element: &Element, file: &str, path: String) {
+        let mut stack = vec![(element, path)];
+        while let Some((element, path)) = stack.pop() {
+            if let Some(id) = element.get_attribute("id") {
+                self.add_at(id, ID_NAMESPACE, SymbolKind::Id, file, path.clone(), element.span.clone());
+            }
+            stack.extend(
+                element
+                    .children
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, child)| (child, format!("{}/{}[{}]", path, child.name, index))),
+            );
+        }
+    }
+
+    /// 同一命名空间中重复的名字报告为error，嵌套命名空间（group成员、依赖）中
+    /// 遮蔽了顶层名字的报告为warning
+    pub fn check(&self, diagnostics: &mut Diagnostics) {
+        let mut first_definitions: HashMap<(&str, &str), &Symbol> = HashMap::new();
+        for symbol in &self.symbols {
+            let key = (symbol.namespace.as_str(), symbol.name.as_str());
+            match first_definitions.get(&key) {
+                Some(first) => {
+                    diagnostics.set_element(None, symbol.span.clone());
+                    diagnostics.record(
+                        diagnostics::DUPLICATE_DEFINITION,
+                        format!(
+                            "{} '{}' is defined at {} and again at {}",
+                            symbol.kind, symbol.name, first.location, symbol.location
+                        ),
+                    );
+                }
+                None => {
+                    first_definitions.insert(key, symbol);
+                }
+            }
+        }
+
+        for symbol in &self.symbols {
+            if symbol.namespace == ROOT_NAMESPACE || symbol.namespace == ID_NAMESPACE {
+                continue;
+            }
+            let Some(root) = first_definitions.get(&(ROOT_NAMESPACE, symbol.name.as_str())) else {
+                continue;
+            };
+            diagnostics.record(
+                diagnostics::SHADOWED_DEFINITION,
+                format!(
+                    "{} '{}' at {} shadows {} '{}' at {}",
+                    symbol.kind, symbol.name, symbol.location, root.kind, root.name, root.location
+                ),
+            );
+        }
+        diagnostics.set_element(None, None);
+    }
+
+    /// 便于阅读的符号表，每行一个符号
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for symbol in &self.symbols {
+            let qualified = if symbol.namespace.is_empty() {
+                symbol.name.clone()
+            } else if symbol.namespace == ID_NAMESPACE {
+                format!("#{}", symbol.name)
+            } else {
+                format!("{}::{}", symbol.namespace, symbol.name)
+            };
+            out.push_str(&format!("{:<14} {:<32} {}\n", symbol.kind, qualified, symbol.location));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+
+    fn package(toml_str: &str) -> Package {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    const MEMBER_A: &str = r#"
+        [package]
+        name = "a"
+        description = ""
+        [objects]
+        table_plane = { geom-type = "box", size = "1m 1m 10cm" }
+        [[groups]]
+        name = "bottles"
+        [groups.objects]
+        bottle0 = { size = "5cm 5cm 20cm" }
+    "#;
+
+    const MEMBER_B: &str = r#"
+        groups = []
+        [package]
+        name = "b"
+        description = ""
+        [objects]
+        table_plane = { geom-type = "box", size = "2m 1m 10cm" }
+    "#;
+
+    #[test]
+    fn test_object_defined_in_two_packages() {
+        let mut table = SymbolTable::new();
+        table.add_package(&package(MEMBER_A), "a/package.toml");
+        table.add_package(&package(MEMBER_B), "b/package.toml");
+
+        let mut diagnostics = Diagnostics::default();
+        table.check(&mut diagnostics);
+        let items: Vec<_> = diagnostics.iter().collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, diagnostics::DUPLICATE_DEFINITION);
+        assert_eq!(items[0].severity, Severity::Error);
+        assert!(items[0].message.contains("a/package.toml:objects.table_plane"));
+        assert!(items[0].message.contains("b/package.toml:objects.table_plane"));
+    }
+
+    #[test]
+    fn test_reused_id() {
+        let mut root = Element::new("body".to_string());
+        for _ in 0..2 {
+            let mut shelf = Element::new("space".to_string());
+            shelf.attributes.insert("id".to_string(), "shelf1".to_string());
+            root.children.push(shelf);
+        }
+        let mut table = SymbolTable::new();
+        table.add_document(&root, "scene.rsml");
+
+        let mut diagnostics = Diagnostics::default();
+        table.check(&mut diagnostics);
+        assert_eq!(diagnostics.count(Severity::Error), 1);
+        assert!(table.dump().contains("#shelf1"));
+    }
+
+    #[test]
+    fn test_namespaced_names_do_not_collide() {
+        let other_group = r#"
+            objects = {}
+            [package]
+            name = "c"
+            description = ""
+            [[groups]]
+            name = "cups"
+            [groups.objects]
+            bottle0 = { size = "5cm 5cm 10cm" }
+        "#;
+        let mut table = SymbolTable::new();
+        table.add_package(&package(MEMBER_A), "a/package.toml");
+        table.add_package(&package(other_group), "c/package.toml");
+
+        let mut diagnostics = Diagnostics::default();
+        table.check(&mut diagnostics);
+        assert!(diagnostics.is_empty());
+
+        // 组成员与根命名空间中的物体同名：警告，并给出两处定义位置
+        let shadowing = r#"
+            objects = {}
+            [package]
+            name = "d"
+            description = ""
+            [[groups]]
+            name = "tables"
+            [groups.objects]
+            table_plane = { size = "1m 1m 10cm" }
+        "#;
+        table.add_package(&package(shadowing), "d/package.toml");
+        let mut diagnostics = Diagnostics::default();
+        table.check(&mut diagnostics);
+        let items: Vec<_> = diagnostics.iter().collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, diagnostics::SHADOWED_DEFINITION);
+        assert_eq!(items[0].severity, Severity::Warning);
+        assert!(items[0].message.contains("d/package.toml:groups[0].objects.table_plane"));
+        assert!(items[0].message.contains("a/package.toml:objects.table_plane"));
+    }
+}
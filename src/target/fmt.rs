@@ -0,0 +1,178 @@
+//! Numeric formatting shared by all output targets.
+//!
+//! Lengths are stored in millimeters and every target writes meters, so the
+//! conversion and the precision policy live here instead of in each backend.
+
+use crate::base::Length;
+use crate::dim3::Dim3;
+
+/// How many digits a formatted number keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// A fixed number of decimal places, with trailing zeros trimmed
+    Decimals(usize),
+    /// A fixed number of significant digits, with trailing zeros trimmed
+    Significant(usize),
+    /// The shortest string that parses back to exactly the same f64
+    Exact,
+}
+
+impl Precision {
+    /// Largest absolute difference between `value` and the parsed formatted string
+    pub fn tolerance(&self, value: f64) -> f64 {
+        match self {
+            Precision::Decimals(decimals) => 0.5 * 10f64.powi(-(*decimals as i32)),
+            Precision::Significant(digits) => {
+                if value == 0.0 {
+                    0.0
+                } else {
+                    let exponent = value.abs().log10().floor() as i32;
+                    0.5 * 10f64.powi(exponent + 1 - *digits as i32)
+                }
+            }
+            Precision::Exact => 0.0,
+        }
+    }
+}
+
+/// Format a plain number under the given precision policy
+pub fn number(value: f64, precision: Precision) -> String {
+    match precision {
+        Precision::Decimals(decimals) => trim_zeros(format!("{:.*}", decimals, value)),
+        Precision::Significant(digits) => {
+            if value == 0.0 {
+                return "0".to_string();
+            }
+            let exponent = value.abs().log10().floor() as i32;
+            let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+            let factor = 10f64.powi(exponent + 1 - digits as i32);
+            let rounded = if decimals == 0 {
+                (value / factor).round() * factor
+            } else {
+                value
+            };
+            trim_zeros(format!("{:.*}", decimals, rounded))
+        }
+        Precision::Exact => format!("{}", value),
+    }
+}
+
+/// Format a length as meters
+pub fn meters(length: Length, precision: Precision) -> String {
+    number(length.mm() as f64 / 1000.0, precision)
+}
+
+/// Format a Dim3 of lengths as space separated meters, e.g. "1 0.5 0.05"
+pub fn vector3(dim3: Dim3<Length>, precision: Precision) -> String {
+    number3(dim3.map(|length| length.mm() as f64 / 1000.0), precision)
+}
+
+/// Format three plain numbers separated by spaces, e.g. a gravity of "0 0 -9.81"
+pub fn number3(vector: Dim3<f64>, precision: Precision) -> String {
+    format!(
+        "{} {} {}",
+        number(vector.x, precision),
+        number(vector.y, precision),
+        number(vector.z, precision)
+    )
+}
+
+/// The offset of `position` from `origin` in meters; negative where a child sticks out
+/// of its parent space
+pub fn offset(position: Dim3<Length>, origin: Dim3<Length>) -> Dim3<f64> {
+    position.zip_with(origin, |position, origin| {
+        (position.mm() as i64 - origin.mm() as i64) as f64 / 1000.0
+    })
+}
+
+/// 去除小数部分末尾的0以及多余的小数点，"-0"统一为"0"
+fn trim_zeros(s: String) -> String {
+    let trimmed = if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s.as_str()
+    };
+    if trimmed == "-0" {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICIES: [Precision; 5] = [
+        Precision::Decimals(3),
+        Precision::Decimals(6),
+        Precision::Significant(3),
+        Precision::Significant(9),
+        Precision::Exact,
+    ];
+
+    #[test]
+    fn test_meters_boundaries() {
+        let zero = Length::from_mm(0);
+        let one_mm = Length::from_mm(1);
+        let max = Length::from_mm(u32::MAX);
+
+        for policy in POLICIES {
+            assert_eq!(meters(zero, policy), "0");
+        }
+
+        assert_eq!(meters(one_mm, Precision::Decimals(6)), "0.001");
+        assert_eq!(meters(one_mm, Precision::Decimals(2)), "0");
+        assert_eq!(meters(one_mm, Precision::Significant(3)), "0.001");
+        assert_eq!(meters(one_mm, Precision::Exact), "0.001");
+
+        assert_eq!(meters(max, Precision::Decimals(3)), "4294967.295");
+        assert_eq!(meters(max, Precision::Significant(3)), "4290000");
+        assert_eq!(meters(max, Precision::Exact), "4294967.295");
+    }
+
+    #[test]
+    fn test_round_trip_within_precision() {
+        for mm in [0, 1, 7, 999, 1000, 1234, 33_333, 1_000_001, u32::MAX] {
+            let value = mm as f64 / 1000.0;
+            for policy in POLICIES {
+                let formatted = meters(Length::from_mm(mm), policy);
+                let parsed: f64 = formatted.parse().unwrap();
+                let tolerance = policy.tolerance(value);
+                assert!(
+                    (parsed - value).abs() <= tolerance + f64::EPSILON * value.abs(),
+                    "{} formatted as {} under {:?}",
+                    value,
+                    formatted,
+                    policy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sub_millimeter_numbers() {
+        // Length目前以毫米为单位，这里直接验证小于1mm的数值不会被格式化成"0"
+        assert_eq!(number(0.0000004, Precision::Significant(3)), "0.0000004");
+        assert_eq!(number(0.0000004, Precision::Exact), "0.0000004");
+        assert_eq!(number(0.0000004, Precision::Decimals(6)), "0");
+    }
+
+    #[test]
+    fn test_vector3() {
+        let dim3 = Dim3::new(
+            Length::from_m(1.0),
+            Length::from_cm(50),
+            Length::from_mm(5),
+        );
+        assert_eq!(vector3(dim3, Precision::Decimals(6)), "1 0.5 0.005");
+        assert_eq!(number3(Dim3::new(0.0, -0.0, -9.81), Precision::Decimals(6)), "0 0 -9.81");
+    }
+
+    #[test]
+    fn test_offset() {
+        let origin = Dim3::new(Length::from_m(1.0), Length::from_cm(50), Length::from_mm(0));
+        let position = Dim3::new(Length::from_m(1.5), Length::from_cm(20), Length::from_mm(5));
+        assert_eq!(offset(position, origin), Dim3::new(0.5, -0.3, 0.005));
+    }
+}
@@ -0,0 +1,1333 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::package::GeomType as PackageGeomType;
+use crate::render_tree::{NodeId, RenderNode, RenderNodeType, RenderTree};
+use crate::settings::LightType;
+use crate::style::{Color, Rotation};
+use crate::world::{Floor, UpAxis};
+use crate::target::fmt::{self, Precision};
+use crate::target::mesh_path;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+pub struct MjcfGenerator;
+
+/// MJCF的输出选项
+#[derive(Debug, Clone, Default)]
+pub struct MjcfOptions {
+    /// 在每个geom之后输出尺寸和位置的注释
+    pub annotate: bool,
+    /// 所有geom直接放在worldbody中，不为space生成body
+    pub flat: bool,
+}
+
+impl MjcfGenerator {
+    /// MJCF中数值的格式：保留6位小数（去除尾随零）
+    pub const PRECISION: Precision = Precision::Decimals(6);
+
+    /// dynamic物体的占位质量（kg）
+    pub const DEFAULT_MASS: f64 = 1.0;
+
+    /// 地面渲染时的网格间距（m）
+    pub const PLANE_GRID_SPACING: f64 = 0.1;
+}
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "mujoco")]
+pub struct Mujoco {
+    #[serde(rename = "@model")] // XML 属性
+    pub model: String,
+
+    #[serde(rename = "option")]
+    pub option: MjOption,
+
+    /// 透传属性的元数据，没有时不输出
+    #[serde(rename = "custom", default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<Custom>,
+
+    /// mesh类型的geom引用的网格文件，没有时不输出
+    #[serde(rename = "asset", default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<Asset>,
+
+    #[serde(rename = "worldbody")]
+    pub worldbody: WorldBody,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MjOption {
+    #[serde(rename = "@gravity")]
+    pub gravity: String, // 例如 "0 0 -9.81"
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Custom {
+    #[serde(rename = "text")]
+    pub texts: Vec<CustomText>,
+}
+
+/// `<text name="meta:<geom>:<attribute>" data="<value>"/>`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CustomText {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "@data")]
+    pub data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Asset {
+    #[serde(rename = "mesh", default, skip_serializing_if = "Vec::is_empty")]
+    pub meshes: Vec<MeshAsset>,
+
+    #[serde(rename = "material", default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<MaterialAsset>,
+}
+
+/// `<mesh name="..." file="..."/>`，同一个文件只注册一次
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MeshAsset {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "@file")]
+    pub file: String,
+
+    /// 物体style中的scale，没有缩放时不输出
+    #[serde(rename = "@scale", default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<String>,
+}
+
+/// `<material name="..."/>`，由style中的material属性引用，外观在MJCF中自行补充
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MaterialAsset {
+    #[serde(rename = "@name")]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct WorldBody {
+    #[serde(rename = "geom", default)]
+    pub geoms: Vec<Geom>,
+
+    #[serde(rename = "body", default, skip_serializing_if = "Vec::is_empty")]
+    pub bodies: Vec<Body>,
+
+    #[serde(rename = "light")]
+    pub lights: Vec<Light>,
+}
+
+/// 每个space对应一个body，位置是相对于父body的最小角；
+/// dynamic的物体是worldbody下带freejoint的body，位置是物体中心的世界坐标
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Body {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "@pos")]
+    pub pos: String,
+
+    #[serde(rename = "freejoint", default, skip_serializing_if = "Option::is_none")]
+    pub freejoint: Option<FreeJoint>,
+
+    #[serde(rename = "inertial", default, skip_serializing_if = "Option::is_none")]
+    pub inertial: Option<Inertial>,
+
+    #[serde(rename = "geom", default)]
+    pub geoms: Vec<Geom>,
+
+    #[serde(rename = "body", default, skip_serializing_if = "Vec::is_empty")]
+    pub bodies: Vec<Body>,
+}
+
+impl Drop for Body {
+    /// 逐个释放嵌套的body，很深的场景不会因为递归释放而栈溢出
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.bodies);
+        while let Some(mut body) = stack.pop() {
+            stack.append(&mut body.bodies);
+        }
+    }
+}
+
+/// `<freejoint name="..."/>`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FreeJoint {
+    #[serde(rename = "@name")]
+    pub name: String,
+}
+
+/// 占位的质量和惯量：按DEFAULT_MASS的均匀长方体计算，需要真实物理参数时手动修改
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Inertial {
+    #[serde(rename = "@pos")]
+    pub pos: String,
+
+    #[serde(rename = "@mass")]
+    pub mass: String,
+
+    #[serde(rename = "@diaginertia")]
+    pub diaginertia: String,
+}
+
+impl Mujoco {
+    /// 场景中物体的geom，即不包括地面的all_geoms
+    pub fn item_geoms(&self) -> Vec<&Geom> {
+        self.all_geoms()
+            .into_iter()
+            .filter(|geom| geom.geom_type != GeomType::Plane)
+            .collect()
+    }
+
+    /// 按XML中的顺序列出所有geom，包括嵌套body中的
+    pub fn all_geoms(&self) -> Vec<&Geom> {
+        let mut geoms: Vec<&Geom> = self.worldbody.geoms.iter().collect();
+        let mut stack: Vec<&Body> = self.worldbody.bodies.iter().rev().collect();
+        while let Some(body) = stack.pop() {
+            geoms.extend(&body.geoms);
+            stack.extend(body.bodies.iter().rev());
+        }
+        geoms
+    }
+}
+
+/// MJCF的type属性只接受小写的名称
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeomType {
+    Box, // 长宽高
+    Mesh,
+    Plane, // 地面，size是半长和网格间距
+}
+
+impl From<PackageGeomType> for GeomType {
+    fn from(geom_type: PackageGeomType) -> Self {
+        match geom_type {
+            PackageGeomType::Box => GeomType::Box,
+            PackageGeomType::Mesh => GeomType::Mesh,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Geom {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    /// mesh类型的geom尺寸由网格决定，不输出size
+    #[serde(rename = "@size", default, skip_serializing_if = "String::is_empty")]
+    pub size: String, // 半长，例如 "0.5 0.5 0.05"
+
+    #[serde(rename = "@pos")]
+    pub pos: String, // 中心，例如 "1 1 0.05"
+
+    /// style中的rotate，四元数 "w x y z"，绕geom中心旋转；没有旋转时不输出
+    #[serde(rename = "@quat", default, skip_serializing_if = "Option::is_none")]
+    pub quat: Option<String>,
+
+    #[serde(rename = "@type")]
+    pub geom_type: GeomType, // 例如 "box"
+
+    /// 引用的asset中的mesh名称，缩放过的mesh是 "文件@缩放"，每种缩放单独注册一个asset
+    #[serde(rename = "@mesh", default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<String>,
+
+    /// mesh的缩放，输出到asset中
+    #[serde(skip)]
+    pub mesh_scale: Option<String>,
+
+    /// style中的color，没有时使用MuJoCo的默认颜色
+    #[serde(rename = "@rgba", default, skip_serializing_if = "Option::is_none")]
+    pub rgba: Option<String>,
+
+    /// style中的material，引用asset中的同名material
+    #[serde(rename = "@material", default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+
+    /// 人工审阅用的注释，使用完整的尺寸和世界坐标中的最小角，例如 "1m x 1m x 1dm @ (5dm, 2m, 0m)"，只在annotate模式下输出
+    #[serde(skip)]
+    pub annotation: String,
+
+    /// 源元素上schema未定义的属性，输出到custom中
+    #[serde(skip)]
+    pub metadata: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Light {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "@pos")]
+    pub pos: String, // 例如 "0 0 2"
+
+    #[serde(rename = "@mode", default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>, // 例如 "trackcom"
+
+    #[serde(rename = "@directional", default, skip_serializing_if = "Option::is_none")]
+    pub directional: Option<bool>,
+
+    #[serde(rename = "@dir", default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+
+    #[serde(rename = "@diffuse", default, skip_serializing_if = "Option::is_none")]
+    pub diffuse: Option<String>, // rgb，例如 "0.8 0.8 0.8"
+}
+
+impl MjcfGenerator {
+    /// 使用默认选项生成，每个space生成一个嵌套的body
+    pub fn generate(render_tree: &RenderTree) -> Result<Mujoco> {
+        Self::generate_with(render_tree, &MjcfOptions::default())
+    }
+
+    pub fn generate_with(render_tree: &RenderTree, options: &MjcfOptions) -> Result<Mujoco> {
+        let mut geoms = Vec::new();
+        let mut bodies = Vec::new();
+        // freejoint只能用在worldbody的直接子body上，两种模式下dynamic物体都放在最外层
+        let mut free_bodies = Vec::new();
+        // 自动生成的名称避开所有用户id和导入物体的名称
+        let mut names = UniqueNames::new(
+            render_tree
+                .ids()
+                .map(str::to_string)
+                .chain(render_tree.imported.iter().map(|item| item.name.clone())),
+        );
+
+        // 遍历渲染树，收集所有的Item节点；嵌套模式下从<body>开始，它就是worldbody
+        match render_tree.find_body_node() {
+            Some(body_node) if !options.flat => {
+                Self::collect_bodies(render_tree, body_node, &mut geoms, &mut bodies, &mut free_bodies, &mut names)?;
+            }
+            _ => Self::collect_item_geoms(render_tree, &mut geoms, &mut free_bodies, &mut names)?,
+        }
+        bodies.extend(free_bodies);
+
+        // 导入的固定物体，名称已带命名空间前缀
+        for item in &render_tree.imported {
+            let (size, pos) = Self::box_size_and_pos(item.size(), item.position(), Dim3::default());
+            let mesh = mesh_path(&item.geom_type, &item.path, &item.name)?;
+            geoms.push(Geom {
+                name: item.name.clone(),
+                size: if mesh.is_some() { String::new() } else { size },
+                pos,
+                quat: None,
+                geom_type: item.geom_type.clone().into(),
+                mesh,
+                mesh_scale: None,
+                rgba: None,
+                material: None,
+                annotation: Self::annotation(item.size(), item.position()),
+                metadata: item.attributes.clone(),
+            });
+        }
+        
+        // 地面放在最前面，和body一样大
+        if let Some(plane) = Self::floor_plane(render_tree) {
+            geoms.insert(0, plane);
+        }
+
+        // <settings>中声明的灯光朝向地面；没有声明时添加默认光源，位于地面上方2m
+        let up = render_tree.world.up_vector();
+        let lights = if render_tree.settings.lights.is_empty() {
+            vec![Light {
+                name: "default_light".to_string(),
+                pos: fmt::number3(Dim3::new(up.x * 2.0, up.y * 2.0, up.z * 2.0), Self::PRECISION),
+                mode: Some("trackcom".to_string()),
+                directional: None,
+                dir: None,
+                diffuse: None,
+            }]
+        } else {
+            render_tree
+                .settings
+                .lights
+                .iter()
+                .map(|light| Light {
+                    name: light.name.clone(),
+                    pos: fmt::vector3(light.pos, Self::PRECISION),
+                    mode: None,
+                    directional: (light.light_type == LightType::Directional).then_some(true),
+                    dir: Some(fmt::number3(Dim3::new(-up.x, -up.y, -up.z), Self::PRECISION)),
+                    diffuse: light.diffuse.map(|color| {
+                        [color.r, color.g, color.b]
+                            .map(|component| fmt::number(component, Self::PRECISION))
+                            .join(" ")
+                    }),
+                })
+                .collect()
+        };
+        
+        let mut mujoco = Mujoco {
+            model: "rsml_model".to_string(),
+            option: MjOption {
+                gravity: fmt::number3(render_tree.world.gravity(), Self::PRECISION),
+            },
+            custom: None,
+            asset: None,
+            worldbody: WorldBody {
+                geoms,
+                bodies,
+                lights,
+            },
+        };
+        let texts: Vec<CustomText> = mujoco
+            .all_geoms()
+            .into_iter()
+            .flat_map(|geom| {
+                geom.metadata.iter().map(|(key, value)| CustomText {
+                    name: format!("meta:{}:{}", geom.name, key),
+                    data: value.clone(),
+                })
+            })
+            .collect();
+        mujoco.custom = (!texts.is_empty()).then_some(Custom { texts });
+
+        // 没有缩放的mesh的名称就是文件路径，按首次出现的顺序去重
+        let mut meshes: Vec<MeshAsset> = Vec::new();
+        for geom in mujoco.all_geoms() {
+            let Some(mesh) = &geom.mesh else {
+                continue;
+            };
+            if !meshes.iter().any(|asset| &asset.name == mesh) {
+                let file = match &geom.mesh_scale {
+                    Some(_) => mesh.rsplit_once('@').map_or(mesh.as_str(), |(file, _)| file),
+                    None => mesh.as_str(),
+                };
+                meshes.push(MeshAsset {
+                    name: mesh.clone(),
+                    file: file.to_string(),
+                    scale: geom.mesh_scale.clone(),
+                });
+            }
+        }
+        let mut materials: Vec<MaterialAsset> = Vec::new();
+        for material in mujoco.all_geoms().into_iter().filter_map(|geom| geom.material.as_ref()) {
+            if !materials.iter().any(|asset| &asset.name == material) {
+                materials.push(MaterialAsset {
+                    name: material.clone(),
+                });
+            }
+        }
+        mujoco.asset = (!meshes.is_empty() || !materials.is_empty()).then_some(Asset { meshes, materials });
+        Ok(mujoco)
+    }
+    
+    /// 序列化为XML；annotate为true时在每个geom之后插入尺寸和位置的注释
+    pub fn to_xml(mujoco: &Mujoco, annotate: bool) -> std::result::Result<String, quick_xml::SeError> {
+        let xml = quick_xml::se::to_string(mujoco)?;
+        if !annotate {
+            return Ok(xml);
+        }
+
+        let mut out = String::with_capacity(xml.len());
+        let mut rest = xml.as_str();
+        for geom in mujoco.all_geoms() {
+            // 属性值中的'>'会被转义，所以第一个"/>"就是geom的结尾
+            let Some(end) = rest
+                .find("<geom ")
+                .and_then(|start| rest[start..].find("/>").map(|end| start + end + 2))
+            else {
+                break;
+            };
+            out.push_str(&rest[..end]);
+            out.push_str(&format!("<!-- {} -->", geom.annotation.replace("--", "- -")));
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// 生成MJCF并写入文件，写入前检查生成的XML可以重新解析；返回写入的内容
+    pub fn write_to_file(
+        render_tree: &RenderTree,
+        path: impl AsRef<Path>,
+        options: &MjcfOptions,
+    ) -> Result<String> {
+        let xml = Self::to_checked_xml(render_tree, options)?;
+        std::fs::write(path, &xml)?;
+        Ok(xml)
+    }
+
+    /// 生成MJCF并检查生成的XML可以重新解析，不写入文件
+    pub fn to_checked_xml(render_tree: &RenderTree, options: &MjcfOptions) -> Result<String> {
+        let mujoco = Self::generate_with(render_tree, options)?;
+        let mjcf_error = |message: String| RsmlError::ParseError {
+            field: "mjcf".to_string(),
+            message,
+        };
+        let xml = Self::to_xml(&mujoco, options.annotate).map_err(|e| mjcf_error(e.to_string()))?;
+        // MuJoCo只接受小写的根元素
+        if !xml.starts_with("<mujoco ") {
+            return Err(mjcf_error(format!(
+                "expected a <mujoco> root element, found {}",
+                xml.split(['>', ' ']).next().unwrap_or_default()
+            )));
+        }
+        let parsed: Mujoco = quick_xml::de::from_str(&xml).map_err(|e| mjcf_error(e.to_string()))?;
+        if parsed.all_geoms().len() != mujoco.all_geoms().len() {
+            return Err(mjcf_error(format!(
+                "expected {} geoms after re-parsing, found {}",
+                mujoco.all_geoms().len(),
+                parsed.all_geoms().len()
+            )));
+        }
+        Ok(xml)
+    }
+
+    /// 使用Length的Display格式化，与其他工具的输出保持一致
+    fn annotation(size: Dim3<Length>, pos: Dim3<Length>) -> String {
+        format!(
+            "{} x {} x {} @ ({}, {}, {})",
+            size.x, size.y, size.z, pos.x, pos.y, pos.z
+        )
+    }
+
+    /// 渲染树中的尺寸是完整的长宽高，位置是最小角；
+    /// MJCF的box尺寸是半长，位置是相对于所在body原点origin的中心，单位为米
+    fn box_size_and_pos(
+        size: Dim3<Length>,
+        min: Dim3<Length>,
+        origin: Dim3<Length>,
+    ) -> (String, String) {
+        let half = size.map(|length| length.mm() as f64 / 2000.0);
+        let center = fmt::offset(min, origin).zip_with(half, |offset, half| offset + half);
+        (fmt::number3(half, Self::PRECISION), fmt::number3(center, Self::PRECISION))
+    }
+
+    /// 和<body>一样大的地面，位于world的地面一侧，法线指向up方向；关闭地面或body没有计算尺寸时返回None
+    fn floor_plane(render_tree: &RenderTree) -> Option<Geom> {
+        if !render_tree.settings.ground_plane {
+            return None;
+        }
+        let body_ref = render_tree.node(render_tree.find_body_node()?);
+        let size = body_ref.computed_style.size.get_length()?;
+        let min = body_ref.computed_style.position.get_length().unwrap_or_default();
+
+        let meters = |length: Length| length.mm() as f64 / 1000.0;
+        let center = |min: Length, size: Length| meters(min) + meters(size) / 2.0;
+        let floor = |min: Length, size: Length| match render_tree.world.floor {
+            Floor::Min => meters(min),
+            Floor::Max => meters(min) + meters(size),
+        };
+        // plane的法线是局部z轴，旋转到up方向后局部x、y轴对应的世界轴决定半长
+        let (pos, extents) = match render_tree.world.up {
+            UpAxis::X => (
+                Dim3::new(floor(min.x, size.x), center(min.y, size.y), center(min.z, size.z)),
+                (size.z, size.y),
+            ),
+            UpAxis::Y => (
+                Dim3::new(center(min.x, size.x), floor(min.y, size.y), center(min.z, size.z)),
+                (size.x, size.z),
+            ),
+            UpAxis::Z => (
+                Dim3::new(center(min.x, size.x), center(min.y, size.y), floor(min.z, size.z)),
+                (size.x, size.y),
+            ),
+        };
+        let angles = match (render_tree.world.up, render_tree.world.floor) {
+            (UpAxis::X, Floor::Min) => Dim3::new(0.0, 90.0, 0.0),
+            (UpAxis::X, Floor::Max) => Dim3::new(0.0, -90.0, 0.0),
+            (UpAxis::Y, Floor::Min) => Dim3::new(-90.0, 0.0, 0.0),
+            (UpAxis::Y, Floor::Max) => Dim3::new(90.0, 0.0, 0.0),
+            (UpAxis::Z, Floor::Min) => Dim3::new(0.0, 0.0, 0.0),
+            (UpAxis::Z, Floor::Max) => Dim3::new(180.0, 0.0, 0.0),
+        };
+
+        Some(Geom {
+            name: "floor".to_string(),
+            size: fmt::number3(
+                Dim3::new(meters(extents.0) / 2.0, meters(extents.1) / 2.0, Self::PLANE_GRID_SPACING),
+                Self::PRECISION,
+            ),
+            pos: fmt::number3(pos, Self::PRECISION),
+            quat: Self::format_rotation(Rotation(angles)),
+            geom_type: GeomType::Plane,
+            mesh: None,
+            mesh_scale: None,
+            rgba: None,
+            material: None,
+            annotation: Self::annotation(size, min),
+            metadata: Vec::new(),
+        })
+    }
+
+    fn format_rotation(rotation: Rotation) -> Option<String> {
+        (!rotation.is_zero()).then(|| {
+            rotation
+                .quaternion()
+                .map(|component| fmt::number(component, Self::PRECISION))
+                .join(" ")
+        })
+    }
+
+    fn format_color(color: Color) -> String {
+        [color.r, color.g, color.b, color.a]
+            .map(|component| fmt::number(component, Self::PRECISION))
+            .join(" ")
+    }
+
+    /// Item节点的geom，位置相对于origin；组合物体的每个成员各生成一个Geom。
+    /// count是之前已经生成的geom数量，用于没有id的节点的名称
+    fn item_geoms(
+        node_ref: &RenderNode,
+        origin: Dim3<Length>,
+        count: usize,
+        names: &mut UniqueNames,
+    ) -> Result<Vec<Geom>> {
+        // 生成Geom名称
+        let base_name = if let Some(id) = &node_ref.id {
+            id.clone()
+        } else {
+            names.unique(format!("{}_{}", node_ref.tag_name, count))
+        };
+
+        node_ref
+            .placed_objects()
+            .into_iter()
+            .map(|placed| {
+                let name = match &placed.suffix {
+                    Some(suffix) => format!("{}/{}", base_name, suffix),
+                    None => base_name.clone(),
+                };
+                let (size, pos) = Self::box_size_and_pos(placed.object.size, placed.position, origin);
+                let mesh = mesh_path(&placed.object.geom_type, &placed.object.path, &node_ref.text_content)?;
+                // box的尺寸在布局时已经缩放，mesh的尺寸由文件决定，需要在asset中缩放
+                let scale = node_ref.specified_style.scale;
+                let mesh_scale = (mesh.is_some() && !scale.is_identity()).then(|| fmt::number3(scale.0, Self::PRECISION));
+                let mesh = match &mesh_scale {
+                    Some(scale) => mesh.map(|file| format!("{}@{}", file, scale.replace(' ', "_"))),
+                    None => mesh,
+                };
+                Ok(Geom {
+                    name,
+                    size: if mesh.is_some() { String::new() } else { size },
+                    pos,
+                    quat: Self::format_rotation(node_ref.specified_style.rotate),
+                    geom_type: placed.object.geom_type.clone().into(),
+                    mesh,
+                    mesh_scale,
+                    rgba: node_ref.specified_style.color.map(Self::format_color),
+                    material: node_ref.specified_style.material.clone(),
+                    annotation: Self::annotation(placed.object.size, placed.position),
+                    metadata: node_ref.extra_attributes.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// dynamic的Item节点：每个成员物体是一个带freejoint的body，位于物体中心，geom在body的原点
+    fn free_bodies(node_ref: &RenderNode, count: usize, names: &mut UniqueNames) -> Result<Vec<Body>> {
+        let geoms = Self::item_geoms(node_ref, Dim3::default(), count, names)?;
+        let meters = |length: Length| length.mm() as f64 / 1000.0;
+        Ok(geoms
+            .into_iter()
+            .zip(node_ref.placed_objects())
+            .map(|(mut geom, placed)| {
+                let size = placed.object.size;
+                let (x, y, z) = (meters(size.x), meters(size.y), meters(size.z));
+                let inertia = |a: f64, b: f64| Self::DEFAULT_MASS * (a * a + b * b) / 12.0;
+                Body {
+                    name: geom.name.clone(),
+                    pos: std::mem::replace(&mut geom.pos, "0 0 0".to_string()),
+                    freejoint: Some(FreeJoint {
+                        name: format!("{}_joint", geom.name),
+                    }),
+                    inertial: Some(Inertial {
+                        pos: "0 0 0".to_string(),
+                        mass: fmt::number(Self::DEFAULT_MASS, Self::PRECISION),
+                        diaginertia: fmt::number3(
+                            Dim3::new(inertia(y, z), inertia(x, z), inertia(x, y)),
+                            Self::PRECISION,
+                        ),
+                    }),
+                    geoms: vec![geom],
+                    bodies: Vec::new(),
+                }
+            })
+            .collect())
+    }
+
+    /// 按先序把所有Item节点转换为Geom，dynamic的转换为body
+    fn collect_item_geoms(
+        render_tree: &RenderTree,
+        geoms: &mut Vec<Geom>,
+        free_bodies: &mut Vec<Body>,
+        names: &mut UniqueNames,
+    ) -> Result<()> {
+        for node in render_tree.iter_items() {
+            let node_ref = render_tree.node(node);
+            let count = geoms.len() + free_bodies.len();
+            if node_ref.dynamic {
+                free_bodies.extend(Self::free_bodies(node_ref, count, names)?);
+            } else {
+                geoms.extend(Self::item_geoms(node_ref, Dim3::default(), count, names)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// 把node的后代放进worldbody：space生成嵌套的body，Item生成geom，位置都相对于所在body的原点
+    ///
+    /// 用栈代替递归，很深的文档也不会栈溢出：body按先序放进列表并记录父body的下标，
+    /// 子body总在父body之后，最后从后往前挂到父body上
+    fn collect_bodies(
+        render_tree: &RenderTree,
+        node: NodeId,
+        geoms: &mut Vec<Geom>,
+        bodies: &mut Vec<Body>,
+        free_bodies: &mut Vec<Body>,
+        names: &mut UniqueNames,
+    ) -> Result<()> {
+        let empty_body = |name: String, pos: String| Body {
+            name,
+            pos,
+            freejoint: None,
+            inertial: None,
+            geoms: Vec::new(),
+            bodies: Vec::new(),
+        };
+        // 下标0是worldbody；每个body记录父body的下标和已经生成的子body数量，后者用于没有id的body的名称
+        let mut list = vec![empty_body(String::new(), String::new())];
+        let mut parents = vec![0];
+        let mut child_bodies = vec![0];
+        let mut count = 0;
+        let mut stack: Vec<(NodeId, usize, Dim3<Length>)> = render_tree
+            .node(node)
+            .children
+            .iter()
+            .rev()
+            .map(|&child| (child, 0, Dim3::default()))
+            .collect();
+        while let Some((child, parent, origin)) = stack.pop() {
+            let child_ref = render_tree.node(child);
+            let (body, origin) = if child_ref.node_type == RenderNodeType::Item {
+                if child_ref.dynamic {
+                    let item_bodies = Self::free_bodies(child_ref, count, names)?;
+                    count += item_bodies.len();
+                    free_bodies.extend(item_bodies);
+                } else {
+                    let item_geoms = Self::item_geoms(child_ref, origin, count, names)?;
+                    count += item_geoms.len();
+                    list[parent].geoms.extend(item_geoms);
+                }
+                (parent, origin)
+            } else {
+                let position = child_ref.computed_style.position.get_length().unwrap_or_default();
+                let name = child_ref
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| names.unique(format!("{}_{}", child_ref.tag_name, child_bodies[parent])));
+                child_bodies[parent] += 1;
+                list.push(empty_body(name, fmt::number3(fmt::offset(position, origin), Self::PRECISION)));
+                parents.push(parent);
+                child_bodies.push(0);
+                (list.len() - 1, position)
+            };
+            stack.extend(child_ref.children.iter().rev().map(|&grandchild| (grandchild, body, origin)));
+        }
+
+        // 从后往前挂上去的子body是倒序的
+        while list.len() > 1 {
+            let mut body = list.pop().unwrap();
+            body.bodies.reverse();
+            let parent = parents[list.len()];
+            list[parent].bodies.push(body);
+        }
+        let mut world = list.pop().unwrap();
+        world.bodies.reverse();
+        geoms.append(&mut world.geoms);
+        bodies.append(&mut world.bodies);
+        Ok(())
+    }
+    
+}
+
+/// 自动生成的geom和body名称：和已有的名称重复时加上数字后缀
+struct UniqueNames {
+    used: HashSet<String>,
+    /// 每个名称下次尝试的后缀；更小的后缀都已被使用，很深的文档中同名的body不必从头尝试
+    next_suffix: HashMap<String, usize>,
+}
+
+impl UniqueNames {
+    fn new(reserved: impl IntoIterator<Item = String>) -> Self {
+        UniqueNames {
+            used: reserved.into_iter().collect(),
+            next_suffix: HashMap::new(),
+        }
+    }
+
+    /// `name`，已被使用时依次尝试`name_1`、`name_2`...
+    fn unique(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let suffix = self.next_suffix.entry(name.clone()).or_insert(1);
+        while self.used.contains(&candidate) {
+            candidate = format!("{}_{}", name, suffix);
+            *suffix += 1;
+        }
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Length;
+    use crate::package::GeomType as PackageGeomType;
+    use crate::scene::Builder;
+    use crate::world::World;
+
+    #[test]
+    fn test_length_to_meters() {
+        // Test 1 meter
+        let length = Length::from_m(1.0);
+        assert_eq!(fmt::meters(length, MjcfGenerator::PRECISION), "1");
+        
+        // Test 10 centimeters
+        let length = Length::from_cm(10);
+        assert_eq!(fmt::meters(length, MjcfGenerator::PRECISION), "0.1");
+        
+        // Test 5 millimeters
+        let length = Length::from_mm(5);
+        assert_eq!(fmt::meters(length, MjcfGenerator::PRECISION), "0.005");
+    }
+    
+    #[test]
+    fn test_geom_type_conversion() {
+        let box_type: GeomType = PackageGeomType::Box.into();
+        assert_eq!(box_type, GeomType::Box);
+        
+        let mesh_type: GeomType = PackageGeomType::Mesh.into();
+        assert_eq!(mesh_type, GeomType::Mesh);
+    }
+    
+    #[test]
+    fn test_generate_empty_tree() {
+        // 只有一个Space节点的渲染树
+        let element = crate::xml_parser::Element::new("test".to_string());
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let render_tree = RenderTree::new(&element, &package).unwrap();
+        
+        // 我们直接测试collect_item_geoms方法
+        
+        let mut geoms = Vec::new();
+        let mut free_bodies = Vec::new();
+        MjcfGenerator::collect_item_geoms(&render_tree, &mut geoms, &mut free_bodies, &mut UniqueNames::new([])).unwrap();
+        
+        // 应该没有geom，因为根节点是Space类型
+        assert_eq!(geoms.len(), 0);
+        assert!(free_bodies.is_empty());
+    }
+
+    /// 1m见方的房间里放两个桌腿a、b，up轴由world指定
+    fn world_scene(up: UpAxis) -> Builder {
+        Builder::body()
+            .head(Builder::new_world(&World { up, ..World::default() }))
+            .space(|room| {
+                room.style("size:1m 1m 1m")
+                    .object_with("table_leg", |leg| leg.id("a"))
+                    .object_with("table_leg", |leg| leg.id("b"))
+            })
+    }
+
+    #[test]
+    fn test_world_up_axis() {
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+
+        let stacked_axis = |up: UpAxis| {
+            let mut render_tree = world_scene(up).render_tree(&package).unwrap();
+            render_tree.calculate().unwrap();
+            let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+            let pos = |i: usize| -> Vec<String> {
+                mujoco.item_geoms()[i]
+                    .pos
+                    .split(' ')
+                    .map(|v| v.to_string())
+                    .collect()
+            };
+            let (a, b) = (pos(0), pos(1));
+            let differing: Vec<usize> = (0..3).filter(|&i| a[i] != b[i]).collect();
+            (differing, mujoco.option.gravity)
+        };
+
+        assert_eq!(stacked_axis(UpAxis::Z), (vec![2], "0 0 -9.81".to_string()));
+        assert_eq!(stacked_axis(UpAxis::Y), (vec![1], "0 -9.81 0".to_string()));
+    }
+
+    #[test]
+    fn test_single_item_geom() {
+        use crate::xml_parser::Element;
+
+        let mut leg = Element::new("object".to_string());
+        leg.attributes.insert("id".to_string(), "leg".to_string());
+        leg.attributes.insert("style".to_string(), "pos:1cm 2cm 3cm".to_string());
+        leg.text = "table_leg".to_string();
+        let mut room = Element::new("space".to_string());
+        room.attributes.insert("style".to_string(), "size:1m 1m 1m".to_string());
+        room.children.push(leg);
+        let mut body = Element::new("body".to_string());
+        body.children.push(room);
+        let mut root = Element::new("rsml".to_string());
+        root.children.push(body);
+
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+
+        // table_leg是10cm x 10cm x 50cm的box：半长和中心
+        assert_eq!(mujoco.item_geoms().len(), 1);
+        let geom = mujoco.item_geoms()[0];
+        assert_eq!(geom.name, "leg");
+        assert_eq!(geom.size, "0.05 0.05 0.25");
+        assert_eq!(geom.pos, "0.06 0.07 0.28");
+        assert_eq!(geom.geom_type, GeomType::Box);
+    }
+
+    #[test]
+    fn test_generated_names_do_not_collide() {
+        // 没有id的space在每一层都从space_0开始编号，用户id也可能占用自动生成的名称
+        let xml = r#"<rsml><head></head><body>
+            <space style="size:2m 2m 2m">
+                <space style="size:1m 1m 1m"><object>table_leg</object></space>
+                <space id="object_0" style="size:1m 1m 1m"><object id="space_0">table_leg</object></space>
+            </space>
+        </body></rsml>"#;
+        let dom = crate::xml_parser::parse_xml_str(xml).unwrap();
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+
+        fn names(body: &Body, out: &mut Vec<String>) {
+            out.extend(body.geoms.iter().map(|geom| geom.name.clone()));
+            for child in &body.bodies {
+                out.push(child.name.clone());
+                names(child, out);
+            }
+        }
+        let mut all = Vec::new();
+        for body in &mujoco.worldbody.bodies {
+            all.push(body.name.clone());
+            names(body, &mut all);
+        }
+        assert_eq!(all, ["space_0_1", "space_0_2", "object_0_1", "object_0", "space_0"]);
+    }
+
+    #[test]
+    fn test_nested_bodies_compose_world_positions() {
+        use crate::xml_parser::Element;
+
+        let space = |id: &str, style: &str| {
+            let mut space = Element::new("space".to_string());
+            space.attributes.insert("id".to_string(), id.to_string());
+            space.attributes.insert("style".to_string(), style.to_string());
+            space
+        };
+        let mut leg = Element::new("object".to_string());
+        leg.attributes.insert("id".to_string(), "leg".to_string());
+        leg.text = "table_leg".to_string();
+        let mut shelf = space("shelf", "size:1m 1m 1m;pos:50cm 20cm 0");
+        shelf.children.push(leg);
+        let mut room = space("room", "size:3m 3m 3m;pos:1m 2m 0");
+        room.children.push(shelf);
+        let mut body = Element::new("body".to_string());
+        body.children.push(room);
+        let mut root = Element::new("rsml".to_string());
+        root.children.push(body);
+
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let nested = MjcfGenerator::generate(&render_tree).unwrap();
+        let flat = MjcfGenerator::generate_with(
+            &render_tree,
+            &MjcfOptions {
+                flat: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // 嵌套模式下worldbody中只有地面
+        assert_eq!(nested.worldbody.geoms.len(), 1);
+        let room = &nested.worldbody.bodies[0];
+        let shelf = &room.bodies[0];
+        assert_eq!((room.name.as_str(), shelf.name.as_str()), ("room", "shelf"));
+        assert_eq!(shelf.geoms[0].name, "leg");
+
+        // 各级body的相对位置相加等于平铺模式下的世界坐标
+        let vector = |s: &str| -> Vec<f64> { s.split(' ').map(|v| v.parse().unwrap()).collect() };
+        let composed: Vec<f64> = (0..3)
+            .map(|i| vector(&room.pos)[i] + vector(&shelf.pos)[i] + vector(&shelf.geoms[0].pos)[i])
+            .collect();
+        let world = vector(&flat.item_geoms()[0].pos);
+        for i in 0..3 {
+            assert!((composed[i] - world[i]).abs() < 1e-9, "{:?} != {:?}", composed, world);
+        }
+        assert_eq!(shelf.pos, "0.5 0.2 0");
+        assert!(flat.worldbody.bodies.is_empty());
+    }
+
+    #[test]
+    fn test_mesh_assets_are_deduplicated() {
+        use crate::xml_parser::Element;
+
+        let package = crate::package::Package::from_toml_str(
+            r#"
+            groups = []
+            [package]
+            name = "kitchen"
+            description = ""
+
+            [objects]
+            mug_red = { path = "mugs/mug.obj", size = "10cm 10cm 12cm" }
+            mug_blue = { path = "mugs/mug.obj", size = "10cm 10cm 12cm" }
+            plate = { path = "plates/plate.obj", size = "20cm 20cm 2cm" }
+            bowl = { size = "15cm 15cm 8cm" }
+            "#,
+        )
+        .unwrap();
+        let scene = |objects: &[&str]| {
+            let mut room = Element::new("space".to_string());
+            room.attributes
+                .insert("style".to_string(), "size:1m 1m 1m;flex-direction:x".to_string());
+            for object in objects {
+                let mut item = Element::new("object".to_string());
+                item.text = object.to_string();
+                room.children.push(item);
+            }
+            let mut body = Element::new("body".to_string());
+            body.children.push(room);
+            let mut root = Element::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        let mut render_tree = RenderTree::new(&scene(&["mug_red", "plate", "mug_blue"]), &package).unwrap();
+        render_tree.calculate().unwrap();
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+        let asset = mujoco.asset.as_ref().unwrap();
+        let files: Vec<&str> = asset.meshes.iter().map(|mesh| mesh.file.as_str()).collect();
+        assert_eq!(files, vec!["mugs/mug.obj", "plates/plate.obj"]);
+
+        // mesh类型的geom引用asset，不输出size
+        let geoms = mujoco.item_geoms();
+        assert_eq!(geoms[0].mesh.as_deref(), Some("mugs/mug.obj"));
+        assert_eq!(geoms[2].mesh.as_deref(), Some("mugs/mug.obj"));
+        let xml = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+        assert!(xml.contains(r#"<asset><mesh name="mugs/mug.obj" file="mugs/mug.obj"/>"#));
+        assert!(xml.contains(r#"type="mesh" mesh="mugs/mug.obj""#));
+        // 只有地面输出size
+        assert_eq!(xml.matches("size=").count(), 1);
+
+        let mut render_tree = RenderTree::new(&scene(&["bowl"]), &package).unwrap();
+        render_tree.calculate().unwrap();
+        let err = MjcfGenerator::generate(&render_tree).unwrap_err();
+        assert!(matches!(err, RsmlError::PackageConfigError(_)));
+        assert!(err.to_string().contains("mesh object 'bowl' has no path"));
+    }
+
+    #[test]
+    fn test_scaled_objects() {
+        use crate::xml_parser::Element;
+
+        let package = crate::package::Package::from_toml_str(
+            r#"
+            groups = []
+            [package]
+            name = "warehouse"
+            description = ""
+
+            [objects]
+            mug = { path = "mugs/mug.obj", size = "10cm 10cm 12cm" }
+            crate = { geom-type = "box", size = "60cm 40cm 30cm" }
+            "#,
+        )
+        .unwrap();
+        let scene = |space_style: &str, objects: &[(&str, &str)]| {
+            let mut room = Element::new("space".to_string());
+            room.attributes.insert("style".to_string(), space_style.to_string());
+            for (object, style) in objects {
+                let mut item = Element::new("object".to_string());
+                item.attributes.insert("id".to_string(), format!("{}_{}", object, room.children.len()));
+                item.attributes.insert("style".to_string(), style.to_string());
+                item.text = object.to_string();
+                room.children.push(item);
+            }
+            let mut body = Element::new("body".to_string());
+            body.children.push(room);
+            let mut root = Element::new("rsml".to_string());
+            root.children.push(body);
+            root
+        };
+
+        let objects = [
+            ("crate", "scale:0.5"),
+            ("crate", ""),
+            ("mug", "scale:0.5"),
+            ("mug", "scale:2 1 1"),
+            ("mug", ""),
+            ("mug", "scale:0.5"),
+        ];
+        let mut render_tree = RenderTree::new(&scene("size:3m 1m 1m;flex-direction:x", &objects), &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        // 布局使用缩放后的尺寸
+        let size = |id: &str| render_tree.node(render_tree.find_by_id(id).unwrap()).computed_style.size.get_length().unwrap();
+        assert_eq!(size("crate_0"), Dim3::new(Length::from_cm(30), Length::from_cm(20), Length::from_cm(15)));
+        assert_eq!(size("crate_1"), Dim3::new(Length::from_cm(60), Length::from_cm(40), Length::from_cm(30)));
+        assert_eq!(size("mug_3"), Dim3::new(Length::from_cm(20), Length::from_cm(10), Length::from_cm(12)));
+
+        // box输出缩放后的半长，mesh的缩放写在asset上，每种缩放一个asset
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+        let geoms = mujoco.item_geoms();
+        assert_eq!(geoms[0].size, "0.15 0.1 0.075");
+        assert_eq!(geoms[1].size, "0.3 0.2 0.15");
+        let meshes: Vec<Option<&str>> = geoms[2..].iter().map(|geom| geom.mesh.as_deref()).collect();
+        assert_eq!(
+            meshes,
+            vec![
+                Some("mugs/mug.obj@0.5_0.5_0.5"),
+                Some("mugs/mug.obj@2_1_1"),
+                Some("mugs/mug.obj"),
+                Some("mugs/mug.obj@0.5_0.5_0.5"),
+            ]
+        );
+        let xml = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+        assert!(xml.contains(concat!(
+            r#"<asset><mesh name="mugs/mug.obj@0.5_0.5_0.5" file="mugs/mug.obj" scale="0.5 0.5 0.5"/>"#,
+            r#"<mesh name="mugs/mug.obj@2_1_1" file="mugs/mug.obj" scale="2 1 1"/>"#,
+            r#"<mesh name="mugs/mug.obj" file="mugs/mug.obj"/></asset>"#
+        )));
+
+        // space没有package中的尺寸，不能缩放
+        let error = RenderTree::new(&scene("size:3m 1m 1m;scale:2", &[]), &package).err().unwrap();
+        assert!(error.to_string().contains("scale is only supported on objects and groups"), "{}", error);
+    }
+
+    #[test]
+    fn test_color_and_material_attributes() {
+        use crate::xml_parser::Element;
+
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut room = Element::new("space".to_string());
+        room.attributes
+            .insert("style".to_string(), "size:1m 1m 1m;flex-direction:x".to_string());
+        for (id, style) in [("red", "color:#ff0000"), ("wood", "material:oak"), ("plain", "")] {
+            let mut leg = Element::new("object".to_string());
+            leg.attributes.insert("id".to_string(), id.to_string());
+            leg.attributes.insert("style".to_string(), style.to_string());
+            leg.text = "table_leg".to_string();
+            room.children.push(leg);
+        }
+        let mut body = Element::new("body".to_string());
+        body.children.push(room);
+        let mut root = Element::new("rsml".to_string());
+        root.children.push(body);
+
+        let mut render_tree = RenderTree::new(&root, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+        let geoms = mujoco.item_geoms();
+        assert_eq!(geoms[0].rgba.as_deref(), Some("1 0 0 1"));
+        assert_eq!(geoms[1].material.as_deref(), Some("oak"));
+        // 没有color的geom保持MuJoCo的默认外观
+        assert_eq!((&geoms[2].rgba, &geoms[2].material), (&None, &None));
+
+        let xml = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+        assert!(xml.contains(r#"<asset><material name="oak"/></asset>"#), "{}", xml);
+        assert!(xml.contains(r#"type="box" rgba="1 0 0 1"/>"#), "{}", xml);
+        assert!(xml.contains(r#"type="box" material="oak"/>"#), "{}", xml);
+    }
+
+    #[test]
+    fn test_rotated_geom_quat() {
+        use crate::xml_parser::Element;
+
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let scene = |rotate: &str| {
+            let mut room = Element::new("space".to_string());
+            room.attributes.insert("style".to_string(), "size:1m 1m 1m".to_string());
+            for (id, style) in [("lying", rotate), ("standing", "")] {
+                let mut leg = Element::new("object".to_string());
+                leg.attributes.insert("id".to_string(), id.to_string());
+                leg.attributes.insert("style".to_string(), style.to_string());
+                leg.text = "table_leg".to_string();
+                room.children.push(leg);
+            }
+            let mut body = Element::new("body".to_string());
+            body.children.push(room);
+            let mut root = Element::new("rsml".to_string());
+            root.children.push(body);
+            let mut render_tree = RenderTree::new(&root, &package).unwrap();
+            render_tree.calculate().unwrap();
+            MjcfGenerator::generate(&render_tree).unwrap()
+        };
+
+        let rotated = scene("rotate:0 90 0");
+        let upright = scene("");
+        let geoms = rotated.item_geoms();
+        assert_eq!(geoms[0].quat.as_deref(), Some("0.707107 0 0.707107 0"));
+        assert_eq!(geoms[1].quat, None);
+        // 旋转不参与布局，尺寸和位置与未旋转时相同
+        for (rotated, upright) in geoms.iter().zip(upright.item_geoms()) {
+            assert_eq!((&rotated.size, &rotated.pos), (&upright.size, &upright.pos));
+        }
+
+        let xml = MjcfGenerator::to_xml(&rotated, false).unwrap();
+        assert!(xml.contains(r#"quat="0.707107 0 0.707107 0" type="box"/>"#), "{}", xml);
+    }
+
+    #[test]
+    fn test_dynamic_items_get_freejoints() {
+        use crate::xml_parser::Element;
+
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let scene = |flat: bool| {
+            let mut shelf = Element::new("space".to_string());
+            shelf.attributes.insert("id".to_string(), "shelf".to_string());
+            shelf.attributes
+                .insert("style".to_string(), "size:1m 1m 1m;flex-direction:x".to_string());
+            for (id, dynamic) in [("fixed", None), ("free", Some("true")), ("explicit", Some("false"))] {
+                let mut leg = Element::new("object".to_string());
+                leg.attributes.insert("id".to_string(), id.to_string());
+                if let Some(dynamic) = dynamic {
+                    leg.attributes.insert("dynamic".to_string(), dynamic.to_string());
+                }
+                leg.text = "table_leg".to_string();
+                shelf.children.push(leg);
+            }
+            let mut body = Element::new("body".to_string());
+            body.children.push(shelf);
+            let mut root = Element::new("rsml".to_string());
+            root.children.push(body);
+            let mut render_tree = RenderTree::new(&root, &package).unwrap();
+            render_tree.calculate().unwrap();
+            let options = MjcfOptions {
+                flat,
+                ..Default::default()
+            };
+            MjcfGenerator::generate_with(&render_tree, &options).unwrap()
+        };
+
+        for flat in [false, true] {
+            let mujoco = scene(flat);
+            let xml = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+            assert_eq!(xml.matches("<freejoint").count(), 1, "{}", xml);
+
+            // dynamic的物体总在worldbody下，位置是世界坐标中的中心，和平铺模式下的geom一致
+            let free = mujoco.worldbody.bodies.last().unwrap();
+            assert_eq!(free.name, "free");
+            assert_eq!(free.freejoint.as_ref().unwrap().name, "free_joint");
+            assert_eq!(free.pos, "0.15 0.5 0.25");
+            assert_eq!(free.geoms[0].pos, "0 0 0");
+            let inertial = free.inertial.as_ref().unwrap();
+            assert_eq!(inertial.mass, "1");
+            assert_eq!(inertial.diaginertia, "0.021667 0.021667 0.001667");
+
+            let names: Vec<&str> = mujoco.item_geoms().iter().map(|geom| geom.name.as_str()).collect();
+            assert_eq!(names, vec!["fixed", "explicit", "free"]);
+        }
+    }
+
+    #[test]
+    fn test_settings_lights_and_plane_snapshot() {
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let scene = |settings: Builder, up: UpAxis| {
+            let mut render_tree = world_scene(up).head(settings).render_tree(&package).unwrap();
+            render_tree.calculate().unwrap();
+            MjcfGenerator::generate(&render_tree).unwrap()
+        };
+
+        let mut settings = Builder::new_settings();
+        for (name, pos, light_type, diffuse) in [
+            ("sun", "5m 5m 8m", "directional", "#ffffff"),
+            ("lamp", "1m 1m 3m", "spot", "0.5 0.4 0.3"),
+        ] {
+            settings = settings.light(|light| {
+                light
+                    .attribute("name", name)
+                    .attribute("pos", pos)
+                    .attribute("type", light_type)
+                    .attribute("diffuse", diffuse)
+            });
+        }
+        let mujoco = scene(settings, UpAxis::Z);
+        let xml = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+        let worldbody = xml.split_once("</body>").unwrap().1;
+        assert_eq!(
+            worldbody,
+            concat!(
+                r#"<light name="sun" pos="5 5 8" directional="true" dir="0 0 -1" diffuse="1 1 1"/>"#,
+                r#"<light name="lamp" pos="1 1 3" dir="0 0 -1" diffuse="0.5 0.4 0.3"/>"#,
+                "</worldbody></mujoco>"
+            )
+        );
+        assert!(xml.contains(r#"<worldbody><geom name="floor" size="5 5 0.1" pos="5 5 0" type="plane"/>"#));
+
+        // plane="false"时不输出地面，灯光保持默认
+        let mujoco = scene(Builder::new_settings().attribute("plane", "false"), UpAxis::Z);
+        assert_eq!(mujoco.all_geoms().len(), mujoco.item_geoms().len());
+        assert_eq!(mujoco.worldbody.lights[0].name, "default_light");
+
+        // y轴向上时地面的法线转到+y，半长对应x和z轴
+        let mujoco = scene(Builder::new_settings(), UpAxis::Y);
+        let floor = mujoco.all_geoms()[0];
+        assert_eq!(floor.geom_type, GeomType::Plane);
+        assert_eq!(floor.pos, "5 0 5");
+        assert_eq!(floor.quat.as_deref(), Some("0.707107 -0.707107 0 0"));
+    }
+
+    #[test]
+    fn test_write_to_file_matches_golden() {
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut render_tree = world_scene(UpAxis::Z).render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let path = std::env::temp_dir().join("rsmlc_two_legs.mjcf.xml");
+        let xml = MjcfGenerator::write_to_file(&render_tree, &path, &MjcfOptions::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), xml);
+        assert_eq!(xml, include_str!("testdata/two_legs.mjcf.xml"));
+        assert!(xml.starts_with(r#"<mujoco model="rsml_model">"#) && xml.ends_with("</mujoco>"));
+        assert!(xml.contains(r#"type="box""#));
+    }
+
+    #[test]
+    fn test_annotated_xml_snapshot() {
+        let package = crate::package::Package::from_file("package.toml").unwrap();
+        let mut render_tree = world_scene(UpAxis::Z).render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+
+        let plain = MjcfGenerator::to_xml(&mujoco, false).unwrap();
+        assert_eq!(plain, quick_xml::se::to_string(&mujoco).unwrap());
+        assert!(!plain.contains("<!--"));
+
+        let annotated = MjcfGenerator::to_xml(&mujoco, true).unwrap();
+        assert_eq!(
+            annotated,
+            concat!(
+                r#"<mujoco model="rsml_model"><option gravity="0 0 -9.81"/><worldbody>"#,
+                r#"<geom name="floor" size="5 5 0.1" pos="5 5 0" type="plane"/>"#,
+                "<!-- 10m x 10m x 10m @ (0m, 0m, 0m) -->",
+                r#"<body name="space_0" pos="0 0 0">"#,
+                r#"<geom name="a" size="0.05 0.05 0.25" pos="0.5 0.5 0.75" type="box"/>"#,
+                "<!-- 10cm x 10cm x 50cm @ (45cm, 45cm, 5dm) -->",
+                r#"<geom name="b" size="0.05 0.05 0.25" pos="0.5 0.5 0.25" type="box"/>"#,
+                "<!-- 10cm x 10cm x 50cm @ (45cm, 45cm, 0m) -->",
+                r#"</body><light name="default_light" pos="0 0 2" mode="trackcom"/></worldbody></mujoco>"#
+            )
+        );
+        // 去掉注释后与普通输出完全一致
+        let stripped = annotated
+            .split("<!--")
+            .map(|part| part.split_once("-->").map_or(part, |(_, rest)| rest))
+            .collect::<String>();
+        assert_eq!(stripped, plain);
+    }
+}
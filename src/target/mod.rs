@@ -0,0 +1,147 @@
+pub mod fmt;
+pub mod mjcf;
+pub mod preview;
+pub mod sdf;
+pub mod svg;
+pub mod urdf;
+
+pub use mjcf::{MjcfGenerator, MjcfOptions};
+pub use preview::{PreviewGenerator, PreviewOptions};
+pub use sdf::SdfGenerator;
+pub use svg::{Plane, SvgGenerator, SvgOptions};
+pub use urdf::UrdfGenerator;
+
+use crate::error::{Result, RsmlError};
+use crate::package::GeomType;
+use crate::render_tree::RenderTree;
+use std::fmt as std_fmt;
+use std::str::FromStr;
+
+/// 输出格式，命令行通过 `--target` 选择
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TargetFormat {
+    #[default]
+    Mjcf,
+    Urdf,
+    Sdf,
+    /// 用于查看布局的OBJ网格
+    Preview,
+    /// 二维平面图
+    Svg,
+}
+
+impl TargetFormat {
+    /// 生成该格式的文档，XML格式检查生成的XML可以重新解析；mjcf、preview和svg选项只对各自的格式有效
+    pub fn to_checked_output(
+        &self,
+        render_tree: &RenderTree,
+        mjcf: &MjcfOptions,
+        preview: &PreviewOptions,
+        svg: &SvgOptions,
+    ) -> Result<String> {
+        match self {
+            TargetFormat::Mjcf => MjcfGenerator::to_checked_xml(render_tree, mjcf),
+            TargetFormat::Urdf => UrdfGenerator::to_checked_xml(render_tree),
+            TargetFormat::Sdf => SdfGenerator::to_checked_xml(render_tree),
+            TargetFormat::Preview => PreviewGenerator::to_obj(render_tree, preview),
+            TargetFormat::Svg => SvgGenerator::to_checked_xml(render_tree, svg),
+        }
+    }
+
+    /// 没有指定输出路径时使用的文件名
+    pub fn default_output(&self) -> &'static str {
+        match self {
+            TargetFormat::Mjcf => "output.xml",
+            TargetFormat::Urdf => "output.urdf",
+            TargetFormat::Sdf => "output.sdf",
+            TargetFormat::Preview => "output.obj",
+            TargetFormat::Svg => "output.svg",
+        }
+    }
+}
+
+impl std_fmt::Display for TargetFormat {
+    fn fmt(&self, f: &mut std_fmt::Formatter<'_>) -> std_fmt::Result {
+        match self {
+            TargetFormat::Mjcf => write!(f, "MJCF"),
+            TargetFormat::Urdf => write!(f, "URDF"),
+            TargetFormat::Sdf => write!(f, "SDF"),
+            TargetFormat::Preview => write!(f, "OBJ preview"),
+            TargetFormat::Svg => write!(f, "SVG"),
+        }
+    }
+}
+
+impl FromStr for TargetFormat {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "mjcf" => Ok(TargetFormat::Mjcf),
+            "urdf" => Ok(TargetFormat::Urdf),
+            "sdf" => Ok(TargetFormat::Sdf),
+            "preview" => Ok(TargetFormat::Preview),
+            "svg" => Ok(TargetFormat::Svg),
+            _ => Err(RsmlError::InvalidAttribute {
+                attribute: "--target".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// mesh类型的物体引用的网格文件，box返回None；mesh必须在package中配置path
+pub(crate) fn mesh_path(geom_type: &GeomType, path: &Option<String>, name: &str) -> Result<Option<String>> {
+    match geom_type {
+        GeomType::Box => Ok(None),
+        GeomType::Mesh => path.clone().map(Some).ok_or_else(|| {
+            RsmlError::PackageConfigError(format!(
+                "mesh object '{}' has no path to a mesh file",
+                name
+            ))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::xml_parser::parse_xml_str;
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow_the_stack() {
+        // 10万层嵌套的space，递归生成会在测试线程的栈上溢出
+        const DEPTH: usize = 100_000;
+        let package = Package::from_file("package.toml").unwrap();
+        let xml = format!(
+            "<rsml><head></head><body>{}<object id=\"leg\">table_leg</object>{}</body></rsml>",
+            "<space>".repeat(DEPTH),
+            "</space>".repeat(DEPTH)
+        );
+        let mut render_tree = RenderTree::new(&parse_xml_str(&xml).unwrap(), &package).unwrap();
+        render_tree.calculate().unwrap();
+
+        // URDF中每个space是一个空的link，通过fixed joint连到上一层
+        let robot = UrdfGenerator::generate(&render_tree).unwrap();
+        assert_eq!(robot.links.len(), DEPTH + 2);
+        assert_eq!(robot.joints.len(), DEPTH + 1);
+        assert_eq!(robot.links.last().unwrap().name, "leg");
+        assert!(UrdfGenerator::to_checked_xml(&render_tree).is_ok());
+
+        // SDF把嵌套的space展开到同一个model中
+        let sdf = SdfGenerator::generate(&render_tree).unwrap();
+        assert_eq!(sdf.world.models.len(), 1);
+        assert_eq!(sdf.world.models[0].links[0].name, "leg");
+        assert!(SdfGenerator::to_checked_xml(&render_tree).is_ok());
+
+        // MJCF中每个space是一个嵌套的body
+        let mujoco = MjcfGenerator::generate(&render_tree).unwrap();
+        assert_eq!(mujoco.item_geoms()[0].name, "leg");
+        let flat = MjcfOptions {
+            flat: true,
+            ..Default::default()
+        };
+        assert!(MjcfGenerator::to_checked_xml(&render_tree, &flat).is_ok());
+    }
+}
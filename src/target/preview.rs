@@ -0,0 +1,224 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{RenderNodeType, RenderTree};
+use crate::target::fmt::{self, Precision};
+use std::fmt::Write;
+use std::path::Path;
+
+/// 生成OBJ预览：每个物体是一个轴对齐的长方体（8个顶点、12个三角形），组名是物体的名称，
+/// 位置和尺寸都是世界坐标，单位为米。只用于查看布局，不包含网格和旋转
+pub struct PreviewGenerator;
+
+/// 预览的选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreviewOptions {
+    /// 为body中的每个space输出12条边的线框
+    pub spaces: bool,
+}
+
+/// 长方体的两个三角形一个面，顶点序号按最小角的偏移编码：第0位x、第1位y、第2位z，法线朝外
+const FACES: [[usize; 3]; 12] = [
+    [0, 2, 3],
+    [0, 3, 1],
+    [4, 5, 7],
+    [4, 7, 6],
+    [0, 1, 5],
+    [0, 5, 4],
+    [2, 6, 7],
+    [2, 7, 3],
+    [0, 4, 6],
+    [0, 6, 2],
+    [1, 3, 7],
+    [1, 7, 5],
+];
+
+/// 线框的12条边
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+impl PreviewGenerator {
+    /// 顶点坐标的格式，与MJCF保持一致
+    pub const PRECISION: Precision = Precision::Decimals(6);
+
+    /// 生成OBJ文本
+    pub fn to_obj(render_tree: &RenderTree, options: &PreviewOptions) -> Result<String> {
+        let body_node = render_tree
+            .find_body_node()
+            .ok_or(RsmlError::MissingElement {
+                element: "body".to_string(),
+            })?;
+        let mut obj = Obj::default();
+
+        for id in render_tree.descendants(body_node) {
+            let node_ref = render_tree.node(id);
+            let base_name = match &node_ref.id {
+                Some(name) => name.clone(),
+                None => format!("{}_{}", node_ref.tag_name, id.index()),
+            };
+            match node_ref.node_type {
+                RenderNodeType::Item => {
+                    for placed in node_ref.placed_objects() {
+                        let name = match &placed.suffix {
+                            Some(suffix) => format!("{}/{}", base_name, suffix),
+                            None => base_name.clone(),
+                        };
+                        obj.solid(&name, placed.position, placed.object.size);
+                    }
+                }
+                RenderNodeType::Space if options.spaces && id != body_node => {
+                    let computed = &node_ref.computed_style;
+                    if let (Some(position), Some(size)) = (computed.position.get_length(), computed.size.get_length()) {
+                        obj.wireframe(&base_name, position, size);
+                    }
+                }
+                RenderNodeType::Space => {}
+            }
+        }
+
+        // 导入的固定物体已经是世界坐标
+        for item in &render_tree.imported {
+            obj.solid(&item.name, item.position(), item.size());
+        }
+        Ok(obj.finish())
+    }
+
+    /// 生成OBJ并写入文件；返回写入的内容
+    pub fn write_to_file(render_tree: &RenderTree, path: impl AsRef<Path>, options: &PreviewOptions) -> Result<String> {
+        let obj = Self::to_obj(render_tree, options)?;
+        std::fs::write(path, &obj)?;
+        Ok(obj)
+    }
+}
+
+/// 正在生成的OBJ文档，OBJ的顶点序号从1开始、在整个文件中累计
+#[derive(Default)]
+struct Obj {
+    body: String,
+    vertices: usize,
+    solids: usize,
+    wireframes: usize,
+}
+
+impl Obj {
+    /// 输出一个长方体的8个顶点，返回第一个顶点的序号
+    fn corners(&mut self, name: &str, position: Dim3<Length>, size: Dim3<Length>) -> usize {
+        let first = self.vertices + 1;
+        let _ = writeln!(self.body, "g {}", name.replace(char::is_whitespace, "_"));
+        for corner in 0..8 {
+            let pick = |bit: usize, min: Length, extent: Length| match corner & bit {
+                0 => min,
+                _ => min + extent,
+            };
+            let vertex = Dim3::new(
+                pick(1, position.x, size.x),
+                pick(2, position.y, size.y),
+                pick(4, position.z, size.z),
+            );
+            let _ = writeln!(self.body, "v {}", fmt::vector3(vertex, PreviewGenerator::PRECISION));
+        }
+        self.vertices += 8;
+        first
+    }
+
+    fn solid(&mut self, name: &str, position: Dim3<Length>, size: Dim3<Length>) {
+        let first = self.corners(name, position, size);
+        for [a, b, c] in FACES {
+            let _ = writeln!(self.body, "f {} {} {}", first + a, first + b, first + c);
+        }
+        self.solids += 1;
+    }
+
+    fn wireframe(&mut self, name: &str, position: Dim3<Length>, size: Dim3<Length>) {
+        let first = self.corners(name, position, size);
+        for [a, b] in EDGES {
+            let _ = writeln!(self.body, "l {} {}", first + a, first + b);
+        }
+        self.wireframes += 1;
+    }
+
+    fn finish(self) -> String {
+        format!(
+            "# rsmlc layout preview: {} object(s), {} space(s), meters\n{}",
+            self.solids, self.wireframes, self.body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    /// 1m见方的房间沿x排列两个桌腿（1dm x 1dm x 5dm）
+    fn two_legs() -> Builder {
+        Builder::body().space(|room| {
+            room.id("room")
+                .style("size:1m 1m 1m;flex-direction:x;align-items:flex-start flex-start")
+                .object_with("table_leg", |leg| leg.id("a"))
+                .object_with("table_leg", |leg| leg.id("b"))
+        })
+    }
+
+    fn count(obj: &str, prefix: &str) -> usize {
+        obj.lines().filter(|line| line.starts_with(prefix)).count()
+    }
+
+    #[test]
+    fn test_preview_boxes() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_legs().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let obj = PreviewGenerator::to_obj(&render_tree, &PreviewOptions::default()).unwrap();
+        assert!(obj.starts_with("# rsmlc layout preview: 2 object(s), 0 space(s), meters\n"));
+        assert_eq!(count(&obj, "v "), 16);
+        assert_eq!(count(&obj, "f "), 24);
+        assert_eq!(count(&obj, "l "), 0);
+        let groups: Vec<&str> = obj.lines().filter(|line| line.starts_with("g ")).collect();
+        assert_eq!(groups, ["g a", "g b"]);
+
+        // 第二个桌腿紧挨着第一个：最小角在x=0.1m，最大角在(0.2m, 0.1m, 0.5m)
+        let room = render_tree.node(render_tree.find_by_id("room").unwrap());
+        let origin = room.computed_style.position.get_length().unwrap();
+        let vertex = |x: u32, y: u32, z: u32| {
+            let corner = origin + Dim3::new(Length::from_mm(x), Length::from_mm(y), Length::from_mm(z));
+            format!("v {}", fmt::vector3(corner, PreviewGenerator::PRECISION))
+        };
+        let b: Vec<&str> = obj.lines().skip_while(|line| *line != "g b").skip(1).take(8).collect();
+        assert_eq!(b[0], vertex(100, 0, 0));
+        assert_eq!(b[7], vertex(200, 100, 500));
+        // 面引用的是第二组顶点
+        assert!(obj.contains("\nf 9 11 12\n"));
+    }
+
+    #[test]
+    fn test_preview_space_wireframes() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_legs().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let options = PreviewOptions { spaces: true };
+        let obj = PreviewGenerator::to_obj(&render_tree, &options).unwrap();
+        // body不输出线框，只有房间
+        assert_eq!(count(&obj, "v "), 24);
+        assert_eq!(count(&obj, "f "), 24);
+        assert_eq!(count(&obj, "l "), 12);
+        assert!(obj.starts_with("# rsmlc layout preview: 2 object(s), 1 space(s), meters\n"));
+        assert!(obj.contains("g room\n"));
+        assert!(obj.contains("\nl 1 2\n"));
+    }
+}
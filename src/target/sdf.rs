@@ -0,0 +1,291 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::package::Object;
+use crate::render_tree::{NodeId, RenderNode, RenderNodeType, RenderTree};
+use crate::style::Rotation;
+use crate::target::fmt::{self, Precision};
+use crate::target::mesh_path;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 生成SDF（Gazebo）：每个顶层space是一个静态model，Item是其中的link，位置使用世界坐标
+pub struct SdfGenerator;
+
+impl SdfGenerator {
+    /// SDF中数值的格式，与MJCF保持一致
+    pub const PRECISION: Precision = Precision::Decimals(6);
+
+    pub const VERSION: &'static str = "1.9";
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "sdf")]
+pub struct Sdf {
+    #[serde(rename = "@version")]
+    pub version: String,
+
+    pub world: World,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct World {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    pub gravity: String,
+
+    #[serde(rename = "model", default)]
+    pub models: Vec<Model>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Model {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    /// 布局出来的场景不参与动力学
+    #[serde(rename = "static")]
+    pub is_static: bool,
+
+    #[serde(rename = "link")]
+    pub links: Vec<Link>,
+}
+
+/// link的pose是物体中心的世界坐标，model本身位于原点
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Link {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    pub pose: String,
+
+    pub collision: Shape,
+
+    pub visual: Shape,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Shape {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    pub geometry: Geometry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Geometry {
+    #[serde(rename = "box", default, skip_serializing_if = "Option::is_none")]
+    pub box_shape: Option<BoxShape>,
+
+    #[serde(rename = "mesh", default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshShape>,
+}
+
+/// SDF的box尺寸是完整的长宽高，不是MJCF的半长
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BoxShape {
+    pub size: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MeshShape {
+    pub uri: String,
+}
+
+impl SdfGenerator {
+    pub fn generate(render_tree: &RenderTree) -> Result<Sdf> {
+        let mut models = Vec::new();
+        let body_node = render_tree
+            .find_body_node()
+            .ok_or(RsmlError::MissingElement {
+                element: "body".to_string(),
+            })?;
+
+        for &child in &render_tree.node(body_node).children {
+            let child_ref = render_tree.node(child);
+            let name = child_ref
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("{}_{}", child_ref.tag_name, models.len()));
+            let mut links = Vec::new();
+            if child_ref.node_type == RenderNodeType::Item {
+                Self::push_node_links(&mut links, child_ref, models.len())?;
+            }
+            Self::collect_links(render_tree, child, &mut links)?;
+
+            // Gazebo不接受没有link的model，空的space直接跳过
+            if !links.is_empty() {
+                models.push(Model {
+                    name,
+                    is_static: true,
+                    links,
+                });
+            }
+        }
+
+        // 导入的固定物体各自是一个model
+        for item in &render_tree.imported {
+            let object = Object {
+                geom_type: item.geom_type.clone(),
+                size: item.size(),
+                path: item.path.clone(),
+            };
+            models.push(Model {
+                name: item.name.clone(),
+                is_static: true,
+                links: vec![Self::link(
+                    &item.name,
+                    &item.name,
+                    &object,
+                    item.position(),
+                    Rotation::default(),
+                )?],
+            });
+        }
+
+        Ok(Sdf {
+            version: Self::VERSION.to_string(),
+            world: World {
+                name: "rsml_world".to_string(),
+                gravity: fmt::number3(render_tree.world.gravity(), Self::PRECISION),
+                models,
+            },
+        })
+    }
+
+    pub fn to_xml(sdf: &Sdf) -> std::result::Result<String, quick_xml::SeError> {
+        quick_xml::se::to_string(sdf)
+    }
+
+    /// 生成SDF并写入文件，写入前检查生成的XML可以重新解析；返回写入的内容
+    pub fn write_to_file(render_tree: &RenderTree, path: impl AsRef<Path>) -> Result<String> {
+        let xml = Self::to_checked_xml(render_tree)?;
+        std::fs::write(path, &xml)?;
+        Ok(xml)
+    }
+
+    /// 生成SDF并检查生成的XML可以重新解析，不写入文件
+    pub fn to_checked_xml(render_tree: &RenderTree) -> Result<String> {
+        let sdf = Self::generate(render_tree)?;
+        let sdf_error = |message: String| RsmlError::ParseError {
+            field: "sdf".to_string(),
+            message,
+        };
+        let xml = Self::to_xml(&sdf).map_err(|e| sdf_error(e.to_string()))?;
+        let parsed: Sdf = quick_xml::de::from_str(&xml).map_err(|e| sdf_error(e.to_string()))?;
+        if parsed.world.models.len() != sdf.world.models.len() {
+            return Err(sdf_error(format!(
+                "expected {} models after re-parsing, found {}",
+                sdf.world.models.len(),
+                parsed.world.models.len()
+            )));
+        }
+        Ok(xml)
+    }
+
+    /// 按先序收集node下所有Item的link，嵌套的space展开到同一个model中
+    fn collect_links(render_tree: &RenderTree, node: NodeId, links: &mut Vec<Link>) -> Result<()> {
+        for child in render_tree.descendants(node).skip(1) {
+            let child_ref = render_tree.node(child);
+            if child_ref.node_type == RenderNodeType::Item {
+                Self::push_node_links(links, child_ref, links.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把Item节点放进model的link列表：SDF的link没有父子关系，pose直接使用世界坐标；
+    /// 组合物体的成员命名为`id/成员`，没有id时按link的个数编号
+    fn push_node_links(links: &mut Vec<Link>, node_ref: &RenderNode, count: usize) -> Result<()> {
+        let base_name = match &node_ref.id {
+            Some(id) => id.clone(),
+            None => format!("{}_{}", node_ref.tag_name, count),
+        };
+        for placed in node_ref.placed_objects() {
+            let name = match &placed.suffix {
+                Some(suffix) => format!("{}/{}", base_name, suffix),
+                None => base_name.clone(),
+            };
+            links.push(Self::link(
+                &name,
+                &node_ref.text_content,
+                placed.object,
+                placed.position,
+                node_ref.specified_style.rotate,
+            )?);
+        }
+        Ok(())
+    }
+
+    /// 带几何体的link；object_name是package中的物体名称，用于报错
+    fn link(
+        name: &str,
+        object_name: &str,
+        object: &Object,
+        min: Dim3<Length>,
+        rotation: Rotation,
+    ) -> Result<Link> {
+        let geometry = match mesh_path(&object.geom_type, &object.path, object_name)? {
+            Some(uri) => Geometry {
+                box_shape: None,
+                mesh: Some(MeshShape { uri }),
+            },
+            None => Geometry {
+                box_shape: Some(BoxShape {
+                    size: fmt::vector3(object.size, Self::PRECISION),
+                }),
+                mesh: None,
+            },
+        };
+
+        // pose是物体中心和style中rotate的弧度
+        let center = fmt::offset(min, Dim3::default())
+            .zip_with(object.size, |min, size| min + size.mm() as f64 / 2000.0);
+        let pose = fmt::number3(center, Self::PRECISION);
+        Ok(Link {
+            name: name.to_string(),
+            pose: format!("{} {}", pose, fmt::number3(rotation.radians(), Self::PRECISION)),
+            collision: Shape {
+                name: "collision".to_string(),
+                geometry: geometry.clone(),
+            },
+            visual: Shape {
+                name: "visual".to_string(),
+                geometry,
+            },
+        })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    fn two_legs_scene() -> Builder {
+        Builder::body()
+            .space(|room| {
+                room.id("room")
+                    .style("size:1m 1m 1m")
+                    .object_with("table_leg", |leg| leg.id("a"))
+                    .object_with("table_leg", |leg| leg.id("b"))
+            })
+            // 没有Item的space不生成model
+            .space(|empty| empty.style("size:1m 1m 1m"))
+    }
+
+    #[test]
+    fn test_write_to_file_matches_fixture() {
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = two_legs_scene().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+
+        let path = std::env::temp_dir().join("rsmlc_two_legs.sdf");
+        let xml = SdfGenerator::write_to_file(&render_tree, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), xml);
+        assert_eq!(xml, include_str!("testdata/two_legs.sdf"));
+    }
+}
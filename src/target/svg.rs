@@ -0,0 +1,347 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::render_tree::{RenderNodeType, RenderTree};
+use crate::report;
+use crate::target::fmt::{self, Precision};
+use quick_xml::Reader;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use std::fmt as std_fmt;
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// 生成布局的二维平面图：body的范围是画布，每个space是描边的矩形，每个物体是填充的矩形并标注名称，
+/// 下方有比例尺
+pub struct SvgGenerator;
+
+/// 投影的平面，前一个轴向右，后一个轴向上
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Plane {
+    /// 俯视图
+    #[default]
+    Xy,
+    Xz,
+    Yz,
+}
+
+impl Plane {
+    /// 投影到平面上的两个坐标，单位mm
+    fn project(&self, dim: Dim3<Length>) -> (f64, f64) {
+        let (u, v) = match self {
+            Plane::Xy => (dim.x, dim.y),
+            Plane::Xz => (dim.x, dim.z),
+            Plane::Yz => (dim.y, dim.z),
+        };
+        (u.mm() as f64, v.mm() as f64)
+    }
+}
+
+impl FromStr for Plane {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "xy" => Ok(Plane::Xy),
+            "xz" => Ok(Plane::Xz),
+            "yz" => Ok(Plane::Yz),
+            _ => Err(RsmlError::InvalidAttribute {
+                attribute: "--svg-plane".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std_fmt::Display for Plane {
+    fn fmt(&self, f: &mut std_fmt::Formatter<'_>) -> std_fmt::Result {
+        match self {
+            Plane::Xy => write!(f, "xy"),
+            Plane::Xz => write!(f, "xz"),
+            Plane::Yz => write!(f, "yz"),
+        }
+    }
+}
+
+/// 平面图的选项
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    pub plane: Plane,
+    /// 画布较长一边的像素数，另一边按比例缩放
+    pub max_size: f64,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            plane: Plane::Xy,
+            max_size: 800.0,
+        }
+    }
+}
+
+/// 比例尺所在的底部区域的高度，像素
+const SCALE_BAR_HEIGHT: f64 = 30.0;
+
+/// 一个要画的矩形，坐标是世界坐标
+struct Rect {
+    label: String,
+    /// 物体的颜色按包里的名称区分；space没有填充
+    fill: Option<String>,
+    position: Dim3<Length>,
+    size: Dim3<Length>,
+}
+
+impl SvgGenerator {
+    /// 生成SVG文本
+    pub fn to_svg(render_tree: &RenderTree, options: &SvgOptions) -> Result<String> {
+        let body_node = render_tree
+            .find_body_node()
+            .ok_or(RsmlError::MissingElement {
+                element: "body".to_string(),
+            })?;
+        let body_ref = render_tree.node(body_node);
+        let (Some(origin), Some(extent)) = (
+            body_ref.computed_style.position.get_length(),
+            body_ref.computed_style.size.get_length(),
+        ) else {
+            return Err(RsmlError::StyleComputation {
+                message: "body has no computed size".to_string(),
+            });
+        };
+
+        let mut spaces = Vec::new();
+        let mut items = Vec::new();
+        for id in render_tree.descendants(body_node) {
+            let node_ref = render_tree.node(id);
+            let base_name = match &node_ref.id {
+                Some(name) => name.clone(),
+                None => format!("{}_{}", node_ref.tag_name, id.index()),
+            };
+            match node_ref.node_type {
+                RenderNodeType::Space => {
+                    let computed = &node_ref.computed_style;
+                    if let (Some(position), Some(size)) =
+                        (computed.position.get_length(), computed.size.get_length())
+                    {
+                        spaces.push(Rect {
+                            label: base_name,
+                            fill: None,
+                            position,
+                            size,
+                        });
+                    }
+                }
+                RenderNodeType::Item => {
+                    for placed in node_ref.placed_objects() {
+                        items.push(Rect {
+                            label: match &placed.suffix {
+                                Some(suffix) => format!("{}/{}", base_name, suffix),
+                                None => base_name.clone(),
+                            },
+                            fill: Some(report::color(&node_ref.text_content)),
+                            position: placed.position,
+                            size: placed.object.size,
+                        });
+                    }
+                }
+            }
+        }
+        for item in &render_tree.imported {
+            items.push(Rect {
+                label: item.name.clone(),
+                fill: Some(report::color(&item.name)),
+                position: item.position(),
+                size: item.size(),
+            });
+        }
+
+        let plane = options.plane;
+        let (origin_u, origin_v) = plane.project(origin);
+        let (width, depth) = plane.project(extent);
+        if width == 0.0 || depth == 0.0 {
+            return Err(RsmlError::StyleComputation {
+                message: format!("body has an empty {} projection", plane),
+            });
+        }
+        // 每mm对应的像素数
+        let scale = options.max_size / width.max(depth);
+        let (canvas_width, canvas_height) = (width * scale, depth * scale);
+        let to_canvas = |rect: &Rect| {
+            let (u, v) = plane.project(rect.position);
+            let (w, d) = plane.project(rect.size);
+            // SVG的y轴向下，翻转后第二个轴朝上
+            let x = (u - origin_u) * scale;
+            let y = canvas_height - (v - origin_v + d) * scale;
+            (x, y, w * scale, d * scale)
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.1} {:.1}\">",
+            canvas_width,
+            canvas_height + SCALE_BAR_HEIGHT,
+            canvas_width,
+            canvas_height + SCALE_BAR_HEIGHT
+        );
+        let _ = writeln!(svg, "<g class=\"spaces\" fill=\"none\" stroke=\"#555\">");
+        for space in &spaces {
+            let (x, y, w, h) = to_canvas(space);
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"><title>{}</title></rect>",
+                x,
+                y,
+                w,
+                h,
+                escape(&space.label)
+            );
+        }
+        svg.push_str("</g>\n");
+
+        let _ = writeln!(svg, "<g class=\"items\" fill-opacity=\"0.7\" stroke=\"#333\">");
+        for item in &items {
+            let (x, y, w, h) = to_canvas(item);
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+                x,
+                y,
+                w,
+                h,
+                item.fill.as_deref().unwrap_or("none")
+            );
+        }
+        svg.push_str("</g>\n");
+
+        // 名称画在所有矩形之上
+        let _ = writeln!(svg, "<g class=\"labels\" font-size=\"10\" text-anchor=\"middle\">");
+        for item in &items {
+            let (x, y, w, h) = to_canvas(item);
+            let _ = writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                x + w / 2.0,
+                y + h / 2.0,
+                escape(&item.label)
+            );
+        }
+        svg.push_str("</g>\n");
+
+        write_scale_bar(&mut svg, width, scale, canvas_height);
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    /// 生成SVG并检查它是良构的XML
+    pub fn to_checked_xml(render_tree: &RenderTree, options: &SvgOptions) -> Result<String> {
+        let svg = Self::to_svg(render_tree, options)?;
+        let svg_error = |message: String| RsmlError::ParseError {
+            field: "svg".to_string(),
+            message,
+        };
+        let mut reader = Reader::from_str(&svg);
+        let mut depth = 0i32;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(_)) => depth += 1,
+                Ok(Event::End(_)) => depth -= 1,
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(svg_error(e.to_string())),
+            }
+        }
+        if depth != 0 {
+            return Err(svg_error(format!("{} unclosed element(s)", depth)));
+        }
+        Ok(svg)
+    }
+
+    /// 生成SVG并写入文件；返回写入的内容
+    pub fn write_to_file(render_tree: &RenderTree, path: impl AsRef<Path>, options: &SvgOptions) -> Result<String> {
+        let svg = Self::to_checked_xml(render_tree, options)?;
+        std::fs::write(path, &svg)?;
+        Ok(svg)
+    }
+}
+
+/// 比例尺的长度取1、2、5乘以10的幂，不超过画布宽度的四分之一，标注的单位是米
+fn write_scale_bar(svg: &mut String, width: f64, scale: f64, canvas_height: f64) {
+    let limit = width / 4.0;
+    let mut length = 1.0;
+    while length * 10.0 <= limit {
+        length *= 10.0;
+    }
+    if length * 5.0 <= limit {
+        length *= 5.0;
+    } else if length * 2.0 <= limit {
+        length *= 2.0;
+    }
+    let y = canvas_height + SCALE_BAR_HEIGHT / 2.0;
+    let _ = writeln!(
+        svg,
+        "<g class=\"scale\" stroke=\"#000\" font-size=\"10\"><line x1=\"10\" y1=\"{y:.1}\" x2=\"{x2:.1}\" y2=\"{y:.1}\"/><text x=\"{tx:.1}\" y=\"{ty:.1}\" stroke=\"none\">{label}m</text></g>",
+        y = y,
+        x2 = 10.0 + length * scale,
+        tx = 15.0 + length * scale,
+        ty = y + 4.0,
+        label = fmt::meters(Length::from_mm(length as u32), Precision::Decimals(3)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::xml_parser::parse_xml_file;
+
+    fn floor_plan(options: &SvgOptions) -> String {
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = parse_xml_file("tests/fixtures/floor_plan.xml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+        SvgGenerator::to_checked_xml(&render_tree, options).unwrap()
+    }
+
+    /// 去掉每行首尾的空白和空行，golden文件的缩进和换行符不影响比较
+    fn normalize(svg: &str) -> String {
+        svg.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_floor_plan_matches_golden() {
+        let svg = floor_plan(&SvgOptions::default());
+        assert_eq!(normalize(&svg), normalize(include_str!("testdata/floor_plan.svg")));
+
+        let path = std::env::temp_dir().join("rsmlc_floor_plan.svg");
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = parse_xml_file("tests/fixtures/floor_plan.xml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let written = SvgGenerator::write_to_file(&render_tree, &path, &SvgOptions::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+    }
+
+    #[test]
+    fn test_projection_plane_and_canvas_size() {
+        let options = SvgOptions {
+            plane: Plane::Xz,
+            max_size: 400.0,
+        };
+        let svg = floor_plan(&options);
+        // body是4m x 4m x 2m：xz平面上宽400像素、高200像素，再加上比例尺
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="230""#), "{}", svg);
+        // aisle叠在work上方（z在1m到2m之间），桌腿50cm高，立在aisle的底面上
+        assert!(svg.contains(r#"<rect x="0.0" y="50.0" width="10.0" height="50.0" fill="#), "{}", svg);
+        assert!(svg.contains(">1m</text>"));
+
+        assert_eq!("XZ".parse::<Plane>().unwrap(), Plane::Xz);
+        assert!("zx".parse::<Plane>().is_err());
+    }
+}
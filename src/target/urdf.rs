@@ -0,0 +1,356 @@
+use crate::base::Length;
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::package::Object;
+use crate::render_tree::{NodeId, RenderNode, RenderNodeType, RenderTree};
+use crate::style::Rotation;
+use crate::target::fmt::{self, Precision};
+use crate::target::mesh_path;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 生成URDF：每个space和Item各是一个link，用fixed joint按相对位置连接到父空间的link
+pub struct UrdfGenerator;
+
+impl UrdfGenerator {
+    /// URDF中数值的格式，与MJCF保持一致
+    pub const PRECISION: Precision = Precision::Decimals(6);
+
+    /// <body>对应的根link
+    pub const ROOT_LINK: &'static str = "world";
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "robot")]
+pub struct Robot {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "link")]
+    pub links: Vec<Link>,
+
+    #[serde(rename = "joint", default)]
+    pub joints: Vec<Joint>,
+}
+
+/// space的link没有几何体，只作为子link的坐标系
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Link {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "visual", default, skip_serializing_if = "Option::is_none")]
+    pub visual: Option<Shape>,
+
+    #[serde(rename = "collision", default, skip_serializing_if = "Option::is_none")]
+    pub collision: Option<Shape>,
+}
+
+/// visual和collision使用相同的几何体，原点在link的中心
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Shape {
+    pub geometry: Geometry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Geometry {
+    #[serde(rename = "box", default, skip_serializing_if = "Option::is_none")]
+    pub box_shape: Option<BoxShape>,
+
+    #[serde(rename = "mesh", default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshShape>,
+}
+
+/// URDF的box尺寸是完整的长宽高
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BoxShape {
+    #[serde(rename = "@size")]
+    pub size: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MeshShape {
+    #[serde(rename = "@filename")]
+    pub filename: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Joint {
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    #[serde(rename = "@type")]
+    pub joint_type: String, // 目前只有 "fixed"
+
+    pub parent: LinkRef,
+
+    pub child: LinkRef,
+
+    pub origin: Origin,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct LinkRef {
+    #[serde(rename = "@link")]
+    pub link: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Origin {
+    #[serde(rename = "@xyz")]
+    pub xyz: String,
+
+    /// style中的rotate，弧度；没有旋转时不输出
+    #[serde(rename = "@rpy", default, skip_serializing_if = "Option::is_none")]
+    pub rpy: Option<String>,
+}
+
+impl UrdfGenerator {
+    pub fn generate(render_tree: &RenderTree) -> Result<Robot> {
+        let mut robot = Robot {
+            name: "rsml_model".to_string(),
+            links: vec![Link {
+                name: Self::ROOT_LINK.to_string(),
+                visual: None,
+                collision: None,
+            }],
+            joints: Vec::new(),
+        };
+
+        let body_node = render_tree
+            .find_body_node()
+            .ok_or(RsmlError::MissingElement {
+                element: "body".to_string(),
+            })?;
+        Self::collect_links(render_tree, body_node, Self::ROOT_LINK, Dim3::default(), &mut robot)?;
+
+        // 导入的固定物体使用世界坐标，直接连接到根link
+        for item in &render_tree.imported {
+            let object = Object {
+                geom_type: item.geom_type.clone(),
+                size: item.size(),
+                path: item.path.clone(),
+            };
+            Self::push_item(
+                &mut robot,
+                Self::ROOT_LINK,
+                &item.name,
+                &item.name,
+                &object,
+                fmt::offset(item.position(), Dim3::default()),
+                Rotation::default(),
+            )?;
+        }
+        Ok(robot)
+    }
+
+    pub fn to_xml(robot: &Robot) -> std::result::Result<String, quick_xml::SeError> {
+        quick_xml::se::to_string(robot)
+    }
+
+    /// 生成URDF并写入文件，写入前检查生成的XML可以重新解析；返回写入的内容
+    pub fn write_to_file(render_tree: &RenderTree, path: impl AsRef<Path>) -> Result<String> {
+        let xml = Self::to_checked_xml(render_tree)?;
+        std::fs::write(path, &xml)?;
+        Ok(xml)
+    }
+
+    /// 生成URDF并检查生成的XML可以重新解析，不写入文件
+    pub fn to_checked_xml(render_tree: &RenderTree) -> Result<String> {
+        let robot = Self::generate(render_tree)?;
+        let urdf_error = |message: String| RsmlError::ParseError {
+            field: "urdf".to_string(),
+            message,
+        };
+        let xml = Self::to_xml(&robot).map_err(|e| urdf_error(e.to_string()))?;
+        let parsed: Robot = quick_xml::de::from_str(&xml).map_err(|e| urdf_error(e.to_string()))?;
+        if parsed.links.len() != robot.links.len() {
+            return Err(urdf_error(format!(
+                "expected {} links after re-parsing, found {}",
+                robot.links.len(),
+                parsed.links.len()
+            )));
+        }
+        Ok(xml)
+    }
+
+    /// 把node的后代连接到parent_link：space生成空的link，Item生成带几何体的link，位置相对于所连接的link
+    ///
+    /// 用栈代替递归，很深的文档也不会栈溢出；栈中的每个节点带着它连接的link和该link的原点
+    fn collect_links(
+        render_tree: &RenderTree,
+        node: NodeId,
+        parent_link: &str,
+        origin: Dim3<Length>,
+        robot: &mut Robot,
+    ) -> Result<()> {
+        let mut stack: Vec<(NodeId, String, Dim3<Length>)> = render_tree
+            .node(node)
+            .children
+            .iter()
+            .rev()
+            .map(|&child| (child, parent_link.to_string(), origin))
+            .collect();
+        while let Some((child, parent_link, origin)) = stack.pop() {
+            let child_ref = render_tree.node(child);
+            let (link, origin) = if child_ref.node_type == RenderNodeType::Item {
+                Self::push_node_items(robot, &parent_link, child_ref, origin)?;
+                (parent_link, origin)
+            } else {
+                let position = child_ref.computed_style.position.get_length().unwrap_or_default();
+                let name = child_ref
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| format!("{}_{}", child_ref.tag_name, robot.links.len()));
+                robot.links.push(Link {
+                    name: name.clone(),
+                    visual: None,
+                    collision: None,
+                });
+                robot.joints.push(Self::fixed_joint(&parent_link, &name, fmt::offset(position, origin)));
+                (name, position)
+            };
+            stack.extend(child_ref.children.iter().rev().map(|&grandchild| (grandchild, link.clone(), origin)));
+        }
+        Ok(())
+    }
+
+    /// Item节点的link，组合物体的每个成员各生成一个
+    fn push_node_items(
+        robot: &mut Robot,
+        parent_link: &str,
+        node_ref: &RenderNode,
+        origin: Dim3<Length>,
+    ) -> Result<()> {
+        let base_name = match &node_ref.id {
+            Some(id) => id.clone(),
+            None => format!("{}_{}", node_ref.tag_name, robot.links.len()),
+        };
+        for placed in node_ref.placed_objects() {
+            let name = match &placed.suffix {
+                Some(suffix) => format!("{}/{}", base_name, suffix),
+                None => base_name.clone(),
+            };
+            Self::push_item(
+                robot,
+                parent_link,
+                &name,
+                &node_ref.text_content,
+                placed.object,
+                fmt::offset(placed.position, origin),
+                node_ref.specified_style.rotate,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 带几何体的link，offset是物体最小角相对父link的偏移；object_name是package中的物体名称，用于报错
+    fn push_item(
+        robot: &mut Robot,
+        parent_link: &str,
+        name: &str,
+        object_name: &str,
+        object: &Object,
+        offset: Dim3<f64>,
+        rotation: Rotation,
+    ) -> Result<()> {
+        let geometry = match mesh_path(&object.geom_type, &object.path, object_name)? {
+            Some(filename) => Geometry {
+                box_shape: None,
+                mesh: Some(MeshShape { filename }),
+            },
+            None => Geometry {
+                box_shape: Some(BoxShape {
+                    size: fmt::vector3(object.size, Self::PRECISION),
+                }),
+                mesh: None,
+            },
+        };
+        let shape = Shape { geometry };
+        robot.links.push(Link {
+            name: name.to_string(),
+            visual: Some(shape.clone()),
+            collision: Some(shape),
+        });
+
+        // link的原点在物体中心
+        let half = |length: Length| length.mm() as f64 / 2000.0;
+        let center = Dim3::new(
+            offset.x + half(object.size.x),
+            offset.y + half(object.size.y),
+            offset.z + half(object.size.z),
+        );
+        let mut joint = Self::fixed_joint(parent_link, name, center);
+        if !rotation.is_zero() {
+            joint.origin.rpy = Some(fmt::number3(rotation.radians(), Self::PRECISION));
+        }
+        robot.joints.push(joint);
+        Ok(())
+    }
+
+    fn fixed_joint(parent: &str, child: &str, xyz: Dim3<f64>) -> Joint {
+        Joint {
+            name: format!("{}_joint", child),
+            joint_type: "fixed".to_string(),
+            parent: LinkRef {
+                link: parent.to_string(),
+            },
+            child: LinkRef {
+                link: child.to_string(),
+            },
+            origin: Origin {
+                xyz: fmt::number3(xyz, Self::PRECISION),
+                rpy: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    fn table_scene() -> Builder {
+        Builder::body().space(|room| {
+            room.id("room")
+                .style("size:2m 2m 2m;flex-direction:z")
+                .object_with("table_plane", |table| table.id("table"))
+                .object_with("bottle", |bottle| bottle.id("bottle"))
+        })
+    }
+
+    #[test]
+    fn test_table_and_bottle_snapshot() {
+        let package = Package::from_toml_str(
+            r#"
+            groups = []
+            [package]
+            name = "table"
+            description = ""
+
+            [objects]
+            table_plane = { geom-type = "box", size = "1m 1m 10cm" }
+            bottle = { path = "bottles/bottle.obj", size = "10cm 10cm 30cm" }
+            "#,
+        )
+        .unwrap();
+        let mut render_tree = table_scene().render_tree(&package).unwrap();
+        render_tree.calculate().unwrap();
+        let xml = UrdfGenerator::to_xml(&UrdfGenerator::generate(&render_tree).unwrap()).unwrap();
+        let expected = concat!(
+            r#"<robot name="rsml_model"><link name="world"/><link name="room"/>"#,
+            r#"<link name="table"><visual><geometry><box size="1 1 0.1"/></geometry></visual>"#,
+            r#"<collision><geometry><box size="1 1 0.1"/></geometry></collision></link>"#,
+            r#"<link name="bottle"><visual><geometry><mesh filename="bottles/bottle.obj"/></geometry></visual>"#,
+            r#"<collision><geometry><mesh filename="bottles/bottle.obj"/></geometry></collision></link>"#,
+            r#"<joint name="room_joint" type="fixed"><parent link="world"/><child link="room"/><origin xyz="0 0 0"/></joint>"#,
+            r#"<joint name="table_joint" type="fixed"><parent link="room"/><child link="table"/><origin xyz="1 1 0.05"/></joint>"#,
+            r#"<joint name="bottle_joint" type="fixed"><parent link="room"/><child link="bottle"/><origin xyz="1 1 0.25"/></joint>"#,
+            "</robot>"
+        );
+        // 瓶子放在桌面上：桌子中心0.05m，瓶子中心 0.1 + 0.15 = 0.25m
+        assert_eq!(xml, expected);
+    }
+}
@@ -0,0 +1,341 @@
+//! 可复用的子树：在head中写`<define name="shelf">`，在body中写`<use ref="shelf"/>`
+//!
+//! ```xml
+//! <head>
+//!     <define name="shelf">
+//!         <space style="size:1m 40cm 2m"><object>table_leg</object></space>
+//!     </define>
+//! </head>
+//! <body>
+//!     <use ref="shelf" id="shelf3" style="pos:1m 0 0"/>
+//! </body>
+//! ```
+//!
+//! 构建渲染树之前，每个`<use>`都会被替换为其define唯一根元素的副本；带`count="N"`的元素
+//! 会被替换为N个兄弟副本，例如`<object count="12">bottle</object>`可以摆满一层货架。
+//! 两者都在计算尺寸之前完成，所以flex布局能看到每个副本。
+
+use crate::error::{Result, RsmlError};
+use crate::xml_parser::Element;
+use std::collections::HashMap;
+
+/// 展开文档中所有的`<use>`和`count`，并删除`<define>`元素
+///
+/// 在副本的根元素上，use元素的`id`和其他属性会替换define的属性，`class`名会追加，
+/// `style`声明也会追加在后面以覆盖define的声明。计数元素的副本会给整个子树的id加上
+/// `-0`、`-1`……后缀，保证输出的名字唯一；`count="0"`会删除该元素
+pub fn expand(root: &Element) -> Result<Element> {
+    let defines = collect_defines(root)?;
+    expand_element(root, &defines)
+}
+
+/// 按名字索引head中的define；每个define必须恰好包含一个元素
+fn collect_defines(root: &Element) -> Result<HashMap<&str, &Element>> {
+    let mut defines = HashMap::new();
+    let Some(head) = root.find_child("head") else {
+        return Ok(defines);
+    };
+    for define in head.find_children("define") {
+        let invalid = |message: String| {
+            RsmlError::InvalidStructure { message }.at(define.span.as_ref())
+        };
+        let name = define.get_attribute("name").ok_or_else(|| {
+            RsmlError::MissingElement {
+                element: "define@name".to_string(),
+            }
+            .at(define.span.as_ref())
+        })?;
+        let [template] = &define.children[..] else {
+            return Err(invalid(format!(
+                "define '{}' must contain exactly one element, found {}",
+                name,
+                define.children.len()
+            )));
+        };
+        if defines.insert(name.as_str(), template).is_some() {
+            return Err(invalid(format!("define '{}' is declared more than once", name)));
+        }
+    }
+    Ok(defines)
+}
+
+/// 正在复制的元素：`source`是该元素本身，或其use最终指向的define根元素，
+/// 接下来展开它的子元素
+struct Frame<'a> {
+    source: &'a Element,
+    expanded: Element,
+    /// `source`下一个子元素的下标
+    next: usize,
+    /// 到达`source`经过的use元素，最外层在前
+    uses: Vec<&'a Element>,
+    /// 元素完成后要复制的份数
+    count: Option<usize>,
+}
+
+impl<'a> Frame<'a> {
+    /// 沿着`element`的use找到它展开成的define；`chain`保存正在展开的define
+    fn start(
+        element: &'a Element,
+        count: Option<usize>,
+        defines: &HashMap<&str, &'a Element>,
+        chain: &mut Vec<String>,
+    ) -> Result<Self> {
+        let mut source = element;
+        let mut uses = Vec::new();
+        while source.name == "use" {
+            let (name, template) = resolve_use(source, defines)?;
+            if chain.contains(name) {
+                let mut names = chain.clone();
+                names.push(name.clone());
+                return Err(RsmlError::InvalidStructure {
+                    message: format!("recursive define: {}", names.join(" -> ")),
+                }
+                .at(source.span.as_ref()));
+            }
+            chain.push(name.clone());
+            uses.push(source);
+            source = template;
+        }
+        Ok(Frame {
+            source,
+            expanded: source.shallow_copy(),
+            next: 0,
+            uses,
+            count,
+        })
+    }
+
+    /// 按从内到外的顺序合并use的属性后得到的副本，同时退出对应的define
+    fn finish(self, chain: &mut Vec<String>) -> Element {
+        let mut expanded = self.expanded;
+        for element in self.uses.iter().rev() {
+            chain.pop();
+            merge_use_attributes(&mut expanded, element);
+        }
+        expanded
+    }
+}
+
+/// 复制`root`并展开其中的use和count；用栈代替递归，避免很深的文档导致栈溢出
+fn expand_element(root: &Element, defines: &HashMap<&str, &Element>) -> Result<Element> {
+    let mut chain = Vec::new();
+    let mut stack = vec![Frame::start(root, None, defines, &mut chain)?];
+    loop {
+        let frame = stack.last_mut().expect("the root frame is popped last");
+        if let Some(child) = frame.source.children.get(frame.next) {
+            frame.next += 1;
+            if child.name == "define" {
+                continue;
+            }
+            let count = parse_count(child)?;
+            if count != Some(0) {
+                stack.push(Frame::start(child, count, defines, &mut chain)?);
+            }
+            continue;
+        }
+
+        let frame = stack.pop().expect("the stack is not empty");
+        let count = frame.count;
+        let mut expanded = frame.finish(&mut chain);
+        let Some(parent) = stack.last_mut() else {
+            return Ok(expanded);
+        };
+        let Some(count) = count else {
+            parent.expanded.children.push(expanded);
+            continue;
+        };
+        expanded.attributes.remove("count");
+        for index in 0..count {
+            let mut copy = expanded.clone();
+            suffix_ids(&mut copy, index);
+            parent.expanded.children.push(copy);
+        }
+    }
+}
+
+fn parse_count(element: &Element) -> Result<Option<usize>> {
+    let Some(count) = element.get_attribute("count") else {
+        return Ok(None);
+    };
+    let count = count.trim().parse().map_err(|_| {
+        let label = match element.get_attribute("id") {
+            Some(id) => format!("{}#{}", element.name, id),
+            None => element.name.clone(),
+        };
+        RsmlError::InvalidAttribute {
+            attribute: format!("{}@count", label),
+            value: count.clone(),
+        }
+        .at(element.span.as_ref())
+    })?;
+    Ok(Some(count))
+}
+
+fn suffix_ids(element: &mut Element, index: usize) {
+    let mut stack = vec![element];
+    while let Some(element) = stack.pop() {
+        if let Some(id) = element.attributes.get_mut("id") {
+            *id = format!("{}-{}", id, index);
+        }
+        stack.extend(element.children.iter_mut());
+    }
+}
+
+/// use的ref以及其define的根元素
+fn resolve_use<'a, 'e>(
+    element: &'e Element,
+    defines: &HashMap<&str, &'a Element>,
+) -> Result<(&'e String, &'a Element)> {
+    let name = element.get_attribute("ref").ok_or_else(|| {
+        RsmlError::MissingElement {
+            element: "use@ref".to_string(),
+        }
+        .at(element.span.as_ref())
+    })?;
+    let template = defines.get(name.as_str()).ok_or_else(|| {
+        RsmlError::InvalidStructure {
+            message: format!("<use> references undefined define '{}'", name),
+        }
+        .at(element.span.as_ref())
+    })?;
+    Ok((name, template))
+}
+
+fn merge_use_attributes(expanded: &mut Element, element: &Element) {
+    for (key, value) in &element.attributes {
+        match key.as_str() {
+            "ref" => {}
+            "class" | "style" => {
+                let separator = if key == "class" { " " } else { ";" };
+                let merged = match expanded.attributes.get(key) {
+                    Some(base) if !base.trim().is_empty() => format!("{}{}{}", base, separator, value),
+                    _ => value.clone(),
+                };
+                expanded.attributes.insert(key.clone(), merged);
+            }
+            _ => {
+                expanded.attributes.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::render_tree::RenderTree;
+    use crate::xml_parser::parse_xml_str;
+
+    const SHELF: &str = r#"
+        <define name="shelf">
+            <space class="shelf" style="size:1m 1m 2m; flex-direction:z">
+                <object>table_leg</object>
+                <object>table_leg</object>
+            </space>
+        </define>"#;
+
+    fn document(head: &str, body: &str) -> String {
+        format!("<rsml><head>{}</head><body>{}</body></rsml>", head, body)
+    }
+
+    #[test]
+    fn test_five_uses_are_independent_subtrees() {
+        let uses: String = (0..5)
+            .map(|index| format!(r#"<use ref="shelf" id="shelf{}" style="pos:{}m 0 0" class="s{}"/>"#, index, index * 2, index))
+            .collect();
+        let body = format!(r#"<space id="room" style="size:10m 2m 2m; display:cube">{}</space>"#, uses);
+        let dom = expand(&parse_xml_str(&document(SHELF, &body)).unwrap()).unwrap();
+        let room = &dom.find_child("body").unwrap().children[0];
+        assert_eq!(room.children.len(), 5);
+        assert!(dom.find_child("head").unwrap().find_child("define").is_none());
+        let shelf = &room.children[2];
+        assert_eq!(shelf.name, "space");
+        assert_eq!(shelf.get_attribute("id").unwrap(), "shelf2");
+        assert_eq!(shelf.classes(), ["shelf", "s2"]);
+        assert_eq!(
+            shelf.get_attribute("style").unwrap(),
+            "size:1m 1m 2m; flex-direction:z;pos:4m 0 0"
+        );
+        assert!(shelf.get_attribute("ref").is_none());
+
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let items = render_tree.to_plan().unwrap().items;
+        assert_eq!(items.len(), 10);
+        // 每个副本的布局相同，只有pos覆盖的x不同
+        for (index, pair) in items.chunks(2).enumerate() {
+            for (item, first) in pair.iter().zip(&items[..2]) {
+                assert_eq!(item.position[0], first.position[0] + index as u32 * 2000);
+                assert_eq!(item.position[1..], first.position[1..]);
+                assert_eq!(item.size, first.size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let error = |head: &str, body: &str| {
+            expand(&parse_xml_str(&document(head, body)).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        let message = error(SHELF, r#"<use ref="cabinet"/>"#);
+        assert!(message.contains("<use> references undefined define 'cabinet'"), "{}", message);
+        assert!(error(SHELF, "<use/>").contains("use@ref"));
+
+        let recursive = r#"
+            <define name="a"><space><use ref="b"/></space></define>
+            <define name="b"><space><use ref="a"/></space></define>"#;
+        let message = error(recursive, r#"<use ref="a"/>"#);
+        assert!(message.contains("recursive define: a -> b -> a"), "{}", message);
+
+        let message = error(r#"<define name="empty"></define>"#, "");
+        assert!(message.contains("define 'empty' must contain exactly one element, found 0"), "{}", message);
+        let message = error(&format!("{}{}", SHELF, SHELF), "");
+        assert!(message.contains("define 'shelf' is declared more than once"), "{}", message);
+    }
+
+    #[test]
+    fn test_count_copies() {
+        let body = r#"
+            <space id="shelf" style="size:2.5m 1m 1m; flex-direction:x; justify-content:space-evenly">
+                <object id="bottle" count="12">table_leg</object>
+                <object count="0">table_leg</object>
+                <use ref="shelf" count="2"/>
+            </space>"#;
+        let dom = expand(&parse_xml_str(&document(SHELF, body)).unwrap()).unwrap();
+        let shelf = &dom.find_child("body").unwrap().children[0];
+        let ids: Vec<_> = shelf.children.iter().map(|c| c.get_attribute("id").cloned()).collect();
+        assert_eq!(ids.len(), 14);
+        assert_eq!(ids[0].as_deref(), Some("bottle-0"));
+        assert_eq!(ids[11].as_deref(), Some("bottle-11"));
+        assert_eq!(ids[12], None);
+        assert!(shelf.children.iter().all(|c| c.get_attribute("count").is_none()));
+
+        let message = expand(&parse_xml_str(&document("", r#"<object id="b" count="many">x</object>"#)).unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("object#b@count = many"), "{}", message);
+    }
+
+    #[test]
+    fn test_count_layout_space_evenly() {
+        let body = r#"
+            <space id="shelf" style="size:2.5m 1m 1m; flex-direction:x; justify-content:space-evenly">
+                <object id="bottle" count="12">table_leg</object>
+            </space>"#;
+        let dom = parse_xml_str(&document("", body)).unwrap();
+        let package = Package::from_file("package.toml").unwrap();
+        let mut render_tree = RenderTree::new(&dom, &package).unwrap();
+        render_tree.calculate().unwrap();
+        let items = render_tree.to_plan().unwrap().items;
+        assert_eq!(items.len(), 12);
+        // 剩余的1.3m平均分成13份，每个间距10cm
+        for (index, item) in items.iter().enumerate() {
+            assert_eq!(item.name, format!("bottle-{}", index));
+            assert_eq!(item.position[0], 100 + index as u32 * 200);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::error::{Result, RsmlError};
+use crate::package::Package;
+use crate::plan::{PlanItem, ScenePlan};
+use crate::render_tree::RenderTree;
+use crate::xml_parser::Element;
+use serde::Serialize;
+
+/// 一个物体在各个seed下的差异
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeVariance {
+    pub name: String,
+    pub position_varied: bool,
+    pub size_varied: bool,
+    /// 解析出了不同的组成员（mesh路径或geom类型）
+    pub member_varied: bool,
+    /// 各个seed下位置在单个轴上的最大差值，单位mm
+    pub spread_mm: u32,
+}
+
+impl NodeVariance {
+    pub fn varied(&self) -> bool {
+        self.position_varied || self.size_varied || self.member_varied
+    }
+}
+
+/// 同一场景用多个seed编译后每个节点的差异
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VarianceReport {
+    pub seeds: Vec<u64>,
+    pub nodes: Vec<NodeVariance>,
+}
+
+impl VarianceReport {
+    /// 每个seed编译一次场景，逐个物体比较计算结果
+    pub fn analyze(dom_element: &Element, package: &Package, seeds: &[u64]) -> Result<Self> {
+        let mut plans = Vec::new();
+        for seed in seeds {
+            let mut render_tree = RenderTree::new(dom_element, package)?;
+            render_tree.set_seed(*seed);
+            render_tree.calculate()?;
+            plans.push(render_tree.to_plan()?);
+        }
+        Ok(VarianceReport {
+            seeds: seeds.to_vec(),
+            nodes: compare_plans(&plans)?,
+        })
+    }
+
+    pub fn varying_nodes(&self) -> impl Iterator<Item = &NodeVariance> {
+        self.nodes.iter().filter(|node| node.varied())
+    }
+
+    /// 位置、尺寸或组成员随seed变化的物体所占的比例
+    pub fn randomized_fraction(&self) -> f64 {
+        if self.nodes.is_empty() {
+            return 0.0;
+        }
+        self.varying_nodes().count() as f64 / self.nodes.len() as f64
+    }
+
+    /// 所有物体位置差值的平均值，单位mm
+    pub fn mean_spread_mm(&self) -> f64 {
+        if self.nodes.is_empty() {
+            return 0.0;
+        }
+        let total: u64 = self.nodes.iter().map(|node| node.spread_mm as u64).sum();
+        total as f64 / self.nodes.len() as f64
+    }
+
+    /// 纯文本表格，每个物体一行，最后是汇总的统计
+    pub fn to_table(&self) -> String {
+        let mut out = format!(
+            "{:<32} {:>8} {:>8} {:>8} {:>10}\n",
+            "node", "position", "size", "member", "spread_mm"
+        );
+        let mark = |varied: bool| if varied { "varied" } else { "-" };
+        for node in &self.nodes {
+            out.push_str(&format!(
+                "{:<32} {:>8} {:>8} {:>8} {:>10}\n",
+                node.name,
+                mark(node.position_varied),
+                mark(node.size_varied),
+                mark(node.member_varied),
+                node.spread_mm
+            ));
+        }
+        out.push_str(&format!(
+            "randomized: {:.1}% of {} items, mean spread: {:.1}mm\n",
+            self.randomized_fraction() * 100.0,
+            self.nodes.len(),
+            self.mean_spread_mm()
+        ));
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| RsmlError::ParseError {
+            field: "variance".to_string(),
+            message: e.to_string(),
+        })
+    }
+}
+
+fn compare_plans(plans: &[ScenePlan]) -> Result<Vec<NodeVariance>> {
+    let Some(first) = plans.first() else {
+        return Ok(Vec::new());
+    };
+    let mut nodes = Vec::new();
+    for (index, item) in first.items.iter().enumerate() {
+        let mut samples: Vec<&PlanItem> = vec![item];
+        for plan in &plans[1..] {
+            match plan.items.get(index) {
+                Some(other) if other.name == item.name => samples.push(other),
+                _ => {
+                    return Err(RsmlError::RenderTree {
+                        message: format!("item '{}' is not present under every seed", item.name),
+                    })
+                }
+            }
+        }
+
+        let spread = (0..3)
+            .map(|axis| {
+                let values = samples.iter().map(|sample| sample.position[axis]);
+                values.clone().max().unwrap_or(0) - values.min().unwrap_or(0)
+            })
+            .max()
+            .unwrap_or(0);
+        nodes.push(NodeVariance {
+            name: item.name.clone(),
+            position_varied: samples.iter().any(|s| s.position != item.position),
+            size_varied: samples.iter().any(|s| s.size != item.size),
+            member_varied: samples
+                .iter()
+                .any(|s| s.path != item.path || s.geom_type != item.geom_type),
+            spread_mm: spread,
+        });
+    }
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Builder;
+
+    fn scene(package: &Package, items: Vec<Builder>) -> Element {
+        let room = items.into_iter().fold(
+            Builder::new_space().style("size:2m 2m 2m;flex-direction:x"),
+            Builder::append_child,
+        );
+        Builder::body().append_child(room).build(package).unwrap()
+    }
+
+    #[test]
+    fn test_one_random_node_varies() {
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = scene(
+            &package,
+            vec![
+                Builder::new_object("table_leg").id("leg"),
+                Builder::new_group("bottles", "random").id("bottle"),
+            ],
+        );
+        let seeds: Vec<u64> = (1..=8).collect();
+        let report = VarianceReport::analyze(&dom, &package, &seeds).unwrap();
+
+        let varying: Vec<&str> = report.varying_nodes().map(|n| n.name.as_str()).collect();
+        assert_eq!(varying, vec!["bottle"]);
+        assert!((report.randomized_fraction() - 0.5).abs() < 1e-9);
+
+        // 相同的seed结果可以复现
+        let again = VarianceReport::analyze(&dom, &package, &[3, 3]).unwrap();
+        assert_eq!(again.varying_nodes().count(), 0);
+    }
+
+    #[test]
+    fn test_non_random_scene_has_zero_variance() {
+        let package = Package::from_file("package.toml").unwrap();
+        let dom = scene(
+            &package,
+            vec![
+                Builder::new_object("table_leg").id("leg"),
+                Builder::new_object("table_plane").id("plane"),
+                Builder::new_group("bottles", "bottle3").id("bottle"),
+            ],
+        );
+        let report = VarianceReport::analyze(&dom, &package, &[1, 2, 3]).unwrap();
+        assert_eq!(report.nodes.len(), 3);
+        assert_eq!(report.varying_nodes().count(), 0);
+        assert_eq!(report.randomized_fraction(), 0.0);
+        assert_eq!(report.mean_spread_mm(), 0.0);
+        assert!(report.to_table().contains("randomized: 0.0% of 3 items"));
+        assert!(report.to_json().unwrap().contains("\"spread_mm\": 0"));
+    }
+}
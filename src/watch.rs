@@ -0,0 +1,335 @@
+//! 监视模式：场景本身、它引用的文件或包发生变化时重新构建场景
+//!
+//! 监视的是文件所在的目录而不是文件本身，这样通过替换文件来保存的编辑器也能被察觉。
+//! 事件会做防抖处理，并且每次重建后都会重新计算监视的文件，因为引用可能增加或删除
+//!
+//! 包只完整加载一次，之后增量重新加载：只重新解析内容变化了的包文件。如果只有包文件
+//! 变化，并且场景没有引用它们重新定义的物体和组，就跳过这次重建
+
+use crate::error::{Result, RsmlError};
+use crate::package::Package;
+use crate::reload::{PackageReloader, Reload};
+use crate::xml_parser::{parse_xml_file_tracked, IncludeOptions};
+use notify::{RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// 最后一次变化后等待多久没有新变化才重建
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// 一次构建读取的文件以及如何重建
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub scene: PathBuf,
+    pub package: PathBuf,
+    pub includes: IncludeOptions,
+    pub debounce: Duration,
+}
+
+impl WatchOptions {
+    pub fn new(scene: impl Into<PathBuf>, package: impl Into<PathBuf>) -> Self {
+        WatchOptions {
+            scene: scene.into(),
+            package: package.into(),
+            includes: IncludeOptions::default(),
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+}
+
+/// 一次重建的结果
+#[derive(Debug)]
+pub struct Rebuild<T> {
+    /// 重建被跳过时为None：只有包文件变化，并且场景没有引用它们重新定义的名字
+    pub result: Option<Result<T>>,
+    pub elapsed: Duration,
+    /// 场景、它引用的文件以及包文件
+    pub files: Vec<PathBuf>,
+    /// 这次重建重新解析的包文件
+    pub reparsed: Vec<PathBuf>,
+}
+
+impl<T> Rebuild<T> {
+    /// `ok in 12ms`、`failed in 3ms: <error>`或`skipped in 1ms: ...`
+    pub fn summary(&self) -> String {
+        let millis = self.elapsed.as_millis();
+        match &self.result {
+            Some(Ok(_)) => format!("ok in {}ms", millis),
+            Some(Err(error)) => format!("failed in {}ms: {}", millis, error),
+            None => format!("skipped in {}ms: the package changes do not affect the scene", millis),
+        }
+    }
+}
+
+/// 多次重建之间保留的状态：增量重新加载的包
+#[derive(Debug)]
+pub struct WatchSession {
+    options: WatchOptions,
+    /// 包第一次无错误加载之前为None
+    package: Option<PackageReloader>,
+}
+
+impl WatchSession {
+    pub fn new(options: WatchOptions) -> Self {
+        WatchSession { options, package: None }
+    }
+
+    /// 上次重建时的包，加载失败时为None
+    pub fn package(&self) -> Option<&PackageReloader> {
+        self.package.as_ref()
+    }
+
+    /// 重新加载包并用它运行`build`，对两者计时，并找出构建依赖的文件。`changed`是上次重建后
+    /// 变化了的文件的绝对路径，第一次构建时为None
+    ///
+    /// 通过解析场景找到引用的文件；解析失败时仍返回出错前读取的文件，这样修复它们就会触发下一次重建
+    pub fn rebuild<T>(
+        &mut self,
+        changed: Option<&BTreeSet<PathBuf>>,
+        build: impl FnOnce(&Package) -> Result<T>,
+    ) -> Rebuild<T> {
+        let start = Instant::now();
+        let package_files: BTreeSet<PathBuf> = match &self.package {
+            Some(reloader) => reloader.files().into_iter().map(absolute).collect(),
+            None => BTreeSet::new(),
+        };
+        let package_only = changed.is_some_and(|changed| changed.is_subset(&package_files));
+
+        let mut reparsed = Vec::new();
+        let result = match self.reload_package() {
+            Err(error) => Some(Err(error)),
+            Ok(reload) => {
+                let reload = reload.filter(|_| package_only);
+                if let Some(reload) = &reload {
+                    reparsed = reload.reparsed.clone();
+                }
+                let scene = parse_xml_file_tracked(
+                    &self.options.scene.to_string_lossy(),
+                    &self.options.includes,
+                    &mut Vec::new(),
+                );
+                match (reload, scene) {
+                    (Some(reload), Ok(scene)) if !reload.affects(&scene) => None,
+                    _ => {
+                        let reloader = self.package.as_ref().expect("the package was loaded");
+                        Some(build(reloader.package()))
+                    }
+                }
+            }
+        };
+        let elapsed = start.elapsed();
+
+        let mut files = Vec::new();
+        let _ = parse_xml_file_tracked(&self.options.scene.to_string_lossy(), &self.options.includes, &mut files);
+        let package_files = match &self.package {
+            Some(reloader) => reloader.files().into_iter().map(Path::to_path_buf).collect(),
+            None => vec![self.options.package.clone()],
+        };
+        for file in package_files {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        Rebuild {
+            result,
+            elapsed,
+            files,
+            reparsed,
+        }
+    }
+
+    /// 第一次完整加载包，之后增量重新加载；完整加载时返回None
+    fn reload_package(&mut self) -> Result<Option<Reload>> {
+        match &mut self.package {
+            Some(reloader) => reloader.reload().map(Some),
+            None => {
+                self.package = Some(PackageReloader::load(&self.options.package)?);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// 立即重建，之后监视的文件每次变化都重建，直到监视失败
+///
+/// 每次重建都会传给`report`；构建失败也会报告，然后继续监视
+pub fn watch<T>(
+    options: &WatchOptions,
+    mut build: impl FnMut(&Package) -> Result<T>,
+    mut report: impl FnMut(&Rebuild<T>),
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    let mut directories = BTreeSet::new();
+    let mut session = WatchSession::new(options.clone());
+    let mut changed = None;
+    loop {
+        let outcome = session.rebuild(changed.as_ref(), &mut build);
+        report(&outcome);
+
+        let files: BTreeSet<PathBuf> = outcome.files.iter().map(|file| absolute(file)).collect();
+        let wanted: BTreeSet<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect();
+        for directory in directories.difference(&wanted) {
+            let _ = watcher.unwatch(directory);
+        }
+        for directory in wanted.difference(&directories) {
+            // A missing directory is watched again after the next rebuild
+            let _ = watcher.watch(directory, RecursiveMode::NonRecursive);
+        }
+        directories = wanted;
+
+        changed = Some(wait_for_change(&receiver, &files, options.debounce)?);
+    }
+}
+
+/// 阻塞直到`files`中有文件变化，并且`debounce`时间内没有新事件；返回变化了的文件
+fn wait_for_change(
+    receiver: &mpsc::Receiver<notify::Result<notify::Event>>,
+    files: &BTreeSet<PathBuf>,
+    debounce: Duration,
+) -> Result<BTreeSet<PathBuf>> {
+    let relevant = |event: notify::Result<notify::Event>| -> Result<Vec<PathBuf>> {
+        let event = event.map_err(watch_error)?;
+        if event.kind.is_access() {
+            return Ok(Vec::new());
+        }
+        Ok(event.paths.iter().map(|path| absolute(path)).filter(|path| files.contains(path)).collect())
+    };
+    let mut changed = BTreeSet::new();
+    while changed.is_empty() {
+        let event = receiver
+            .recv()
+            .map_err(|_| watcher_stopped())?;
+        changed.extend(relevant(event)?);
+    }
+    loop {
+        match receiver.recv_timeout(debounce) {
+            Ok(event) => changed.extend(relevant(event)?),
+            Err(mpsc::RecvTimeoutError::Timeout) => return Ok(changed),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(watcher_stopped());
+            }
+        }
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn watcher_stopped() -> RsmlError {
+    RsmlError::Io(std::io::Error::other("the file watcher stopped"))
+}
+
+fn watch_error(error: notify::Error) -> RsmlError {
+    match error.kind {
+        notify::ErrorKind::Io(error) => RsmlError::Io(error),
+        kind => RsmlError::Io(std::io::Error::other(format!("{:?}", kind))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::{compile, CompileOptions, PackageSource, RsmlSource};
+
+    const SCENE: &str = r#"<rsml><head></head><body>
+        <space id="room" style="size:1m 1m 1m"><include src="parts/leg.xml"/></space>
+    </body></rsml>"#;
+
+    #[test]
+    fn test_rebuild_after_rewrites() {
+        let dir = std::env::temp_dir().join(format!("rsmlc_watch_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let scene = dir.join("scene.xml");
+        let include = dir.join("parts/leg.xml");
+        std::fs::write(&scene, SCENE).unwrap();
+        std::fs::write(&include, r#"<object id="leg">table_leg</object>"#).unwrap();
+        let mut session = WatchSession::new(WatchOptions::new(&scene, "package.toml"));
+        let build = |package: &Package| {
+            compile(CompileOptions::new(
+                RsmlSource::Path(scene.clone()),
+                PackageSource::Package(package.clone()),
+            ))
+        };
+
+        let outcome = session.rebuild(None, build);
+        assert!(outcome.summary().starts_with("ok in "), "{}", outcome.summary());
+        assert!(outcome.result.unwrap().unwrap().output.contains("name=\"leg\""));
+        assert_eq!(outcome.files, [scene.clone(), include.clone(), PathBuf::from("package.toml")]);
+
+        // 被包含的文件出错时重建失败，但仍然监视它
+        std::fs::write(&include, "<object id=\"leg\">table_leg").unwrap();
+        let changed = BTreeSet::from([absolute(&include)]);
+        let outcome = session.rebuild(Some(&changed), build);
+        assert!(outcome.summary().starts_with("failed in "), "{}", outcome.summary());
+        assert!(outcome.files.contains(&include));
+
+        std::fs::write(&include, r#"<object id="stool">table_leg</object>"#).unwrap();
+        let outcome = session.rebuild(Some(&changed), build);
+        assert!(outcome.result.unwrap().unwrap().output.contains("name=\"stool\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_package_edits_reload_incrementally() {
+        let dir = std::env::temp_dir().join(format!("rsmlc_watch_package_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let package = dir.join("package.toml");
+        std::fs::write(
+            &package,
+            "include = [\"parts/shelves.toml\", \"parts/bottles.toml\"]\n\
+             [package]\nname = \"kitchen\"\ndescription = \"\"\n\
+             [objects]\ntable_plane = { geom-type = \"box\", size = \"1m 1m 10cm\" }\n",
+        )
+        .unwrap();
+        let shelves = dir.join("parts/shelves.toml");
+        let bottles = dir.join("parts/bottles.toml");
+        let shelf = |size: &str| format!("[objects]\nshelf = {{ geom-type = \"box\", size = \"{}\" }}\n", size);
+        let bottle = |size: &str| format!("[[groups]]\nname = \"bottles\"\n[groups.objects]\nbottle0 = {{ size = \"{}\" }}\n", size);
+        std::fs::write(&shelves, shelf("80cm 30cm 2cm")).unwrap();
+        std::fs::write(&bottles, bottle("5cm 5cm 20cm")).unwrap();
+        let scene = dir.join("scene.xml");
+        std::fs::write(
+            &scene,
+            r#"<rsml><head></head><body>
+                <space id="room" style="size:2m 2m 2m"><object id="shelf">shelf</object></space>
+            </body></rsml>"#,
+        )
+        .unwrap();
+
+        let mut session = WatchSession::new(WatchOptions::new(&scene, &package));
+        let build = |package: &Package| {
+            compile(CompileOptions::new(
+                RsmlSource::Path(scene.clone()),
+                PackageSource::Package(package.clone()),
+            ))
+        };
+        let outcome = session.rebuild(None, build);
+        assert!(outcome.result.unwrap().unwrap().output.contains(r#"size="0.4 0.15 0.01""#));
+        assert_eq!(outcome.files, [scene.clone(), package.clone(), shelves.clone(), bottles.clone()]);
+
+        // 场景没有引用的组：只重新解析bottles.toml，跳过重建
+        std::fs::write(&bottles, bottle("6cm 6cm 20cm")).unwrap();
+        let outcome = session.rebuild(Some(&BTreeSet::from([absolute(&bottles)])), build);
+        assert!(outcome.result.is_none(), "{}", outcome.summary());
+        assert!(outcome.summary().starts_with("skipped in "), "{}", outcome.summary());
+        assert_eq!(outcome.reparsed, vec![bottles.clone()]);
+
+        // 修改场景引用的物体：只重新解析shelves.toml，输出中的尺寸更新
+        std::fs::write(&shelves, shelf("1m 30cm 2cm")).unwrap();
+        let outcome = session.rebuild(Some(&BTreeSet::from([absolute(&shelves)])), build);
+        assert_eq!(outcome.reparsed, vec![shelves.clone()]);
+        assert!(outcome.result.unwrap().unwrap().output.contains(r#"size="0.5 0.15 0.01""#));
+        let metrics = session.package().unwrap().metrics;
+        assert_eq!((metrics.parsed, metrics.reused), (5, 4));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+use crate::dim3::Dim3;
+use crate::error::{Result, RsmlError};
+use crate::style::{AlignItem, AlignItems, FlexDirection, JustifyContent};
+use crate::xml_parser::Element;
+use std::fmt;
+use std::str::FromStr;
+
+/// 标准重力加速度，单位m/s^2
+pub const GRAVITY: f64 = 9.81;
+
+/// 向上的坐标轴
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpAxis {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+impl FromStr for UpAxis {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "x" => Ok(UpAxis::X),
+            "y" => Ok(UpAxis::Y),
+            "z" => Ok(UpAxis::Z),
+            _ => Err(RsmlError::InvalidAttribute {
+                attribute: "up".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for UpAxis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpAxis::X => write!(f, "x"),
+            UpAxis::Y => write!(f, "y"),
+            UpAxis::Z => write!(f, "z"),
+        }
+    }
+}
+
+/// 地面位于up轴的哪一侧
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Floor {
+    #[default]
+    Min,
+    Max,
+}
+
+impl FromStr for Floor {
+    type Err = RsmlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "min" => Ok(Floor::Min),
+            "max" => Ok(Floor::Max),
+            _ => Err(RsmlError::InvalidAttribute {
+                attribute: "floor".to_string(),
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Floor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Floor::Min => write!(f, "min"),
+            Floor::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// 世界坐标系声明：`<head><world up="z" floor="min"/></head>`
+///
+/// 默认的flex-direction、重力方向以及输出中依赖朝向的部分都由它推导。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct World {
+    pub up: UpAxis,
+    pub floor: Floor,
+}
+
+impl World {
+    /// 读取`<head>`中的`<world>`声明，没有声明时z轴向上、地面在min一侧
+    pub fn from_element(root: &Element) -> Result<Self> {
+        let Some(world) = root.find_child("head").and_then(|head| head.find_child("world")) else {
+            return Ok(World::default());
+        };
+        let mut result = World::default();
+        if let Some(up) = world.get_attribute("up") {
+            result.up = up.parse().map_err(|e: RsmlError| e.at(world.span.as_ref()))?;
+        }
+        if let Some(floor) = world.get_attribute("floor") {
+            result.floor = floor.parse().map_err(|e: RsmlError| e.at(world.span.as_ref()))?;
+        }
+        Ok(result)
+    }
+
+    /// 空间默认的排列方向：按照顺序从上往下排列
+    pub fn default_flex_direction(&self) -> FlexDirection {
+        match (self.up, self.floor) {
+            (UpAxis::X, Floor::Min) => FlexDirection::ReverseX,
+            (UpAxis::Y, Floor::Min) => FlexDirection::ReverseY,
+            (UpAxis::Z, Floor::Min) => FlexDirection::ReverseZ,
+            (UpAxis::X, Floor::Max) => FlexDirection::X,
+            (UpAxis::Y, Floor::Max) => FlexDirection::Y,
+            (UpAxis::Z, Floor::Max) => FlexDirection::Z,
+        }
+    }
+
+    /// 未指定align-items时的默认对齐：水平的交叉轴居中，竖直的交叉轴贴地
+    pub fn default_align_items(&self, direction: &FlexDirection) -> AlignItems {
+        let (cross1, cross2) = match direction {
+            FlexDirection::X | FlexDirection::ReverseX => (UpAxis::Y, UpAxis::Z),
+            FlexDirection::Y | FlexDirection::ReverseY => (UpAxis::X, UpAxis::Z),
+            FlexDirection::Z | FlexDirection::ReverseZ => (UpAxis::X, UpAxis::Y),
+        };
+        let align = |axis: UpAxis| {
+            if axis != self.up {
+                AlignItem::Center
+            } else if self.floor == Floor::Min {
+                AlignItem::FlexStart
+            } else {
+                AlignItem::FlexEnd
+            }
+        };
+        AlignItems {
+            cross1: align(cross1),
+            cross2: align(cross2),
+        }
+    }
+
+    /// 未指定justify-content时的默认值：沿up轴排列时贴地，其余方向从主轴的起点开始
+    pub fn default_justify_content(&self, direction: &FlexDirection) -> JustifyContent {
+        let (main, reverse) = match direction {
+            FlexDirection::X => (UpAxis::X, false),
+            FlexDirection::Y => (UpAxis::Y, false),
+            FlexDirection::Z => (UpAxis::Z, false),
+            FlexDirection::ReverseX => (UpAxis::X, true),
+            FlexDirection::ReverseY => (UpAxis::Y, true),
+            FlexDirection::ReverseZ => (UpAxis::Z, true),
+        };
+        // 主轴的起点在地面的另一侧时，从终点开始排列
+        if main == self.up && reverse == (self.floor == Floor::Min) {
+            JustifyContent::FlexEnd
+        } else {
+            JustifyContent::FlexStart
+        }
+    }
+
+    /// 背离地面方向的单位向量
+    pub fn up_vector(&self) -> Dim3<f64> {
+        let sign = match self.floor {
+            Floor::Min => 1.0,
+            Floor::Max => -1.0,
+        };
+        match self.up {
+            UpAxis::X => Dim3::new(sign, 0.0, 0.0),
+            UpAxis::Y => Dim3::new(0.0, sign, 0.0),
+            UpAxis::Z => Dim3::new(0.0, 0.0, sign),
+        }
+    }
+
+    /// 指向地面的重力向量，单位m/s^2
+    pub fn gravity(&self) -> Dim3<f64> {
+        let up = self.up_vector();
+        Dim3::new(-up.x * GRAVITY, -up.y * GRAVITY, -up.z * GRAVITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+    use crate::scene::Builder;
+
+    fn root_with_world(world: Builder) -> Element {
+        let package = Package::from_file("package.toml").unwrap();
+        Builder::body().head(world).build(&package).unwrap()
+    }
+
+    #[test]
+    fn test_world_from_element() {
+        let root = Element::new("rsml".to_string());
+        assert_eq!(World::from_element(&root).unwrap(), World::default());
+
+        let declared = World {
+            up: UpAxis::Y,
+            floor: Floor::Max,
+        };
+        let world = World::from_element(&root_with_world(Builder::new_world(&declared))).unwrap();
+        assert_eq!(world.up, UpAxis::Y);
+        assert_eq!(world.floor, Floor::Max);
+        assert_eq!(world.default_flex_direction(), FlexDirection::Y);
+        assert_eq!(world.gravity(), Dim3::new(0.0, GRAVITY, 0.0));
+        let align = world.default_align_items(&FlexDirection::X);
+        assert_eq!(align.cross1, AlignItem::FlexEnd);
+        assert_eq!(align.cross2, AlignItem::Center);
+
+        assert_eq!(world.default_justify_content(&FlexDirection::Y), JustifyContent::FlexEnd);
+        assert_eq!(world.default_justify_content(&FlexDirection::ReverseY), JustifyContent::FlexStart);
+
+        assert!(World::from_element(&root_with_world(Builder::new_world(&World::default()).attribute("up", "w"))).is_err());
+        assert!(World::from_element(&root_with_world(Builder::new_world(&World::default()).attribute("floor", "top"))).is_err());
+    }
+
+    #[test]
+    fn test_default_world() {
+        let world = World::default();
+        assert_eq!(world.default_flex_direction(), FlexDirection::default());
+        assert_eq!(world.gravity(), Dim3::new(0.0, 0.0, -GRAVITY));
+        // 默认从上往下排列，贴地
+        assert_eq!(world.default_justify_content(&FlexDirection::ReverseZ), JustifyContent::FlexEnd);
+        assert_eq!(world.default_justify_content(&FlexDirection::Z), JustifyContent::FlexStart);
+        assert_eq!(world.default_justify_content(&FlexDirection::ReverseX), JustifyContent::FlexStart);
+        let align = world.default_align_items(&FlexDirection::ReverseZ);
+        assert_eq!(align.cross1, AlignItem::Center);
+        assert_eq!(align.cross2, AlignItem::Center);
+    }
+}
@@ -1,16 +1,39 @@
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::io::BufReader;
-use std::fs::File;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use crate::error::{RsmlError, Result};
 
-#[derive(Debug, Clone)]
+/// 元素在源文件中的位置，行和列从1开始，列按字符计算
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    /// 从字符串解析时没有文件
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    /// `path:line:col`，编辑器可以直接跳转
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
 pub struct Element {
     pub name: String,
     pub attributes: HashMap<String, String>,
     pub text: String,
     pub children: Vec<Element>,
+    /// 开始标签的位置；不是从XML解析出来的元素没有位置
+    pub span: Option<Span>,
 }
 
 impl Element {
@@ -20,6 +43,7 @@ impl Element {
             attributes: HashMap::new(),
             text: String::new(),
             children: Vec::new(),
+            span: None,
         }
     }
     
@@ -27,6 +51,13 @@ impl Element {
         self.attributes.get(name)
     }
     
+    /// class属性中空白分隔的类名，按出现顺序
+    pub fn classes(&self) -> Vec<String> {
+        self.get_attribute("class")
+            .map(|class| class.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+    
     pub fn find_child(&self, name: &str) -> Option<&Element> {
         self.children.iter().find(|child| child.name == name)
     }
@@ -34,82 +65,364 @@ impl Element {
     pub fn find_children(&self, name: &str) -> Vec<&Element> {
         self.children.iter().filter(|child| child.name == name).collect()
     }
+
+    /// 复制名称、属性、文本和位置，不复制子元素
+    pub fn shallow_copy(&self) -> Element {
+        Element {
+            name: self.name.clone(),
+            attributes: self.attributes.clone(),
+            text: self.text.clone(),
+            children: Vec::new(),
+            span: self.span.clone(),
+        }
+    }
 }
 
+impl Clone for Element {
+    /// 用栈代替递归复制，和Drop一样适用于很深的文档
+    fn clone(&self) -> Self {
+        let mut stack = vec![(self, self.shallow_copy())];
+        loop {
+            let (source, copy) = stack.last().expect("the root is popped last");
+            let (source, next) = (*source, copy.children.len());
+            if let Some(child) = source.children.get(next) {
+                stack.push((child, child.shallow_copy()));
+                continue;
+            }
+            let (_, copy) = stack.pop().expect("the stack is not empty");
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(copy),
+                None => return copy,
+            }
+        }
+    }
+}
+
+impl Drop for Element {
+    /// 逐个释放子元素，很深的文档不会因为递归释放而栈溢出
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.children);
+        while let Some(mut child) = stack.pop() {
+            stack.append(&mut child.children);
+        }
+    }
+}
+
+/// 去掉开头的UTF-8 BOM；空文件或只有空白的文件报告为空文档，而不是找不到根元素
+fn document_text<'a>(xml: &'a str, file_path: &str) -> Result<&'a str> {
+    let xml = xml.strip_prefix('\u{feff}').unwrap_or(xml);
+    if xml.trim().is_empty() {
+        return Err(RsmlError::InvalidStructure {
+            message: format!("'{}' is empty: expected an <rsml> document", file_path),
+        });
+    }
+    Ok(xml)
+}
+
+/// `<include src="..."/>`默认最多嵌套的层数
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 16;
+
+/// `<include src="aisle.xml"/>`的解析选项
+#[derive(Debug, Clone)]
+pub struct IncludeOptions {
+    /// 包含的文件中还可以再包含文件，超过这个层数时报错
+    pub max_depth: usize,
+}
+
+impl Default for IncludeOptions {
+    fn default() -> Self {
+        IncludeOptions {
+            max_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+        }
+    }
+}
+
+/// 解析RSML文件，`<include>`替换为被包含文件的根元素
 pub fn parse_xml_file(file_path: &str) -> Result<Element> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut xml_reader = Reader::from_reader(reader);
+    parse_xml_file_with_includes(file_path, &IncludeOptions::default())
+}
+
+/// 和parse_xml_file相同，使用指定的include选项
+pub fn parse_xml_file_with_includes(file_path: &str, options: &IncludeOptions) -> Result<Element> {
+    parse_xml_file_tracked(file_path, options, &mut Vec::new())
+}
+
+/// 和parse_xml_file_with_includes相同，同时把读取的文件按顺序加入`files`：先是根文件，
+/// 之后是包含的文件，每个只出现一次。解析失败时已经读取的文件也在其中，watch模式据此
+/// 决定监视哪些文件
+pub fn parse_xml_file_tracked(
+    file_path: &str,
+    options: &IncludeOptions,
+    files: &mut Vec<PathBuf>,
+) -> Result<Element> {
+    files.push(PathBuf::from(file_path));
+    let xml = std::fs::read_to_string(file_path)?;
+    // 根元素之前的空白、注释、XML声明和处理指令都作为普通事件跳过
+    let mut root = parse_xml(document_text(&xml, file_path)?, Some(file_path))?;
+    let mut chain = vec![IncludedFile::new(Path::new(file_path))?];
+    resolve_includes(&mut root, Path::new(file_path), options, &mut chain, 0, files)?;
+    Ok(root)
+}
+
+/// 从字符串解析RSML文档，和parse_xml_file的结果相同；`<include>`的路径相对于当前目录
+pub fn parse_xml_str(xml: &str) -> Result<Element> {
+    let mut root = parse_xml(xml.strip_prefix('\u{feff}').unwrap_or(xml), None)?;
+    let options = IncludeOptions::default();
+    resolve_includes(&mut root, Path::new("."), &options, &mut Vec::new(), 0, &mut Vec::new())?;
+    Ok(root)
+}
+
+/// 从任意Read解析RSML文档，例如标准输入或内存中的缓冲区
+///
+/// 先读入整个文档，元素的位置需要从原文计算行和列
+pub fn parse_xml_reader<R: Read>(mut reader: R) -> Result<Element> {
+    let mut xml = String::new();
+    reader.read_to_string(&mut xml)?;
+    parse_xml_str(&xml)
+}
+
+/// include链中的一个文件：用规范化的路径检测循环，报错时输出原来的路径
+struct IncludedFile {
+    canonical: PathBuf,
+    display: String,
+}
+
+impl IncludedFile {
+    fn new(path: &Path) -> Result<Self> {
+        Ok(IncludedFile {
+            canonical: std::fs::canonicalize(path)?,
+            display: path.display().to_string(),
+        })
+    }
+}
+
+/// 把element下所有的`<include>`替换为被包含文件的根元素，src相对于base文件所在的目录
+///
+/// include元素上除src之外的属性（例如id）覆盖被包含的根元素上的同名属性；
+/// depth是element所在的文件已经嵌套的层数。同一个文件中的元素按先序用栈遍历，
+/// 很深的文档不会栈溢出；递归只发生在include的文件之间，层数受max_depth限制
+fn resolve_includes(
+    element: &mut Element,
+    base: &Path,
+    options: &IncludeOptions,
+    chain: &mut Vec<IncludedFile>,
+    depth: usize,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut stack: Vec<&mut Element> = element.children.iter_mut().rev().collect();
+    while let Some(child) = stack.pop() {
+        if child.name != "include" {
+            stack.extend(child.children.iter_mut().rev());
+            continue;
+        }
+        let span = child.span.clone();
+        let src = child.get_attribute("src").ok_or_else(|| {
+            RsmlError::MissingElement {
+                element: "include@src".to_string(),
+            }
+            .at(span.as_ref())
+        })?;
+        let path = base.parent().unwrap_or(Path::new(".")).join(src);
+        if !files.contains(&path) {
+            files.push(path.clone());
+        }
+        let file = IncludedFile::new(&path).map_err(|e| e.at(span.as_ref()))?;
+
+        let mut names: Vec<&str> = chain.iter().map(|file| file.display.as_str()).collect();
+        names.push(&file.display);
+        if chain.iter().any(|included| included.canonical == file.canonical) {
+            return Err(RsmlError::InvalidStructure {
+                message: format!("include cycle: {}", names.join(" -> ")),
+            }
+            .at(span.as_ref()));
+        }
+        if depth >= options.max_depth {
+            return Err(RsmlError::InvalidStructure {
+                message: format!(
+                    "includes nested deeper than {} levels: {}",
+                    options.max_depth,
+                    names.join(" -> ")
+                ),
+            }
+            .at(span.as_ref()));
+        }
+
+        let xml = std::fs::read_to_string(&path).map_err(|e| RsmlError::from(e).at(span.as_ref()))?;
+        let xml = document_text(&xml, &file.display).map_err(|e| e.at(span.as_ref()))?;
+        let mut included = parse_xml(xml, Some(&file.display))?;
+        for (key, value) in &child.attributes {
+            if key != "src" {
+                included.attributes.insert(key.clone(), value.clone());
+            }
+        }
+        chain.push(file);
+        let result = resolve_includes(&mut included, &path, options, chain, depth + 1, files);
+        chain.pop();
+        result?;
+        *child = included;
+    }
+    Ok(())
+}
+
+/// 把字节偏移转换为行和列；偏移只会增加，从上一次的位置继续计算
+struct Locator<'a> {
+    xml: &'a str,
+    file: Option<&'a str>,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> Locator<'a> {
+    fn new(xml: &'a str, file: Option<&'a str>) -> Self {
+        Locator {
+            xml,
+            file,
+            offset: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    fn span(&mut self, offset: usize) -> Span {
+        let offset = offset.min(self.xml.len());
+        if offset < self.offset {
+            // 错误的位置可能在上一个元素之前，从头重新计算
+            *self = Locator::new(self.xml, self.file);
+        }
+        for (index, byte) in self.xml.as_bytes()[self.offset..offset].iter().enumerate() {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.line_start = self.offset + index + 1;
+            }
+        }
+        self.offset = offset;
+        Span {
+            file: self.file.map(str::to_string),
+            line: self.line,
+            column: self.xml[self.line_start..offset].chars().count() + 1,
+        }
+    }
+
+    /// 刚读完的标签的起始位置：属性值中不能出现'<'，所以是结束位置之前最后一个'<'
+    fn tag_span(&mut self, end: u64) -> Span {
+        let end = (end as usize).min(self.xml.len());
+        let start = self.xml[..end].rfind('<').unwrap_or(0);
+        self.span(start)
+    }
+}
+
+/// 开始标签或自闭合标签对应的元素
+fn start_element(start: &BytesStart, span: Span) -> Result<Element> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+    let mut element = Element::new(name);
+    
+    for attr in start.attributes() {
+        let attr = attr.map_err(|e| RsmlError::from(e).at(Some(&span)))?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+        let value = String::from_utf8_lossy(&attr.value).to_string();
+        element.attributes.insert(key, value);
+    }
+    element.span = Some(span);
+    Ok(element)
+}
+
+/// 文本属于当前的元素；根元素之外只能有空白
+fn append_text(stack: &mut [Element], text: &str, span: impl FnOnce() -> Span) -> Result<()> {
+    match stack.last_mut() {
+        Some(element) => element.text.push_str(text),
+        None if text.trim().is_empty() => {}
+        None => {
+            return Err(RsmlError::InvalidStructure {
+                message: format!("text '{}' outside the root element", text.trim()),
+            }
+            .at(Some(&span())));
+        }
+    }
+    Ok(())
+}
+
+fn parse_xml(xml: &str, file: Option<&str>) -> Result<Element> {
+    let mut xml_reader = Reader::from_str(xml);
     xml_reader.config_mut().trim_text(true);
+    let mut locator = Locator::new(xml, file);
     
-    let mut buf = Vec::new();
+    // 还没有结束的元素，最外层是根元素
     let mut stack: Vec<Element> = Vec::new();
+    let mut root: Option<Element> = None;
     
     loop {
-        match xml_reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
-                let mut element = Element::new(name);
-                
-                for attr in e.attributes() {
-                    let attr = attr?;
-                    let key = String::from_utf8_lossy(&attr.key.as_ref()).to_string();
-                    let value = String::from_utf8_lossy(&attr.value).to_string();
-                    element.attributes.insert(key, value);
-                }
-                
-                stack.push(element);
-            }
-            Ok(Event::Empty(ref e)) => {
-                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
-                let mut element = Element::new(name);
-                
-                for attr in e.attributes() {
-                    let attr = attr?;
-                    let key = String::from_utf8_lossy(&attr.key.as_ref()).to_string();
-                    let value = String::from_utf8_lossy(&attr.value).to_string();
-                    element.attributes.insert(key, value);
+        let event = xml_reader.read_event();
+        let outside_root = stack.is_empty();
+        match event {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let span = locator.tag_span(xml_reader.buffer_position());
+                let element = start_element(e, span)?;
+                if outside_root && root.is_some() {
+                    return Err(RsmlError::InvalidStructure {
+                        message: format!("element <{}> after the root element", element.name),
+                    }
+                    .at(element.span.as_ref()));
                 }
-                
-                if let Some(parent) = stack.last_mut() {
-                    parent.children.push(element);
+                if matches!(event, Ok(Event::Start(_))) {
+                    stack.push(element);
                 } else {
-                    // This is the root element
-                    return Ok(element);
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        // 自闭合的根元素
+                        None => root = Some(element),
+                    }
                 }
             }
             Ok(Event::End(_)) => {
-                if stack.len() > 1 {
-                    let element = stack.pop().unwrap();
-                    if let Some(parent) = stack.last_mut() {
-                        parent.children.push(element);
+                // 结束标签的名称由quick-xml检查，没有对应开始标签的结束标签也由它报错
+                if let Some(element) = stack.pop() {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => root = Some(element),
                     }
                 }
             }
-            Ok(Event::Text(e)) => {
-                if let Some(element) = stack.last_mut() {
-                    element.text.push_str(&String::from_utf8_lossy(e.as_ref()));
-                }
+            Ok(Event::Text(ref e)) => {
+                let start = xml_reader.buffer_position() as usize - e.len();
+                append_text(&mut stack, &String::from_utf8_lossy(e.as_ref()), || locator.span(start))?;
             }
-            Ok(Event::CData(e)) => {
-                if let Some(element) = stack.last_mut() {
-                    element.text.push_str(&String::from_utf8_lossy(e.as_ref()));
-                }
+            Ok(Event::CData(ref e)) => {
+                let start = xml_reader.buffer_position() as usize - e.len();
+                append_text(&mut stack, &String::from_utf8_lossy(e.as_ref()), || locator.span(start))?;
             }
             Ok(Event::Eof) => break,
-            Err(e) => return Err(RsmlError::XmlParse(e)),
+            Err(e) => {
+                let span = locator.span(xml_reader.error_position() as usize);
+                return Err(RsmlError::XmlParse(e).at(Some(&span)));
+            }
+            // XML声明、注释、DOCTYPE和处理指令不影响文档结构
             _ => (),
         }
-        buf.clear();
     }
     
-    if stack.is_empty() {
-        Err(RsmlError::InvalidStructure { 
-            message: "No root element found".to_string() 
-        })
-    } else {
-        Ok(stack.remove(0))
+    if let Some(element) = stack.pop() {
+        let span = element.span.clone();
+        return Err(RsmlError::InvalidStructure {
+            message: format!(
+                "unclosed element <{}> started at line {}",
+                element.name,
+                span.as_ref().map_or(0, |span| span.line)
+            ),
+        }
+        .at(span.as_ref()));
     }
+    root.ok_or(RsmlError::InvalidStructure {
+        message: "No root element found".to_string(),
+    })
+}
+
+/// 转义XML/HTML文本和双引号属性值中的特殊字符
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 #[cfg(test)]
@@ -119,64 +432,7 @@ mod tests {
     #[test]
     fn test_parse_simple_xml() {
         let xml = r#"<root><child id="1">Text</child></root>"#;
-        let mut reader = Reader::from_str(xml);
-        reader.config_mut().trim_text(true);
-        
-        let mut buf = Vec::new();
-        let mut stack: Vec<Element> = Vec::new();
-        
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) => {
-                    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
-                    let mut element = Element::new(name);
-                    
-                    for attr in e.attributes() {
-                        let attr = attr.unwrap();
-                        let key = String::from_utf8_lossy(&attr.key.as_ref()).to_string();
-                        let value = String::from_utf8_lossy(&attr.value).to_string();
-                        element.attributes.insert(key, value);
-                    }
-                    
-                    stack.push(element);
-                }
-                Ok(Event::Empty(ref e)) => {
-                    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
-                    let mut element = Element::new(name);
-                    
-                    for attr in e.attributes() {
-                        let attr = attr.unwrap();
-                        let key = String::from_utf8_lossy(&attr.key.as_ref()).to_string();
-                        let value = String::from_utf8_lossy(&attr.value).to_string();
-                        element.attributes.insert(key, value);
-                    }
-                    
-                    if let Some(parent) = stack.last_mut() {
-                        parent.children.push(element);
-                    }
-                }
-                Ok(Event::End(_)) => {
-                    if stack.len() > 1 {
-                        let element = stack.pop().unwrap();
-                        if let Some(parent) = stack.last_mut() {
-                            parent.children.push(element);
-                        }
-                    }
-                }
-                Ok(Event::Text(e)) => {
-                    if let Some(element) = stack.last_mut() {
-                        element.text.push_str(&String::from_utf8_lossy(e.as_ref()));
-                    }
-                }
-                Ok(Event::Eof) => break,
-                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-                _ => (),
-            }
-            buf.clear();
-        }
-        
-        assert_eq!(stack.len(), 1);
-        let root = &stack[0];
+        let root = parse_xml_str(xml).unwrap();
         assert_eq!(root.name, "root");
         assert_eq!(root.children.len(), 1);
         
@@ -185,4 +441,162 @@ mod tests {
         assert_eq!(child.get_attribute("id"), Some(&"1".to_string()));
         assert_eq!(child.text.trim(), "Text");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_document_prolog() {
+        // Windows工具写出的BOM，以及根元素之前的XML声明、注释和处理指令
+        for fixture in ["bom.xml", "leading_comments.xml"] {
+            let root = parse_xml_file(&format!("tests/fixtures/{}", fixture)).unwrap();
+            assert_eq!(root.name, "rsml", "{}", fixture);
+            assert_eq!(root.children.len(), 1, "{}", fixture);
+            assert_eq!(root.children[0].name, "body", "{}", fixture);
+        }
+        let bom = std::fs::read("tests/fixtures/bom.xml").unwrap();
+        assert!(bom.starts_with(&[0xef, 0xbb, 0xbf]));
+    }
+    
+    #[test]
+    fn test_empty_document() {
+        for fixture in ["empty.xml", "whitespace_only.xml"] {
+            let path = format!("tests/fixtures/{}", fixture);
+            let err = parse_xml_file(&path).unwrap_err();
+            assert!(matches!(err, RsmlError::InvalidStructure { .. }), "{}", err);
+            assert!(err.to_string().contains(&format!("'{}' is empty", path)), "{}", err);
+        }
+        // 只有BOM的文件也是空文档
+        let path = std::env::temp_dir().join("rsmlc_bom_only.xml");
+        std::fs::write(&path, "\u{feff}\n").unwrap();
+        let err = parse_xml_file(&path.to_string_lossy()).unwrap_err();
+        assert!(err.to_string().contains("is empty"), "{}", err);
+    }
+    
+    #[test]
+    fn test_parse_xml_reader() {
+        let xml = r#"<root><empty/><child>Text</child></root>"#;
+        let root = parse_xml_reader(xml.as_bytes()).unwrap();
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "empty");
+        assert_eq!(root.children[1].text, "Text");
+        
+        assert!(parse_xml_str("").is_err());
+        assert!(parse_xml_reader("<root><child></root>".as_bytes()).is_err());
+    }
+    
+    #[test]
+    fn test_element_spans() {
+        let xml = "<rsml>\n  <body>\n    <space id=\"a\"/>\n    <space>\n      <object>x</object>\n    </space>\n  </body>\n</rsml>";
+        let root = parse_xml_str(xml).unwrap();
+        let span = |element: &Element| {
+            let span = element.span.as_ref().unwrap();
+            (span.line, span.column)
+        };
+        assert_eq!(span(&root), (1, 1));
+        let body = &root.children[0];
+        assert_eq!(span(body), (2, 3));
+        assert_eq!(span(&body.children[0]), (3, 5));
+        assert_eq!(span(&body.children[1]), (4, 5));
+        assert_eq!(span(&body.children[1].children[0]), (5, 7));
+        assert_eq!(root.span.as_ref().unwrap().to_string(), "1:1");
+        
+        // 语法错误带上位置
+        let err = parse_xml_str("<rsml>\n  <body>\n  </space>\n</rsml>").unwrap_err();
+        assert!(matches!(err.inner(), RsmlError::XmlParse(_)));
+        assert_eq!(err.span().unwrap().line, 3);
+        assert!(err.to_string().starts_with("3:"), "{}", err);
+    }
+    
+    #[test]
+    fn test_document_boundaries() {
+        // 自闭合的根元素，以及XML声明、注释和DOCTYPE
+        let root = parse_xml_str("<?xml version=\"1.0\"?>\n<!DOCTYPE rsml>\n<!-- scene -->\n<rsml/>\n<!-- end -->").unwrap();
+        assert_eq!(root.name, "rsml");
+        assert!(root.children.is_empty());
+        assert_eq!(root.span.as_ref().unwrap().line, 4);
+        
+        // 缺少结束标签
+        let err = parse_xml_str("<rsml>\n  <body>\n    <space>\n  </body>\n</rsml>").unwrap_err();
+        assert!(matches!(err.inner(), RsmlError::XmlParse(_)), "{}", err);
+        let err = parse_xml_str("<rsml>\n  <body>\n    <space>").unwrap_err();
+        assert!(matches!(err.inner(), RsmlError::InvalidStructure { .. }), "{}", err);
+        assert!(err.to_string().ends_with("unclosed element <space> started at line 3"), "{}", err);
+        
+        // 根元素之后的内容
+        let err = parse_xml_str("<rsml></rsml>\ntrailing").unwrap_err();
+        assert!(err.to_string().contains("text 'trailing' outside the root element"), "{}", err);
+        assert_eq!(err.span().unwrap().line, 2);
+        let err = parse_xml_str("<rsml/>\n<rsml></rsml>").unwrap_err();
+        assert!(err.to_string().contains("element <rsml> after the root element"), "{}", err);
+        
+        assert!(parse_xml_str("<!-- nothing -->").is_err());
+    }
+    
+    #[test]
+    fn test_nested_includes() {
+        let root = parse_xml_file("tests/fixtures/includes/warehouse.xml").unwrap();
+        let warehouse = &root.find_child("body").unwrap().children[0];
+        let aisles = &warehouse.children;
+        assert_eq!(aisles.len(), 2);
+        // include上的属性覆盖被包含的根元素的属性
+        assert_eq!(aisles[0].get_attribute("id").unwrap(), "aisle_a");
+        assert_eq!(aisles[0].get_attribute("style").unwrap(), "size:2m 2m 2m");
+        assert_eq!(aisles[1].get_attribute("id").unwrap(), "aisle_b");
+        assert_eq!(aisles[1].get_attribute("style").unwrap(), "size:1m 2m 2m");
+        assert!(aisles[1].get_attribute("src").is_none());
+        
+        // 嵌套的include相对于包含它的文件，位置指向被包含的文件
+        let shelf = &aisles[0].children[0];
+        assert_eq!(shelf.classes(), ["shelf"]);
+        assert_eq!(shelf.children[0].text.trim(), "table_leg");
+        let span = shelf.children[0].span.as_ref().unwrap();
+        assert_eq!(span.file.as_deref(), Some("tests/fixtures/includes/parts/shelf.xml"));
+        assert_eq!(span.line, 2);
+        
+        // 超过最大层数
+        let options = IncludeOptions { max_depth: 1 };
+        let err = parse_xml_file_with_includes("tests/fixtures/includes/warehouse.xml", &options).unwrap_err();
+        assert!(err.to_string().contains("includes nested deeper than 1 levels"), "{}", err);
+        assert!(err.to_string().starts_with("tests/fixtures/includes/aisle.xml:3:5: "), "{}", err);
+    }
+    
+    #[test]
+    fn test_tracked_include_files() {
+        let mut files = Vec::new();
+        parse_xml_file_tracked("tests/fixtures/includes/warehouse.xml", &IncludeOptions::default(), &mut files).unwrap();
+        let files: Vec<String> = files.iter().map(|file| file.display().to_string()).collect();
+        assert_eq!(
+            files,
+            [
+                "tests/fixtures/includes/warehouse.xml",
+                "tests/fixtures/includes/aisle.xml",
+                "tests/fixtures/includes/parts/shelf.xml",
+            ]
+        );
+
+        // 出错时已经读取的文件也会记录
+        let mut files = Vec::new();
+        let options = IncludeOptions { max_depth: 1 };
+        assert!(parse_xml_file_tracked("tests/fixtures/includes/warehouse.xml", &options, &mut files).is_err());
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let err = parse_xml_file("tests/fixtures/includes/cycle_a.xml").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "tests/fixtures/includes/cycle_b.xml:2:5: Invalid RSML structure: include cycle: \
+             tests/fixtures/includes/cycle_a.xml -> tests/fixtures/includes/cycle_b.xml -> \
+             tests/fixtures/includes/cycle_a.xml"
+        );
+        
+        let err = parse_xml_str("<rsml><include src=\"tests/fixtures/includes/missing.xml\"/></rsml>").unwrap_err();
+        assert!(matches!(err.inner(), RsmlError::Io(_)), "{}", err);
+        assert_eq!(err.span().unwrap().column, 7);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"a < b && c > "d""#), "a &lt; b &amp;&amp; c &gt; &quot;d&quot;");
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+    }
+}
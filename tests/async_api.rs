@@ -0,0 +1,108 @@
+#![cfg(feature = "async")]
+
+use rsmlc::async_api::{AsyncApiClient, Download};
+use rsmlc::error::RsmlError;
+use rsmlc::package::lock::sha256_file;
+use std::io::{BufRead, BufReader, Write};
+
+/// 本地的资源服务器：一个分类，10个资源，文件内容是资源的路径
+fn serve_assets() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let files = base_url.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            reader.read_line(&mut request_line).unwrap();
+            let mut authorized = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                authorized |= line.trim_end().eq_ignore_ascii_case("authorization: Bearer secret");
+                line.clear();
+            }
+            let target = request_line.split_whitespace().nth(1).unwrap_or("").to_string();
+            let (status, body) = if target.starts_with("/files/") {
+                ("200 OK", target)
+            } else if !authorized {
+                ("401 Unauthorized", "missing token".to_string())
+            } else if target == "/api/robot-3d-assets/categories/name?name=cutlery" {
+                (
+                    "200 OK",
+                    r#"{"code":0,"message":"ok","data":{"id":"c7","name":"cutlery","description":"","utime":0,"ctime":0,"asset_count":10}}"#.to_string(),
+                )
+            } else if target == "/api/robot-3d-assets/assets?category_id=c7&page=1&limit=100" {
+                let items: Vec<String> = (0..10)
+                    .map(|index| {
+                        format!(
+                            r#"{{"id":"a{0}","name":"fork{0}","resource_url":"{1}/files/a{0}.obj","x_len":20.0,"y_len":40.0,"z_len":120.0}}"#,
+                            index, files
+                        )
+                    })
+                    .collect();
+                (
+                    "200 OK",
+                    format!(
+                        r#"{{"code":0,"message":"ok","data":{{"items":[{}],"total":10,"total_pages":1}}}}"#,
+                        items.join(",")
+                    ),
+                )
+            } else {
+                ("404 Not Found", "not found".to_string())
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    base_url
+}
+
+#[tokio::test]
+async fn test_async_fetch_and_download_all() {
+    let base_url = serve_assets();
+    let client = AsyncApiClient::new(&base_url).with_token("secret");
+    let category = client.fetch_dependency("cutlery").await.unwrap();
+    assert_eq!(category.id, "c7");
+    let assets = client.fetch_assets_in_category(&category.id, 1, 100).await.unwrap();
+    assert_eq!(assets.items.len(), 10);
+
+    let dir = std::env::temp_dir().join(format!("rsmlc_async_{}", std::process::id()));
+    let downloads: Vec<Download> = assets
+        .items
+        .iter()
+        .map(|asset| Download::asset(asset, dir.join(&asset.id)))
+        .collect();
+    let mut reported = Vec::new();
+    let hashes = client
+        .download_all(&downloads, 4, |progress| {
+            assert_eq!(progress.total, 10);
+            reported.push((progress.completed, progress.download.path.clone()));
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(hashes.len(), 10);
+    assert_eq!(reported.iter().map(|(completed, _)| *completed).collect::<Vec<_>>(), (1..=10).collect::<Vec<_>>());
+    for (download, hash) in downloads.iter().zip(&hashes) {
+        let content = std::fs::read(&download.path).unwrap();
+        assert!(download.url.ends_with(std::str::from_utf8(&content).unwrap()));
+        assert_eq!(hash, &sha256_file(&download.path).unwrap());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_async_client_without_token() {
+    let client = AsyncApiClient::new(serve_assets());
+    match client.fetch_dependency("cutlery").await {
+        Err(RsmlError::ApiError { status, .. }) => assert_eq!(status, 401),
+        other => panic!("expected an API error, got {:?}", other.map(|category| category.id)),
+    }
+}
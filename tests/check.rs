@@ -0,0 +1,48 @@
+use rsmlc::check::IssueKind;
+use rsmlc::{check, PackageSource, RsmlSource};
+
+/// tests/fixtures/check中每个有问题的场景和它的报告；RSMLC_UPDATE_GOLDEN=1时重新生成报告
+const FIXTURES: &[&str] = &[
+    "missing_body",
+    "styles",
+    "references",
+    "cube_without_size",
+    "overflow",
+    "unclosed",
+];
+
+fn report_json(name: &str) -> String {
+    let scene = format!("tests/fixtures/check/{}.xml", name);
+    let report = check(RsmlSource::Path(scene.into()), PackageSource::Path("package.toml".into())).unwrap();
+    report.to_json().unwrap() + "\n"
+}
+
+#[test]
+fn test_check_reports_match_golden_files() {
+    for name in FIXTURES {
+        let json = report_json(name);
+        let golden = format!("tests/fixtures/check/{}.json", name);
+        if std::env::var_os("RSMLC_UPDATE_GOLDEN").is_some() {
+            std::fs::write(&golden, &json).unwrap();
+        }
+        assert_eq!(json, std::fs::read_to_string(&golden).unwrap(), "{}", golden);
+    }
+}
+
+#[test]
+fn test_every_fixture_has_issues_of_its_kind() {
+    for (name, kind) in [
+        ("missing_body", IssueKind::Structure),
+        ("styles", IssueKind::Style),
+        ("references", IssueKind::Reference),
+        ("cube_without_size", IssueKind::Sizing),
+        ("overflow", IssueKind::Sizing),
+        ("unclosed", IssueKind::Structure),
+    ] {
+        let scene = format!("tests/fixtures/check/{}.xml", name);
+        let report = check(RsmlSource::Path(scene.into()), PackageSource::Path("package.toml".into())).unwrap();
+        assert!(report.issues.iter().any(|issue| issue.kind == kind), "{}: {:#?}", name, report.issues);
+        // 每个问题都能定位到源文件
+        assert!(report.issues.iter().all(|issue| issue.span.is_some()), "{}: {:#?}", name, report.issues);
+    }
+}
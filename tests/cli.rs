@@ -0,0 +1,132 @@
+use assert_cmd::assert::OutputAssertExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn rsmlc() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_rsmlc"))
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rsmlc_cli_{}_{}", name, std::process::id()))
+}
+
+#[test]
+fn test_build_writes_output_and_layout() {
+    let output = temp_path("scene.urdf");
+    let layout = temp_path("layout.json");
+    rsmlc()
+        .args(["build", "rsml_example.xml", "--package", "package.toml", "--target", "urdf", "--seed", "7"])
+        .arg("--out")
+        .arg(&output)
+        .arg("--emit-layout-json")
+        .arg(&layout)
+        .assert()
+        .success();
+
+    let urdf = std::fs::read_to_string(&output).unwrap();
+    assert!(urdf.starts_with("<robot"), "{}", urdf);
+    let layout_json = std::fs::read_to_string(&layout).unwrap();
+    assert!(layout_json.contains("\"leg1\""));
+    std::fs::remove_file(output).unwrap();
+    std::fs::remove_file(layout).unwrap();
+}
+
+#[test]
+fn test_check_reports_diagnostics() {
+    let assert = rsmlc().args(["check", "tests/fixtures/three_errors.xml"]).assert().code(1);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("error[E0130]: tests/fixtures/three_errors.xml:5:13"), "{}", stderr);
+    assert!(stderr.contains("compilation failed with 3 error diagnostic(s)"), "{}", stderr);
+
+    // 警告不影响退出码，-D warnings时变成error
+    let fixture = "tests/fixtures/malformed_style.xml";
+    rsmlc().args(["check", fixture]).assert().success();
+    rsmlc().args(["check", fixture, "-D", "warnings"]).assert().code(1);
+}
+
+#[test]
+fn test_missing_input_is_an_io_error() {
+    rsmlc().args(["check", "tests/fixtures/no_such_scene.xml"]).assert().code(2);
+    rsmlc()
+        .args(["build", "rsml_example.xml", "--package", "no_such_package.toml"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_verbose_prints_dom_tree() {
+    let assert = rsmlc().args(["check", "rsml_example.xml", "--verbose"]).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("DOM Tree:"), "{}", stdout);
+
+    let assert = rsmlc().args(["check", "rsml_example.xml"]).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(!stdout.contains("DOM Tree:"), "{}", stdout);
+    assert!(stdout.contains("rsml_example.xml: ok"), "{}", stdout);
+}
+
+#[test]
+fn test_package_resolve_without_dependencies() {
+    let lockfile = temp_path("rsml.lock");
+    let assert = rsmlc()
+        .args(["package", "resolve", "--package", "package.toml"])
+        .arg("--lockfile")
+        .arg(&lockfile)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("Lockfile: "), "{}", stdout);
+    assert!(lockfile.exists());
+    std::fs::remove_file(lockfile).unwrap();
+}
+
+#[test]
+fn test_check_json_report() {
+    let assert = rsmlc()
+        .args(["check", "tests/fixtures/check/references.xml", "--format", "json"])
+        .assert()
+        .code(1);
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert_eq!(stdout, std::fs::read_to_string("tests/fixtures/check/references.json").unwrap());
+
+    let assert = rsmlc().args(["check", "rsml_example.xml", "--format", "json"]).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("\"issues\": []"), "{}", stdout);
+}
+
+#[test]
+fn test_import_paths_are_relative_to_the_scene() {
+    // 在另一个目录中运行：base.plan相对于scene.xml查找，而不是当前目录
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let output = temp_path("imported.xml");
+    rsmlc()
+        .current_dir(std::env::temp_dir())
+        .arg("build")
+        .arg(root.join("tests/fixtures/imports/scene.xml"))
+        .arg("--package")
+        .arg(root.join("package.toml"))
+        .arg("--out")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let mjcf = std::fs::read_to_string(&output).unwrap();
+    assert!(mjcf.contains(r#"name="base/leg""#), "{}", mjcf);
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn test_query_symbols_resolves_includes() {
+    let assert = rsmlc()
+        .args(["query", "tests/fixtures/includes/warehouse.xml", "--symbols"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    // aisle_a和aisle_b是被包含的文件中的space
+    assert!(stdout.contains("#aisle_a"), "{}", stdout);
+    assert!(stdout.contains("object table_leg"), "{}", stdout);
+
+    rsmlc().args(["query", "tests/fixtures/includes/warehouse.xml"]).assert().failure();
+    rsmlc().args(["schema", "--format", "yaml"]).assert().failure();
+    rsmlc().args(["schema", "--format", "json"]).assert().success();
+}
@@ -0,0 +1,177 @@
+use rsmlc::diagnostics::{SeverityConfig, INVALID_STYLE, UNKNOWN_STYLE_PROPERTY};
+use rsmlc::error::RsmlError;
+use rsmlc::package::Package;
+use rsmlc::plan::ScenePlan;
+use rsmlc::target::TargetFormat;
+use rsmlc::{compile, CompileOptions, PackageSource, RsmlSource};
+
+fn example_options() -> CompileOptions {
+    let mut options = CompileOptions::new(
+        RsmlSource::Path("rsml_example.xml".into()),
+        PackageSource::Path("package.toml".into()),
+    );
+    options.seed = Some(7);
+    options
+}
+
+#[test]
+fn test_compile_example_to_mjcf() {
+    let compiled = compile(example_options()).unwrap();
+    assert!(compiled.output.starts_with("<mujoco model=\"rsml_model\">"));
+    assert!(compiled.output.contains("<body name=\"table_area\""));
+
+    let plan = ScenePlan::from_json_str(&compiled.layout_json).unwrap();
+    assert!(plan.items.iter().any(|item| item.name == "leg1"));
+
+    // 相同的seed生成相同的输出
+    assert_eq!(compile(example_options()).unwrap().output, compiled.output);
+}
+
+#[test]
+fn test_compile_example_to_every_target() {
+    for (target, root) in [
+        (TargetFormat::Urdf, "<robot"),
+        (TargetFormat::Sdf, "<sdf"),
+    ] {
+        let mut options = example_options();
+        options.target = target;
+        let compiled = compile(options).unwrap();
+        assert!(compiled.output.starts_with(root), "{}: {}", target, compiled.output);
+    }
+}
+
+#[test]
+fn test_compile_from_string_and_parsed_package() {
+    let rsml = r#"<rsml>
+        <head></head>
+        <body>
+            <space id="room" style="size:1m 1m 1m; unknown-property:1">
+                <object id="leg">table_leg</object>
+            </space>
+        </body>
+    </rsml>"#;
+    let package = || PackageSource::Package(Package::from_file("package.toml").unwrap());
+
+    // 未知的样式属性默认只是warning
+    let compiled = compile(CompileOptions::new(RsmlSource::Text(rsml.to_string()), package())).unwrap();
+    assert!(compiled.output.contains("<geom name=\"leg\""));
+    assert!(compiled
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.code == UNKNOWN_STYLE_PROPERTY));
+
+    // strict模式下变成error，compile失败并带回所有诊断
+    let mut options = CompileOptions::new(RsmlSource::Text(rsml.to_string()), package());
+    options.layout.strict_styles = true;
+    match compile(options) {
+        Err(RsmlError::DiagnosticErrors(diagnostics)) => assert!(diagnostics.has_errors()),
+        other => panic!("expected diagnostic errors, got {:?}", other.map(|c| c.output)),
+    }
+}
+
+#[test]
+fn test_malformed_style_reports_line() {
+    let fixture = "tests/fixtures/malformed_style.xml";
+    let options = || {
+        let mut options = CompileOptions::new(
+            RsmlSource::Path(fixture.into()),
+            PackageSource::Path("package.toml".into()),
+        );
+        options.seed = Some(7);
+        options
+    };
+
+    let compiled = compile(options()).unwrap();
+    let diagnostic = compiled
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.code == INVALID_STYLE)
+        .unwrap();
+    let span = diagnostic.span.as_ref().unwrap();
+    assert_eq!((span.line, span.column), (14, 13));
+    assert_eq!(diagnostic.element.as_deref(), Some("space#shelf"));
+
+    // 作为error时输出path:line:col，编辑器可以直接跳转
+    let mut denied = options();
+    denied.severity = SeverityConfig::from_toml_str("[diagnostics]\nW0111 = \"error\"").unwrap();
+    let Err(RsmlError::DiagnosticErrors(diagnostics)) = compile(denied) else {
+        panic!("expected diagnostic errors");
+    };
+    let message = diagnostics.iter().next().unwrap().to_string();
+    assert!(
+        message.starts_with("error[W0111]: tests/fixtures/malformed_style.xml:14:13: space#shelf: "),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_all_errors_reported_in_one_run() {
+    let options = CompileOptions::new(
+        RsmlSource::Path("tests/fixtures/three_errors.xml".into()),
+        PackageSource::Path("package.toml".into()),
+    );
+    let Err(RsmlError::DiagnosticErrors(diagnostics)) = compile(options) else {
+        panic!("expected diagnostic errors");
+    };
+    let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(messages.len(), 3, "{:#?}", messages);
+    assert!(messages[0].starts_with("error[E0100]: tests/fixtures/three_errors.xml:1:1: "));
+    assert!(messages[0].ends_with("Missing required element: head"));
+    assert!(messages[1].starts_with("error[E0120]: tests/fixtures/three_errors.xml:6:13: id 'leg' is defined at"));
+    assert_eq!(
+        messages[2],
+        "error[E0130]: tests/fixtures/three_errors.xml:5:13: object#vase: \
+         object/group not found in package 'tiny_example': 'no_such_vase'"
+    );
+}
+
+#[test]
+fn test_compile_with_includes() {
+    let mut options = CompileOptions::new(
+        RsmlSource::Path("tests/fixtures/includes/warehouse.xml".into()),
+        PackageSource::Path("package.toml".into()),
+    );
+    let compiled = compile(options.clone()).unwrap();
+    assert!(compiled.output.contains("<body name=\"aisle_a\""));
+    assert!(compiled.output.contains("<body name=\"aisle_b\""));
+
+    options.includes.max_depth = 0;
+    let error = compile(options).unwrap_err();
+    assert!(error.to_string().contains("includes nested deeper than 0 levels"), "{}", error);
+}
+
+fn unresolved_error(fixture: &str) -> String {
+    let options = CompileOptions::new(RsmlSource::Path(fixture.into()), PackageSource::Path("package.toml".into()));
+    compile(options).map(|compiled| compiled.output).unwrap_err().to_string()
+}
+
+#[test]
+fn test_unresolved_sizes_are_reported_together() {
+    // 空的auto容器不会再悄悄变成0，每个节点和轴都列出来
+    let message = unresolved_error("tests/fixtures/unresolved_empty.xml");
+    assert!(message.starts_with("tests/fixtures/unresolved_empty.xml:5:13: "), "{}", message);
+    assert!(
+        message.ends_with(concat!(
+            "unresolved size after layout:\n",
+            "  space#placeholder (x, y, z): auto size and no children\n",
+            "  space#shelf (x): auto size and no children"
+        )),
+        "{}",
+        message
+    );
+
+    // 百分比依赖auto的父元素，父元素又只有百分比的子元素
+    let message = unresolved_error("tests/fixtures/unresolved_percentage.xml");
+    assert!(message.starts_with("tests/fixtures/unresolved_percentage.xml:5:13: "), "{}", message);
+    assert!(
+        message.ends_with(concat!(
+            "unresolved size after layout:\n",
+            "  space#row (x): auto size and only percentage-sized children\n",
+            "  space#left (x): 50% of space#row, whose size is unresolved\n",
+            "  space#right (x): 50% of space#row, whose size is unresolved"
+        )),
+        "{}",
+        message
+    );
+}
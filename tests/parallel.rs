@@ -0,0 +1,64 @@
+#![cfg(feature = "parallel")]
+
+use rsmlc::plan::ScenePlan;
+use rsmlc::target::TargetFormat;
+use rsmlc::{compile, CompileOptions, PackageSource, RsmlSource};
+use std::collections::HashSet;
+
+/// 300个随机摆放的瓶子和300个随机位置的桌腿，每一层都足够宽，会并行计算
+fn random_scene() -> String {
+    let mut xml = String::from(
+        r#"<rsml><head></head><body style="size:100m 100m 10m">
+        <space id="grid" style="size:100m 100m 1m;flex-direction:x;flex-wrap:wrap">"#,
+    );
+    for index in 0..300 {
+        xml.push_str(&format!(
+            r#"<space id="cell{}" style="size:30cm 30cm 50cm;display:cube">
+                <group id="bottle{}" select="random" style="pos:random random min">bottles</group>
+            </space>"#,
+            index, index
+        ));
+        xml.push_str(&format!(
+            r#"<space style="size:50cm 30cm 50cm"><object id="leg{}" style="pos:random auto auto">table_leg</object></space>"#,
+            index
+        ));
+    }
+    xml.push_str("</space></body></rsml>");
+    xml
+}
+
+fn compile_scene(rsml: String, parallel: bool) -> rsmlc::Result<(String, String)> {
+    let mut options = CompileOptions::new(RsmlSource::Text(rsml), PackageSource::Path("package.toml".into()));
+    options.target = TargetFormat::Urdf;
+    options.seed = Some(1562);
+    options.layout.parallel = parallel;
+    let compiled = compile(options)?;
+    Ok((compiled.output, compiled.layout_json))
+}
+
+#[test]
+fn test_parallel_layout_matches_serial() {
+    let (serial_output, serial_layout) = compile_scene(random_scene(), false).unwrap();
+    let (parallel_output, parallel_layout) = compile_scene(random_scene(), true).unwrap();
+    assert_eq!(parallel_layout, serial_layout);
+    assert_eq!(parallel_output, serial_output);
+
+    // 随机的选择和位置确实各不相同，而不是都取了同一个值
+    let plan = ScenePlan::from_json_str(&serial_layout).unwrap();
+    let paths: HashSet<_> = plan.items.iter().filter_map(|item| item.path.as_deref()).collect();
+    assert!(paths.len() > 10, "{:?}", paths);
+    let positions: HashSet<_> = plan.items.iter().map(|item| item.position).collect();
+    assert_eq!(positions.len(), plan.items.len());
+}
+
+#[test]
+fn test_parallel_layout_reports_the_serial_error() {
+    // 两处溢出，串行计算先遇到cell10
+    let scene = random_scene()
+        .replace(r#"id="cell10" style="size:30cm"#, r#"id="cell10" style="size:3cm"#)
+        .replace(r#"id="cell200" style="size:30cm"#, r#"id="cell200" style="size:3cm"#);
+    let serial = compile_scene(scene.clone(), false).unwrap_err().to_string();
+    let parallel = compile_scene(scene, true).unwrap_err().to_string();
+    assert!(serial.contains("cell10"), "{}", serial);
+    assert_eq!(parallel, serial);
+}